@@ -0,0 +1,206 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Headers of the files rml writes.
+
+/*!
+Every serialized artifact, such as a dataset cache or a saved `KNN`, starts with a `Header` that
+records what it holds, the format version, the version of rml that wrote it, the number of
+features and a fingerprint of its configuration. Loading checks the header first, so reading a
+file of the wrong kind, from an incompatible format, or whose contents don't match its
+configuration is a clear error instead of silently wrong predictions.
+
+`read_header` reads just the header, to inspect an artifact or check its number of features
+before loading it.
+
+# Example
+```rust
+use rml::artifact::read_header;
+use rml::datasets::Dataset;
+
+let data = Dataset::new(vec![vec![1.0, 2.0]], vec![0]).unwrap();
+let mut bytes = Vec::new();
+data.write_cache(&mut bytes).unwrap();
+let header = read_header(&mut &bytes[..]).unwrap();
+
+assert_eq!(header.kind, "Dataset<f64, i32>");
+assert_eq!(header.n_features, 2);
+assert!(header.check_n_features(3).is_err());
+```
+!*/
+
+use crate::datasets::cache::CacheElement;
+use crate::error::{Error, Result};
+use std::io::{Read, Write};
+
+const MAGIC: &[u8; 8] = b"RMLARTIF";
+/// The magic of dataset caches written before headers were added.
+const OLD_CACHE_MAGIC: &[u8; 8] = b"RMLCACHE";
+
+/// The version of the artifact format this version of rml reads and writes.
+pub const FORMAT_VERSION: u8 = 4;
+
+/// The header of an artifact.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    /// What the artifact holds, including its type parameters, such as `KNN<f64>`.
+    pub kind: String,
+    /// The version of the artifact format.
+    pub format_version: u8,
+    /// The version of rml that wrote the artifact.
+    pub crate_version: String,
+    /// The number of features of the data or model.
+    pub n_features: usize,
+    /// A hash of the configuration of the data or model.
+    pub fingerprint: u64,
+}
+
+impl Header {
+    /// Create a header for an artifact written by this version of rml.
+    pub(crate) fn new(kind: String, n_features: usize, fingerprint: u64) -> Header {
+        Header {
+            kind,
+            format_version: FORMAT_VERSION,
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            n_features,
+            fingerprint,
+        }
+    }
+
+    /// Write the header.
+    pub(crate) fn write_to<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        w.write_all(MAGIC)?;
+        self.format_version.write_to(w)?;
+        self.crate_version.write_to(w)?;
+        self.kind.write_to(w)?;
+        self.n_features.write_to(w)?;
+        self.fingerprint.write_to(w)
+    }
+
+    /// Check that the artifact holds `kind`.
+    pub(crate) fn check_kind(&self, kind: &str) -> Result<()> {
+        if self.kind != kind {
+            return Err(Error::Parse(format!(
+                "The file holds a {}, not a {}.",
+                self.kind, kind
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check that the loaded contents have the configuration the header was written with.
+    pub(crate) fn check_fingerprint(&self, fingerprint: u64) -> Result<()> {
+        if self.fingerprint != fingerprint {
+            return Err(Error::Parse(format!(
+                "The {} does not match the configuration it was saved with; the file is corrupt.",
+                self.kind
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check that the artifact has `n_features` features.
+    pub fn check_n_features(&self, n_features: usize) -> Result<()> {
+        if self.n_features != n_features {
+            return Err(Error::ShapeMismatch(format!(
+                "The {} has {} features but {} are expected.",
+                self.kind, self.n_features, n_features
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Read the header of an artifact.
+/// Returns an error if `r` is not an artifact or uses a different format version.
+pub fn read_header<R: Read>(r: &mut R) -> Result<Header> {
+    let mut magic = [0; 8];
+    r.read_exact(&mut magic)?;
+    if &magic == OLD_CACHE_MAGIC {
+        return Err(Error::Parse(
+            "The file was written by an older version of rml; create it again.".into(),
+        ));
+    }
+    if &magic != MAGIC {
+        return Err(Error::Parse("Not an rml file.".into()));
+    }
+    let format_version = u8::read_from(r)?;
+    let crate_version = String::read_from(r)?;
+    if format_version != FORMAT_VERSION {
+        return Err(Error::Parse(format!(
+            "The file uses format version {} (written by rml {}), but this version of rml \
+             reads version {}.",
+            format_version, crate_version, FORMAT_VERSION
+        )));
+    }
+    Ok(Header {
+        format_version,
+        crate_version,
+        kind: String::read_from(r)?,
+        n_features: usize::read_from(r)?,
+        fingerprint: u64::read_from(r)?,
+    })
+}
+
+/// Hash a description of a configuration with 64-bit FNV-1a, which is stable across platforms
+/// and releases.
+pub(crate) fn fingerprint(config: &str) -> u64 {
+    config.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_round_trip_test() {
+        let header = Header::new(String::from("Test"), 3, fingerprint("k=1"));
+        let mut bytes = Vec::new();
+        header.write_to(&mut bytes).unwrap();
+        let read = read_header(&mut &bytes[..]).unwrap();
+
+        assert_eq!(read, header);
+        assert!(read.check_kind("Test").is_ok());
+        assert!(read.check_kind("Other").is_err());
+        assert!(read.check_fingerprint(fingerprint("k=2")).is_err());
+        assert!(matches!(
+            read.check_n_features(2),
+            Err(Error::ShapeMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn incompatible_header_test() {
+        let mut header = Header::new(String::from("Test"), 3, 0);
+        header.format_version = 1;
+        let mut bytes = Vec::new();
+        header.write_to(&mut bytes).unwrap();
+        let error = read_header(&mut &bytes[..]).unwrap_err().to_string();
+
+        assert!(error.contains("format version 1"));
+        assert!(read_header(&mut &b"RMLCACHE\x01"[..]).is_err());
+        assert!(read_header(&mut &b"not rml!"[..]).is_err());
+    }
+
+    #[test]
+    fn fingerprint_test() {
+        assert_eq!(fingerprint(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fingerprint("a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
@@ -0,0 +1,330 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Frequent itemset mining and association rules.
+
+/*!
+Given a list of transactions, each a set of items such as the products in one basket or the
+tokens of one document, `Apriori` finds the itemsets that occur in at least a minimum fraction
+of the transactions, then the rules `A => C` between them that hold often enough.
+
+A rule is measured by its support, the fraction of transactions containing both sides; its
+confidence, the fraction of transactions containing `A` that also contain `C`; and its lift,
+the confidence divided by the support of `C`, which is above 1 when `A` makes `C` more likely.
+
+# Example
+```rust
+use rml::association::Apriori;
+
+let transactions = vec![
+    vec!["bread", "milk"],
+    vec!["bread", "butter", "milk"],
+    vec!["butter", "milk"],
+    vec!["bread", "butter"],
+];
+let apriori = Apriori::new(0.5).min_confidence(0.6);
+let itemsets = apriori.frequent_itemsets(&transactions).unwrap();
+let rules = apriori.rules(&itemsets);
+
+assert_eq!(itemsets.len(), 6);
+assert!(rules.iter().all(|r| r.confidence >= 0.6));
+```
+!*/
+
+use crate::error::{Error, Result};
+use std::collections::BTreeMap;
+
+/// A set of items and the fraction of transactions that contain it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Itemset<T> {
+    /// The sorted items.
+    pub items: Vec<T>,
+    /// The fraction of transactions containing every item.
+    pub support: f64,
+}
+
+/// An association rule `antecedent => consequent`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule<T> {
+    /// The sorted items on the left of the rule.
+    pub antecedent: Vec<T>,
+    /// The sorted items on the right of the rule.
+    pub consequent: Vec<T>,
+    /// The fraction of transactions containing both sides.
+    pub support: f64,
+    /// The fraction of transactions containing the antecedent that also contain the consequent.
+    pub confidence: f64,
+    /// The confidence divided by the support of the consequent.
+    pub lift: f64,
+}
+
+/// Mines frequent itemsets level by level with the Apriori algorithm: candidates of size `k + 1`
+/// are joined from frequent itemsets of size `k`, and dropped unless all their subsets are
+/// frequent, before their support is counted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Apriori {
+    /// The smallest support of a frequent itemset, in `(0, 1]`.
+    pub min_support: f64,
+    /// The smallest confidence of a rule.
+    pub min_confidence: f64,
+    /// The smallest lift of a rule.
+    pub min_lift: f64,
+    /// The largest itemset to mine, or None for no limit.
+    pub max_len: Option<usize>,
+}
+
+impl Apriori {
+    /// Create an Apriori with the given minimum support, keeping every rule between the frequent
+    /// itemsets.
+    pub fn new(min_support: f64) -> Apriori {
+        Apriori {
+            min_support,
+            min_confidence: 0.0,
+            min_lift: 0.0,
+            max_len: None,
+        }
+    }
+
+    /// Set the smallest confidence of a rule.
+    pub fn min_confidence(mut self, min_confidence: f64) -> Self {
+        self.min_confidence = min_confidence;
+        self
+    }
+
+    /// Set the smallest lift of a rule.
+    pub fn min_lift(mut self, min_lift: f64) -> Self {
+        self.min_lift = min_lift;
+        self
+    }
+
+    /// Set the largest itemset to mine.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = Some(max_len);
+        self
+    }
+
+    /// Find every itemset contained in at least `min_support` of the `transactions`, ordered by
+    /// size and then by items. Repeated items within a transaction count once.
+    /// Returns an error if there are no transactions or `min_support` is not in `(0, 1]`.
+    pub fn frequent_itemsets<T: Ord + Clone>(
+        &self,
+        transactions: &[Vec<T>],
+    ) -> Result<Vec<Itemset<T>>> {
+        if transactions.is_empty() {
+            return Err(Error::InvalidInput("There are no transactions.".into()));
+        }
+        if !(self.min_support > 0.0 && self.min_support <= 1.0) {
+            return Err(Error::InvalidInput(format!(
+                "The minimum support {} is not in (0, 1].",
+                self.min_support
+            )));
+        }
+        let n = transactions.len() as f64;
+        let transactions: Vec<Vec<&T>> = transactions
+            .iter()
+            .map(|t| {
+                let mut t: Vec<&T> = t.iter().collect();
+                t.sort_unstable();
+                t.dedup();
+                t
+            })
+            .collect();
+
+        let mut counts: BTreeMap<&T, usize> = BTreeMap::new();
+        for item in transactions.iter().flatten() {
+            *counts.entry(*item).or_insert(0) += 1;
+        }
+        let frequent = |count: usize| count as f64 / n >= self.min_support;
+        let mut level: Vec<(Vec<&T>, usize)> = counts
+            .into_iter()
+            .filter(|&(_, c)| frequent(c))
+            .map(|(item, c)| (vec![item], c))
+            .collect();
+
+        let mut itemsets = Vec::new();
+        while !level.is_empty() {
+            let size = level[0].0.len();
+            itemsets.extend(level.iter().map(|(items, c)| Itemset {
+                items: items.iter().map(|&i| i.clone()).collect(),
+                support: *c as f64 / n,
+            }));
+            if self.max_len.is_some_and(|max| size >= max) {
+                break;
+            }
+            let previous: Vec<&Vec<&T>> = level.iter().map(|(items, _)| items).collect();
+            level = candidates(&previous)
+                .into_iter()
+                .map(|c| {
+                    let count = transactions.iter().filter(|t| is_subset(&c, t)).count();
+                    (c, count)
+                })
+                .filter(|&(_, c)| frequent(c))
+                .collect();
+        }
+        Ok(itemsets)
+    }
+
+    /// Every rule between the `itemsets` meeting the minimum confidence and lift, splitting each
+    /// itemset into a non-empty antecedent and consequent. The itemsets must be the output of
+    /// [`Apriori::frequent_itemsets`], which holds every subset of a frequent itemset.
+    pub fn rules<T: Ord + Clone>(&self, itemsets: &[Itemset<T>]) -> Vec<Rule<T>> {
+        let support: BTreeMap<&[T], f64> = itemsets
+            .iter()
+            .map(|s| (s.items.as_slice(), s.support))
+            .collect();
+        let mut rules = Vec::new();
+        for itemset in itemsets.iter().filter(|s| s.items.len() > 1) {
+            let k = itemset.items.len();
+            // Each mask below 2^k - 1, other than 0, picks the items of the antecedent.
+            for mask in 1..(1u64 << k) - 1 {
+                let side = |left: bool| -> Vec<T> {
+                    let items = itemset.items.iter().enumerate();
+                    items
+                        .filter(|(i, _)| (mask & (1 << i) != 0) == left)
+                        .map(|(_, t)| t.clone())
+                        .collect()
+                };
+                let (antecedent, consequent) = (side(true), side(false));
+                let (a, c) = match (support.get(&antecedent[..]), support.get(&consequent[..])) {
+                    (Some(&a), Some(&c)) => (a, c),
+                    _ => continue,
+                };
+                let confidence = itemset.support / a;
+                let lift = confidence / c;
+                if confidence >= self.min_confidence && lift >= self.min_lift {
+                    rules.push(Rule {
+                        antecedent,
+                        consequent,
+                        support: itemset.support,
+                        confidence,
+                        lift,
+                    });
+                }
+            }
+        }
+        rules
+    }
+}
+
+/// The candidates one item larger than the sorted, equally sized `itemsets`: each pair sharing
+/// all but its last item is joined, and kept only if every subset one item smaller is among
+/// `itemsets`.
+fn candidates<'a, T: Ord>(itemsets: &[&Vec<&'a T>]) -> Vec<Vec<&'a T>> {
+    let mut joined = Vec::new();
+    for (i, a) in itemsets.iter().enumerate() {
+        let prefix = &a[..a.len() - 1];
+        for b in itemsets[i + 1..]
+            .iter()
+            .take_while(|b| &b[..b.len() - 1] == prefix)
+        {
+            let mut candidate = a.to_vec();
+            candidate.push(b[b.len() - 1]);
+            let all_frequent = (0..candidate.len() - 2).all(|skip| {
+                let subset: Vec<&T> = candidate
+                    .iter()
+                    .enumerate()
+                    .filter(|&(j, _)| j != skip)
+                    .map(|(_, &t)| t)
+                    .collect();
+                itemsets
+                    .binary_search_by(|s| s[..].cmp(&subset[..]))
+                    .is_ok()
+            });
+            if all_frequent {
+                joined.push(candidate);
+            }
+        }
+    }
+    joined
+}
+
+/// Whether every item of the sorted `items` is in the sorted `transaction`.
+fn is_subset<T: Ord>(items: &[&T], transaction: &[&T]) -> bool {
+    let mut rest = transaction.iter();
+    items.iter().all(|item| rest.any(|t| t == item))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transactions() -> Vec<Vec<&'static str>> {
+        vec![
+            vec!["beer", "diapers", "bread"],
+            vec!["beer", "diapers"],
+            vec!["beer", "diapers", "milk"],
+            vec!["bread", "milk"],
+            vec!["bread", "milk", "beer", "beer"],
+        ]
+    }
+
+    #[test]
+    fn frequent_itemsets_test() {
+        let itemsets = Apriori::new(0.4)
+            .frequent_itemsets(&transactions())
+            .unwrap();
+        let found: Vec<(Vec<&str>, f64)> =
+            itemsets.into_iter().map(|s| (s.items, s.support)).collect();
+
+        assert_eq!(
+            found,
+            vec![
+                (vec!["beer"], 0.8),
+                (vec!["bread"], 0.6),
+                (vec!["diapers"], 0.6),
+                (vec!["milk"], 0.6),
+                (vec!["beer", "bread"], 0.4),
+                (vec!["beer", "diapers"], 0.6),
+                (vec!["beer", "milk"], 0.4),
+                (vec!["bread", "milk"], 0.4),
+            ]
+        );
+        let singles = Apriori::new(0.4)
+            .max_len(1)
+            .frequent_itemsets(&transactions())
+            .unwrap();
+        assert_eq!(singles.len(), 4);
+        let all = Apriori::new(0.2)
+            .frequent_itemsets(&transactions())
+            .unwrap();
+        assert!(all
+            .iter()
+            .any(|s| s.items == vec!["beer", "bread", "diapers"]));
+        assert!(Apriori::new(0.0)
+            .frequent_itemsets(&transactions())
+            .is_err());
+        assert!(Apriori::new(0.5).frequent_itemsets::<u8>(&[]).is_err());
+    }
+
+    #[test]
+    fn rules_test() {
+        let apriori = Apriori::new(0.4).min_confidence(0.7).min_lift(1.1);
+        let rules = apriori.rules(&apriori.frequent_itemsets(&transactions()).unwrap());
+
+        // diapers => beer holds in every transaction with diapers; beer => diapers in 3 of 4.
+        assert_eq!(rules.len(), 2);
+        let rule = rules
+            .iter()
+            .find(|r| r.antecedent == vec!["diapers"])
+            .unwrap();
+        assert_eq!(rule.consequent, vec!["beer"]);
+        assert_eq!(rule.support, 0.6);
+        assert_eq!(rule.confidence, 1.0);
+        assert!((rule.lift - 1.25).abs() < 1e-12);
+        assert!(rules.iter().all(|r| r.confidence >= 0.7 && r.lift >= 1.1));
+    }
+}
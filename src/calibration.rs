@@ -0,0 +1,506 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+//! Probability calibration.
+
+/*!
+Many classifiers predict probabilities that rank samples well but are not calibrated: of the
+samples given a probability of 0.8, far more or fewer than 80% may belong to the class.
+`calibration_curve` measures this, and `CalibratedClassifier` corrects it by learning a mapping
+from predicted to observed probabilities on samples held out from training.
+
+Two mappings are available: Platt scaling fits a sigmoid, and works well with little data;
+isotonic regression fits any increasing step function, and needs more data but makes no
+assumption about the shape.
+
+# Example
+```rust
+use rml::calibration::{CalibratedClassifier, Method};
+use rml::knn::KNN;
+use rml::traits::{Fit, PredictProba};
+
+let x: Vec<Vec<f64>> = (0..40).map(|i| vec![(i % 4) as f64]).collect();
+let y: Vec<i32> = (0..40).map(|i| i32::from(i % 4 >= 2)).collect();
+
+let knn = KNN::unfitted(5, None, None);
+let mut model = CalibratedClassifier::new(knn, Method::Sigmoid).holdout(0.3).seed(1);
+model.fit(&x, &y).unwrap();
+
+let p = model.predict_proba(&[vec![3.0]]).unwrap();
+assert!(p[0][1] > 0.5);
+```
+!*/
+
+use crate::describe::{Describe, Summary};
+use crate::error::{check_lengths, Error, Result};
+use crate::math::random::Rng;
+use crate::traits::{Fit, Predict, PredictProba};
+
+/// Compute a reliability curve for binary predictions.
+///
+/// The probabilities are split into `n_bins` equal-width bins over `[0, 1]`. For each non-empty
+/// bin, returns the mean predicted probability and the fraction of positive samples, in that
+/// order. For a calibrated classifier the two are close.
+///
+/// # Example
+/// ```rust
+/// use rml::calibration::calibration_curve;
+///
+/// let y_true = vec![false, false, true, true];
+/// let y_prob = vec![0.1, 0.2, 0.7, 0.9];
+/// let (predicted, observed) = calibration_curve(&y_true, &y_prob, 2).unwrap();
+///
+/// assert_eq!(observed, vec![0.0, 1.0]);
+/// ```
+pub fn calibration_curve(
+    y_true: &[bool],
+    y_prob: &[f64],
+    n_bins: usize,
+) -> Result<(Vec<f64>, Vec<f64>)> {
+    if n_bins == 0 {
+        return Err(Error::InvalidInput(
+            "There must be at least one bin.".into(),
+        ));
+    }
+    check_lengths("Labels and probabilities", y_true.len(), y_prob.len())?;
+    if let Some(p) = y_prob.iter().find(|p| !(0.0..=1.0).contains(*p)) {
+        return Err(Error::InvalidInput(format!(
+            "Probability {} is not in [0, 1].",
+            p
+        )));
+    }
+
+    // (sum of probabilities, number of positives, number of samples) per bin.
+    let mut bins = vec![(0.0, 0, 0); n_bins];
+    for (&t, &p) in y_true.iter().zip(y_prob) {
+        let bin = &mut bins[((p * n_bins as f64) as usize).min(n_bins - 1)];
+        bin.0 += p;
+        bin.1 += usize::from(t);
+        bin.2 += 1;
+    }
+    Ok(bins
+        .iter()
+        .filter(|b| b.2 > 0)
+        .map(|&(sum, positives, n)| (sum / n as f64, positives as f64 / n as f64))
+        .unzip())
+}
+
+/// Maps scores to probabilities with a sigmoid, `1 / (1 + exp(-(a * score + b)))`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlattScaling {
+    /// The slope.
+    pub a: f64,
+    /// The intercept.
+    pub b: f64,
+}
+
+impl PlattScaling {
+    /// Fit the sigmoid to scores and binary labels by maximum likelihood (Newton's method),
+    /// using Platt's smoothed targets to avoid overfitting small sets.
+    pub fn fit(scores: &[f64], y: &[bool]) -> Result<PlattScaling> {
+        check_binary(scores, y)?;
+        let n_pos = y.iter().filter(|&&t| t).count() as f64;
+        let n_neg = y.len() as f64 - n_pos;
+        let targets: Vec<f64> = y
+            .iter()
+            .map(|&t| {
+                if t {
+                    (n_pos + 1.0) / (n_pos + 2.0)
+                } else {
+                    1.0 / (n_neg + 2.0)
+                }
+            })
+            .collect();
+        let loss = |a: f64, b: f64| -> f64 {
+            scores
+                .iter()
+                .zip(&targets)
+                .map(|(s, t)| {
+                    let z = a * s + b;
+                    // log(1 + exp(z)) - t * z, computed without overflow.
+                    z.max(0.0) + (-z.abs()).exp().ln_1p() - t * z
+                })
+                .sum()
+        };
+
+        let mut platt = PlattScaling {
+            a: 0.0,
+            b: ((n_pos + 1.0) / (n_neg + 1.0)).ln(),
+        };
+        for _ in 0..100 {
+            let (mut ga, mut gb, mut haa, mut hab, mut hbb) = (0.0, 0.0, 1e-12, 0.0, 1e-12);
+            for (s, t) in scores.iter().zip(&targets) {
+                let p = platt.transform(*s);
+                let w = p * (1.0 - p);
+                ga += (p - t) * s;
+                gb += p - t;
+                haa += w * s * s;
+                hab += w * s;
+                hbb += w;
+            }
+            let det = haa * hbb - hab * hab;
+            let (da, db) = ((hbb * ga - hab * gb) / det, (haa * gb - hab * ga) / det);
+
+            // Halve the Newton step until the loss decreases.
+            let current = loss(platt.a, platt.b);
+            let mut step = 1.0;
+            while step > 1e-10 && loss(platt.a - step * da, platt.b - step * db) > current {
+                step /= 2.0;
+            }
+            platt.a -= step * da;
+            platt.b -= step * db;
+            if (step * da).abs() < 1e-10 && (step * db).abs() < 1e-10 {
+                break;
+            }
+        }
+        Ok(platt)
+    }
+
+    /// Map a score to a probability.
+    pub fn transform(&self, score: f64) -> f64 {
+        1.0 / (1.0 + (-(self.a * score + self.b)).exp())
+    }
+}
+
+/// An increasing piecewise linear map from scores to probabilities.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IsotonicRegression {
+    /// The scores at the ends of each fitted step, in increasing order.
+    pub x: Vec<f64>,
+    /// The probability at each score in `x`.
+    pub y: Vec<f64>,
+}
+
+impl IsotonicRegression {
+    /// Fit an increasing function to scores and binary labels with the pool adjacent violators
+    /// algorithm.
+    pub fn fit(scores: &[f64], y: &[bool]) -> Result<IsotonicRegression> {
+        check_binary(scores, y)?;
+        let mut order: Vec<usize> = (0..scores.len()).collect();
+        order.sort_by(|&i, &j| {
+            scores[i]
+                .partial_cmp(&scores[j])
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        // Each block is (sum of labels, number of samples, lowest score, highest score).
+        let mut blocks: Vec<(f64, f64, f64, f64)> = Vec::new();
+        for i in order {
+            let s = scores[i];
+            blocks.push((f64::from(u8::from(y[i])), 1.0, s, s));
+            while blocks.len() > 1 {
+                let (last, prev) = (blocks[blocks.len() - 1], blocks[blocks.len() - 2]);
+                // Pool blocks that are out of order, or that share a score.
+                if prev.0 / prev.1 < last.0 / last.1 && prev.3 < last.2 {
+                    break;
+                }
+                blocks.pop();
+                *blocks.last_mut().unwrap() = (prev.0 + last.0, prev.1 + last.1, prev.2, last.3);
+            }
+        }
+
+        let mut fitted = IsotonicRegression {
+            x: Vec::new(),
+            y: Vec::new(),
+        };
+        for (sum, n, low, high) in blocks {
+            for s in if low == high {
+                vec![low]
+            } else {
+                vec![low, high]
+            } {
+                fitted.x.push(s);
+                fitted.y.push(sum / n);
+            }
+        }
+        Ok(fitted)
+    }
+
+    /// Map a score to a probability, interpolating between the fitted points and clamping
+    /// outside them.
+    pub fn transform(&self, score: f64) -> f64 {
+        let i = self.x.partition_point(|&x| x < score);
+        if i == 0 {
+            self.y[0]
+        } else if i == self.x.len() {
+            self.y[i - 1]
+        } else {
+            let t = (score - self.x[i - 1]) / (self.x[i] - self.x[i - 1]);
+            self.y[i - 1] + t * (self.y[i] - self.y[i - 1])
+        }
+    }
+}
+
+/// Check the inputs of a binary calibrator.
+fn check_binary(scores: &[f64], y: &[bool]) -> Result<()> {
+    if scores.is_empty() {
+        return Err(Error::InvalidInput("No samples to calibrate on.".into()));
+    }
+    check_lengths("Scores and labels", scores.len(), y.len())?;
+    Ok(())
+}
+
+/// How probabilities are calibrated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    /// Platt scaling.
+    Sigmoid,
+    /// Isotonic regression.
+    Isotonic,
+}
+
+/// A fitted one-vs-rest calibrator for one class.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Calibrator {
+    /// Platt scaling.
+    Sigmoid(PlattScaling),
+    /// Isotonic regression.
+    Isotonic(IsotonicRegression),
+}
+
+impl Calibrator {
+    /// Map a probability to a calibrated one.
+    pub fn transform(&self, p: f64) -> f64 {
+        match self {
+            Calibrator::Sigmoid(c) => c.transform(p),
+            Calibrator::Isotonic(c) => c.transform(p),
+        }
+    }
+}
+
+/// Wraps a classifier and calibrates its probabilities on samples held out from training.
+///
+/// Fitting trains the classifier on a random part of the data and fits one calibrator per class
+/// to its probabilities on the rest. Class labels are class indices, as in `PredictProba`.
+#[derive(Debug, Clone)]
+pub struct CalibratedClassifier<E> {
+    /// The wrapped classifier.
+    pub estimator: E,
+    /// The calibration method.
+    pub method: Method,
+    holdout: f64,
+    seed: Option<u64>,
+    calibrators: Vec<Calibrator>,
+}
+
+impl<E> CalibratedClassifier<E> {
+    /// Wrap an estimator. By default 20% of the samples are held out, chosen with the global seed.
+    pub fn new(estimator: E, method: Method) -> CalibratedClassifier<E> {
+        CalibratedClassifier {
+            estimator,
+            method,
+            holdout: 0.2,
+            seed: None,
+            calibrators: Vec::new(),
+        }
+    }
+
+    /// The fraction of samples held out for calibration, in `(0, 1)`.
+    pub fn holdout(mut self, fraction: f64) -> Self {
+        self.holdout = fraction;
+        self
+    }
+
+    /// The seed used to choose the held out samples.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// The fitted calibrator of each class.
+    pub fn calibrators(&self) -> &[Calibrator] {
+        &self.calibrators
+    }
+}
+
+impl<E: Describe> Describe for CalibratedClassifier<E> {
+    fn describe(&self) -> Summary {
+        let mut summary = Summary::new("CalibratedClassifier")
+            .param("method", self.method)
+            .param("holdout", self.holdout)
+            .param("seed", self.seed)
+            .part(self.estimator.describe());
+        if !self.calibrators.is_empty() {
+            summary.n_classes = Some(self.calibrators.len());
+        }
+        summary.memory = self
+            .calibrators
+            .iter()
+            .map(|c| match c {
+                Calibrator::Sigmoid(_) => 0,
+                Calibrator::Isotonic(c) => {
+                    (c.x.capacity() + c.y.capacity()) * std::mem::size_of::<f64>()
+                }
+            })
+            .sum::<usize>()
+            + self.calibrators.capacity() * std::mem::size_of::<Calibrator>();
+        summary
+    }
+}
+
+impl<E, T> Fit<T, i32> for CalibratedClassifier<E>
+where
+    E: Fit<T, i32> + PredictProba<T>,
+    T: Clone,
+{
+    fn fit(&mut self, x: &[Vec<T>], y: &[i32]) -> Result<()> {
+        if !(self.holdout > 0.0 && self.holdout < 1.0) {
+            return Err(Error::InvalidInput(format!(
+                "Holdout fraction {} is not in (0, 1).",
+                self.holdout
+            )));
+        }
+        check_lengths("Features and labels", x.len(), y.len())?;
+        let mut order: Vec<usize> = (0..x.len()).collect();
+        Rng::from_seed(self.seed).shuffle(&mut order);
+        let n_holdout = (self.holdout * x.len() as f64).round() as usize;
+        if n_holdout == 0 || n_holdout == x.len() {
+            return Err(Error::InvalidInput(
+                "Too few samples to hold out for calibration.".into(),
+            ));
+        }
+        let (held_out, train) = order.split_at(n_holdout);
+        let take_x = |rows: &[usize]| rows.iter().map(|&i| x[i].clone()).collect::<Vec<_>>();
+        let take_y = |rows: &[usize]| rows.iter().map(|&i| y[i]).collect::<Vec<_>>();
+
+        self.estimator.fit(&take_x(train), &take_y(train))?;
+        let proba = self.estimator.predict_proba(&take_x(held_out))?;
+        let y_held_out = take_y(held_out);
+        let n_classes = proba.first().map_or(0, |p| p.len());
+
+        self.calibrators = (0..n_classes)
+            .map(|class| {
+                let scores: Vec<f64> = proba.iter().map(|p| p[class]).collect();
+                let targets: Vec<bool> = y_held_out.iter().map(|&t| t == class as i32).collect();
+                Ok(match self.method {
+                    Method::Sigmoid => Calibrator::Sigmoid(PlattScaling::fit(&scores, &targets)?),
+                    Method::Isotonic => {
+                        Calibrator::Isotonic(IsotonicRegression::fit(&scores, &targets)?)
+                    }
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(())
+    }
+}
+
+impl<E, T> PredictProba<T> for CalibratedClassifier<E>
+where
+    E: PredictProba<T>,
+{
+    /// Calibrate each class probability, then normalize each row to sum to 1.
+    fn predict_proba(&self, x: &[Vec<T>]) -> Result<Vec<Vec<f64>>> {
+        if self.calibrators.is_empty() {
+            return Err(Error::NotFitted);
+        }
+        Ok(self
+            .estimator
+            .predict_proba(x)?
+            .into_iter()
+            .map(|p| {
+                let calibrated: Vec<f64> = p
+                    .iter()
+                    .zip(&self.calibrators)
+                    .map(|(&pj, c)| c.transform(pj))
+                    .collect();
+                let total: f64 = calibrated.iter().sum();
+                if total > 0.0 {
+                    calibrated.iter().map(|c| c / total).collect()
+                } else {
+                    vec![1.0 / calibrated.len() as f64; calibrated.len()]
+                }
+            })
+            .collect())
+    }
+}
+
+impl<E, T> Predict<T, i32> for CalibratedClassifier<E>
+where
+    E: PredictProba<T>,
+{
+    /// Predict the class with the highest calibrated probability.
+    fn predict(&self, x: &[Vec<T>]) -> Result<Vec<i32>> {
+        Ok(self
+            .predict_proba(x)?
+            .iter()
+            .map(|p| (0..p.len()).fold(0, |best, j| if p[j] > p[best] { j } else { best }) as i32)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calibration_curve_test() {
+        let y_true = vec![false, true, false, true, true];
+        let y_prob = vec![0.1, 0.3, 0.6, 0.8, 1.0];
+        let (predicted, observed) = calibration_curve(&y_true, &y_prob, 2).unwrap();
+
+        assert!((predicted[0] - 0.2).abs() < 1e-12 && (predicted[1] - 0.8).abs() < 1e-12);
+        assert_eq!(observed, vec![0.5, 2.0 / 3.0]);
+        assert!(calibration_curve(&y_true, &y_prob, 0).is_err());
+        assert!(calibration_curve(&[true], &[1.5], 2).is_err());
+    }
+
+    #[test]
+    fn platt_scaling_test() {
+        let scores: Vec<f64> = (0..100).map(|i| i as f64 / 100.0).collect();
+        let y: Vec<bool> = (0..100).map(|i| (i * 7) % 100 < i).collect();
+        let platt = PlattScaling::fit(&scores, &y).unwrap();
+
+        assert!(platt.a > 0.0);
+        assert!(platt.transform(0.0) < 0.5 && platt.transform(1.0) > 0.5);
+        assert!(PlattScaling::fit(&[], &[]).is_err());
+    }
+
+    #[test]
+    fn isotonic_regression_test() {
+        let scores = vec![0.1, 0.2, 0.3, 0.4, 0.5, 0.5];
+        let y = vec![false, true, false, true, true, false];
+        let iso = IsotonicRegression::fit(&scores, &y).unwrap();
+
+        assert_eq!(iso.x, vec![0.1, 0.2, 0.3, 0.4, 0.5]);
+        assert_eq!(iso.y, vec![0.0, 0.5, 0.5, 2.0 / 3.0, 2.0 / 3.0]);
+        assert_eq!(iso.transform(0.0), 0.0);
+        assert!((iso.transform(0.15) - 0.25).abs() < 1e-12);
+        assert_eq!(iso.transform(0.9), 2.0 / 3.0);
+    }
+
+    #[cfg(feature = "datasets")]
+    #[test]
+    fn calibrated_knn_test() {
+        let iris = crate::datasets::load_iris();
+        let knn = crate::knn::KNN::unfitted(10, None, None);
+
+        for method in [Method::Sigmoid, Method::Isotonic] {
+            let mut model = CalibratedClassifier::new(knn.clone(), method).holdout(0.4);
+            model.fit(&iris.x, &iris.y).unwrap();
+            let proba = model.predict_proba(&iris.x).unwrap();
+            assert!(proba
+                .iter()
+                .all(|p| (p.iter().sum::<f64>() - 1.0).abs() < 1e-9));
+
+            let pred = model.predict(&iris.x).unwrap();
+            assert!(crate::metrics::accuracy(&iris.y, &pred).unwrap() > 0.9);
+
+            let summary = model.describe();
+            assert_eq!(summary.n_classes, Some(3));
+            assert_eq!(summary.parts[0].n_samples, Some(90));
+        }
+    }
+}
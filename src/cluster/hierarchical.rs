@@ -0,0 +1,417 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Agglomerative hierarchical clustering.
+
+use crate::describe::{Describe, Summary};
+use crate::error::{Error, Result};
+use crate::math::dense::DenseMatrix;
+use crate::math::distance::{self, Distance};
+use crate::math::Float;
+use crate::parallel::{self, Parallelism};
+use rayon::prelude::*;
+
+/// How the distance between two clusters is measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Linkage {
+    /// The distance between their closest samples.
+    Single,
+    /// The distance between their farthest samples.
+    Complete,
+    /// The mean distance between their samples.
+    Average,
+    /// The increase in the within-cluster sum of squares when they are merged. Only defined for
+    /// euclidean distance.
+    Ward,
+}
+
+/// The merge of two clusters. Clusters `0..n` are the `n` samples, and cluster `n + i` is the
+/// one formed by the `i`th merge.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Merge {
+    /// The smaller id of the merged clusters.
+    pub left: usize,
+    /// The larger id of the merged clusters.
+    pub right: usize,
+    /// The linkage distance between the merged clusters.
+    pub distance: f64,
+    /// The number of samples in the new cluster.
+    pub size: usize,
+}
+
+/// The merge tree of a hierarchical clustering, with the merges in increasing order of distance.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Dendrogram {
+    /// The number of clustered samples.
+    pub n_samples: usize,
+    /// The `n_samples - 1` merges, in order.
+    pub merges: Vec<Merge>,
+}
+
+impl Dendrogram {
+    /// The cluster of each sample after every merge at a distance of at most `threshold`.
+    /// Clusters are numbered in order of their first sample.
+    pub fn cut_distance(&self, threshold: f64) -> Vec<usize> {
+        let n_merges = self
+            .merges
+            .iter()
+            .take_while(|m| m.distance <= threshold)
+            .count();
+        self.labels(n_merges)
+    }
+
+    /// The cluster of each sample when the tree is cut into `n_clusters` clusters. Clusters are
+    /// numbered in order of their first sample.
+    /// Returns an error if `n_clusters` is not in `1..=n_samples`.
+    pub fn cut_clusters(&self, n_clusters: usize) -> Result<Vec<usize>> {
+        if n_clusters == 0 || n_clusters > self.n_samples {
+            return Err(Error::InvalidInput(format!(
+                "Can't cut {} samples into {} clusters.",
+                self.n_samples, n_clusters
+            )));
+        }
+        Ok(self.labels(self.n_samples - n_clusters))
+    }
+
+    /// The cluster of each sample after the first `n_merges` merges.
+    fn labels(&self, n_merges: usize) -> Vec<usize> {
+        // The cluster each sample or merged cluster was merged into.
+        let mut parent: Vec<usize> = (0..self.n_samples + n_merges).collect();
+        for (i, m) in self.merges[..n_merges].iter().enumerate() {
+            parent[m.left] = self.n_samples + i;
+            parent[m.right] = self.n_samples + i;
+        }
+        let mut ids = vec![usize::MAX; parent.len()];
+        let mut next = 0;
+        (0..self.n_samples)
+            .map(|i| {
+                let mut root = i;
+                while parent[root] != root {
+                    root = parent[root];
+                }
+                if ids[root] == usize::MAX {
+                    ids[root] = next;
+                    next += 1;
+                }
+                ids[root]
+            })
+            .collect()
+    }
+}
+
+/// Builds a [`Dendrogram`] bottom up, starting from one cluster per sample and repeatedly
+/// merging the two closest clusters under the linkage.
+///
+/// The clustering uses the nearest-neighbor chain algorithm, which takes `O(n²)` time and
+/// memory for `n` samples.
+///
+/// # Example
+/// ```rust
+/// use rml::cluster::{AgglomerativeClustering, Linkage};
+///
+/// let x = vec![vec![0.0], vec![1.0], vec![10.0], vec![12.0]];
+/// let mut clustering = AgglomerativeClustering::new(Linkage::Average);
+/// clustering.fit(&x).unwrap();
+///
+/// assert_eq!(clustering.dendrogram.cut_clusters(2).unwrap(), vec![0, 0, 1, 1]);
+/// assert_eq!(clustering.dendrogram.cut_distance(1.5), vec![0, 0, 1, 2]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct AgglomerativeClustering {
+    /// How the distance between two clusters is measured.
+    pub linkage: Linkage,
+    /// Type of distance between samples, euclidean by default.
+    pub distance: Option<Distance>,
+    /// The parallelism of distance computations, or None for the global setting.
+    pub parallelism: Option<Parallelism>,
+    /// The merge tree, once fit.
+    pub dendrogram: Dendrogram,
+}
+
+impl AgglomerativeClustering {
+    /// Create an unfitted AgglomerativeClustering with the given linkage.
+    pub fn new(linkage: Linkage) -> AgglomerativeClustering {
+        AgglomerativeClustering {
+            linkage,
+            distance: None,
+            parallelism: None,
+            dendrogram: Dendrogram::default(),
+        }
+    }
+
+    /// Set the distance between samples. With `Distance::Precomputed`, row `i` of the data is
+    /// the distances from sample `i` to every sample.
+    pub fn distance(mut self, distance: Distance) -> Self {
+        self.distance = Some(distance);
+        self
+    }
+
+    /// Set the parallelism of distance computations, overriding the global setting.
+    pub fn parallelism(mut self, parallelism: Parallelism) -> Self {
+        self.parallelism = Some(parallelism);
+        self
+    }
+
+    /// Build the merge tree of the samples `x`.
+    /// Returns an error if there are no samples, the samples have different dimensions, the
+    /// distance is invalid, a precomputed distance matrix is not square, or Ward linkage is used
+    /// with a distance other than euclidean.
+    pub fn fit<F: Float>(&mut self, x: &[Vec<F>]) -> Result<()> {
+        let x = DenseMatrix::from_rows(x)?;
+        let n = x.n_rows();
+        if n == 0 {
+            return Err(Error::InvalidInput("There are no samples.".into()));
+        }
+        let metric = self.distance.unwrap_or(Distance::Euclidean);
+        distance::validate(metric)?;
+        if self.linkage == Linkage::Ward && metric != Distance::Euclidean {
+            return Err(Error::InvalidInput(
+                "Ward linkage needs euclidean distance.".into(),
+            ));
+        }
+        if metric == Distance::Precomputed && x.n_cols() != n {
+            return Err(Error::ShapeMismatch(format!(
+                "A precomputed distance matrix of {} rows has {} columns.",
+                n,
+                x.n_cols()
+            )));
+        }
+
+        let distance_fn = |p: &[F], q: &[F]| match metric {
+            Distance::Manhattan => distance::manhattan_distance(p, q),
+            Distance::Minkowski(power) => distance::minkowski_distance(p, q, power),
+            Distance::Hamming => distance::hamming_distance(p, q),
+            Distance::Cosine => distance::cosine_distance(p, q),
+            _ => distance::euclidean_distance(p, q),
+        };
+        let mut d: Vec<Vec<f64>> = parallel::install(self.parallelism, || {
+            (0..n)
+                .into_par_iter()
+                .map(|i| match metric {
+                    Distance::Precomputed => x.row(i).iter().map(|v| v.to_f64()).collect(),
+                    _ => x
+                        .rows()
+                        .map(|q| distance_fn(x.row(i), q).to_f64())
+                        .collect(),
+                })
+                .collect()
+        });
+
+        let pairs = self.nn_chain(&mut d);
+        self.dendrogram = Dendrogram {
+            n_samples: n,
+            merges: build_merges(n, pairs),
+        };
+        Ok(())
+    }
+
+    /// Merge clusters by the nearest-neighbor chain algorithm over the distance matrix `d`,
+    /// which is updated in place. Each cluster lives in the slot of one of its samples. Returns
+    /// the merged slots and their distance, in the order merged.
+    fn nn_chain(&self, d: &mut [Vec<f64>]) -> Vec<(usize, usize, f64)> {
+        let n = d.len();
+        let mut active = vec![true; n];
+        let mut size = vec![1usize; n];
+        let mut chain: Vec<usize> = Vec::with_capacity(n);
+        let mut pairs = Vec::with_capacity(n.saturating_sub(1));
+        for _ in 1..n {
+            if chain.is_empty() {
+                chain.push(active.iter().position(|&a| a).unwrap());
+            }
+            let (a, b) = loop {
+                let a = chain[chain.len() - 1];
+                // Prefer the previous cluster of the chain on ties, so the chain always ends.
+                let previous = chain.len().checked_sub(2).map(|i| chain[i]);
+                let mut nearest = previous;
+                for k in (0..n).filter(|&k| active[k] && k != a) {
+                    if nearest.is_none_or(|b| d[a][k] < d[a][b]) {
+                        nearest = Some(k);
+                    }
+                }
+                let b = nearest.unwrap();
+                if Some(b) == previous {
+                    break (a, b);
+                }
+                chain.push(b);
+            };
+            chain.truncate(chain.len() - 2);
+            pairs.push((a, b, d[a][b]));
+
+            // Lance-Williams update of the distances to the merged cluster, kept in slot b.
+            let (na, nb) = (size[a] as f64, size[b] as f64);
+            let dab = d[a][b];
+            for k in (0..n).filter(|&k| active[k] && k != a && k != b) {
+                let (dak, dbk) = (d[a][k], d[b][k]);
+                let nk = size[k] as f64;
+                let merged = match self.linkage {
+                    Linkage::Single => dak.min(dbk),
+                    Linkage::Complete => dak.max(dbk),
+                    Linkage::Average => (na * dak + nb * dbk) / (na + nb),
+                    Linkage::Ward => (((na + nk) * dak * dak + (nb + nk) * dbk * dbk
+                        - nk * dab * dab)
+                        / (na + nb + nk))
+                        .max(0.0)
+                        .sqrt(),
+                };
+                d[b][k] = merged;
+                d[k][b] = merged;
+            }
+            active[a] = false;
+            size[b] += size[a];
+        }
+        pairs
+    }
+}
+
+/// Turn the merged slot pairs of [`AgglomerativeClustering::nn_chain`] into merges of cluster
+/// ids, in increasing order of distance.
+fn build_merges(n: usize, mut pairs: Vec<(usize, usize, f64)>) -> Vec<Merge> {
+    pairs.sort_by(|a, b| a.2.total_cmp(&b.2));
+    // Union-find over the slots, with the cluster id and size of each root.
+    let mut parent: Vec<usize> = (0..n).collect();
+    let mut id: Vec<usize> = (0..n).collect();
+    let mut size = vec![1usize; n];
+    let find = |parent: &mut Vec<usize>, mut i: usize| {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    };
+    pairs
+        .into_iter()
+        .enumerate()
+        .map(|(i, (a, b, distance))| {
+            let (ra, rb) = (find(&mut parent, a), find(&mut parent, b));
+            let merge = Merge {
+                left: id[ra].min(id[rb]),
+                right: id[ra].max(id[rb]),
+                distance,
+                size: size[ra] + size[rb],
+            };
+            parent[ra] = rb;
+            id[rb] = n + i;
+            size[rb] = merge.size;
+            merge
+        })
+        .collect()
+}
+
+impl Describe for AgglomerativeClustering {
+    fn describe(&self) -> Summary {
+        let mut summary = Summary::new("AgglomerativeClustering")
+            .param("linkage", self.linkage)
+            .param("distance", self.distance.unwrap_or(Distance::Euclidean))
+            .param("parallelism", self.parallelism);
+        if self.dendrogram.n_samples > 0 {
+            summary.n_samples = Some(self.dendrogram.n_samples);
+        }
+        summary.memory = self.dendrogram.merges.capacity() * std::mem::size_of::<Merge>();
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linkage_test() {
+        let x = vec![vec![0.0], vec![1.0], vec![5.0]];
+        let merges = |linkage| {
+            let mut clustering = AgglomerativeClustering::new(linkage);
+            clustering.fit(&x).unwrap();
+            clustering.dendrogram.merges
+        };
+        let first = Merge {
+            left: 0,
+            right: 1,
+            distance: 1.0,
+            size: 2,
+        };
+        let second = |distance| Merge {
+            left: 2,
+            right: 3,
+            distance,
+            size: 3,
+        };
+
+        assert_eq!(merges(Linkage::Single), vec![first, second(4.0)]);
+        assert_eq!(merges(Linkage::Complete), vec![first, second(5.0)]);
+        assert_eq!(merges(Linkage::Average), vec![first, second(4.5)]);
+        let ward = merges(Linkage::Ward);
+        assert_eq!(ward[0], first);
+        // sqrt(2 * 2 * 1 / 3) times the distance between the centroids 0.5 and 5.
+        assert!((ward[1].distance - (4.0f64 / 3.0).sqrt() * 4.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn cut_test() {
+        let x = vec![
+            vec![0.0, 0.0],
+            vec![10.0, 0.0],
+            vec![0.0, 1.0],
+            vec![10.0, 2.0],
+            vec![30.0, 0.0],
+        ];
+        let mut clustering = AgglomerativeClustering::new(Linkage::Single);
+        clustering.fit(&x).unwrap();
+        let dendrogram = &clustering.dendrogram;
+
+        assert_eq!(dendrogram.merges.len(), 4);
+        assert!(dendrogram
+            .merges
+            .windows(2)
+            .all(|w| w[0].distance <= w[1].distance));
+        assert_eq!(dendrogram.merges[3].size, 5);
+        assert_eq!(dendrogram.cut_clusters(3).unwrap(), vec![0, 1, 0, 1, 2]);
+        assert_eq!(dendrogram.cut_clusters(5).unwrap(), vec![0, 1, 2, 3, 4]);
+        assert_eq!(dendrogram.cut_clusters(1).unwrap(), vec![0; 5]);
+        assert_eq!(dendrogram.cut_distance(1.0), vec![0, 1, 0, 2, 3]);
+        assert_eq!(dendrogram.cut_distance(0.5), vec![0, 1, 2, 3, 4]);
+        assert!(dendrogram.cut_clusters(0).is_err());
+        assert!(dendrogram.cut_clusters(6).is_err());
+    }
+
+    #[test]
+    fn precomputed_test() {
+        let x = vec![vec![0.0], vec![1.0], vec![10.0], vec![12.0]];
+        let d: Vec<Vec<f64>> = x
+            .iter()
+            .map(|p| x.iter().map(|q| (p[0] - q[0]).abs()).collect())
+            .collect();
+        let mut direct = AgglomerativeClustering::new(Linkage::Complete);
+        direct.fit(&x).unwrap();
+        let mut precomputed =
+            AgglomerativeClustering::new(Linkage::Complete).distance(Distance::Precomputed);
+        precomputed.fit(&d).unwrap();
+
+        assert_eq!(direct.dendrogram, precomputed.dendrogram);
+        assert!(precomputed.fit(&x).is_err());
+        assert!(AgglomerativeClustering::new(Linkage::Single)
+            .distance(Distance::Minkowski(0.5))
+            .fit(&x)
+            .is_err());
+        assert!(AgglomerativeClustering::new(Linkage::Ward)
+            .distance(Distance::Manhattan)
+            .fit(&x)
+            .is_err());
+        assert!(AgglomerativeClustering::new(Linkage::Single)
+            .fit::<f64>(&[])
+            .is_err());
+    }
+}
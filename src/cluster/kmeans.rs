@@ -0,0 +1,478 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! K-means clustering.
+
+use crate::describe::{Describe, Summary};
+use crate::error::{Error, Result};
+use crate::math::dense::DenseMatrix;
+use crate::math::distance::{self, Distance};
+use crate::math::random::Rng;
+use crate::math::Float;
+use crate::parallel::{self, Parallelism};
+use rayon::prelude::*;
+
+/// How the initial centroids are chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Init {
+    /// Each centroid is a sample chosen with probability proportional to its squared distance
+    /// to the nearest centroid already chosen, which spreads the centroids out.
+    KMeansPlusPlus,
+    /// The centroids are distinct samples chosen uniformly at random.
+    Random,
+}
+
+/// Groups samples into `n_clusters` clusters by Lloyd's algorithm: each sample is assigned to
+/// its nearest centroid, and each centroid is moved to the center of its samples, until the
+/// centroids stop moving.
+///
+/// The center of a cluster is the mean of its samples, except with Manhattan distance, where it
+/// is the per-feature median (k-medians). The initial centroids are distinct samples chosen by
+/// k-means++ by default, and the fit is repeated `n_init` times, keeping the run with the
+/// lowest inertia.
+#[derive(Debug, Clone)]
+pub struct KMeans<F = f64> {
+    /// The number of clusters.
+    pub n_clusters: usize,
+    /// The maximum number of iterations.
+    pub max_iter: usize,
+    /// The fit stops once the centroids move less than this in total, measured as the sum of
+    /// their squared euclidean shifts.
+    pub tol: f64,
+    /// Type of distance to use, euclidean by default.
+    pub distance: Option<Distance>,
+    /// How the initial centroids are chosen.
+    pub init: Init,
+    /// The number of runs with different initial centroids.
+    pub n_init: usize,
+    /// The seed used to choose the initial centroids, or None for the global seed.
+    pub seed: Option<u64>,
+    /// The parallelism of distance computations, or None for the global setting.
+    pub parallelism: Option<Parallelism>,
+    /// The centroids, one row per cluster, once fit.
+    pub centroids: DenseMatrix<F>,
+    /// The sum of the squared distances from each training sample to its centroid, once fit.
+    pub inertia: f64,
+    /// The number of iterations the last fit ran.
+    pub n_iter: usize,
+}
+
+impl<F: Float> KMeans<F> {
+    /// Create an unfitted KMeans with `n_clusters` clusters, k-means++ initialization, a single
+    /// run of at most 300 iterations and a tolerance of `1e-4`.
+    pub fn new(n_clusters: usize) -> KMeans<F> {
+        KMeans {
+            n_clusters,
+            max_iter: 300,
+            tol: 1e-4,
+            distance: None,
+            init: Init::KMeansPlusPlus,
+            n_init: 1,
+            seed: None,
+            parallelism: None,
+            centroids: DenseMatrix::new(0),
+            inertia: 0.0,
+            n_iter: 0,
+        }
+    }
+
+    /// Set the maximum number of iterations.
+    pub fn max_iter(mut self, max_iter: usize) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    /// Set the tolerance on the movement of the centroids.
+    pub fn tol(mut self, tol: f64) -> Self {
+        self.tol = tol;
+        self
+    }
+
+    /// Set the distance between samples and centroids.
+    pub fn distance(mut self, distance: Distance) -> Self {
+        self.distance = Some(distance);
+        self
+    }
+
+    /// Set how the initial centroids are chosen.
+    pub fn init(mut self, init: Init) -> Self {
+        self.init = init;
+        self
+    }
+
+    /// Set the number of runs with different initial centroids. The run with the lowest
+    /// inertia is kept.
+    pub fn n_init(mut self, n_init: usize) -> Self {
+        self.n_init = n_init;
+        self
+    }
+
+    /// The seed used to choose the initial centroids.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Set the parallelism of distance computations, overriding the global setting.
+    pub fn parallelism(mut self, parallelism: Parallelism) -> Self {
+        self.parallelism = Some(parallelism);
+        self
+    }
+
+    /// The heap memory of the centroids, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.centroids.memory_usage()
+    }
+
+    /// Find `n_clusters` clusters in `x`.
+    /// Returns an error if the samples have different dimensions, there are fewer samples than
+    /// clusters, there are no clusters or runs, or the distance is precomputed or invalid.
+    pub fn fit(&mut self, x: &[Vec<F>]) -> Result<()> {
+        let x = DenseMatrix::from_rows(x)?;
+        if self.n_clusters == 0 || self.n_clusters > x.n_rows() {
+            return Err(Error::InvalidInput(format!(
+                "There are {} clusters but {} samples.",
+                self.n_clusters,
+                x.n_rows()
+            )));
+        }
+        if self.n_init == 0 {
+            return Err(Error::InvalidInput("There must be at least 1 run.".into()));
+        }
+        if self.distance == Some(Distance::Precomputed) {
+            return Err(Error::InvalidInput(
+                "KMeans can't cluster precomputed distances.".into(),
+            ));
+        }
+        if let Some(metric) = self.distance {
+            distance::validate(metric)?;
+        }
+
+        let mut rng = Rng::from_seed(self.seed);
+        let mut best: Option<(DenseMatrix<F>, f64, usize)> = None;
+        for _ in 0..self.n_init {
+            self.centroids = self.initial_centroids(&x, &mut rng)?;
+            let mut n_iter = 0;
+            let mut assignments = self.assign(&x);
+            while n_iter < self.max_iter {
+                n_iter += 1;
+                let shift = self.update_centroids(&x, &assignments);
+                assignments = self.assign(&x);
+                if shift <= self.tol {
+                    break;
+                }
+            }
+            let inertia: f64 = assignments.iter().map(|&(_, d)| d.to_f64().powi(2)).sum();
+            if best.as_ref().is_none_or(|&(_, lowest, _)| inertia < lowest) {
+                best = Some((self.centroids.clone(), inertia, n_iter));
+            }
+        }
+        if let Some((centroids, inertia, n_iter)) = best {
+            self.centroids = centroids;
+            self.inertia = inertia;
+            self.n_iter = n_iter;
+        }
+        Ok(())
+    }
+
+    /// The index of the nearest centroid to each sample of `x`.
+    /// Returns an error if the KMeans is not fit or a sample has the wrong number of features.
+    pub fn predict(&self, x: &[Vec<F>]) -> Result<Vec<usize>> {
+        if self.centroids.is_empty() {
+            return Err(Error::NotFitted);
+        }
+        let x = DenseMatrix::from_rows(x)?;
+        if !x.is_empty() && x.n_cols() != self.centroids.n_cols() {
+            return Err(Error::ShapeMismatch(format!(
+                "Expected {} features but a sample has {}.",
+                self.centroids.n_cols(),
+                x.n_cols()
+            )));
+        }
+        Ok(self.assign(&x).into_iter().map(|(c, _)| c).collect())
+    }
+
+    /// Fit to `x`, then return the cluster of each of its samples.
+    /// Returns an error under the same conditions as [`KMeans::fit`].
+    pub fn fit_predict(&mut self, x: &[Vec<F>]) -> Result<Vec<usize>> {
+        self.fit(x)?;
+        self.predict(x)
+    }
+
+    /// Choose `n_clusters` distinct rows of `x` as centroids.
+    pub(super) fn initial_centroids(
+        &self,
+        x: &DenseMatrix<F>,
+        rng: &mut Rng,
+    ) -> Result<DenseMatrix<F>> {
+        let mut chosen = match self.init {
+            Init::Random => {
+                let mut order: Vec<usize> = (0..x.n_rows()).collect();
+                rng.shuffle(&mut order);
+                order.truncate(self.n_clusters);
+                order
+            }
+            Init::KMeansPlusPlus => {
+                let distance_fn = self.distance_fn();
+                let mut chosen = vec![rng.below(x.n_rows())];
+                let mut nearest: Vec<f64> = vec![f64::INFINITY; x.n_rows()];
+                while chosen.len() < self.n_clusters {
+                    let last = chosen[chosen.len() - 1];
+                    for (i, d) in nearest.iter_mut().enumerate() {
+                        *d = d.min(distance_fn(x.row(i), x.row(last)).to_f64().powi(2));
+                    }
+                    // Rounding can leave a chosen sample a tiny distance from itself.
+                    nearest[last] = 0.0;
+                    let total: f64 = nearest.iter().sum();
+                    // Every sample duplicates a centroid, so any sample not yet chosen will do.
+                    let next = match total > 0.0 {
+                        true => {
+                            let mut target = rng.next_f64() * total;
+                            nearest
+                                .iter()
+                                .position(|&d| {
+                                    target -= d;
+                                    d > 0.0 && target < 0.0
+                                })
+                                .unwrap_or_else(|| nearest.iter().rposition(|&d| d > 0.0).unwrap())
+                        }
+                        false => (0..x.n_rows()).find(|i| !chosen.contains(i)).unwrap(),
+                    };
+                    chosen.push(next);
+                }
+                chosen
+            }
+        };
+        chosen.sort_unstable();
+        let mut centroids = DenseMatrix::new(x.n_cols());
+        for i in chosen {
+            centroids.push_row(x.row(i))?;
+        }
+        Ok(centroids)
+    }
+
+    /// The nearest centroid to each row of `x` and the distance to it.
+    fn assign(&self, x: &DenseMatrix<F>) -> Vec<(usize, F)> {
+        let distance_fn = self.distance_fn();
+        parallel::install(self.parallelism, || {
+            x.par_rows()
+                .map(|row| nearest_centroid(&self.centroids, row, distance_fn))
+                .collect()
+        })
+    }
+
+    /// Move each centroid to the center of its samples and return the sum of the squared
+    /// euclidean shifts. A cluster that lost all its samples takes the sample farthest from
+    /// its centroid instead.
+    fn update_centroids(&mut self, x: &DenseMatrix<F>, assignments: &[(usize, F)]) -> f64 {
+        let mut members: Vec<Vec<usize>> = vec![Vec::new(); self.n_clusters];
+        for (i, &(c, _)) in assignments.iter().enumerate() {
+            members[c].push(i);
+        }
+        let mut farthest: Vec<usize> = (0..assignments.len()).collect();
+        farthest.sort_unstable_by(|&a, &b| assignments[b].1.total_cmp(&assignments[a].1));
+        let mut farthest = farthest.into_iter();
+
+        let mut shift = 0.0;
+        for (c, samples) in members.iter().enumerate() {
+            let center: Vec<F> = match samples.is_empty() {
+                true => match farthest.next() {
+                    Some(i) => x.row(i).to_vec(),
+                    None => continue,
+                },
+                false => (0..x.n_cols())
+                    .map(|j| self.center(samples.iter().map(|&i| x.row(i)[j]).collect()))
+                    .collect(),
+            };
+            let centroid = self.centroids.row_mut(c);
+            shift += distance::euclidean_distance(centroid, &center)
+                .to_f64()
+                .powi(2);
+            centroid.copy_from_slice(&center);
+        }
+        shift
+    }
+
+    /// The center of the values of one feature in a cluster.
+    fn center(&self, mut values: Vec<F>) -> F {
+        match self.distance {
+            Some(Distance::Manhattan) => {
+                values.sort_unstable_by(F::total_cmp);
+                let mid = values.len() / 2;
+                match values.len() % 2 {
+                    0 => (values[mid - 1] + values[mid]) / F::from_f64(2.0),
+                    _ => values[mid],
+                }
+            }
+            _ => {
+                let n = F::from_f64(values.len() as f64);
+                values.into_iter().sum::<F>() / n
+            }
+        }
+    }
+
+    /// The configured distance function.
+    pub(super) fn distance_fn(&self) -> impl Fn(&[F], &[F]) -> F + Copy + Send + Sync {
+        let metric = self.distance;
+        move |p: &[F], q: &[F]| match metric {
+            Some(Distance::Manhattan) => distance::manhattan_distance(p, q),
+            Some(Distance::Minkowski(power)) => distance::minkowski_distance(p, q, power),
+            Some(Distance::Hamming) => distance::hamming_distance(p, q),
+            Some(Distance::Cosine) => distance::cosine_distance(p, q),
+            _ => distance::euclidean_distance(p, q),
+        }
+    }
+}
+
+impl<F: Float> Describe for KMeans<F> {
+    fn describe(&self) -> Summary {
+        let mut summary = Summary::new("KMeans")
+            .param("n_clusters", self.n_clusters)
+            .param("max_iter", self.max_iter)
+            .param("tol", self.tol)
+            .param("distance", self.distance.unwrap_or(Distance::Euclidean))
+            .param("init", self.init)
+            .param("n_init", self.n_init)
+            .param("seed", self.seed)
+            .param("parallelism", self.parallelism);
+        if !self.centroids.is_empty() {
+            summary.n_features = Some(self.centroids.n_cols());
+        }
+        summary.memory = self.memory_usage();
+        summary
+    }
+}
+
+/// The nearest of `centroids` to `row` and the distance to it.
+pub(super) fn nearest_centroid<F: Float>(
+    centroids: &DenseMatrix<F>,
+    row: &[F],
+    distance_fn: impl Fn(&[F], &[F]) -> F,
+) -> (usize, F) {
+    centroids
+        .rows()
+        .map(|c| distance_fn(row, c))
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or((0, F::zero()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Three well separated blobs of 10 points each.
+    fn blobs() -> Vec<Vec<f64>> {
+        let mut rng = Rng::new(5);
+        [(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)]
+            .iter()
+            .flat_map(|&(cx, cy)| {
+                (0..10)
+                    .map(|_| vec![cx + rng.next_f64(), cy + rng.next_f64()])
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn kmeans_test() {
+        let x = blobs();
+        let mut kmeans = KMeans::new(3);
+        let labels = kmeans.fit_predict(&x).unwrap();
+
+        for blob in labels.chunks(10) {
+            assert!(blob.iter().all(|&l| l == blob[0]));
+        }
+        assert_ne!(labels[0], labels[10]);
+        assert_ne!(labels[0], labels[20]);
+        assert_ne!(labels[10], labels[20]);
+        assert!(kmeans.inertia < 30.0 * 0.5);
+        assert!(kmeans.n_iter >= 1);
+        assert_eq!(kmeans.predict(&[vec![10.2, 0.3]]).unwrap()[0], labels[10]);
+        assert_eq!(kmeans.describe().n_features, Some(2));
+    }
+
+    #[test]
+    fn init_test() {
+        let x = blobs();
+        for init in [Init::KMeansPlusPlus, Init::Random].iter() {
+            let fit = || {
+                let mut kmeans = KMeans::new(3).init(*init).n_init(5).seed(7);
+                kmeans.fit(&x).unwrap();
+                kmeans
+            };
+            let (a, b) = (fit(), fit());
+            assert_eq!(a.centroids, b.centroids);
+            assert_eq!(a.inertia, b.inertia);
+            assert!(a.inertia < 30.0 * 0.5);
+        }
+
+        // With as many clusters as samples, k-means++ picks every sample once.
+        let mut kmeans = KMeans::new(30).seed(1);
+        kmeans.fit(&x).unwrap();
+        assert_eq!(kmeans.inertia, 0.0);
+        assert!(KMeans::new(2).n_init(0).fit(&x).is_err());
+    }
+
+    #[test]
+    fn manhattan_test() {
+        let x = vec![
+            vec![0.0],
+            vec![1.0],
+            vec![2.0],
+            vec![50.0],
+            vec![51.0],
+            vec![100.0],
+        ];
+        let mut kmeans = KMeans::new(2).distance(Distance::Manhattan);
+        let labels = kmeans.fit_predict(&x).unwrap();
+
+        assert_eq!(labels[0], labels[2]);
+        assert_eq!(labels[3], labels[5]);
+        let mut centers = kmeans.centroids.to_rows();
+        centers.sort_by(|a, b| a[0].total_cmp(&b[0]));
+        assert_eq!(centers, vec![vec![1.0], vec![51.0]]);
+    }
+
+    #[test]
+    fn validation_test() {
+        let x = vec![vec![0.0], vec![1.0]];
+        assert!(KMeans::new(3).fit(&x).is_err());
+        assert!(KMeans::new(0).fit(&x).is_err());
+        assert!(KMeans::new(1)
+            .distance(Distance::Minkowski(0.5))
+            .fit(&x)
+            .is_err());
+        assert!(KMeans::new(1).fit(&[vec![0.0], vec![1.0, 2.0]]).is_err());
+        assert!(KMeans::new(1)
+            .distance(Distance::Precomputed)
+            .fit(&x)
+            .is_err());
+        assert!(matches!(
+            KMeans::<f64>::new(1).predict(&x),
+            Err(Error::NotFitted)
+        ));
+
+        let mut kmeans = KMeans::new(2);
+        kmeans.fit(&x).unwrap();
+        assert!(matches!(
+            kmeans.predict(&[vec![0.0, 1.0]]),
+            Err(Error::ShapeMismatch(_))
+        ));
+        assert_eq!(kmeans.inertia, 0.0);
+    }
+}
@@ -0,0 +1,362 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! K-medoids clustering.
+
+use crate::describe::{Describe, Summary};
+use crate::error::{check_lengths, Error, Result};
+use crate::math::dense::DenseMatrix;
+use crate::math::distance::{self, Distance};
+use crate::math::Float;
+use crate::parallel::{self, Parallelism};
+use rayon::prelude::*;
+
+/// Groups samples into `n_clusters` clusters, each represented by one of its samples, the
+/// medoid, by Partitioning Around Medoids (PAM).
+///
+/// The medoids are chosen greedily one at a time to reduce the total distance from each sample
+/// to its nearest medoid the most, then medoids are swapped with other samples while any swap
+/// reduces it. Since the clusters minimize plain rather than squared distances and centers are
+/// actual samples, outliers pull on them far less than on KMeans centroids. Any distance works,
+/// including `Distance::Precomputed`, where row `i` of the data is the distances from sample
+/// `i` to every training sample.
+///
+/// The full distance matrix is kept during the fit, which takes `O(n²)` memory for `n` samples.
+///
+/// # Example
+/// ```rust
+/// use rml::cluster::KMedoids;
+/// use rml::math::distance::Distance;
+///
+/// let x = vec![vec![0.0], vec![1.0], vec![2.0], vec![10.0], vec![11.0], vec![12.0]];
+/// let mut kmedoids: KMedoids = KMedoids::new(2).distance(Distance::Manhattan);
+/// let labels = kmedoids.fit_predict(&x).unwrap();
+///
+/// assert_eq!(kmedoids.medoid_indices, vec![1, 4]);
+/// assert_eq!(labels, vec![0, 0, 0, 1, 1, 1]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct KMedoids<F = f64> {
+    /// The number of clusters.
+    pub n_clusters: usize,
+    /// The maximum number of swaps.
+    pub max_iter: usize,
+    /// Type of distance to use, euclidean by default.
+    pub distance: Option<Distance>,
+    /// The parallelism of distance computations, or None for the global setting.
+    pub parallelism: Option<Parallelism>,
+    /// The index of each medoid in the training data, once fit.
+    pub medoid_indices: Vec<usize>,
+    /// The medoids, one row per cluster, once fit. With precomputed distances these are the
+    /// rows of the training distance matrix.
+    pub medoids: DenseMatrix<F>,
+    /// The sum of the distances from each training sample to its medoid, once fit.
+    pub inertia: f64,
+    /// The number of swaps the last fit made.
+    pub n_iter: usize,
+}
+
+impl<F: Float> KMedoids<F> {
+    /// Create an unfitted KMedoids with `n_clusters` clusters and at most 300 swaps.
+    pub fn new(n_clusters: usize) -> KMedoids<F> {
+        KMedoids {
+            n_clusters,
+            max_iter: 300,
+            distance: None,
+            parallelism: None,
+            medoid_indices: Vec::new(),
+            medoids: DenseMatrix::new(0),
+            inertia: 0.0,
+            n_iter: 0,
+        }
+    }
+
+    /// Set the maximum number of swaps.
+    pub fn max_iter(mut self, max_iter: usize) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    /// Set the distance between samples.
+    pub fn distance(mut self, distance: Distance) -> Self {
+        self.distance = Some(distance);
+        self
+    }
+
+    /// Set the parallelism of distance computations, overriding the global setting.
+    pub fn parallelism(mut self, parallelism: Parallelism) -> Self {
+        self.parallelism = Some(parallelism);
+        self
+    }
+
+    /// The heap memory of the medoids, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.medoids.memory_usage() + self.medoid_indices.capacity() * std::mem::size_of::<usize>()
+    }
+
+    /// Find `n_clusters` clusters in `x`.
+    /// Returns an error if the samples have different dimensions, there are fewer samples than
+    /// clusters, there are no clusters, the distance is invalid, or a precomputed distance matrix
+    /// is not square.
+    pub fn fit(&mut self, x: &[Vec<F>]) -> Result<()> {
+        let x = DenseMatrix::from_rows(x)?;
+        let n = x.n_rows();
+        if self.n_clusters == 0 || self.n_clusters > n {
+            return Err(Error::InvalidInput(format!(
+                "There are {} clusters but {} samples.",
+                self.n_clusters, n
+            )));
+        }
+        if let Some(metric) = self.distance {
+            distance::validate(metric)?;
+        }
+        let precomputed = self.distance == Some(Distance::Precomputed);
+        if precomputed && x.n_cols() != n {
+            return Err(Error::ShapeMismatch(format!(
+                "A precomputed distance matrix of {} rows has {} columns.",
+                n,
+                x.n_cols()
+            )));
+        }
+        let distance_fn = self.distance_fn();
+        let d: Vec<Vec<f64>> = parallel::install(self.parallelism, || {
+            (0..n)
+                .into_par_iter()
+                .map(|i| match precomputed {
+                    true => x.row(i).iter().map(|v| v.to_f64()).collect(),
+                    false => x
+                        .rows()
+                        .map(|q| distance_fn(x.row(i), q).to_f64())
+                        .collect(),
+                })
+                .collect()
+        });
+
+        let mut medoids = build(&d, self.n_clusters);
+        self.n_iter = 0;
+        while self.n_iter < self.max_iter {
+            match best_swap(&d, &medoids) {
+                Some((slot, sample)) => medoids[slot] = sample,
+                None => break,
+            }
+            self.n_iter += 1;
+        }
+
+        self.inertia = (0..n)
+            .map(|j| {
+                medoids
+                    .iter()
+                    .map(|&m| d[j][m])
+                    .fold(f64::INFINITY, f64::min)
+            })
+            .sum();
+        self.medoids = DenseMatrix::new(x.n_cols());
+        for &m in &medoids {
+            self.medoids.push_row(x.row(m))?;
+        }
+        self.medoid_indices = medoids;
+        Ok(())
+    }
+
+    /// The index of the nearest medoid to each sample of `x`. With precomputed distances each
+    /// sample is its distances to every training sample.
+    /// Returns an error if the KMedoids is not fit or a sample has the wrong number of features.
+    pub fn predict(&self, x: &[Vec<F>]) -> Result<Vec<usize>> {
+        if self.medoid_indices.is_empty() {
+            return Err(Error::NotFitted);
+        }
+        for row in x {
+            check_lengths("Features and medoids", row.len(), self.medoids.n_cols())?;
+        }
+        let distance_fn = self.distance_fn();
+        let precomputed = self.distance == Some(Distance::Precomputed);
+        Ok(parallel::install(self.parallelism, || {
+            x.par_iter()
+                .map(|row| {
+                    let distances: Vec<F> = match precomputed {
+                        true => self.medoid_indices.iter().map(|&m| row[m]).collect(),
+                        false => self.medoids.rows().map(|m| distance_fn(row, m)).collect(),
+                    };
+                    (0..distances.len())
+                        .min_by(|&a, &b| distances[a].total_cmp(&distances[b]))
+                        .unwrap_or(0)
+                })
+                .collect()
+        }))
+    }
+
+    /// Fit to `x`, then return the cluster of each of its samples.
+    /// Returns an error under the same conditions as [`KMedoids::fit`].
+    pub fn fit_predict(&mut self, x: &[Vec<F>]) -> Result<Vec<usize>> {
+        self.fit(x)?;
+        self.predict(x)
+    }
+
+    /// The configured distance function.
+    fn distance_fn(&self) -> impl Fn(&[F], &[F]) -> F + Copy + Send + Sync {
+        let metric = self.distance;
+        move |p: &[F], q: &[F]| match metric {
+            Some(Distance::Manhattan) => distance::manhattan_distance(p, q),
+            Some(Distance::Minkowski(power)) => distance::minkowski_distance(p, q, power),
+            Some(Distance::Hamming) => distance::hamming_distance(p, q),
+            Some(Distance::Cosine) => distance::cosine_distance(p, q),
+            _ => distance::euclidean_distance(p, q),
+        }
+    }
+}
+
+/// The greedy initial medoids of PAM: each is the sample that most reduces the total distance
+/// from every sample to its nearest medoid so far.
+fn build(d: &[Vec<f64>], k: usize) -> Vec<usize> {
+    let n = d.len();
+    let mut nearest = vec![f64::INFINITY; n];
+    let mut medoids = Vec::with_capacity(k);
+    for _ in 0..k {
+        let cost = |c: usize| (0..n).map(|j| nearest[j].min(d[j][c])).sum::<f64>();
+        let best = (0..n)
+            .filter(|c| !medoids.contains(c))
+            .map(|c| (c, cost(c)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(c, _)| c)
+            .unwrap_or(0);
+        for (j, near) in nearest.iter_mut().enumerate() {
+            *near = near.min(d[j][best]);
+        }
+        medoids.push(best);
+    }
+    medoids
+}
+
+/// The swap of a medoid, by its slot, with a non-medoid sample that most reduces the total
+/// distance from every sample to its nearest medoid, or None if no swap reduces it.
+fn best_swap(d: &[Vec<f64>], medoids: &[usize]) -> Option<(usize, usize)> {
+    let n = d.len();
+    // The slot of the nearest medoid of each sample, the distance to it and to the second
+    // nearest medoid.
+    let closest: Vec<(usize, f64, f64)> = (0..n)
+        .map(|j| {
+            let (mut slot, mut first, mut second) = (0, f64::INFINITY, f64::INFINITY);
+            for (s, &m) in medoids.iter().enumerate() {
+                if d[j][m] < first {
+                    second = first;
+                    first = d[j][m];
+                    slot = s;
+                } else if d[j][m] < second {
+                    second = d[j][m];
+                }
+            }
+            (slot, first, second)
+        })
+        .collect();
+
+    let mut best: Option<(usize, usize, f64)> = None;
+    for candidate in (0..n).filter(|c| !medoids.contains(c)) {
+        for slot in 0..medoids.len() {
+            let change: f64 = closest
+                .iter()
+                .enumerate()
+                .map(|(j, &(nearest, first, second))| match nearest == slot {
+                    true => d[j][candidate].min(second) - first,
+                    false => (d[j][candidate] - first).min(0.0),
+                })
+                .sum();
+            if change < -1e-12 && best.is_none_or(|(_, _, b)| change < b) {
+                best = Some((slot, candidate, change));
+            }
+        }
+    }
+    best.map(|(slot, candidate, _)| (slot, candidate))
+}
+
+impl<F: Float> Describe for KMedoids<F> {
+    fn describe(&self) -> Summary {
+        let mut summary = Summary::new("KMedoids")
+            .param("n_clusters", self.n_clusters)
+            .param("max_iter", self.max_iter)
+            .param("distance", self.distance.unwrap_or(Distance::Euclidean))
+            .param("parallelism", self.parallelism);
+        if !self.medoids.is_empty() {
+            summary.n_features = Some(self.medoids.n_cols());
+        }
+        summary.memory = self.memory_usage();
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kmedoids_test() {
+        // Two tight groups and an outlier, which does not move the medoid of its cluster.
+        let mut x: Vec<Vec<f64>> = (0..5).map(|i| vec![i as f64 * 0.1, 0.0]).collect();
+        x.extend((0..5).map(|i| vec![10.0 + i as f64 * 0.1, 0.0]));
+        x.push(vec![30.0, 0.0]);
+        let mut kmedoids = KMedoids::new(2);
+        let labels = kmedoids.fit_predict(&x).unwrap();
+
+        let mut medoids = kmedoids.medoid_indices.clone();
+        medoids.sort_unstable();
+        assert_eq!(medoids, vec![2, 7]);
+        assert!(labels[..5].iter().all(|&l| l == labels[0]));
+        assert!(labels[5..].iter().all(|&l| l == labels[5]));
+        assert_ne!(labels[0], labels[5]);
+        assert!((kmedoids.inertia - (0.6 + 0.6 + 19.8)).abs() < 1e-9);
+        assert_eq!(kmedoids.medoids.n_rows(), 2);
+    }
+
+    #[test]
+    fn swap_test() {
+        // The greedy start picks the middle of all points first; swapping fixes it.
+        let x = vec![vec![0.0], vec![1.0], vec![5.0], vec![9.0], vec![10.0]];
+        let mut kmedoids = KMedoids::new(2).distance(Distance::Manhattan);
+        kmedoids.fit(&x).unwrap();
+        let mut medoids = kmedoids.medoid_indices.clone();
+        medoids.sort_unstable();
+
+        assert_eq!(kmedoids.inertia, 6.0);
+        assert!(medoids[0] <= 1 && medoids[1] >= 3);
+    }
+
+    #[test]
+    fn precomputed_test() {
+        let x = vec![vec![0.0], vec![1.0], vec![10.0], vec![12.0]];
+        let d: Vec<Vec<f64>> = x
+            .iter()
+            .map(|p| x.iter().map(|q| (p[0] - q[0]).abs()).collect())
+            .collect();
+        let mut direct = KMedoids::new(2).distance(Distance::Manhattan);
+        let mut precomputed = KMedoids::new(2).distance(Distance::Precomputed);
+
+        assert_eq!(
+            direct.fit_predict(&x).unwrap(),
+            precomputed.fit_predict(&d).unwrap()
+        );
+        assert_eq!(direct.medoid_indices, precomputed.medoid_indices);
+        assert!(precomputed.fit(&x).is_err());
+        assert!(KMedoids::new(5).fit(&x).is_err());
+        assert!(KMedoids::new(2)
+            .distance(Distance::Minkowski(0.0))
+            .fit(&x)
+            .is_err());
+        assert!(matches!(
+            KMedoids::<f64>::new(1).predict(&x),
+            Err(Error::NotFitted)
+        ));
+    }
+}
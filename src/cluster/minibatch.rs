@@ -0,0 +1,428 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Mini-batch k-means clustering.
+
+use super::kmeans::{self, Init, KMeans};
+use crate::describe::{Describe, Summary};
+use crate::error::{Error, Result};
+use crate::math::dense::DenseMatrix;
+use crate::math::distance::{self, Distance};
+use crate::math::random::Rng;
+use crate::math::Float;
+use crate::parallel::{self, Parallelism};
+use rayon::prelude::*;
+
+/// K-means fit on small random batches of samples instead of the whole data set on every
+/// iteration, trading a little inertia for much less time on large data sets.
+///
+/// Each iteration assigns a batch to the nearest centroids and moves each centroid towards its
+/// samples with a step of one over the number of samples it has been assigned so far, so every
+/// centroid is the running mean of its samples. The samples are never copied, and only a sample
+/// of `3 * batch_size` of them is used to choose the initial centroids.
+///
+/// # Example
+/// ```rust
+/// use rml::cluster::MiniBatchKMeans;
+///
+/// let x: Vec<Vec<f64>> = (0..100)
+///     .map(|i| vec![(i % 2 * 10) as f64 + (i % 5) as f64 * 0.1])
+///     .collect();
+/// let mut kmeans = MiniBatchKMeans::new(2).batch_size(20).seed(1);
+/// let labels = kmeans.fit_predict(&x).unwrap();
+///
+/// assert_eq!(labels[0], labels[2]);
+/// assert_ne!(labels[0], labels[1]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MiniBatchKMeans<F = f64> {
+    /// The number of clusters.
+    pub n_clusters: usize,
+    /// The number of samples in each batch.
+    pub batch_size: usize,
+    /// The number of batches a fit runs.
+    pub max_iter: usize,
+    /// The fit stops once a batch moves the centroids less than this in total, measured as the
+    /// sum of their squared euclidean shifts. 0 runs every batch.
+    pub tol: f64,
+    /// Type of distance to use, euclidean by default.
+    pub distance: Option<Distance>,
+    /// How the initial centroids are chosen.
+    pub init: Init,
+    /// The seed used to choose the initial centroids and the batches, or None for the global
+    /// seed.
+    pub seed: Option<u64>,
+    /// The parallelism of distance computations, or None for the global setting.
+    pub parallelism: Option<Parallelism>,
+    /// The centroids, one row per cluster, once fit.
+    pub centroids: DenseMatrix<F>,
+    /// The number of samples assigned to each centroid so far.
+    pub counts: Vec<usize>,
+    /// The sum of the squared distances from each training sample to its centroid, once fit.
+    pub inertia: f64,
+    /// The number of batches the last fit ran.
+    pub n_iter: usize,
+}
+
+impl<F: Float> MiniBatchKMeans<F> {
+    /// Create an unfitted MiniBatchKMeans with `n_clusters` clusters, k-means++ initialization
+    /// and 100 batches of 1024 samples.
+    pub fn new(n_clusters: usize) -> MiniBatchKMeans<F> {
+        MiniBatchKMeans {
+            n_clusters,
+            batch_size: 1024,
+            max_iter: 100,
+            tol: 0.0,
+            distance: None,
+            init: Init::KMeansPlusPlus,
+            seed: None,
+            parallelism: None,
+            centroids: DenseMatrix::new(0),
+            counts: Vec::new(),
+            inertia: 0.0,
+            n_iter: 0,
+        }
+    }
+
+    /// Set the number of samples in each batch.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Set the number of batches a fit runs.
+    pub fn max_iter(mut self, max_iter: usize) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    /// Set the tolerance on the movement of the centroids.
+    pub fn tol(mut self, tol: f64) -> Self {
+        self.tol = tol;
+        self
+    }
+
+    /// Set the distance between samples and centroids.
+    pub fn distance(mut self, distance: Distance) -> Self {
+        self.distance = Some(distance);
+        self
+    }
+
+    /// Set how the initial centroids are chosen.
+    pub fn init(mut self, init: Init) -> Self {
+        self.init = init;
+        self
+    }
+
+    /// The seed used to choose the initial centroids and the batches.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Set the parallelism of distance computations, overriding the global setting.
+    pub fn parallelism(mut self, parallelism: Parallelism) -> Self {
+        self.parallelism = Some(parallelism);
+        self
+    }
+
+    /// The heap memory of the centroids and counts, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.centroids.memory_usage() + self.counts.capacity() * std::mem::size_of::<usize>()
+    }
+
+    /// Find `n_clusters` clusters in `x`, replacing anything learned before.
+    /// Returns an error if the samples have different dimensions, there are fewer samples than
+    /// clusters, there are no clusters, the batches are empty, or the distance is precomputed or
+    /// invalid.
+    pub fn fit(&mut self, x: &[Vec<F>]) -> Result<()> {
+        self.check(x)?;
+        let mut rng = Rng::from_seed(self.seed);
+        self.initialize(x, &mut rng)?;
+
+        self.n_iter = 0;
+        let mut batch = Vec::with_capacity(self.batch_size);
+        while self.n_iter < self.max_iter {
+            self.n_iter += 1;
+            batch.clear();
+            batch.extend((0..self.batch_size).map(|_| &x[rng.below(x.len())]));
+            if self.update(&batch) <= self.tol {
+                break;
+            }
+        }
+        self.inertia = self
+            .assign(x)
+            .iter()
+            .map(|&(_, d)| d.to_f64().powi(2))
+            .sum();
+        Ok(())
+    }
+
+    /// Move the centroids towards the samples in `x`, keeping what was learned before. An
+    /// unfitted MiniBatchKMeans first chooses its initial centroids from `x`. The inertia is
+    /// that of `x`.
+    /// Returns an error under the same conditions as [`MiniBatchKMeans::fit`], or if `x` has
+    /// the wrong number of features.
+    pub fn partial_fit(&mut self, x: &[Vec<F>]) -> Result<()> {
+        if self.centroids.is_empty() {
+            self.check(x)?;
+            self.initialize(x, &mut Rng::from_seed(self.seed))?;
+            self.n_iter = 0;
+        } else {
+            self.check_features(x)?;
+        }
+        let batch: Vec<&Vec<F>> = x.iter().collect();
+        self.update(&batch);
+        self.n_iter += 1;
+        self.inertia = self
+            .assign(x)
+            .iter()
+            .map(|&(_, d)| d.to_f64().powi(2))
+            .sum();
+        Ok(())
+    }
+
+    /// The index of the nearest centroid to each sample of `x`.
+    /// Returns an error if the MiniBatchKMeans is not fit or a sample has the wrong number of
+    /// features.
+    pub fn predict(&self, x: &[Vec<F>]) -> Result<Vec<usize>> {
+        if self.centroids.is_empty() {
+            return Err(Error::NotFitted);
+        }
+        self.check_features(x)?;
+        Ok(self.assign(x).into_iter().map(|(c, _)| c).collect())
+    }
+
+    /// Fit to `x`, then return the cluster of each of its samples.
+    /// Returns an error under the same conditions as [`MiniBatchKMeans::fit`].
+    pub fn fit_predict(&mut self, x: &[Vec<F>]) -> Result<Vec<usize>> {
+        self.fit(x)?;
+        self.predict(x)
+    }
+
+    /// Check the settings against the training samples `x`.
+    fn check(&self, x: &[Vec<F>]) -> Result<()> {
+        if let Some(row) = x.iter().find(|row| row.len() != x[0].len()) {
+            return Err(Error::ShapeMismatch(format!(
+                "Expected {} features but a sample has {}.",
+                x[0].len(),
+                row.len()
+            )));
+        }
+        if self.n_clusters == 0 || self.n_clusters > x.len() {
+            return Err(Error::InvalidInput(format!(
+                "There are {} clusters but {} samples.",
+                self.n_clusters,
+                x.len()
+            )));
+        }
+        if self.batch_size == 0 {
+            return Err(Error::InvalidInput(
+                "The batch size must be positive.".into(),
+            ));
+        }
+        if self.distance == Some(Distance::Precomputed) {
+            return Err(Error::InvalidInput(
+                "MiniBatchKMeans can't cluster precomputed distances.".into(),
+            ));
+        }
+        if let Some(metric) = self.distance {
+            distance::validate(metric)?;
+        }
+        Ok(())
+    }
+
+    /// Check that every sample of `x` has as many features as the centroids.
+    fn check_features(&self, x: &[Vec<F>]) -> Result<()> {
+        match x.iter().find(|row| row.len() != self.centroids.n_cols()) {
+            Some(row) => Err(Error::ShapeMismatch(format!(
+                "Expected {} features but a sample has {}.",
+                self.centroids.n_cols(),
+                row.len()
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// The KMeans with the same clusters, initialization and distance.
+    fn kmeans(&self) -> KMeans<F> {
+        let kmeans = KMeans::new(self.n_clusters).init(self.init);
+        match self.distance {
+            Some(distance) => kmeans.distance(distance),
+            None => kmeans,
+        }
+    }
+
+    /// Choose the initial centroids from a random sample of `x`.
+    fn initialize(&mut self, x: &[Vec<F>], rng: &mut Rng) -> Result<()> {
+        let size = (3 * self.batch_size).max(self.n_clusters).min(x.len());
+        let mut order: Vec<usize> = (0..x.len()).collect();
+        rng.shuffle(&mut order);
+        let mut sample = DenseMatrix::new(x[0].len());
+        for &i in &order[..size] {
+            sample.push_row(&x[i])?;
+        }
+        self.centroids = self.kmeans().initial_centroids(&sample, rng)?;
+        self.counts = vec![0; self.n_clusters];
+        Ok(())
+    }
+
+    /// Move the centroids towards the samples of `batch` and return the sum of the squared
+    /// euclidean shifts.
+    fn update(&mut self, batch: &[&Vec<F>]) -> f64 {
+        let distance_fn = self.kmeans().distance_fn();
+        let centroids = &self.centroids;
+        let assignments: Vec<usize> = parallel::install(self.parallelism, || {
+            batch
+                .par_iter()
+                .map(|row| kmeans::nearest_centroid(centroids, row, distance_fn).0)
+                .collect()
+        });
+
+        let before = self.centroids.clone();
+        for (row, c) in batch.iter().zip(assignments) {
+            self.counts[c] += 1;
+            let step = F::from_f64(1.0 / self.counts[c] as f64);
+            for (v, &xi) in self.centroids.row_mut(c).iter_mut().zip(row.iter()) {
+                *v += (xi - *v) * step;
+            }
+        }
+        before
+            .rows()
+            .zip(self.centroids.rows())
+            .map(|(a, b)| {
+                a.iter()
+                    .zip(b)
+                    .map(|(&p, &q)| (p - q).to_f64().powi(2))
+                    .sum::<f64>()
+            })
+            .sum()
+    }
+
+    /// The nearest centroid to each sample of `x` and the distance to it.
+    fn assign(&self, x: &[Vec<F>]) -> Vec<(usize, F)> {
+        let distance_fn = self.kmeans().distance_fn();
+        parallel::install(self.parallelism, || {
+            x.par_iter()
+                .map(|row| kmeans::nearest_centroid(&self.centroids, row, distance_fn))
+                .collect()
+        })
+    }
+}
+
+impl<F: Float> Describe for MiniBatchKMeans<F> {
+    fn describe(&self) -> Summary {
+        let mut summary = Summary::new("MiniBatchKMeans")
+            .param("n_clusters", self.n_clusters)
+            .param("batch_size", self.batch_size)
+            .param("max_iter", self.max_iter)
+            .param("tol", self.tol)
+            .param("distance", self.distance.unwrap_or(Distance::Euclidean))
+            .param("init", self.init)
+            .param("seed", self.seed)
+            .param("parallelism", self.parallelism);
+        if !self.centroids.is_empty() {
+            summary.n_features = Some(self.centroids.n_cols());
+        }
+        summary.memory = self.memory_usage();
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Three well separated blobs of 100 points each.
+    fn blobs() -> Vec<Vec<f64>> {
+        let mut rng = Rng::new(9);
+        [(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)]
+            .iter()
+            .flat_map(|&(cx, cy)| {
+                (0..100)
+                    .map(|_| vec![cx + rng.next_f64(), cy + rng.next_f64()])
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn minibatch_test() {
+        let x = blobs();
+        let mut kmeans = MiniBatchKMeans::new(3).batch_size(30).max_iter(50).seed(2);
+        let labels = kmeans.fit_predict(&x).unwrap();
+
+        for blob in labels.chunks(100) {
+            assert!(blob.iter().all(|&l| l == blob[0]));
+        }
+        assert_ne!(labels[0], labels[100]);
+        assert_ne!(labels[0], labels[200]);
+        assert_ne!(labels[100], labels[200]);
+        assert!(kmeans.inertia < 300.0 * 0.5);
+        assert_eq!(kmeans.n_iter, 50);
+        assert_eq!(kmeans.counts.iter().sum::<usize>(), 50 * 30);
+
+        let mut full = KMeans::new(3).seed(2);
+        full.fit(&x).unwrap();
+        assert!(kmeans.inertia < full.inertia * 1.1);
+    }
+
+    #[test]
+    fn partial_fit_test() {
+        let x = blobs();
+        let mut rng = Rng::new(4);
+        let mut order: Vec<usize> = (0..x.len()).collect();
+        rng.shuffle(&mut order);
+        let shuffled: Vec<Vec<f64>> = order.iter().map(|&i| x[i].clone()).collect();
+
+        let mut kmeans = MiniBatchKMeans::new(3).seed(3);
+        for batch in shuffled.chunks(50) {
+            kmeans.partial_fit(batch).unwrap();
+        }
+        let labels = kmeans.predict(&x).unwrap();
+        for blob in labels.chunks(100) {
+            assert!(blob.iter().all(|&l| l == blob[0]));
+        }
+        assert_eq!(kmeans.n_iter, 6);
+        assert_eq!(kmeans.counts.iter().sum::<usize>(), 300);
+    }
+
+    #[test]
+    fn validation_test() {
+        let x = vec![vec![0.0], vec![1.0]];
+        assert!(MiniBatchKMeans::new(3).fit(&x).is_err());
+        assert!(MiniBatchKMeans::new(1).batch_size(0).fit(&x).is_err());
+        assert!(MiniBatchKMeans::new(1)
+            .distance(Distance::Minkowski(f64::NAN))
+            .fit(&x)
+            .is_err());
+        assert!(MiniBatchKMeans::new(1)
+            .fit(&[vec![0.0], vec![1.0, 2.0]])
+            .is_err());
+        assert!(matches!(
+            MiniBatchKMeans::<f64>::new(1).predict(&x),
+            Err(Error::NotFitted)
+        ));
+
+        let mut kmeans = MiniBatchKMeans::new(2);
+        kmeans.fit(&x).unwrap();
+        assert!(matches!(
+            kmeans.partial_fit(&[vec![0.0, 1.0]]),
+            Err(Error::ShapeMismatch(_))
+        ));
+    }
+}
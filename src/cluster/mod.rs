@@ -0,0 +1,48 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Clustering of unlabeled data.
+
+/*!
+`KMeans` groups samples into a fixed number of clusters, each represented by its centroid.
+`MiniBatchKMeans` fits the same model on small random batches, for data sets too large for
+`KMeans`. `KMedoids` represents each cluster by one of its samples, works with any distance and
+resists outliers. `AgglomerativeClustering` builds a tree of merges that can be cut into any number of
+clusters.
+
+# Example
+```rust
+use rml::cluster::KMeans;
+
+let x = vec![vec![0.0, 0.0], vec![0.5, 0.0], vec![10.0, 10.0], vec![10.5, 10.0]];
+let mut kmeans: KMeans = KMeans::new(2);
+let labels = kmeans.fit_predict(&x).unwrap();
+
+assert_eq!(labels[0], labels[1]);
+assert_ne!(labels[0], labels[2]);
+```
+!*/
+
+mod hierarchical;
+mod kmeans;
+mod kmedoids;
+mod minibatch;
+
+pub use hierarchical::{AgglomerativeClustering, Dendrogram, Linkage, Merge};
+pub use kmeans::{Init, KMeans};
+pub use kmedoids::KMedoids;
+pub use minibatch::MiniBatchKMeans;
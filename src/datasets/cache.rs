@@ -0,0 +1,325 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Binary caching of datasets.
+
+/*!
+Parsing a large CSV file on every run is slow. `Dataset::save_cache` writes a dataset in a compact
+little-endian binary format that `Dataset::load_cache` reads back much faster.
+
+The cache starts with an `artifact::Header` recording the feature and label types, so loading it
+as a different type is an error rather than garbage. Caches written with a different format
+version must be created again.
+
+# Example
+```rust,no_run
+use rml::datasets::Dataset;
+use rml::preprocessing::csv::{
+    parse_csv_with_labels, ColumnSelection, CsvOptions, LabelColumn, MissingPolicy,
+};
+
+let cache = "./data/optdigits.tra.cache";
+let data: Dataset<f64, i32> = match Dataset::load_cache(cache) {
+    Ok(data) => data,
+    Err(_) => {
+        let data = parse_csv_with_labels(
+            "./data/optdigits.tra",
+            &CsvOptions::new(),
+            &LabelColumn::Last,
+            &ColumnSelection::All,
+            &MissingPolicy::Error,
+        )
+        .unwrap();
+        data.save_cache(cache).unwrap();
+        data
+    }
+};
+```
+!*/
+
+use super::Dataset;
+use crate::artifact::{self, Header};
+use crate::error::{Error, Result};
+use crate::math::dense::DenseMatrix;
+#[cfg(feature = "fs")]
+use std::fs::File;
+#[cfg(feature = "fs")]
+use std::io::{BufReader, BufWriter};
+use std::io::{Read, Write};
+
+/// A value that can be stored in a dataset cache.
+pub trait CacheElement: Sized {
+    /// A name for the type, stored in the cache to detect type mismatches.
+    fn type_name() -> String;
+
+    /// Write the value.
+    fn write_to<W: Write>(&self, w: &mut W) -> std::io::Result<()>;
+
+    /// Read a value written by `write_to`.
+    fn read_from<R: Read>(r: &mut R) -> Result<Self>;
+}
+
+macro_rules! impl_cache_element {
+    ($($t:ty),*) => {
+        $(
+            impl CacheElement for $t {
+                fn type_name() -> String {
+                    String::from(stringify!($t))
+                }
+
+                fn write_to<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+                    w.write_all(&self.to_le_bytes())
+                }
+
+                fn read_from<R: Read>(r: &mut R) -> Result<Self> {
+                    let mut bytes = [0; std::mem::size_of::<$t>()];
+                    r.read_exact(&mut bytes)?;
+                    Ok(<$t>::from_le_bytes(bytes))
+                }
+            }
+        )*
+    };
+}
+
+impl_cache_element!(f32, f64, i8, i16, i32, i64, u8, u16, u32, u64);
+
+impl CacheElement for usize {
+    fn type_name() -> String {
+        String::from("usize")
+    }
+
+    fn write_to<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        (*self as u64).write_to(w)
+    }
+
+    fn read_from<R: Read>(r: &mut R) -> Result<Self> {
+        Ok(u64::read_from(r)? as usize)
+    }
+}
+
+impl CacheElement for bool {
+    fn type_name() -> String {
+        String::from("bool")
+    }
+
+    fn write_to<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        (*self as u8).write_to(w)
+    }
+
+    fn read_from<R: Read>(r: &mut R) -> Result<Self> {
+        Ok(u8::read_from(r)? != 0)
+    }
+}
+
+impl CacheElement for String {
+    fn type_name() -> String {
+        String::from("String")
+    }
+
+    fn write_to<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        self.len().write_to(w)?;
+        w.write_all(self.as_bytes())
+    }
+
+    fn read_from<R: Read>(r: &mut R) -> Result<Self> {
+        let len = usize::read_from(r)?;
+        let mut bytes = Vec::new();
+        r.take(len as u64).read_to_end(&mut bytes)?;
+        if bytes.len() != len {
+            return Err(Error::Parse("Unexpected end of cache.".into()));
+        }
+        Ok(String::from_utf8(bytes)?)
+    }
+}
+
+impl<E: CacheElement> CacheElement for Vec<E> {
+    fn type_name() -> String {
+        format!("Vec<{}>", E::type_name())
+    }
+
+    fn write_to<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        self.len().write_to(w)?;
+        self.iter().try_for_each(|e| e.write_to(w))
+    }
+
+    fn read_from<R: Read>(r: &mut R) -> Result<Self> {
+        let len = usize::read_from(r)?;
+        // Don't trust the length for the allocation, in case the file is corrupt.
+        let mut values = Vec::with_capacity(len.min(1 << 16));
+        for _ in 0..len {
+            values.push(E::read_from(r)?);
+        }
+        Ok(values)
+    }
+}
+
+/// Written in the same layout as a `Vec<Vec<E>>` of its rows.
+impl<E: CacheElement + Clone> CacheElement for DenseMatrix<E> {
+    fn type_name() -> String {
+        format!("DenseMatrix<{}>", E::type_name())
+    }
+
+    fn write_to<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        self.n_rows().write_to(w)?;
+        self.rows().try_for_each(|row| {
+            row.len().write_to(w)?;
+            row.iter().try_for_each(|e| e.write_to(w))
+        })
+    }
+
+    fn read_from<R: Read>(r: &mut R) -> Result<Self> {
+        let n_rows = usize::read_from(r)?;
+        let mut m = DenseMatrix::new(0);
+        for _ in 0..n_rows {
+            m.push_row(&Vec::<E>::read_from(r)?)?;
+        }
+        Ok(m)
+    }
+}
+
+impl<E: CacheElement> CacheElement for Option<E> {
+    fn type_name() -> String {
+        format!("Option<{}>", E::type_name())
+    }
+
+    fn write_to<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        self.is_some().write_to(w)?;
+        match self {
+            Some(e) => e.write_to(w),
+            None => Ok(()),
+        }
+    }
+
+    fn read_from<R: Read>(r: &mut R) -> Result<Self> {
+        if bool::read_from(r)? {
+            Ok(Some(E::read_from(r)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<T: CacheElement, U: CacheElement> Dataset<T, U> {
+    /// Write the dataset to a binary cache file at `path`.
+    #[cfg(feature = "fs")]
+    pub fn save_cache(&self, path: &str) -> Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        self.write_cache(&mut w)?;
+        w.flush()?;
+        Ok(())
+    }
+
+    /// Read a dataset from a cache file written by [`Dataset::save_cache`].
+    /// Returns an error if the file is not a cache, uses a different format version, holds
+    /// different feature or label types, or is corrupt.
+    #[cfg(feature = "fs")]
+    pub fn load_cache(path: &str) -> Result<Dataset<T, U>> {
+        Dataset::read_cache(&mut BufReader::new(File::open(path)?))
+    }
+
+    /// Write the dataset in the cache format.
+    pub fn write_cache<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        Header::new(Self::cache_kind(), self.n_features(), self.fingerprint()).write_to(w)?;
+        self.x.write_to(w)?;
+        self.y.write_to(w)?;
+        self.feature_names.write_to(w)?;
+        self.label_names.write_to(w)
+    }
+
+    /// Read a dataset in the cache format.
+    pub fn read_cache<R: Read>(r: &mut R) -> Result<Dataset<T, U>> {
+        let header = artifact::read_header(r)?;
+        header.check_kind(&Self::cache_kind())?;
+
+        let x: Vec<Vec<T>> = CacheElement::read_from(r)?;
+        let y: Vec<U> = CacheElement::read_from(r)?;
+        let mut data = Dataset::new(x, y)?;
+        data.feature_names = CacheElement::read_from(r)?;
+        data.label_names = CacheElement::read_from(r)?;
+        header.check_fingerprint(data.fingerprint())?;
+        if let Some(row) = data.x.iter().find(|row| row.len() != header.n_features) {
+            header.check_n_features(row.len())?;
+        }
+        Ok(data)
+    }
+
+    /// The artifact kind of a cache of this type.
+    fn cache_kind() -> String {
+        format!("Dataset<{}, {}>", T::type_name(), U::type_name())
+    }
+
+    /// The fingerprint of the shape and names of the dataset.
+    fn fingerprint(&self) -> u64 {
+        artifact::fingerprint(&format!(
+            "n_samples={} n_features={} feature_names={:?} label_names={:?}",
+            self.n_samples(),
+            self.n_features(),
+            self.feature_names,
+            self.label_names
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn cache_round_trip_test() {
+        let data = Dataset::new(vec![vec![1.5, -2.0], vec![0.0, 3.25]], vec![0, 1])
+            .unwrap()
+            .with_feature_names(vec![String::from("a"), String::from("b")]);
+        let path = std::env::temp_dir().join("rml_cache_round_trip.bin");
+        let path = path.to_str().unwrap();
+        data.save_cache(path).unwrap();
+
+        assert_eq!(Dataset::<f64, i32>::load_cache(path).unwrap(), data);
+        assert!(Dataset::<f32, i32>::load_cache(path).is_err());
+    }
+
+    #[test]
+    fn cache_string_labels_test() {
+        let data = Dataset::new(vec![vec![1u8]], vec![vec![String::from("x")]])
+            .unwrap()
+            .with_label_names(vec![String::from("x")]);
+        let mut bytes = Vec::new();
+        data.write_cache(&mut bytes).unwrap();
+
+        assert_eq!(Dataset::read_cache(&mut &bytes[..]).unwrap(), data);
+        assert!(Dataset::<u8, Vec<String>>::read_cache(&mut &bytes[..10]).is_err());
+        assert!(Dataset::<u8, Vec<String>>::read_cache(&mut &b"not a cache"[..]).is_err());
+    }
+
+    #[test]
+    fn cache_validation_test() {
+        let data = Dataset::new(vec![vec![1i32, 2]], vec![0i32]).unwrap();
+        let mut bytes = Vec::new();
+        data.write_cache(&mut bytes).unwrap();
+
+        let error = Dataset::<f32, i32>::read_cache(&mut &bytes[..]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Parse error: The file holds a Dataset<i32, i32>, not a Dataset<f32, i32>."
+        );
+        // Give the dataset an empty list of label names it was not saved with.
+        let mut corrupt = bytes.clone();
+        *corrupt.last_mut().unwrap() = 1;
+        corrupt.extend_from_slice(&0u64.to_le_bytes());
+        assert!(Dataset::<i32, i32>::read_cache(&mut &corrupt[..]).is_err());
+    }
+}
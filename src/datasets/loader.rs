@@ -0,0 +1,287 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Mini-batch iteration over datasets.
+
+/*!
+`DataLoader` splits an in-memory `Dataset` into mini-batches, optionally shuffled with a fixed
+seed. `StreamingLoader` does the same for a stream of chunks, such as the one returned by
+`preprocessing::csv::read_chunks`, shuffling within each chunk.
+
+# Example
+```rust
+use rml::datasets::loader::DataLoader;
+use rml::datasets::Dataset;
+
+let data = Dataset::new((0..10).map(|i| vec![i as f64]).collect(), vec![0; 10]).unwrap();
+let loader = DataLoader::new(&data, 4).unwrap().shuffle(42).drop_last(true);
+
+for epoch in 0..3 {
+    for batch in loader.batches(epoch) {
+        assert_eq!(batch.n_samples(), 4);
+    }
+}
+```
+!*/
+
+use super::Dataset;
+use crate::error::{self, Error};
+use crate::math::random::Rng;
+
+/// Yields mini-batches of a dataset.
+#[derive(Debug, Clone)]
+pub struct DataLoader<'a, T, U> {
+    data: &'a Dataset<T, U>,
+    batch_size: usize,
+    seed: Option<u64>,
+    drop_last: bool,
+}
+
+impl<'a, T: Clone, U: Clone> DataLoader<'a, T, U> {
+    /// Create a new loader yielding batches of `batch_size` samples in order.
+    /// Returns an error if `batch_size` is 0.
+    pub fn new(data: &'a Dataset<T, U>, batch_size: usize) -> error::Result<DataLoader<'a, T, U>> {
+        check_batch_size(batch_size)?;
+        Ok(DataLoader {
+            data,
+            batch_size,
+            seed: None,
+            drop_last: false,
+        })
+    }
+
+    /// Shuffle the samples before batching. Each epoch uses a different order derived from `seed`.
+    pub fn shuffle(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Whether to drop the last batch if it has fewer than `batch_size` samples.
+    pub fn drop_last(mut self, drop_last: bool) -> Self {
+        self.drop_last = drop_last;
+        self
+    }
+
+    /// The number of batches per epoch.
+    pub fn len(&self) -> usize {
+        let n = self.data.n_samples();
+        if self.drop_last {
+            n / self.batch_size
+        } else {
+            n.div_ceil(self.batch_size)
+        }
+    }
+
+    /// Whether an epoch has no batches.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate over the batches of the given epoch.
+    pub fn batches(&self, epoch: u64) -> Batches<'a, T, U> {
+        let mut order: Vec<usize> = (0..self.data.n_samples()).collect();
+        if let Some(seed) = self.seed {
+            Rng::new(seed ^ epoch.wrapping_mul(0x9E37_79B9_7F4A_7C15)).shuffle(&mut order);
+        }
+        if self.drop_last {
+            order.truncate(self.len() * self.batch_size);
+        }
+        Batches {
+            data: self.data,
+            order,
+            batch_size: self.batch_size,
+            position: 0,
+        }
+    }
+}
+
+/// An iterator over the batches of one epoch, created by [`DataLoader::batches`].
+#[derive(Debug, Clone)]
+pub struct Batches<'a, T, U> {
+    data: &'a Dataset<T, U>,
+    order: Vec<usize>,
+    batch_size: usize,
+    position: usize,
+}
+
+impl<'a, T: Clone, U: Clone> Iterator for Batches<'a, T, U> {
+    type Item = Dataset<T, U>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position >= self.order.len() {
+            return None;
+        }
+        let end = (self.position + self.batch_size).min(self.order.len());
+        let batch = take_rows(self.data, &self.order[self.position..end]);
+        self.position = end;
+        Some(batch)
+    }
+}
+
+/// Yields mini-batches from a stream of dataset chunks.
+///
+/// Samples are shuffled within each chunk (not across chunks) and batches may span chunk
+/// boundaries. Errors from the stream are passed through.
+pub struct StreamingLoader<I, T, U> {
+    chunks: I,
+    buffer: Dataset<T, U>,
+    batch_size: usize,
+    rng: Option<Rng>,
+    drop_last: bool,
+}
+
+impl<I, T, U, E> StreamingLoader<I, T, U>
+where
+    I: Iterator<Item = Result<Dataset<T, U>, E>>,
+    T: Clone,
+    U: Clone,
+{
+    /// Create a new loader yielding batches of `batch_size` samples in stream order.
+    /// Returns an error if `batch_size` is 0.
+    pub fn new(chunks: I, batch_size: usize) -> error::Result<StreamingLoader<I, T, U>> {
+        check_batch_size(batch_size)?;
+        Ok(StreamingLoader {
+            chunks,
+            buffer: Dataset::default(),
+            batch_size,
+            rng: None,
+            drop_last: false,
+        })
+    }
+
+    /// Shuffle the samples of each chunk with a generator seeded by `seed`.
+    pub fn shuffle(mut self, seed: u64) -> Self {
+        self.rng = Some(Rng::new(seed));
+        self
+    }
+
+    /// Whether to drop the last batch if it has fewer than `batch_size` samples.
+    pub fn drop_last(mut self, drop_last: bool) -> Self {
+        self.drop_last = drop_last;
+        self
+    }
+}
+
+impl<I, T, U, E> Iterator for StreamingLoader<I, T, U>
+where
+    I: Iterator<Item = Result<Dataset<T, U>, E>>,
+    T: Clone,
+    U: Clone,
+{
+    type Item = Result<Dataset<T, U>, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.buffer.n_samples() < self.batch_size {
+            match self.chunks.next() {
+                Some(Ok(chunk)) => {
+                    let mut order: Vec<usize> = (0..chunk.n_samples()).collect();
+                    if let Some(rng) = &mut self.rng {
+                        rng.shuffle(&mut order);
+                    }
+                    self.buffer.extend(take_rows(&chunk, &order));
+                }
+                Some(Err(e)) => return Some(Err(e)),
+                None => break,
+            }
+        }
+
+        let n = self.buffer.n_samples();
+        if n == 0 || (n < self.batch_size && self.drop_last) {
+            return None;
+        }
+        let rest = Dataset {
+            x: self.buffer.x.split_off(n.min(self.batch_size)),
+            y: self.buffer.y.split_off(n.min(self.batch_size)),
+            ..Dataset::default()
+        };
+        Some(Ok(std::mem::replace(&mut self.buffer, rest)))
+    }
+}
+
+/// Copy the given rows of a dataset, keeping its names.
+fn take_rows<T: Clone, U: Clone>(data: &Dataset<T, U>, rows: &[usize]) -> Dataset<T, U> {
+    Dataset {
+        x: rows.iter().map(|&i| data.x[i].clone()).collect(),
+        y: rows.iter().map(|&i| data.y[i].clone()).collect(),
+        feature_names: data.feature_names.clone(),
+        label_names: data.label_names.clone(),
+    }
+}
+
+/// Check that batches have at least one sample.
+fn check_batch_size(batch_size: usize) -> error::Result<()> {
+    match batch_size {
+        0 => Err(Error::InvalidInput(
+            "Batch size must be greater than 0.".into(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbered(n: usize) -> Dataset<f64, usize> {
+        Dataset::new((0..n).map(|i| vec![i as f64]).collect(), (0..n).collect()).unwrap()
+    }
+
+    #[test]
+    fn batches_test() {
+        let data = numbered(10);
+        let loader = DataLoader::new(&data, 4).unwrap();
+        let batches: Vec<Dataset<f64, usize>> = loader.batches(0).collect();
+
+        assert_eq!(loader.len(), 3);
+        assert_eq!(batches[0].y, vec![0, 1, 2, 3]);
+        assert_eq!(batches[2].y, vec![8, 9]);
+        assert_eq!(loader.drop_last(true).batches(0).count(), 2);
+        assert!(matches!(
+            DataLoader::new(&data, 0),
+            Err(Error::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn shuffle_test() {
+        let data = numbered(10);
+        let loader = DataLoader::new(&data, 10).unwrap().shuffle(3);
+        let first: Vec<usize> = loader.batches(0).next().unwrap().y;
+
+        assert_eq!(first, loader.batches(0).next().unwrap().y);
+        assert_ne!(first, loader.batches(1).next().unwrap().y);
+        assert_ne!(first, (0..10).collect::<Vec<_>>());
+        let mut sorted = first.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn streaming_test() {
+        let chunks = vec![numbered(3), numbered(4)].into_iter().map(Ok::<_, ()>);
+        let batches: Vec<Dataset<f64, usize>> = StreamingLoader::new(chunks, 2)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        let sizes: Vec<usize> = batches.iter().map(|b| b.n_samples()).collect();
+        assert_eq!(sizes, vec![2, 2, 2, 1]);
+        assert_eq!(batches[1].y, vec![2, 0]);
+        let chunks = vec![numbered(3)].into_iter().map(Ok::<_, ()>);
+        assert!(StreamingLoader::new(chunks, 0).is_err());
+    }
+}
@@ -0,0 +1,174 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Datasets and the `Dataset` type shared by the loaders and estimators.
+
+/*!
+A `Dataset` keeps the feature matrix and labels together with their names, so the metadata
+read by a loader is not lost on the way to a model.
+
+With the `datasets` feature (enabled by default), small classic datasets are embedded in the
+library and available through `load_iris` and `load_digits`.
+
+# Example
+```rust
+use rml::datasets::Dataset;
+
+let data = Dataset::new(vec![vec![1.0, 2.0], vec![3.0, 4.0]], vec![0, 1])
+    .unwrap()
+    .with_feature_names(vec![String::from("width"), String::from("height")]);
+
+assert_eq!(data.n_samples(), 2);
+assert_eq!(data.n_features(), 2);
+```
+!*/
+
+use crate::describe::{rows_memory, strings_memory};
+use crate::error::{check_lengths, Result};
+
+pub mod cache;
+pub mod loader;
+
+#[cfg(feature = "datasets")]
+mod toy;
+#[cfg(feature = "datasets")]
+pub use toy::{load_digits, load_iris};
+
+/// Features, labels and their optional names.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dataset<T = f64, U = i32> {
+    /// Features
+    pub x: Vec<Vec<T>>,
+    /// Class labels for each feature.
+    pub y: Vec<U>,
+    /// The name of each feature column, if known.
+    pub feature_names: Option<Vec<String>>,
+    /// The name of each class, indexed by the class label, if known.
+    pub label_names: Option<Vec<String>>,
+}
+
+impl<T, U> Default for Dataset<T, U> {
+    fn default() -> Self {
+        Dataset {
+            x: Vec::new(),
+            y: Vec::new(),
+            feature_names: None,
+            label_names: None,
+        }
+    }
+}
+
+impl<T, U> Dataset<T, U> {
+    /// Create a new dataset without names. `x` and `y` must have the same length.
+    pub fn new(x: Vec<Vec<T>>, y: Vec<U>) -> Result<Dataset<T, U>> {
+        check_lengths("Features and labels", x.len(), y.len())?;
+        Ok(Dataset {
+            x,
+            y,
+            feature_names: None,
+            label_names: None,
+        })
+    }
+
+    /// Set the feature names.
+    pub fn with_feature_names(mut self, names: Vec<String>) -> Self {
+        self.feature_names = Some(names);
+        self
+    }
+
+    /// Set the label names.
+    pub fn with_label_names(mut self, names: Vec<String>) -> Self {
+        self.label_names = Some(names);
+        self
+    }
+
+    /// The number of samples.
+    pub fn n_samples(&self) -> usize {
+        self.x.len()
+    }
+
+    /// The number of features, taken from the first sample.
+    pub fn n_features(&self) -> usize {
+        self.x.first().map_or(0, |x| x.len())
+    }
+
+    /// Whether the dataset has no samples.
+    pub fn is_empty(&self) -> bool {
+        self.x.is_empty()
+    }
+
+    /// Borrow immutable reference to the data.
+    pub fn data(&self) -> (&Vec<Vec<T>>, &Vec<U>) {
+        (&self.x, &self.y)
+    }
+
+    /// Split the dataset into its features and labels, dropping the names.
+    pub fn into_parts(self) -> (Vec<Vec<T>>, Vec<U>) {
+        (self.x, self.y)
+    }
+
+    /// The approximate heap memory of the features, labels and names, in bytes. Memory owned
+    /// by the values themselves, such as the text of string features, is not counted.
+    pub fn memory_usage(&self) -> usize {
+        rows_memory(&self.x)
+            + self.y.capacity() * std::mem::size_of::<U>()
+            + self.feature_names.as_deref().map_or(0, strings_memory)
+            + self.label_names.as_deref().map_or(0, strings_memory)
+    }
+
+    /// Append the samples of `other`, which must have the same features.
+    pub fn extend(&mut self, other: Dataset<T, U>) {
+        self.x.extend(other.x);
+        self.y.extend(other.y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_test() {
+        let data = Dataset::new(vec![vec![1.0, 2.0]], vec![1]).unwrap();
+        assert_eq!(data.n_samples(), 1);
+        assert_eq!(data.n_features(), 2);
+
+        assert!(Dataset::new(vec![vec![1.0, 2.0]], vec![1, 2]).is_err());
+    }
+
+    #[test]
+    fn memory_usage_test() {
+        let data = Dataset::new(vec![vec![1.0, 2.0]; 3], vec![0u8; 3]).unwrap();
+        let rows = 3 * std::mem::size_of::<Vec<f64>>();
+
+        assert_eq!(data.memory_usage(), 6 * 8 + rows + 3);
+        let named = data.with_label_names(vec![String::from("a")]);
+        assert_eq!(
+            named.memory_usage(),
+            6 * 8 + rows + 3 + 1 + std::mem::size_of::<String>()
+        );
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn extend_test() {
+        let mut data = Dataset::new(vec![vec![1.0]], vec![0]).unwrap();
+        data.extend(Dataset::new(vec![vec![2.0]], vec![1]).unwrap());
+
+        assert_eq!(data.into_parts(), (vec![vec![1.0], vec![2.0]], vec![0, 1]));
+    }
+}
@@ -0,0 +1,108 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Small classic datasets embedded in the library.
+
+use super::Dataset;
+
+const IRIS: &str = include_str!("data/iris.csv");
+const DIGITS: &str = include_str!("data/digits.csv");
+
+/// Load Fisher's iris dataset: 150 samples of 4 measurements, in 3 classes of 50.
+///
+/// Labels are 0 (setosa), 1 (versicolor) and 2 (virginica).
+///
+/// # Example
+/// ```rust
+/// let iris = rml::datasets::load_iris();
+/// assert_eq!(iris.n_samples(), 150);
+/// assert_eq!(iris.n_features(), 4);
+/// ```
+pub fn load_iris() -> Dataset<f64, i32> {
+    let mut lines = IRIS.lines();
+    let header: Vec<String> = lines.next().unwrap().split(',').map(String::from).collect();
+
+    parse_rows(lines)
+        .with_feature_names(header[..header.len() - 1].to_vec())
+        .with_label_names(vec![
+            String::from("setosa"),
+            String::from("versicolor"),
+            String::from("virginica"),
+        ])
+}
+
+/// Load a subsample of the UCI optical recognition of handwritten digits dataset:
+/// 300 samples (30 per digit) of 8x8 pixel counts between 0 and 16.
+///
+/// Labels are the digits 0 to 9.
+///
+/// # Example
+/// ```rust
+/// let digits = rml::datasets::load_digits();
+/// assert_eq!(digits.n_samples(), 300);
+/// assert_eq!(digits.n_features(), 64);
+/// ```
+pub fn load_digits() -> Dataset<f64, i32> {
+    let feature_names = (0..64)
+        .map(|i| format!("pixel_{}_{}", i / 8, i % 8))
+        .collect();
+    let label_names = (0..10).map(|i| i.to_string()).collect();
+
+    parse_rows(DIGITS.lines())
+        .with_feature_names(feature_names)
+        .with_label_names(label_names)
+}
+
+/// Parse embedded rows whose last value is the label.
+fn parse_rows<'a>(lines: impl Iterator<Item = &'a str>) -> Dataset<f64, i32> {
+    let mut data = Dataset::default();
+    for line in lines.filter(|l| !l.is_empty()) {
+        let values: Vec<&str> = line.split(',').collect();
+        let (label, features) = values.split_last().unwrap();
+        data.y.push(label.parse().unwrap());
+        data.x
+            .push(features.iter().map(|v| v.parse().unwrap()).collect());
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_iris_test() {
+        let iris = load_iris();
+        let mean: f64 = iris.x.iter().map(|x| x[0]).sum::<f64>() / 150.0;
+
+        assert_eq!(iris.y.iter().filter(|&&y| y == 2).count(), 50);
+        assert!((mean - 5.8433).abs() < 1e-4);
+        assert_eq!(iris.feature_names.unwrap()[3], "petal width (cm)");
+    }
+
+    #[test]
+    fn load_digits_test() {
+        let digits = load_digits();
+
+        assert!(digits
+            .x
+            .iter()
+            .flatten()
+            .all(|&v| (0.0..=16.0).contains(&v)));
+        assert_eq!(digits.y.iter().filter(|&&y| y == 7).count(), 30);
+    }
+}
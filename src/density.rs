@@ -0,0 +1,315 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Kernel density estimation.
+
+/*!
+`KernelDensity` estimates the probability density of data by placing a kernel of a given
+bandwidth on each training sample. The log density of new samples scores how typical they are,
+so low scores flag anomalies, and new samples can be drawn from the estimate.
+
+The training samples are indexed by a ball tree, so only those within reach of the kernel are
+visited for each query.
+
+# Example
+```rust
+use rml::density::{Kernel, KernelDensity};
+
+let x: Vec<Vec<f64>> = (0..100).map(|i| vec![(i % 10) as f64 / 10.0]).collect();
+let mut kde: KernelDensity = KernelDensity::new(0.2).kernel(Kernel::Gaussian);
+kde.fit(&x).unwrap();
+let scores = kde.score_samples(&[vec![0.5], vec![5.0]]).unwrap();
+
+assert!(scores[0] > scores[1]);
+```
+!*/
+
+use crate::describe::{Describe, Summary};
+use crate::error::{check_lengths, Error, Result};
+use crate::knn::ball_tree::BallTree;
+use crate::math::dense::DenseMatrix;
+use crate::math::distance::{self, Distance};
+use crate::math::random::Rng;
+use crate::math::Float;
+use crate::parallel::{self, Parallelism};
+use rayon::prelude::*;
+
+/// The distance in bandwidths beyond which the Gaussian kernel is taken as 0. The kernel is
+/// below `1e-13` of its peak there.
+const CUTOFF: f64 = 8.0;
+
+/// The shape of the kernel placed on each sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Kernel {
+    /// A normal distribution with the bandwidth as standard deviation.
+    #[default]
+    Gaussian,
+    /// A uniform distribution over the ball with the bandwidth as radius.
+    Tophat,
+}
+
+/// Estimates a probability density as the mean of a kernel centered on each training sample,
+/// with euclidean distance.
+#[derive(Debug, Clone)]
+pub struct KernelDensity<F = f64> {
+    /// The shape of the kernel.
+    pub kernel: Kernel,
+    /// The width of the kernel.
+    pub bandwidth: f64,
+    /// The parallelism of queries, or None for the global setting.
+    pub parallelism: Option<Parallelism>,
+    /// The training samples, one row per sample.
+    pub x: DenseMatrix<F>,
+    tree: Option<BallTree<F>>,
+}
+
+impl<F: Float> KernelDensity<F> {
+    /// Create an unfitted KernelDensity with a Gaussian kernel of width `bandwidth`.
+    pub fn new(bandwidth: f64) -> KernelDensity<F> {
+        KernelDensity {
+            kernel: Kernel::Gaussian,
+            bandwidth,
+            parallelism: None,
+            x: DenseMatrix::new(0),
+            tree: None,
+        }
+    }
+
+    /// Set the shape of the kernel.
+    pub fn kernel(mut self, kernel: Kernel) -> Self {
+        self.kernel = kernel;
+        self
+    }
+
+    /// Set the parallelism of queries, overriding the global setting.
+    pub fn parallelism(mut self, parallelism: Parallelism) -> Self {
+        self.parallelism = Some(parallelism);
+        self
+    }
+
+    /// The heap memory of the training samples and their index, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.x.memory_usage() + self.tree.as_ref().map_or(0, |t| t.memory_usage())
+    }
+
+    /// Fit the estimate to the samples `x`.
+    /// Returns an error if there are no samples, the samples have different dimensions, or the
+    /// bandwidth is not a finite, positive number.
+    pub fn fit(&mut self, x: &[Vec<F>]) -> Result<()> {
+        if x.is_empty() {
+            return Err(Error::InvalidInput("There are no samples.".into()));
+        }
+        if !(self.bandwidth > 0.0 && self.bandwidth.is_finite()) {
+            return Err(Error::InvalidInput(format!(
+                "The bandwidth {} is not a finite, positive number.",
+                self.bandwidth
+            )));
+        }
+        self.x = DenseMatrix::from_rows(x)?;
+        self.tree = Some(BallTree::new(&self.x, Distance::Euclidean));
+        Ok(())
+    }
+
+    /// The log of the estimated density at each sample of `x`. It is negative infinity where
+    /// the density is 0, such as beyond the bandwidth of every training sample with a tophat
+    /// kernel.
+    /// Returns an error if the KernelDensity is not fit or a sample has the wrong number of
+    /// features.
+    pub fn score_samples(&self, x: &[Vec<F>]) -> Result<Vec<f64>> {
+        let tree = self.tree.as_ref().ok_or(Error::NotFitted)?;
+        for row in x {
+            check_lengths("Features and training features", row.len(), self.x.n_cols())?;
+        }
+        let d = self.x.n_cols() as f64;
+        let h = self.bandwidth;
+        let norm = (self.x.n_rows() as f64).ln()
+            + match self.kernel {
+                Kernel::Gaussian => d / 2.0 * (2.0 * std::f64::consts::PI * h * h).ln(),
+                Kernel::Tophat => log_unit_ball(self.x.n_cols()) + d * h.ln(),
+            };
+        Ok(parallel::install(self.parallelism, || {
+            x.par_iter()
+                .map(|row| match self.kernel {
+                    Kernel::Gaussian => {
+                        let reach = F::from_f64(CUTOFF * h);
+                        let mut near = tree.within(&self.x, row, reach);
+                        if near.is_empty() {
+                            near = self
+                                .x
+                                .rows()
+                                .map(|p| distance::euclidean_distance(row, p))
+                                .enumerate()
+                                .collect();
+                        }
+                        let exponents: Vec<f64> = near
+                            .iter()
+                            .map(|&(_, r)| -0.5 * (r.to_f64() / h).powi(2))
+                            .collect();
+                        log_sum_exp(&exponents) - norm
+                    }
+                    Kernel::Tophat => {
+                        let count = tree.within(&self.x, row, F::from_f64(h)).len();
+                        (count as f64).ln() - norm
+                    }
+                })
+                .collect()
+        }))
+    }
+
+    /// The total log-likelihood of the samples `x` under the estimate.
+    /// Returns an error under the same conditions as [`KernelDensity::score_samples`].
+    pub fn score(&self, x: &[Vec<F>]) -> Result<f64> {
+        Ok(self.score_samples(x)?.iter().sum())
+    }
+
+    /// Draw `n_samples` samples from the estimate: each is a random training sample moved by a
+    /// random draw from the kernel.
+    /// Returns an error if the KernelDensity is not fit.
+    pub fn sample(&self, n_samples: usize, rng: &mut Rng) -> Result<Vec<Vec<F>>> {
+        if self.tree.is_none() {
+            return Err(Error::NotFitted);
+        }
+        let d = self.x.n_cols();
+        Ok((0..n_samples)
+            .map(|_| {
+                let center = self.x.row(rng.below(self.x.n_rows()));
+                let mut offset: Vec<f64> = (0..d).map(|_| rng.next_normal()).collect();
+                let scale = match self.kernel {
+                    Kernel::Gaussian => self.bandwidth,
+                    Kernel::Tophat => {
+                        // A uniform direction, and a radius with density proportional to r^(d-1).
+                        let length = offset.iter().map(|v| v * v).sum::<f64>().sqrt();
+                        let radius = rng.next_f64().powf(1.0 / d as f64);
+                        self.bandwidth * radius / length.max(f64::MIN_POSITIVE)
+                    }
+                };
+                offset.iter_mut().for_each(|v| *v *= scale);
+                center
+                    .iter()
+                    .zip(&offset)
+                    .map(|(&c, &o)| c + F::from_f64(o))
+                    .collect()
+            })
+            .collect())
+    }
+}
+
+/// The log of the volume of the unit ball in `d` dimensions.
+fn log_unit_ball(d: usize) -> f64 {
+    // V(d) = V(d - 2) 2 pi / d, from V(0) = 1 and V(1) = 2.
+    let mut log_volume = if d.is_multiple_of(2) { 0.0 } else { 2f64.ln() };
+    for k in (2 + d % 2..=d).step_by(2) {
+        log_volume += (2.0 * std::f64::consts::PI / k as f64).ln();
+    }
+    log_volume
+}
+
+/// The log of the sum of the exponentials of `values`, without overflow.
+fn log_sum_exp(values: &[f64]) -> f64 {
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    if max == f64::NEG_INFINITY {
+        return max;
+    }
+    max + values.iter().map(|v| (v - max).exp()).sum::<f64>().ln()
+}
+
+impl<F: Float> Describe for KernelDensity<F> {
+    fn describe(&self) -> Summary {
+        let mut summary = Summary::new("KernelDensity")
+            .param("kernel", self.kernel)
+            .param("bandwidth", self.bandwidth)
+            .param("parallelism", self.parallelism);
+        if !self.x.is_empty() {
+            summary.n_samples = Some(self.x.n_rows());
+            summary.n_features = Some(self.x.n_cols());
+        }
+        summary.memory = self.memory_usage();
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gaussian_test() {
+        // A single sample gives the normal density itself, in any number of dimensions.
+        let mut kde = KernelDensity::new(0.5);
+        kde.fit(&[vec![1.0, -1.0]]).unwrap();
+        let scores = kde
+            .score_samples(&[vec![1.0, -1.0], vec![2.0, -1.0], vec![100.0, 0.0]])
+            .unwrap();
+        let normal = |r: f64| -(2.0 * std::f64::consts::PI * 0.25).ln() - r * r / 0.5;
+
+        assert!((scores[0] - normal(0.0)).abs() < 1e-12);
+        assert!((scores[1] - normal(1.0)).abs() < 1e-12);
+        // Beyond the cutoff every sample is used.
+        assert!((scores[2] - normal(99.0f64.hypot(1.0))).abs() < 1e-9);
+        assert!(
+            (kde.score(&[vec![1.0, -1.0], vec![2.0, -1.0]]).unwrap() - scores[0] - scores[1]).abs()
+                < 1e-12
+        );
+    }
+
+    #[test]
+    fn tophat_test() {
+        // Integrating the density over a fine grid gives about 1.
+        let x: Vec<Vec<f64>> = (0..50)
+            .map(|i| vec![(i as f64 * 0.37) % 3.0, (i as f64 * 0.71) % 2.0])
+            .collect();
+        let mut kde = KernelDensity::new(0.5).kernel(Kernel::Tophat);
+        kde.fit(&x).unwrap();
+        let step = 0.02;
+        let grid: Vec<Vec<f64>> = (0..250)
+            .flat_map(|i| {
+                (0..200).map(move |j| vec![-0.6 + i as f64 * step, -0.6 + j as f64 * step])
+            })
+            .collect();
+        let total: f64 = kde
+            .score_samples(&grid)
+            .unwrap()
+            .iter()
+            .map(|s| s.exp() * step * step)
+            .sum();
+
+        assert!((total - 1.0).abs() < 0.02, "{}", total);
+        assert_eq!(
+            kde.score_samples(&[vec![10.0, 10.0]]).unwrap()[0],
+            f64::NEG_INFINITY
+        );
+        assert!((log_unit_ball(3) - (4.0 / 3.0 * std::f64::consts::PI).ln()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn sample_test() {
+        let x = vec![vec![0.0, 0.0], vec![10.0, 10.0]];
+        let mut rng = Rng::new(3);
+        let mut kde = KernelDensity::new(1.0).kernel(Kernel::Tophat);
+        assert!(matches!(kde.sample(1, &mut rng), Err(Error::NotFitted)));
+        kde.fit(&x).unwrap();
+        let samples = kde.sample(200, &mut rng).unwrap();
+
+        assert_eq!(samples.len(), 200);
+        assert!(samples
+            .iter()
+            .all(|s| x.iter().any(|c| distance::euclidean_distance(s, c) <= 1.0)));
+        assert!(samples.iter().any(|s| s[0] > 5.0) && samples.iter().any(|s| s[0] < 5.0));
+        assert!(KernelDensity::<f64>::new(0.0).fit(&x).is_err());
+        assert!(kde.score_samples(&[vec![0.0]]).is_err());
+    }
+}
@@ -0,0 +1,167 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Structured summaries of estimators.
+
+/*!
+`Describe` gives a fitted model or preprocessor a `Summary` of its hyperparameters, the shape of
+the data it was fit to and the heap memory it holds. Summaries print as an indented list, for
+quick sanity checks and experiment logs, and their fields can be read directly.
+
+# Example
+```rust
+use rml::describe::Describe;
+use rml::knn::KNN;
+
+let knn: KNN = KNN::new(1, vec![vec![0.0, 1.0], vec![1.0, 0.0]], vec![0, 1], None, None).unwrap();
+let summary = knn.describe();
+
+assert_eq!(summary.n_samples, Some(2));
+assert_eq!(summary.n_features, Some(2));
+println!("{}", summary);
+```
+!*/
+
+use std::fmt;
+use std::mem;
+
+/// A summary of an estimator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+    /// The name of the estimator.
+    pub name: String,
+    /// The hyperparameters, as names and formatted values.
+    pub params: Vec<(String, String)>,
+    /// The number of samples the estimator was fit to, if it keeps track.
+    pub n_samples: Option<usize>,
+    /// The number of features the estimator was fit to, if known.
+    pub n_features: Option<usize>,
+    /// The number of classes learned, if any.
+    pub n_classes: Option<usize>,
+    /// The approximate heap memory held, in bytes, excluding `parts`.
+    pub memory: usize,
+    /// Summaries of wrapped estimators.
+    pub parts: Vec<Summary>,
+}
+
+impl Summary {
+    /// Create an empty summary of the estimator `name`.
+    pub fn new(name: &str) -> Summary {
+        Summary {
+            name: name.to_string(),
+            params: Vec::new(),
+            n_samples: None,
+            n_features: None,
+            n_classes: None,
+            memory: 0,
+            parts: Vec::new(),
+        }
+    }
+
+    /// Add a hyperparameter, formatted with `Debug`.
+    pub fn param<V: fmt::Debug>(mut self, name: &str, value: V) -> Self {
+        self.params.push((name.to_string(), format!("{:?}", value)));
+        self
+    }
+
+    /// Add a wrapped estimator.
+    pub fn part(mut self, part: Summary) -> Self {
+        self.parts.push(part);
+        self
+    }
+
+    /// The memory of this estimator and its parts, in bytes.
+    pub fn total_memory(&self) -> usize {
+        self.memory + self.parts.iter().map(Summary::total_memory).sum::<usize>()
+    }
+
+    fn write_indented(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let pad = " ".repeat(indent);
+        writeln!(f, "{}{}", pad, self.name)?;
+        for (name, value) in &self.params {
+            writeln!(f, "{}  {}: {}", pad, name, value)?;
+        }
+        let shape = [
+            ("samples", self.n_samples),
+            ("features", self.n_features),
+            ("classes", self.n_classes),
+        ];
+        for (name, value) in shape.iter() {
+            if let Some(value) = value {
+                writeln!(f, "{}  {}: {}", pad, name, value)?;
+            }
+        }
+        writeln!(f, "{}  memory: {} bytes", pad, self.memory)?;
+        self.parts
+            .iter()
+            .try_for_each(|part| part.write_indented(f, indent + 2))
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_indented(f, 0)
+    }
+}
+
+/// An estimator that can summarize itself.
+pub trait Describe {
+    /// Summarize the estimator. Unfitted estimators leave the data shape empty.
+    fn describe(&self) -> Summary;
+}
+
+/// The heap memory of the rows of a matrix, in bytes.
+pub(crate) fn rows_memory<T>(x: &[Vec<T>]) -> usize {
+    x.iter().map(|row| row.capacity()).sum::<usize>() * mem::size_of::<T>() + mem::size_of_val(x)
+}
+
+/// The heap memory of a list of strings, in bytes.
+pub(crate) fn strings_memory(strings: &[String]) -> usize {
+    strings.iter().map(String::capacity).sum::<usize>() + mem::size_of_val(strings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_display_test() {
+        let mut inner = Summary::new("Inner").param("k", 3);
+        inner.n_features = Some(2);
+        inner.memory = 16;
+        let summary = Summary::new("Outer")
+            .param("method", Some("sigmoid"))
+            .part(inner);
+
+        assert_eq!(summary.total_memory(), 16);
+        assert_eq!(
+            summary.to_string(),
+            "Outer\n  method: Some(\"sigmoid\")\n  memory: 0 bytes\n  Inner\n    k: 3\n    features: 2\n    memory: 16 bytes\n"
+        );
+    }
+
+    #[test]
+    fn rows_memory_test() {
+        let x: Vec<Vec<f64>> = vec![Vec::with_capacity(2), Vec::with_capacity(2)];
+
+        assert_eq!(rows_memory(&x), 32 + 2 * mem::size_of::<Vec<f64>>());
+        assert_eq!(
+            strings_memory(&[String::from("ab")]),
+            2 + mem::size_of::<String>()
+        );
+    }
+}
@@ -0,0 +1,281 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Early stopping of iterative fits.
+
+/*!
+`EarlyStopping` configures when an iterative trainer stops: after `patience` iterations in which
+the monitored value did not improve by at least `min_delta`. The trainer holds out
+`validation_fraction` of its samples with `EarlyStopping::split`, reports each iteration to a
+`Tracker` as an `observer::Progress`, and restores the best parameters the tracker kept once it
+says to stop.
+
+# Example
+```rust
+use rml::early_stopping::{EarlyStopping, Monitor};
+use rml::observer::Progress;
+use std::time::Duration;
+
+let config = EarlyStopping::new().patience(2).monitor(Monitor::Loss);
+let mut tracker = config.tracker();
+let mut weights = 0.0;
+
+for (i, loss) in [1.0, 0.5, 0.6, 0.7, 0.4].iter().enumerate() {
+    weights += 1.0;
+    let progress = Progress {
+        iteration: i + 1,
+        total: None,
+        loss: Some(*loss),
+        score: None,
+        samples: 0,
+        elapsed: Duration::ZERO,
+    };
+    if tracker.update(&progress, || weights).unwrap() {
+        break;
+    }
+}
+
+assert_eq!(tracker.best_iteration(), Some(2));
+assert_eq!(tracker.into_best(), Some(2.0));
+```
+!*/
+
+use crate::error::{Error, Result};
+use crate::math::random::Rng;
+use crate::model_selection::Fold;
+use crate::observer::Progress;
+
+/// The value early stopping watches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Monitor {
+    /// The validation loss, where lower is better.
+    Loss,
+    /// The validation score, where higher is better.
+    Score,
+}
+
+/// When to stop an iterative fit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EarlyStopping {
+    /// The number of iterations without improvement to wait before stopping.
+    pub patience: usize,
+    /// The smallest change of the monitored value that counts as an improvement.
+    pub min_delta: f64,
+    /// The monitored value.
+    pub monitor: Monitor,
+    /// The fraction of samples held out for validation, in `(0, 1)`.
+    pub validation_fraction: f64,
+    /// The seed used to choose the validation samples, or None for the global seed.
+    pub seed: Option<u64>,
+}
+
+impl Default for EarlyStopping {
+    fn default() -> Self {
+        EarlyStopping {
+            patience: 5,
+            min_delta: 0.0,
+            monitor: Monitor::Score,
+            validation_fraction: 0.1,
+            seed: None,
+        }
+    }
+}
+
+impl EarlyStopping {
+    /// Stop after 5 iterations without a higher validation score, validating on 10% of the
+    /// samples.
+    pub fn new() -> EarlyStopping {
+        EarlyStopping::default()
+    }
+
+    /// The number of iterations without improvement to wait before stopping.
+    pub fn patience(mut self, patience: usize) -> Self {
+        self.patience = patience;
+        self
+    }
+
+    /// The smallest change of the monitored value that counts as an improvement.
+    pub fn min_delta(mut self, min_delta: f64) -> Self {
+        self.min_delta = min_delta;
+        self
+    }
+
+    /// The monitored value.
+    pub fn monitor(mut self, monitor: Monitor) -> Self {
+        self.monitor = monitor;
+        self
+    }
+
+    /// The fraction of samples held out for validation, in `(0, 1)`.
+    pub fn validation_fraction(mut self, fraction: f64) -> Self {
+        self.validation_fraction = fraction;
+        self
+    }
+
+    /// The seed used to choose the validation samples.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Split `n_samples` samples into training and validation indices.
+    /// Returns an error if the fraction is not in `(0, 1)` or either part would be empty.
+    pub fn split(&self, n_samples: usize) -> Result<Fold> {
+        if !(self.validation_fraction > 0.0 && self.validation_fraction < 1.0) {
+            return Err(Error::InvalidInput(format!(
+                "Validation fraction {} is not in (0, 1).",
+                self.validation_fraction
+            )));
+        }
+        let n_validation = (self.validation_fraction * n_samples as f64).round() as usize;
+        if n_validation == 0 || n_validation == n_samples {
+            return Err(Error::InvalidInput(
+                "Too few samples to hold out for validation.".into(),
+            ));
+        }
+        let mut order: Vec<usize> = (0..n_samples).collect();
+        Rng::from_seed(self.seed).shuffle(&mut order);
+        let train = order.split_off(n_validation);
+        Ok((train, order))
+    }
+
+    /// Start tracking a fit that keeps parameters of type `P`.
+    pub fn tracker<P>(&self) -> Tracker<P> {
+        Tracker {
+            config: *self,
+            best: None,
+            waited: 0,
+        }
+    }
+}
+
+/// Tracks the monitored value of a fit and keeps the best parameters seen.
+#[derive(Debug, Clone)]
+pub struct Tracker<P> {
+    config: EarlyStopping,
+    best: Option<(f64, usize, P)>,
+    waited: usize,
+}
+
+impl<P> Tracker<P> {
+    /// Record an iteration. `params` is called to copy the parameters when the monitored value
+    /// improves. Returns whether the fit should stop, or an error if `progress` lacks the
+    /// monitored value.
+    pub fn update<C: FnOnce() -> P>(&mut self, progress: &Progress, params: C) -> Result<bool> {
+        let value = match self.config.monitor {
+            Monitor::Loss => progress.loss.map(|loss| -loss),
+            Monitor::Score => progress.score,
+        }
+        .ok_or_else(|| {
+            Error::InvalidInput(format!(
+                "Early stopping monitors the {:?} but the fit did not report it.",
+                self.config.monitor
+            ))
+        })?;
+
+        let improved = match &self.best {
+            Some((best, _, _)) => value > best + self.config.min_delta,
+            None => true,
+        };
+        if improved {
+            self.best = Some((value, progress.iteration, params()));
+            self.waited = 0;
+        } else {
+            self.waited += 1;
+        }
+        Ok(self.waited >= self.config.patience)
+    }
+
+    /// The best monitored value seen.
+    pub fn best_value(&self) -> Option<f64> {
+        self.best
+            .as_ref()
+            .map(|(value, _, _)| match self.config.monitor {
+                Monitor::Loss => -value,
+                Monitor::Score => *value,
+            })
+    }
+
+    /// The iteration with the best monitored value.
+    pub fn best_iteration(&self) -> Option<usize> {
+        self.best.as_ref().map(|(_, iteration, _)| *iteration)
+    }
+
+    /// Borrow the best parameters.
+    pub fn best(&self) -> Option<&P> {
+        self.best.as_ref().map(|(_, _, params)| params)
+    }
+
+    /// Take the best parameters, to restore them once the fit stops.
+    pub fn into_best(self) -> Option<P> {
+        self.best.map(|(_, _, params)| params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn progress(iteration: usize, score: f64) -> Progress {
+        Progress {
+            iteration,
+            total: None,
+            loss: None,
+            score: Some(score),
+            samples: 0,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn tracker_test() {
+        let mut tracker = EarlyStopping::new().patience(2).min_delta(0.05).tracker();
+        let scores = [0.5, 0.7, 0.72, 0.6, 0.9];
+        let stopped = scores
+            .iter()
+            .enumerate()
+            .position(|(i, &s)| tracker.update(&progress(i + 1, s), || i).unwrap());
+
+        assert_eq!(stopped, Some(3));
+        assert_eq!(tracker.best_iteration(), Some(2));
+        assert_eq!(tracker.best_value(), Some(0.7));
+        assert_eq!(tracker.best(), Some(&1));
+    }
+
+    #[test]
+    fn missing_value_test() {
+        let mut tracker = EarlyStopping::new().monitor(Monitor::Loss).tracker();
+
+        assert!(tracker.update(&progress(1, 0.5), || ()).is_err());
+    }
+
+    #[test]
+    fn split_test() {
+        let (train, validation) = EarlyStopping::new().seed(3).split(20).unwrap();
+
+        assert_eq!((train.len(), validation.len()), (18, 2));
+        let mut all = [train, validation].concat();
+        all.sort_unstable();
+        assert_eq!(all, (0..20).collect::<Vec<_>>());
+        assert!(EarlyStopping::new().split(4).is_err());
+        assert!(EarlyStopping::new()
+            .validation_fraction(1.0)
+            .split(20)
+            .is_err());
+    }
+}
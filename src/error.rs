@@ -0,0 +1,190 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+//! The error type shared by the whole crate.
+
+use std::fmt;
+use std::io;
+
+/// A specialized `Result` type for rml operations.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors returned by rml.
+///
+/// `Error` is `Send` and `Sync`, and converts into `Box<dyn std::error::Error>` with `?`.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing a file failed.
+    Io(io::Error),
+    /// Input data is malformed, such as an invalid file or a value that can't be parsed.
+    Parse(String),
+    /// Inputs have incompatible lengths or dimensions.
+    ShapeMismatch(String),
+    /// A model or preprocessor was used before it was fit.
+    NotFitted,
+    /// An argument is invalid, such as a parameter out of range or an unknown label.
+    InvalidInput(String),
+    /// A long-running operation was cancelled by its caller.
+    Cancelled,
+}
+
+impl Error {
+    /// Prefix the message of this error with `context`, such as the line it occurred on.
+    pub(crate) fn context(self, context: impl fmt::Display) -> Error {
+        match self {
+            Error::Parse(msg) => Error::Parse(format!("{}: {}", context, msg)),
+            Error::ShapeMismatch(msg) => Error::ShapeMismatch(format!("{}: {}", context, msg)),
+            Error::InvalidInput(msg) => Error::InvalidInput(format!("{}: {}", context, msg)),
+            e => e,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "IO error: {}", e),
+            Error::Parse(msg) => write!(f, "Parse error: {}", msg),
+            Error::ShapeMismatch(msg) => write!(f, "Shape mismatch: {}", msg),
+            Error::NotFitted => write!(f, "The model has not been fit."),
+            Error::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
+            Error::Cancelled => write!(f, "The operation was cancelled."),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<::csv::Error> for Error {
+    fn from(e: ::csv::Error) -> Self {
+        if e.is_io_error() {
+            match e.into_kind() {
+                ::csv::ErrorKind::Io(e) => Error::Io(e),
+                kind => Error::Parse(format!("{:?}", kind)),
+            }
+        } else {
+            Error::Parse(e.to_string())
+        }
+    }
+}
+
+impl From<std::num::ParseIntError> for Error {
+    fn from(e: std::num::ParseIntError) -> Self {
+        Error::Parse(e.to_string())
+    }
+}
+
+impl From<std::num::ParseFloatError> for Error {
+    fn from(e: std::num::ParseFloatError) -> Self {
+        Error::Parse(e.to_string())
+    }
+}
+
+impl From<std::str::Utf8Error> for Error {
+    fn from(e: std::str::Utf8Error) -> Self {
+        Error::Parse(e.to_string())
+    }
+}
+
+impl From<std::string::FromUtf8Error> for Error {
+    fn from(e: std::string::FromUtf8Error) -> Self {
+        Error::Parse(e.to_string())
+    }
+}
+
+/// Return a `ShapeMismatch` error if two lengths differ. `what` names the two inputs, e.g.
+/// `"Features and labels"`.
+pub(crate) fn check_lengths(what: &str, a: usize, b: usize) -> Result<()> {
+    if a != b {
+        return Err(Error::ShapeMismatch(format!(
+            "{} have different lengths ({} and {}).",
+            what, a, b
+        )));
+    }
+    Ok(())
+}
+
+/// Check that there is one sample weight per sample and the weights are finite and not negative.
+pub(crate) fn check_weights(n_samples: usize, weights: &[f64]) -> Result<()> {
+    check_lengths("Samples and weights", n_samples, weights.len())?;
+    match weights.iter().find(|w| !(w.is_finite() && **w >= 0.0)) {
+        Some(w) => Err(Error::InvalidInput(format!(
+            "Sample weight {} is not a finite, non-negative number.",
+            w
+        ))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversion_test() {
+        let e: Error = "x".parse::<i32>().unwrap_err().into();
+        assert!(matches!(e, Error::Parse(_)));
+
+        let e: Error = io::Error::new(io::ErrorKind::NotFound, "missing").into();
+        assert!(std::error::Error::source(&e).is_some());
+
+        let boxed: Box<dyn std::error::Error + Send + Sync> = Error::NotFitted.into();
+        assert_eq!(boxed.to_string(), "The model has not been fit.");
+    }
+
+    #[test]
+    fn context_test() {
+        let e = Error::Parse("bad value".into()).context("Line 3");
+        assert_eq!(e.to_string(), "Parse error: Line 3: bad value");
+        assert!(matches!(
+            Error::NotFitted.context("Line 3"),
+            Error::NotFitted
+        ));
+    }
+
+    #[test]
+    fn check_weights_test() {
+        assert!(check_weights(2, &[0.0, 2.5]).is_ok());
+        assert!(matches!(
+            check_weights(1, &[1.0, 1.0]),
+            Err(Error::ShapeMismatch(_))
+        ));
+        assert!(check_weights(2, &[1.0, -1.0]).is_err());
+        assert!(check_weights(1, &[f64::NAN]).is_err());
+    }
+
+    #[test]
+    fn check_lengths_test() {
+        assert!(check_lengths("A and B", 1, 1).is_ok());
+        assert_eq!(
+            check_lengths("A and B", 1, 2).unwrap_err().to_string(),
+            "Shape mismatch: A and B have different lengths (1 and 2)."
+        );
+    }
+}
@@ -0,0 +1,431 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+//! A C interface to the main estimators, behind the `ffi` feature.
+
+/*!
+Building with `--features ffi` exports `extern "C"` functions from the `cdylib`, so models can be
+trained and used from C, or from Python through `ctypes` or `cffi`.
+
+Matrices are passed as row-major `double` arrays with their number of samples and features.
+Functions that can fail return 0 on success and -1 on error; `rml_last_error` copies the message
+of the last error on the calling thread. Objects are created with a `_new` function and must be
+released with the matching `_free` function.
+
+# Example
+```c
+RmlKnn *knn = rml_knn_new(3, RML_EUCLIDEAN, RML_NO_NORM);
+if (rml_knn_fit(knn, x, n_samples, n_features, y) != 0) {
+    char message[256];
+    rml_last_error(message, sizeof message);
+    fprintf(stderr, "%s\n", message);
+}
+rml_knn_predict(knn, x_test, n_test, n_features, predictions);
+rml_knn_free(knn);
+```
+!*/
+
+use crate::error::{Error, Result};
+use crate::knn::KNN;
+use crate::math::distance::Distance;
+use crate::math::norm::Norm;
+use crate::preprocessing::scale::StandardScaler;
+use crate::traits::{Fit, Predict, PredictProba, Transform};
+use std::cell::RefCell;
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+
+/// `distance` value for euclidean distance.
+pub const RML_EUCLIDEAN: i32 = 0;
+/// `distance` value for Manhattan distance.
+pub const RML_MANHATTAN: i32 = 1;
+/// `distance` value for cosine distance.
+pub const RML_COSINE: i32 = 2;
+/// `distance` value for precomputed distances. Each sample is then its distances to every
+/// training point.
+pub const RML_PRECOMPUTED: i32 = 3;
+/// `distance` value for Hamming distance.
+pub const RML_HAMMING: i32 = 4;
+/// `normalize` value for no normalization.
+pub const RML_NO_NORM: i32 = 0;
+/// `normalize` value for L1 normalization.
+pub const RML_L1: i32 = 1;
+/// `normalize` value for L2 normalization.
+pub const RML_L2: i32 = 2;
+
+/// A KNN classifier. Opaque to C.
+pub struct RmlKnn(KNN);
+
+/// A standard scaler. Opaque to C.
+pub struct RmlStandardScaler(StandardScaler);
+
+thread_local! {
+    static LAST_ERROR: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+/// Store `message` as the last error on this thread.
+fn set_last_error(message: String) {
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+}
+
+/// Run `op`, catching a panic so it doesn't unwind into C, which is undefined behavior. A panic
+/// is stored as the last error and returns `fallback`.
+fn catch<R>(fallback: R, op: impl FnOnce() -> R) -> R {
+    panic::catch_unwind(AssertUnwindSafe(op)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|m| m.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        set_last_error(format!("Panicked: {}", message));
+        fallback
+    })
+}
+
+/// Run `op` and convert its result to a status code, storing the message of an error.
+fn status(op: impl FnOnce() -> Result<()>) -> i32 {
+    catch(-1, || match op() {
+        Ok(()) => 0,
+        Err(e) => {
+            set_last_error(e.to_string());
+            -1
+        }
+    })
+}
+
+/// The number of values of a row-major matrix, or an error if it overflows.
+fn len(n_rows: usize, n_cols: usize) -> Result<usize> {
+    n_rows
+        .checked_mul(n_cols)
+        .ok_or_else(|| Error::InvalidInput("The matrix is too large.".into()))
+}
+
+/// The error for a null pointer argument.
+fn null() -> Error {
+    Error::InvalidInput("A pointer argument is null.".into())
+}
+
+/// Copy a row-major matrix into rows.
+///
+/// # Safety
+/// `x` must point to `n_samples * n_features` values, or be null if there are none.
+unsafe fn rows(x: *const f64, n_samples: usize, n_features: usize) -> Result<Vec<Vec<f64>>> {
+    let len = len(n_samples, n_features)?;
+    if n_features == 0 {
+        return Ok(vec![Vec::new(); n_samples]);
+    }
+    Ok(input(x, len)?
+        .chunks(n_features)
+        .map(<[f64]>::to_vec)
+        .collect())
+}
+
+/// Borrow an input buffer of `len` values.
+///
+/// # Safety
+/// `x` must point to `len` values, or be null if `len` is 0.
+unsafe fn input<'a, T>(x: *const T, len: usize) -> Result<&'a [T]> {
+    match len {
+        0 => Ok(&[]),
+        _ if x.is_null() => Err(null()),
+        _ => Ok(slice::from_raw_parts(x, len)),
+    }
+}
+
+/// Borrow an output buffer of `len` values.
+///
+/// # Safety
+/// `out` must point to `len` writable values, or be null if `len` is 0.
+unsafe fn output<'a, T>(out: *mut T, len: usize) -> Result<&'a mut [T]> {
+    match len {
+        0 => Ok(&mut []),
+        _ if out.is_null() => Err(null()),
+        _ => Ok(slice::from_raw_parts_mut(out, len)),
+    }
+}
+
+/// Copy the message of the last error on this thread into `buf` as a nul-terminated string,
+/// truncating it to `len - 1` bytes. Returns the length of the full message, or 0 if there was
+/// no error.
+///
+/// # Safety
+/// `buf` must point to `len` writable bytes, or be null if `len` is 0.
+#[no_mangle]
+pub unsafe extern "C" fn rml_last_error(buf: *mut c_char, len: usize) -> usize {
+    catch(0, || {
+        LAST_ERROR.with(|last| {
+            let message = last.borrow();
+            if !buf.is_null() && len > 0 {
+                let n = message.len().min(len - 1);
+                let out = slice::from_raw_parts_mut(buf as *mut u8, len);
+                out[..n].copy_from_slice(&message.as_bytes()[..n]);
+                out[n] = 0;
+            }
+            message.len()
+        })
+    })
+}
+
+/// Create an unfitted KNN classifier with the given `RML_*` distance and normalization.
+/// Returns null if `distance` or `normalize` is not a known value.
+#[no_mangle]
+pub extern "C" fn rml_knn_new(k: i32, distance: i32, normalize: i32) -> *mut RmlKnn {
+    catch(std::ptr::null_mut(), || knn_new(k, distance, normalize))
+}
+
+/// Create the KNN of [`rml_knn_new`], or null for an unknown value.
+fn knn_new(k: i32, distance: i32, normalize: i32) -> *mut RmlKnn {
+    let distance = match distance {
+        RML_EUCLIDEAN => Distance::Euclidean,
+        RML_MANHATTAN => Distance::Manhattan,
+        RML_COSINE => Distance::Cosine,
+        RML_PRECOMPUTED => Distance::Precomputed,
+        RML_HAMMING => Distance::Hamming,
+        _ => return std::ptr::null_mut(),
+    };
+    let normalize = match normalize {
+        RML_NO_NORM => None,
+        RML_L1 => Some(Norm::L1),
+        RML_L2 => Some(Norm::L2),
+        _ => return std::ptr::null_mut(),
+    };
+    Box::into_raw(Box::new(RmlKnn(KNN::unfitted(
+        k,
+        Some(distance),
+        normalize,
+    ))))
+}
+
+/// Free a KNN classifier. Does nothing if `knn` is null.
+///
+/// # Safety
+/// `knn` must have been returned by `rml_knn_new` and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rml_knn_free(knn: *mut RmlKnn) {
+    catch((), || {
+        if !knn.is_null() {
+            drop(Box::from_raw(knn));
+        }
+    })
+}
+
+/// Train a KNN classifier on `n_samples` rows of `x` and their labels `y`.
+///
+/// # Safety
+/// `knn` must be a live classifier, `x` must point to `n_samples * n_features` values and `y`
+/// to `n_samples` labels.
+#[no_mangle]
+pub unsafe extern "C" fn rml_knn_fit(
+    knn: *mut RmlKnn,
+    x: *const f64,
+    n_samples: usize,
+    n_features: usize,
+    y: *const i32,
+) -> i32 {
+    status(|| {
+        let knn = knn.as_mut().ok_or_else(null)?;
+        let x = rows(x, n_samples, n_features)?;
+        knn.0.fit(&x, input(y, n_samples)?)
+    })
+}
+
+/// The number of classes a KNN classifier was trained on, or 0 if it is null or unfitted.
+///
+/// # Safety
+/// `knn` must be a live classifier or null.
+#[no_mangle]
+pub unsafe extern "C" fn rml_knn_n_classes(knn: *const RmlKnn) -> usize {
+    catch(0, || knn.as_ref().map_or(0, |knn| knn.0.num_labels))
+}
+
+/// Predict the class of `n_samples` rows of `x` into `out`.
+///
+/// # Safety
+/// `knn` must be a live classifier, `x` must point to `n_samples * n_features` values and `out`
+/// to `n_samples` writable labels.
+#[no_mangle]
+pub unsafe extern "C" fn rml_knn_predict(
+    knn: *const RmlKnn,
+    x: *const f64,
+    n_samples: usize,
+    n_features: usize,
+    out: *mut i32,
+) -> i32 {
+    status(|| {
+        let knn = knn.as_ref().ok_or_else(null)?;
+        let predictions = Predict::predict(&knn.0, &rows(x, n_samples, n_features)?)?;
+        output(out, n_samples)?.copy_from_slice(&predictions);
+        Ok(())
+    })
+}
+
+/// Predict class probabilities of `n_samples` rows of `x` into `out`, a row-major matrix with
+/// `rml_knn_n_classes` columns, one per class label in ascending order.
+///
+/// # Safety
+/// `knn` must be a live classifier, `x` must point to `n_samples * n_features` values and `out`
+/// to `n_samples * rml_knn_n_classes(knn)` writable values.
+#[no_mangle]
+pub unsafe extern "C" fn rml_knn_predict_proba(
+    knn: *const RmlKnn,
+    x: *const f64,
+    n_samples: usize,
+    n_features: usize,
+    out: *mut f64,
+) -> i32 {
+    status(|| {
+        let knn = knn.as_ref().ok_or_else(null)?;
+        let probabilities = PredictProba::predict_proba(&knn.0, &rows(x, n_samples, n_features)?)?;
+        let out = output(out, len(n_samples, knn.0.num_labels)?)?;
+        out.iter_mut()
+            .zip(probabilities.iter().flatten())
+            .for_each(|(o, &p)| *o = p);
+        Ok(())
+    })
+}
+
+/// Create an unfitted standard scaler.
+#[no_mangle]
+pub extern "C" fn rml_standard_scaler_new() -> *mut RmlStandardScaler {
+    catch(std::ptr::null_mut(), || {
+        Box::into_raw(Box::new(RmlStandardScaler(StandardScaler::new())))
+    })
+}
+
+/// Free a standard scaler. Does nothing if `scaler` is null.
+///
+/// # Safety
+/// `scaler` must have been returned by `rml_standard_scaler_new` and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn rml_standard_scaler_free(scaler: *mut RmlStandardScaler) {
+    catch((), || {
+        if !scaler.is_null() {
+            drop(Box::from_raw(scaler));
+        }
+    })
+}
+
+/// Learn the mean and standard deviation of each feature of `x`.
+///
+/// # Safety
+/// `scaler` must be a live scaler and `x` must point to `n_samples * n_features` values.
+#[no_mangle]
+pub unsafe extern "C" fn rml_standard_scaler_fit(
+    scaler: *mut RmlStandardScaler,
+    x: *const f64,
+    n_samples: usize,
+    n_features: usize,
+) -> i32 {
+    status(|| {
+        let scaler = scaler.as_mut().ok_or_else(null)?;
+        Fit::<f64, ()>::fit(&mut scaler.0, &rows(x, n_samples, n_features)?, &[])
+    })
+}
+
+/// Standardize `n_samples` rows of `x` into `out`, which may be the same buffer as `x`.
+///
+/// # Safety
+/// `scaler` must be a live scaler, and `x` and `out` must point to `n_samples * n_features`
+/// values.
+#[no_mangle]
+pub unsafe extern "C" fn rml_standard_scaler_transform(
+    scaler: *const RmlStandardScaler,
+    x: *const f64,
+    n_samples: usize,
+    n_features: usize,
+    out: *mut f64,
+) -> i32 {
+    status(|| {
+        let scaler = scaler.as_ref().ok_or_else(null)?;
+        let scaled = scaler.0.transform(&rows(x, n_samples, n_features)?)?;
+        output(out, len(n_samples, n_features)?)?
+            .iter_mut()
+            .zip(scaled.iter().flatten())
+            .for_each(|(o, &v)| *o = v);
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn knn_test() {
+        let x = [0.0, 0.0, 0.1, 0.0, 5.0, 5.0, 5.1, 5.0];
+        let y = [0, 0, 1, 1];
+        let mut out = [0; 2];
+        let mut proba = [0.0; 4];
+
+        unsafe {
+            let knn = rml_knn_new(1, RML_EUCLIDEAN, RML_NO_NORM);
+            assert_eq!(rml_knn_fit(knn, x.as_ptr(), 4, 2, y.as_ptr()), 0);
+            assert_eq!(rml_knn_n_classes(knn), 2);
+            assert_eq!(
+                rml_knn_predict(knn, [4.0, 4.0, 0.0, 0.0].as_ptr(), 2, 2, out.as_mut_ptr()),
+                0
+            );
+            assert_eq!(
+                rml_knn_predict_proba(knn, [4.0, 4.0, 0.0, 0.0].as_ptr(), 2, 2, proba.as_mut_ptr()),
+                0
+            );
+            rml_knn_free(knn);
+        }
+        assert_eq!(out, [1, 0]);
+        assert_eq!(proba, [0.0, 1.0, 1.0, 0.0]);
+        assert!(rml_knn_new(1, 7, RML_NO_NORM).is_null());
+    }
+
+    #[test]
+    fn error_test() {
+        let mut buf = [0 as c_char; 16];
+        unsafe {
+            let knn = rml_knn_new(1, RML_EUCLIDEAN, RML_NO_NORM);
+            assert_eq!(
+                rml_knn_predict(knn, [0.0].as_ptr(), 1, 1, [0].as_mut_ptr()),
+                -1
+            );
+            let len = rml_last_error(buf.as_mut_ptr(), buf.len());
+            rml_knn_free(knn);
+
+            assert_eq!(len, "The model has not been fit.".len());
+            let message = std::ffi::CStr::from_ptr(buf.as_ptr()).to_str().unwrap();
+            assert_eq!(message, "The model has n");
+        }
+        assert!(len(usize::MAX, 2).is_err());
+        assert_eq!(catch(-1, || panic!("Out of range.")), -1);
+        let mut buf = [0 as c_char; 32];
+        unsafe {
+            rml_last_error(buf.as_mut_ptr(), buf.len());
+            let message = std::ffi::CStr::from_ptr(buf.as_ptr()).to_str().unwrap();
+            assert_eq!(message, "Panicked: Out of range.");
+        }
+    }
+
+    #[test]
+    fn standard_scaler_test() {
+        let mut x = [1.0, 10.0, 3.0, 10.0];
+        unsafe {
+            let scaler = rml_standard_scaler_new();
+            assert_eq!(rml_standard_scaler_fit(scaler, x.as_ptr(), 2, 2), 0);
+            let ptr = x.as_mut_ptr();
+            assert_eq!(rml_standard_scaler_transform(scaler, ptr, 2, 2, ptr), 0);
+            rml_standard_scaler_free(scaler);
+        }
+        assert_eq!(x, [-1.0, 0.0, 1.0, 0.0]);
+    }
+}
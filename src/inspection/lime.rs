@@ -0,0 +1,386 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Model-agnostic local explanations (LIME).
+
+/*!
+LIME explains one prediction of any classifier implementing `traits::PredictProba`. It samples
+points around the explained one, asks the model for their probabilities, and fits a weighted
+linear surrogate to them, weighting each point by its closeness. The surrogate's coefficients
+rank the features by their local effect on the probability of a class.
+
+Numeric samples are perturbed with Gaussian noise; bags of words and other count or indicator
+features are perturbed by dropping features, which for text drops tokens. Limiting the
+explanation to `max_features` keeps the features with the largest coefficients and refits the
+surrogate on them, so the explanation is sparse.
+
+# Example
+```rust
+use rml::inspection::lime::{Lime, Perturbation};
+use rml::knn::KNN;
+
+let x = vec![vec![0.0, 0.0], vec![0.0, 1.0], vec![1.0, 0.0], vec![1.0, 1.0]];
+let knn: KNN = KNN::new(3, x, vec![0, 0, 1, 1], None, None).unwrap();
+
+let explanation = Lime::new(Perturbation::Gaussian(vec![0.5, 0.5]))
+    .seed(1)
+    .explain(&knn, &[0.6, 0.5], 1)
+    .unwrap();
+
+// The first feature decides the class.
+assert_eq!(explanation.weights[0].0, 0);
+```
+!*/
+
+use crate::error::{check_lengths, Error, Result};
+use crate::math::linalg::solve;
+use crate::math::random::Rng;
+use crate::traits::PredictProba;
+
+/// Samples in the surrogate's representation and as model inputs.
+type Samples = (Vec<Vec<f64>>, Vec<Vec<f64>>);
+
+/// How the samples around the explained one are generated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Perturbation {
+    /// Add Gaussian noise with the given standard deviation to each feature, such as the `scale`
+    /// of a fitted `StandardScaler`. Feature weights are the change in probability per standard
+    /// deviation.
+    Gaussian(Vec<f64>),
+    /// Set each feature to 0 with the given probability, in `(0, 1)`. Feature weights are the
+    /// change in probability from keeping the feature.
+    Dropout(f64),
+}
+
+/// Explains single predictions with a local linear surrogate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lime {
+    perturbation: Perturbation,
+    n_samples: usize,
+    kernel_width: Option<f64>,
+    max_features: Option<usize>,
+    alpha: f64,
+    seed: Option<u64>,
+}
+
+/// A local explanation of a prediction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LimeExplanation {
+    /// The explained class.
+    pub class: usize,
+    /// The intercept of the surrogate.
+    pub intercept: f64,
+    /// The index and coefficient of each feature in the surrogate, by decreasing magnitude.
+    pub weights: Vec<(usize, f64)>,
+    /// The weighted R² of the surrogate on the samples, measuring how faithful it is locally.
+    pub score: f64,
+}
+
+impl Lime {
+    /// Explain with 1000 samples generated by `perturbation`, using every feature.
+    pub fn new(perturbation: Perturbation) -> Lime {
+        Lime {
+            perturbation,
+            n_samples: 1000,
+            kernel_width: None,
+            max_features: None,
+            alpha: 1.0,
+            seed: None,
+        }
+    }
+
+    /// The number of samples to generate, including the explained one.
+    pub fn n_samples(mut self, n_samples: usize) -> Self {
+        self.n_samples = n_samples;
+        self
+    }
+
+    /// The width of the exponential kernel weighting the samples by closeness. Defaults to
+    /// `0.75 * sqrt(n_features)`.
+    pub fn kernel_width(mut self, width: f64) -> Self {
+        self.kernel_width = Some(width);
+        self
+    }
+
+    /// The number of features to keep in the explanation.
+    pub fn max_features(mut self, max_features: usize) -> Self {
+        self.max_features = Some(max_features);
+        self
+    }
+
+    /// The ridge penalty of the surrogate. Defaults to 1.
+    pub fn alpha(mut self, alpha: f64) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// The seed used to generate the samples.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Explain the probability `model` gives `x` for `class`.
+    /// Returns an error if the options are invalid, the model fails, or `class` is not one of
+    /// its classes.
+    pub fn explain<P: PredictProba<f64>>(
+        &self,
+        model: &P,
+        x: &[f64],
+        class: usize,
+    ) -> Result<LimeExplanation> {
+        if self.n_samples < 2 {
+            return Err(Error::InvalidInput("LIME needs at least 2 samples.".into()));
+        }
+        if self.alpha < 0.0 {
+            return Err(Error::InvalidInput(format!(
+                "Ridge penalty {} is negative.",
+                self.alpha
+            )));
+        }
+        let (z, inputs) = self.perturb(x)?;
+
+        let width = self
+            .kernel_width
+            .unwrap_or_else(|| 0.75 * (x.len() as f64).sqrt());
+        let weights: Vec<f64> = z
+            .iter()
+            .map(|zi| {
+                let d2: f64 = match self.perturbation {
+                    Perturbation::Gaussian(_) => zi.iter().map(|v| v * v).sum(),
+                    Perturbation::Dropout(_) => zi.iter().map(|v| (1.0 - v).powi(2)).sum(),
+                };
+                (-d2 / (width * width)).exp().sqrt()
+            })
+            .collect();
+
+        let y = model
+            .predict_proba(&inputs)?
+            .into_iter()
+            .map(|p| {
+                p.get(class).copied().ok_or_else(|| {
+                    Error::InvalidInput(format!(
+                        "Class {} is not one of the model's {} classes.",
+                        class,
+                        p.len()
+                    ))
+                })
+            })
+            .collect::<Result<Vec<f64>>>()?;
+
+        let all: Vec<usize> = (0..x.len()).collect();
+        let (mut intercept, mut coefs) = weighted_ridge(&z, &all, &y, &weights, self.alpha)?;
+        let mut columns = all;
+        if let Some(max) = self.max_features.filter(|&m| m < x.len()) {
+            columns.sort_by(|&a, &b| coefs[b].abs().total_cmp(&coefs[a].abs()));
+            columns.truncate(max);
+            let refit = weighted_ridge(&z, &columns, &y, &weights, self.alpha)?;
+            intercept = refit.0;
+            coefs = refit.1;
+        }
+
+        let score = weighted_r2(&z, &columns, &y, &weights, intercept, &coefs);
+        let mut feature_weights: Vec<(usize, f64)> = columns.into_iter().zip(coefs).collect();
+        feature_weights.sort_by(|a, b| b.1.abs().total_cmp(&a.1.abs()));
+        Ok(LimeExplanation {
+            class,
+            intercept,
+            weights: feature_weights,
+            score,
+        })
+    }
+
+    /// Generate the samples in the surrogate's representation and as model inputs. The first
+    /// sample is `x` itself.
+    fn perturb(&self, x: &[f64]) -> Result<Samples> {
+        let mut rng = Rng::from_seed(self.seed);
+        let mut z = Vec::with_capacity(self.n_samples);
+        let mut inputs = Vec::with_capacity(self.n_samples);
+        match &self.perturbation {
+            Perturbation::Gaussian(scale) => {
+                check_lengths("Features and scales", x.len(), scale.len())?;
+                z.push(vec![0.0; x.len()]);
+                inputs.push(x.to_vec());
+                for _ in 1..self.n_samples {
+                    let noise: Vec<f64> = scale
+                        .iter()
+                        .map(|&s| if s > 0.0 { rng.next_normal() } else { 0.0 })
+                        .collect();
+                    inputs.push(
+                        x.iter()
+                            .zip(&noise)
+                            .zip(scale)
+                            .map(|((v, e), s)| v + e * s)
+                            .collect(),
+                    );
+                    z.push(noise);
+                }
+            }
+            Perturbation::Dropout(p) => {
+                if !(*p > 0.0 && *p < 1.0) {
+                    return Err(Error::InvalidInput(format!(
+                        "Dropout probability {} is not in (0, 1).",
+                        p
+                    )));
+                }
+                z.push(vec![1.0; x.len()]);
+                inputs.push(x.to_vec());
+                for _ in 1..self.n_samples {
+                    let mask: Vec<f64> = x
+                        .iter()
+                        .map(|_| if rng.next_f64() < *p { 0.0 } else { 1.0 })
+                        .collect();
+                    inputs.push(x.iter().zip(&mask).map(|(v, m)| v * m).collect());
+                    z.push(mask);
+                }
+            }
+        }
+        Ok((z, inputs))
+    }
+}
+
+/// Fit `y` to the `columns` of `z` by weighted ridge regression, without penalizing the
+/// intercept. Returns the intercept and the coefficient of each column.
+fn weighted_ridge(
+    z: &[Vec<f64>],
+    columns: &[usize],
+    y: &[f64],
+    weights: &[f64],
+    alpha: f64,
+) -> Result<(f64, Vec<f64>)> {
+    let total: f64 = weights.iter().sum();
+    let weighted_mean =
+        |f: &dyn Fn(usize) -> f64| (0..z.len()).map(|i| weights[i] * f(i)).sum::<f64>() / total;
+    let z_mean: Vec<f64> = columns
+        .iter()
+        .map(|&j| weighted_mean(&|i| z[i][j]))
+        .collect();
+    let y_mean = weighted_mean(&|i| y[i]);
+
+    // The normal equations (Zc' W Zc + alpha I) b = Zc' W yc of the centered data.
+    let p = columns.len();
+    let mut a = vec![vec![0.0; p]; p];
+    let mut b = vec![0.0; p];
+    for i in 0..z.len() {
+        let zc: Vec<f64> = columns
+            .iter()
+            .zip(&z_mean)
+            .map(|(&j, m)| z[i][j] - m)
+            .collect();
+        for r in 0..p {
+            b[r] += weights[i] * zc[r] * (y[i] - y_mean);
+            for c in 0..p {
+                a[r][c] += weights[i] * zc[r] * zc[c];
+            }
+        }
+    }
+    (0..p).for_each(|r| a[r][r] += alpha);
+
+    let coefs = solve(a, b).ok_or_else(|| {
+        Error::InvalidInput("The surrogate is singular; use a positive ridge penalty.".into())
+    })?;
+    let intercept = y_mean - coefs.iter().zip(&z_mean).map(|(c, m)| c * m).sum::<f64>();
+    Ok((intercept, coefs))
+}
+
+/// The weighted coefficient of determination of the surrogate.
+fn weighted_r2(
+    z: &[Vec<f64>],
+    columns: &[usize],
+    y: &[f64],
+    weights: &[f64],
+    intercept: f64,
+    coefs: &[f64],
+) -> f64 {
+    let total: f64 = weights.iter().sum();
+    let y_mean = y.iter().zip(weights).map(|(y, w)| y * w).sum::<f64>() / total;
+    let (mut ss_res, mut ss_tot) = (0.0, 0.0);
+    for i in 0..z.len() {
+        let pred = intercept
+            + columns
+                .iter()
+                .zip(coefs)
+                .map(|(&j, c)| c * z[i][j])
+                .sum::<f64>();
+        ss_res += weights[i] * (y[i] - pred).powi(2);
+        ss_tot += weights[i] * (y[i] - y_mean).powi(2);
+    }
+    if ss_tot > 0.0 {
+        1.0 - ss_res / ss_tot
+    } else {
+        1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A logistic model of the first feature, and the presence of the third.
+    struct Model;
+
+    impl PredictProba<f64> for Model {
+        fn predict_proba(&self, x: &[Vec<f64>]) -> Result<Vec<Vec<f64>>> {
+            Ok(x.iter()
+                .map(|row| {
+                    let logit = 2.0 * row[0] + if row[2] != 0.0 { 3.0 } else { 0.0 };
+                    let p = 1.0 / (1.0 + (-logit).exp());
+                    vec![1.0 - p, p]
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn gaussian_test() {
+        let explanation = Lime::new(Perturbation::Gaussian(vec![0.1, 0.1, 0.0]))
+            .seed(3)
+            .explain(&Model, &[0.0, 5.0, 1.0], 1)
+            .unwrap();
+
+        assert_eq!(explanation.weights[0].0, 0);
+        assert!(explanation.weights[0].1 > 0.0);
+        assert!(explanation.weights[1].1.abs() < 0.01);
+        assert!(explanation.score > 0.9);
+    }
+
+    #[test]
+    fn dropout_test() {
+        let explanation = Lime::new(Perturbation::Dropout(0.5))
+            .seed(3)
+            .max_features(1)
+            .explain(&Model, &[0.0, 1.0, 1.0], 1)
+            .unwrap();
+
+        assert_eq!(explanation.weights.len(), 1);
+        assert_eq!(explanation.weights[0].0, 2);
+        assert!(explanation.weights[0].1 > 0.0);
+    }
+
+    #[test]
+    fn invalid_test() {
+        let lime = Lime::new(Perturbation::Dropout(0.5));
+
+        assert!(lime.explain(&Model, &[0.0, 1.0, 1.0], 2).is_err());
+        assert!(Lime::new(Perturbation::Dropout(1.0))
+            .explain(&Model, &[0.0, 1.0, 1.0], 1)
+            .is_err());
+        assert!(Lime::new(Perturbation::Gaussian(vec![1.0]))
+            .explain(&Model, &[0.0, 1.0, 1.0], 1)
+            .is_err());
+    }
+}
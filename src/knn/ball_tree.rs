@@ -0,0 +1,321 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A ball tree over the training points of a KNN.
+
+/*!
+Each node of the tree covers a ball: its points' centroid and the largest distance from it to
+one of its points. Nodes are split at the median of the feature with the largest spread until
+they hold at most `LEAF_SIZE` points. A search skips every ball that is further away than the
+k-th nearest point found so far, which only relies on the triangle inequality, so the tree works
+with the metrics in `math::distance`: euclidean, Manhattan, Minkowski and Hamming distance.
+!*/
+
+use crate::math::dense::DenseMatrix;
+use crate::math::distance::{self, Distance};
+use crate::math::Float;
+use std::cmp::Ordering;
+
+/// The largest number of points in a leaf.
+const LEAF_SIZE: usize = 16;
+
+/// A ball tree holding the indices of the points it was built on.
+#[derive(Debug, Clone)]
+pub(crate) struct BallTree<F> {
+    distance: Distance,
+    indices: Vec<usize>,
+    nodes: Vec<Node<F>>,
+}
+
+#[derive(Debug, Clone)]
+struct Node<F> {
+    center: Vec<F>,
+    radius: F,
+    start: usize,
+    end: usize,
+    children: Option<(usize, usize)>,
+}
+
+impl<F: Float> BallTree<F> {
+    /// Build a tree over the points `x` with the given distance.
+    pub(crate) fn new(x: &DenseMatrix<F>, distance: Distance) -> BallTree<F> {
+        let mut tree = BallTree {
+            distance,
+            indices: (0..x.n_rows()).collect(),
+            nodes: Vec::new(),
+        };
+        if !x.is_empty() {
+            tree.build(x, 0, x.n_rows());
+        }
+        tree
+    }
+
+    /// Whether the tree was built over `n` points with `distance`.
+    pub(crate) fn matches(&self, n: usize, distance: Distance) -> bool {
+        self.indices.len() == n && self.distance == distance
+    }
+
+    /// The heap memory of the tree, in bytes.
+    pub(crate) fn memory_usage(&self) -> usize {
+        self.indices.capacity() * std::mem::size_of::<usize>()
+            + self.nodes.capacity() * std::mem::size_of::<Node<F>>()
+            + self
+                .nodes
+                .iter()
+                .map(|n| n.center.capacity() * std::mem::size_of::<F>())
+                .sum::<usize>()
+    }
+
+    /// The indices and distances of the `k` points of `x` nearest to `q`, nearest first. Ties
+    /// are broken by index. `x` must be the points the tree was built on.
+    pub(crate) fn query(&self, x: &DenseMatrix<F>, q: &[F], k: usize) -> Vec<(usize, F)> {
+        let mut best = Vec::with_capacity(k + 1);
+        if let Some(root) = self.nodes.first() {
+            let bound = self.bound(q, root);
+            self.search(x, q, k, 0, bound, &mut best);
+        }
+        best
+    }
+
+    /// The indices and distances of the points of `x` within `radius` of `q`, in no particular
+    /// order. `x` must be the points the tree was built on.
+    pub(crate) fn within(&self, x: &DenseMatrix<F>, q: &[F], radius: F) -> Vec<(usize, F)> {
+        let mut found = Vec::new();
+        if !self.nodes.is_empty() {
+            self.search_within(x, q, radius, 0, &mut found);
+        }
+        found
+    }
+
+    fn dist(&self, p: &[F], q: &[F]) -> F {
+        match self.distance {
+            Distance::Euclidean => distance::euclidean_distance(p, q),
+            Distance::Manhattan => distance::manhattan_distance(p, q),
+            Distance::Minkowski(power) => distance::minkowski_distance(p, q, power),
+            Distance::Hamming => distance::hamming_distance(p, q),
+            Distance::Cosine => distance::cosine_distance(p, q),
+            Distance::Precomputed => unreachable!("Precomputed distances are not indexed."),
+        }
+    }
+
+    /// A lower bound on the distance from `q` to any point in `node`.
+    fn bound(&self, q: &[F], node: &Node<F>) -> F {
+        let d = self.dist(q, &node.center) - node.radius;
+        if d > F::zero() {
+            d
+        } else {
+            F::zero()
+        }
+    }
+
+    /// Build the node covering `indices[start..end]` and its descendants, returning its index.
+    fn build(&mut self, x: &DenseMatrix<F>, start: usize, end: usize) -> usize {
+        let n_features = x.n_cols();
+        let mut center = vec![F::zero(); n_features];
+        for &i in &self.indices[start..end] {
+            center.iter_mut().zip(x.row(i)).for_each(|(c, &v)| *c += v);
+        }
+        let n = F::from_f64((end - start) as f64);
+        center.iter_mut().for_each(|c| *c /= n);
+        let radius = self.indices[start..end]
+            .iter()
+            .map(|&i| self.dist(&center, x.row(i)))
+            .fold(F::zero(), |a, b| if b > a { b } else { a });
+
+        let node = self.nodes.len();
+        self.nodes.push(Node {
+            center,
+            radius,
+            start,
+            end,
+            children: None,
+        });
+        if end - start > LEAF_SIZE {
+            let dim = self.widest_feature(x, start, end, n_features);
+            let mid = start + (end - start) / 2;
+            self.indices[start..end].select_nth_unstable_by(mid - start, |&a, &b| {
+                x.row(a)[dim].total_cmp(&x.row(b)[dim])
+            });
+            let left = self.build(x, start, mid);
+            let right = self.build(x, mid, end);
+            self.nodes[node].children = Some((left, right));
+        }
+        node
+    }
+
+    /// The feature of `indices[start..end]` with the largest range.
+    fn widest_feature(
+        &self,
+        x: &DenseMatrix<F>,
+        start: usize,
+        end: usize,
+        n_features: usize,
+    ) -> usize {
+        (0..n_features)
+            .map(|j| {
+                let first = x.row(self.indices[start])[j];
+                let (mut min, mut max) = (first, first);
+                for &i in &self.indices[start..end] {
+                    let v = x.row(i)[j];
+                    if v < min {
+                        min = v;
+                    }
+                    if v > max {
+                        max = v;
+                    }
+                }
+                (j, max - min)
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map_or(0, |(j, _)| j)
+    }
+
+    /// Add the points of `node` nearer than the current k-th nearest to `best`.
+    fn search(
+        &self,
+        x: &DenseMatrix<F>,
+        q: &[F],
+        k: usize,
+        node: usize,
+        bound: F,
+        best: &mut Vec<(usize, F)>,
+    ) {
+        if best.len() == k && bound > best[k - 1].1 {
+            return;
+        }
+        let node = &self.nodes[node];
+        match node.children {
+            None => {
+                for &i in &self.indices[node.start..node.end] {
+                    insert(best, k, (i, self.dist(q, x.row(i))));
+                }
+            }
+            Some((left, right)) => {
+                let left_bound = self.bound(q, &self.nodes[left]);
+                let right_bound = self.bound(q, &self.nodes[right]);
+                if left_bound <= right_bound {
+                    self.search(x, q, k, left, left_bound, best);
+                    self.search(x, q, k, right, right_bound, best);
+                } else {
+                    self.search(x, q, k, right, right_bound, best);
+                    self.search(x, q, k, left, left_bound, best);
+                }
+            }
+        }
+    }
+
+    /// Add the points of `node` within `radius` of `q` to `found`.
+    fn search_within(
+        &self,
+        x: &DenseMatrix<F>,
+        q: &[F],
+        radius: F,
+        node: usize,
+        found: &mut Vec<(usize, F)>,
+    ) {
+        let node = &self.nodes[node];
+        if self.bound(q, node) > radius {
+            return;
+        }
+        match node.children {
+            None => {
+                for &i in &self.indices[node.start..node.end] {
+                    let d = self.dist(q, x.row(i));
+                    if d <= radius {
+                        found.push((i, d));
+                    }
+                }
+            }
+            Some((left, right)) => {
+                self.search_within(x, q, radius, left, found);
+                self.search_within(x, q, radius, right, found);
+            }
+        }
+    }
+}
+
+/// Insert `point` into `best`, sorted by distance then index, keeping at most `k` points.
+fn insert<F: Float>(best: &mut Vec<(usize, F)>, k: usize, point: (usize, F)) {
+    let position = best
+        .partition_point(|&(i, d)| d.total_cmp(&point.1).then(i.cmp(&point.0)) == Ordering::Less);
+    if position < k {
+        best.insert(position, point);
+        best.truncate(k);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::random::Rng;
+
+    fn brute_force(
+        x: &DenseMatrix<f64>,
+        q: &[f64],
+        k: usize,
+        distance: Distance,
+    ) -> Vec<(usize, f64)> {
+        let tree = BallTree::new(&DenseMatrix::new(0), distance);
+        let mut all: Vec<(usize, f64)> = x
+            .rows()
+            .enumerate()
+            .map(|(i, p)| (i, tree.dist(q, p)))
+            .collect();
+        all.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        all.truncate(k);
+        all
+    }
+
+    #[test]
+    fn query_test() {
+        let mut rng = Rng::new(7);
+        let x: Vec<Vec<f64>> = (0..500)
+            .map(|_| (0..5).map(|_| rng.next_f64()).collect())
+            .collect();
+        let x = DenseMatrix::from_rows(&x).unwrap();
+
+        for &distance in &[Distance::Euclidean, Distance::Manhattan] {
+            let tree = BallTree::new(&x, distance);
+            assert!(tree.matches(500, distance));
+            for _ in 0..20 {
+                let q: Vec<f64> = (0..5).map(|_| rng.next_f64()).collect();
+                assert_eq!(tree.query(&x, &q, 7), brute_force(&x, &q, 7, distance));
+
+                let mut within = tree.within(&x, &q, 0.4);
+                within.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+                let mut expected = brute_force(&x, &q, 500, distance);
+                expected.retain(|p| p.1 <= 0.4);
+                assert_eq!(within, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn duplicates_test() {
+        let x = DenseMatrix::from_rows(&vec![vec![1.0, 1.0]; 40]).unwrap();
+        let tree = BallTree::new(&x, Distance::Euclidean);
+        let nearest = tree.query(&x, &[0.0, 0.0], 3);
+
+        assert_eq!(
+            nearest.iter().map(|p| p.0).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        assert!(BallTree::new(&DenseMatrix::new(0), Distance::Euclidean)
+            .query(&x, &[0.0], 1)
+            .is_empty());
+    }
+}
@@ -0,0 +1,298 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! An approximate nearest neighbor index for a KNN: a hierarchical navigable small world graph.
+
+/*!
+Every point is a node on layer 0 of the graph and, with exponentially falling probability, on
+the layers above it, where it is linked to up to `m` nearby points (`2 * m` on layer 0). A search
+walks greedily from the single entry point on the top layer down to layer 1, then explores
+layer 0 keeping the `ef` nearest points seen. Larger `m`, `ef_construction` and `ef` find the
+true nearest neighbors more often, at the cost of memory and time.
+
+See Malkov and Yashunin, "Efficient and robust approximate nearest neighbor search using
+Hierarchical Navigable Small World graphs" (2016).
+!*/
+
+use crate::math::dense::DenseMatrix;
+use crate::math::distance::{self, Distance};
+use crate::math::random::Rng;
+use crate::math::Float;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
+
+/// A graph over the indices of the points it was built on.
+#[derive(Debug, Clone)]
+pub(crate) struct Hnsw {
+    distance: Distance,
+    m: usize,
+    ef_construction: usize,
+    ef: usize,
+    /// The neighbors of each point on each layer it is on.
+    links: Vec<Vec<Vec<usize>>>,
+    entry: Option<usize>,
+    rng: Rng,
+}
+
+/// A point on a search frontier, ordered by distance then index.
+struct Candidate<F>(F, usize);
+
+impl<F: Float> PartialEq for Candidate<F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<F: Float> Eq for Candidate<F> {}
+
+impl<F: Float> PartialOrd for Candidate<F> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<F: Float> Ord for Candidate<F> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then(self.1.cmp(&other.1))
+    }
+}
+
+impl Hnsw {
+    /// Build a graph over the points `x` with the given distance. `m` is at least 2, and
+    /// `ef_construction` and `ef` at least 1. The layers of the points are drawn from `seed`,
+    /// or the global seed if None.
+    pub(crate) fn new<F: Float>(
+        x: &DenseMatrix<F>,
+        distance: Distance,
+        m: usize,
+        ef_construction: usize,
+        ef: usize,
+        seed: Option<u64>,
+    ) -> Hnsw {
+        let mut graph = Hnsw {
+            distance,
+            m: m.max(2),
+            ef_construction: ef_construction.max(1),
+            ef: ef.max(1),
+            links: Vec::with_capacity(x.n_rows()),
+            entry: None,
+            rng: Rng::from_seed(seed),
+        };
+        graph.extend(x);
+        graph
+    }
+
+    /// Insert the points of `x` after the ones already in the graph.
+    pub(crate) fn extend<F: Float>(&mut self, x: &DenseMatrix<F>) {
+        for i in self.links.len()..x.n_rows() {
+            self.insert(x, i);
+        }
+    }
+
+    /// Whether the graph holds up to `n` points with `distance`, so that it can be extended to
+    /// the first `n` points.
+    pub(crate) fn matches(&self, n: usize, distance: Distance) -> bool {
+        self.links.len() <= n && self.distance == distance
+    }
+
+    /// The number of points in the graph.
+    pub(crate) fn len(&self) -> usize {
+        self.links.len()
+    }
+
+    /// The heap memory of the graph, in bytes.
+    pub(crate) fn memory_usage(&self) -> usize {
+        let node = std::mem::size_of::<Vec<usize>>();
+        self.links.capacity() * std::mem::size_of::<Vec<Vec<usize>>>()
+            + self
+                .links
+                .iter()
+                .flatten()
+                .map(|l| node + l.capacity() * std::mem::size_of::<usize>())
+                .sum::<usize>()
+    }
+
+    /// The indices and distances of approximately the `k` points of `x` nearest to `q`,
+    /// nearest first. `x` must be the points the graph was built on.
+    pub(crate) fn query<F: Float>(&self, x: &DenseMatrix<F>, q: &[F], k: usize) -> Vec<(usize, F)> {
+        let entry = match self.entry {
+            Some(entry) => entry,
+            None => return Vec::new(),
+        };
+        let mut nearest = vec![Candidate(self.dist(q, x.row(entry)), entry)];
+        for layer in (1..self.links[entry].len()).rev() {
+            nearest = self.search_layer(x, q, nearest, 1, layer);
+        }
+        self.search_layer(x, q, nearest, self.ef.max(k), 0)
+            .into_iter()
+            .take(k)
+            .map(|Candidate(d, i)| (i, d))
+            .collect()
+    }
+
+    fn dist<F: Float>(&self, p: &[F], q: &[F]) -> F {
+        match self.distance {
+            Distance::Euclidean => distance::euclidean_distance(p, q),
+            Distance::Manhattan => distance::manhattan_distance(p, q),
+            Distance::Minkowski(power) => distance::minkowski_distance(p, q, power),
+            Distance::Hamming => distance::hamming_distance(p, q),
+            Distance::Cosine => distance::cosine_distance(p, q),
+            Distance::Precomputed => unreachable!("Precomputed distances are not indexed."),
+        }
+    }
+
+    /// Link point `i` of `x` into the graph.
+    fn insert<F: Float>(&mut self, x: &DenseMatrix<F>, i: usize) {
+        let ml = 1.0 / (self.m as f64).ln();
+        let level = (-(1.0 - self.rng.next_f64()).ln() * ml).floor() as usize;
+        self.links.push(vec![Vec::new(); level + 1]);
+        let entry = match self.entry {
+            Some(entry) => entry,
+            None => {
+                self.entry = Some(i);
+                return;
+            }
+        };
+
+        let q = x.row(i);
+        let top = self.links[entry].len() - 1;
+        let mut nearest = vec![Candidate(self.dist(q, x.row(entry)), entry)];
+        for layer in (level + 1..=top).rev() {
+            nearest = self.search_layer(x, q, nearest, 1, layer);
+        }
+        for layer in (0..=level.min(top)).rev() {
+            nearest = self.search_layer(x, q, nearest, self.ef_construction, layer);
+            let max_links = if layer == 0 { 2 * self.m } else { self.m };
+            let neighbors: Vec<usize> = nearest.iter().take(self.m).map(|c| c.1).collect();
+            for &n in &neighbors {
+                self.links[n][layer].push(i);
+                if self.links[n][layer].len() > max_links {
+                    self.prune(x, n, layer, max_links);
+                }
+            }
+            self.links[i][layer] = neighbors;
+        }
+        if level > top {
+            self.entry = Some(i);
+        }
+    }
+
+    /// Keep the `max_links` nearest neighbors of point `n` on `layer`.
+    fn prune<F: Float>(&mut self, x: &DenseMatrix<F>, n: usize, layer: usize, max_links: usize) {
+        let mut links: Vec<Candidate<F>> = self.links[n][layer]
+            .iter()
+            .map(|&j| Candidate(self.dist(x.row(n), x.row(j)), j))
+            .collect();
+        links.sort_unstable();
+        self.links[n][layer] = links.into_iter().take(max_links).map(|c| c.1).collect();
+    }
+
+    /// The up to `ef` nearest points to `q` on `layer` reachable from `entry`, nearest first.
+    fn search_layer<F: Float>(
+        &self,
+        x: &DenseMatrix<F>,
+        q: &[F],
+        entry: Vec<Candidate<F>>,
+        ef: usize,
+        layer: usize,
+    ) -> Vec<Candidate<F>> {
+        let mut visited: HashSet<usize> = entry.iter().map(|c| c.1).collect();
+        let mut candidates: BinaryHeap<Reverse<Candidate<F>>> =
+            entry.iter().map(|c| Reverse(Candidate(c.0, c.1))).collect();
+        let mut found: BinaryHeap<Candidate<F>> = entry.into_iter().collect();
+        while found.len() > ef {
+            found.pop();
+        }
+
+        while let Some(Reverse(candidate)) = candidates.pop() {
+            if found.len() >= ef && found.peek().is_some_and(|worst| candidate > *worst) {
+                break;
+            }
+            for &n in &self.links[candidate.1][layer] {
+                if !visited.insert(n) {
+                    continue;
+                }
+                let next = Candidate(self.dist(q, x.row(n)), n);
+                if found.len() < ef || found.peek().is_none_or(|worst| next < *worst) {
+                    candidates.push(Reverse(Candidate(next.0, next.1)));
+                    found.push(next);
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+        found.into_sorted_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recall_test() {
+        let mut rng = Rng::new(5);
+        let mut points = |n| -> Vec<Vec<f64>> {
+            (0..n)
+                .map(|_| (0..8).map(|_| rng.next_f64()).collect())
+                .collect()
+        };
+        let x = DenseMatrix::from_rows(&points(1000)).unwrap();
+        let queries = points(50);
+        let graph = Hnsw::new(&x, Distance::Euclidean, 8, 64, 32, Some(3));
+        let same = Hnsw::new(&x, Distance::Euclidean, 8, 64, 32, Some(3));
+        assert_eq!(graph.links, same.links);
+
+        let mut hits = 0;
+        for q in &queries {
+            let mut exact: Vec<(usize, f64)> = x
+                .rows()
+                .enumerate()
+                .map(|(i, p)| (i, graph.dist(q, p)))
+                .collect();
+            exact.sort_by(|a, b| a.1.total_cmp(&b.1));
+            let found = graph.query(&x, q, 5);
+            assert_eq!(found.len(), 5);
+            assert!(found.windows(2).all(|w| w[0].1 <= w[1].1));
+            hits += exact[..5]
+                .iter()
+                .filter(|e| found.iter().any(|f| f.0 == e.0))
+                .count();
+        }
+        assert!(hits as f64 / 250.0 > 0.9);
+    }
+
+    #[test]
+    fn extend_test() {
+        let rows: Vec<Vec<f64>> = (0..50).map(|i| vec![i as f64]).collect();
+        let x = DenseMatrix::from_rows(&rows).unwrap();
+        let first = DenseMatrix::from_rows(&rows[..10]).unwrap();
+        let mut graph = Hnsw::new(&first, Distance::Manhattan, 4, 16, 16, None);
+        graph.extend(&x);
+
+        assert_eq!(graph.len(), 50);
+        assert!(graph.matches(50, Distance::Manhattan));
+        let nearest: Vec<usize> = graph.query(&x, &[30.2], 2).iter().map(|p| p.0).collect();
+        assert_eq!(nearest, vec![30, 31]);
+        assert!(
+            Hnsw::new::<f64>(&DenseMatrix::new(0), Distance::Euclidean, 4, 4, 4, None)
+                .query(&x, &[0.0], 1)
+                .is_empty()
+        );
+    }
+}
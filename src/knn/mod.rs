@@ -0,0 +1,2039 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Implementation for K-Nearest Neighbors.
+
+/*!
+Allows for predicting data based on a KNN search.
+A full, working example is contained in the `examples/knn` directory.
+
+# Example
+```rust
+use rml::knn;
+use rml::math;
+
+// Collect and parse data to a format consistent with:
+type CSVOutput = (Vec<Vec<f64>>, Vec<i32>);
+
+let training_data: CSVOutput = (
+    vec![vec![0.0, 1.0], vec![0.1, 0.9], vec![1.0, 0.0], vec![0.9, 0.2]],
+    vec![0, 0, 1, 1],
+);
+let testing_data: CSVOutput = (vec![vec![0.2, 1.0], vec![1.0, 0.1]], vec![0, 1]);
+
+// Create a new KNN with the 3 nearest neighbors voting, euclidean distance and L2
+// normalization.
+let knn: knn::KNN = knn::KNNBuilder::new()
+    .k(3)
+    .normalize(math::norm::Norm::L2)
+    .build(training_data.0, training_data.1)
+    .unwrap();
+
+// Get a prediction for each point of the testing data.
+let pred: Vec<i32> = testing_data
+    .0
+    .iter()
+    .map(|x| knn.predict(x))
+    .collect::<Result<_, _>>()
+    .unwrap();
+
+// Count the number that were predicted correctly.
+let num_correct = pred
+    .iter()
+    .cloned()
+    .zip(&testing_data.1)
+    .filter(|(a, b)| *a == **b)
+    .count();
+
+println!(
+    "Accuracy: {}",
+    (num_correct as f64) / (pred.len() as f64)
+);
+
+```
+!*/
+
+pub(crate) mod ball_tree;
+mod hnsw;
+mod propagation;
+mod radius;
+mod reduction;
+mod tuning;
+
+pub use self::propagation::LabelPropagation;
+pub use self::radius::RadiusNeighborsClassifier;
+pub use self::tuning::KSelection;
+
+use self::ball_tree::BallTree;
+use self::hnsw::Hnsw;
+use crate::artifact::{self, Header};
+use crate::datasets::cache::CacheElement;
+use crate::datasets::Dataset;
+use crate::describe::{Describe, Summary};
+use crate::error::{check_lengths, check_weights, Error, Result};
+use crate::math::dense::DenseMatrix;
+use crate::math::distance;
+use crate::math::norm;
+use crate::math::Float;
+use crate::parallel::{self, Parallelism};
+use crate::traits::{Fit, FitWeighted, IncrementalFit, Predict, PredictProba};
+use rayon::prelude::*;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+#[cfg(feature = "fs")]
+use std::fs::File;
+#[cfg(feature = "fs")]
+use std::io::{BufReader, BufWriter};
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrdering};
+
+/// KNN struct handles the computation and data for the K-Nearest Neighbors algorithm.
+/// It is *highly recommended* to not change values inside of this struct manually. Always
+/// create a new one using ::new.
+///
+/// `F` is the floating point type the features are stored and compared in, `f64` by default.
+/// Use `KNN<f32>` to halve the memory of large training sets.
+#[derive(Debug, Clone)]
+pub struct KNN<F = f64> {
+    /// K-Nearest to analyze
+    pub k: i32,
+    /// Features, one row per training point.
+    pub x: DenseMatrix<F>,
+    /// Class labels for each feature.
+    pub y: Vec<i32>,
+    /// The vote weight of each training point, or None to weigh them equally.
+    pub weights: Option<Vec<f64>>,
+    /// The distinct labels in `y`, in sorted order. Votes and probabilities are in this order.
+    pub classes: Vec<i32>,
+    /// Number of labels.
+    pub num_labels: usize,
+    /// Type of distance to use.
+    pub distance: Option<distance::Distance>,
+    /// A distance used instead of `distance`, or None.
+    pub custom_distance: Option<distance::CustomDistance<F>>,
+    /// The type of normalization, or None.
+    pub normalize: Option<norm::Norm>,
+    /// How each feature is rescaled before normalization, or None.
+    pub standardize: Option<Standardization>,
+    /// How the votes of the k nearest neighbors are weighed.
+    pub weighting: Weighting,
+    /// The parallelism of distance computations, or None for the global setting.
+    pub parallelism: Option<Parallelism>,
+    /// The seed used to shuffle the samples in [`KNN::tune_k`], or None for the global seed.
+    pub seed: Option<u64>,
+    /// How the nearest neighbors are searched for.
+    pub index: IndexKind,
+    neighbor_index: Option<NeighborIndex<F>>,
+    /// The L2 norm of each training point, for cosine distance.
+    norms: Vec<F>,
+    /// The offset and scale of each feature learned for standardization.
+    offsets: Vec<F>,
+    scales: Vec<F>,
+}
+
+/// How a KNN searches for the nearest neighbors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IndexKind {
+    /// Measure the distance to every training point, in parallel.
+    #[default]
+    BruteForce,
+    /// Search a ball tree built when the KNN is fit. Faster on large training sets with
+    /// structure, at the cost of building the tree and some memory. Cosine distance is not a
+    /// metric, so a KNN with cosine distance measures the distance to every training point
+    /// instead.
+    BallTree,
+    /// Search a hierarchical navigable small world graph built when the KNN is fit. Much faster
+    /// on large, high-dimensional training sets, but approximate: some of the neighbors found
+    /// may not be among the k nearest. See [`IndexKind::hnsw`] for typical parameters.
+    Hnsw {
+        /// The number of links per point and layer, at least 2. Usually 8 to 48.
+        m: usize,
+        /// The number of candidates kept while building the graph.
+        ef_construction: usize,
+        /// The number of candidates kept while searching, at least k.
+        ef: usize,
+        /// The seed used to choose the layers of the points, or None for the global seed.
+        seed: Option<u64>,
+    },
+}
+
+impl IndexKind {
+    /// A graph index with `m = 16`, `ef_construction = 200`, `ef = 50` and the global seed.
+    pub fn hnsw() -> IndexKind {
+        IndexKind::Hnsw {
+            m: 16,
+            ef_construction: 200,
+            ef: 50,
+            seed: None,
+        }
+    }
+}
+
+/// A built neighbor index.
+#[derive(Debug, Clone)]
+enum NeighborIndex<F> {
+    BallTree(BallTree<F>),
+    Hnsw(Hnsw),
+}
+
+/// How the votes of the k nearest neighbors are weighed, on top of any sample weights.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Weighting {
+    /// Each neighbor has one vote.
+    #[default]
+    Uniform,
+    /// Each neighbor votes with the inverse of its distance. If some neighbors are at a distance
+    /// of 0, only they vote.
+    Distance,
+}
+
+/// How a KNN rescales each feature, with statistics learned from the training data. Unlike
+/// normalization, which scales each sample, this puts features measured in different units on
+/// the same scale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Standardization {
+    /// Subtract the mean and divide by the standard deviation.
+    ZScore,
+    /// Subtract the minimum and divide by the range, mapping the training data to `0..=1`.
+    MinMax,
+}
+
+/// Builds a [`KNN`] from named settings, then fits it with [`KNNBuilder::build`].
+///
+/// # Example
+/// ```rust
+/// use rml::knn::{KNNBuilder, Weighting, KNN};
+/// use rml::math::distance::Distance;
+///
+/// let knn: KNN = KNNBuilder::new()
+///     .k(1)
+///     .distance(Distance::Manhattan)
+///     .weighting(Weighting::Distance)
+///     .build(vec![vec![0.0, 0.0], vec![5.0, 5.0]], vec![0, 1])
+///     .unwrap();
+///
+/// assert_eq!(knn.predict(&[4.0, 4.0]).unwrap(), 1);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KNNBuilder {
+    k: i32,
+    distance: Option<distance::Distance>,
+    normalize: Option<norm::Norm>,
+    standardize: Option<Standardization>,
+    index: IndexKind,
+    weighting: Weighting,
+}
+
+impl Default for KNNBuilder {
+    fn default() -> Self {
+        KNNBuilder {
+            k: 5,
+            distance: None,
+            normalize: None,
+            standardize: None,
+            index: IndexKind::BruteForce,
+            weighting: Weighting::Uniform,
+        }
+    }
+}
+
+impl KNNBuilder {
+    /// Create a builder with `k = 5`, euclidean distance, no normalization or standardization, a
+    /// brute force search and uniform weighting.
+    pub fn new() -> KNNBuilder {
+        KNNBuilder::default()
+    }
+
+    /// Set the number of neighbors that vote.
+    pub fn k(mut self, k: i32) -> Self {
+        self.k = k;
+        self
+    }
+
+    /// Set the distance between points.
+    pub fn distance(mut self, distance: distance::Distance) -> Self {
+        self.distance = Some(distance);
+        self
+    }
+
+    /// Normalize the training data and each query point.
+    pub fn normalize(mut self, normalize: norm::Norm) -> Self {
+        self.normalize = Some(normalize);
+        self
+    }
+
+    /// Rescale each feature with statistics learned from the training data.
+    pub fn standardize(mut self, standardize: Standardization) -> Self {
+        self.standardize = Some(standardize);
+        self
+    }
+
+    /// Set how the nearest neighbors are searched for.
+    pub fn index(mut self, index: IndexKind) -> Self {
+        self.index = index;
+        self
+    }
+
+    /// Set how the votes of the neighbors are weighed.
+    pub fn weighting(mut self, weighting: Weighting) -> Self {
+        self.weighting = weighting;
+        self
+    }
+
+    /// Create a KNN with these settings and fit it to `x` and `y`.
+    /// Returns an error under the same conditions as [`KNN::new`].
+    pub fn build<F: Float>(self, x: Vec<Vec<F>>, y: Vec<i32>) -> Result<KNN<F>> {
+        let mut knn = KNN::unfitted(self.k, self.distance, self.normalize);
+        knn.standardize = self.standardize;
+        knn.index = self.index;
+        knn.weighting = self.weighting;
+        knn.fit(&x, &y)?;
+        Ok(knn)
+    }
+}
+
+/// A data point.
+#[derive(PartialEq, Debug)]
+pub struct Point<F = f64> {
+    /// The class label for the point.
+    pub class: i32,
+    /// The distance from the test point.
+    pub distance: F,
+}
+
+impl<F: PartialOrd> PartialOrd for Point<F> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.distance.partial_cmp(&other.distance)
+    }
+}
+
+impl<F: PartialEq> Eq for Point<F> {}
+
+/// The reasons for a prediction, returned by [`KNN::explain`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation<F = f64> {
+    /// The predicted class.
+    pub prediction: i32,
+    /// The summed vote weight of each class.
+    pub votes: Vec<f64>,
+    /// The k nearest neighbors, nearest first.
+    pub neighbors: Vec<Neighbor<F>>,
+}
+
+/// A neighbor that voted on a prediction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Neighbor<F = f64> {
+    /// The index of the neighbor in the training data.
+    pub index: usize,
+    /// The class label of the neighbor.
+    pub class: i32,
+    /// The distance from the predicted point.
+    pub distance: F,
+    /// The weight of the neighbor's vote.
+    pub weight: f64,
+    /// The part of the distance due to each feature: the squared difference for euclidean
+    /// distance, whose sum is the squared distance, the absolute difference for Manhattan
+    /// distance, whose sum is the distance, or the product divided by the product of the norms
+    /// for cosine distance, whose sum is 1 minus the distance. Empty for a custom distance.
+    pub contributions: Vec<F>,
+}
+
+impl<F: Float> KNN<F> {
+    /// Create a new KNN with optional normalization. The labels can be any integers; they are
+    /// mapped to [`KNN::classes`] internally and predictions are in the original labels.
+    /// Returns an error if `x` and `y` have different lengths, the samples have different
+    /// dimensions, or `k` is not in `1..=x.len()`.
+    pub fn new(
+        k: i32,
+        x: Vec<Vec<F>>,
+        y: Vec<i32>,
+        distance: Option<distance::Distance>,
+        normalize: Option<norm::Norm>,
+    ) -> Result<KNN<F>> {
+        let mut knn = KNN::unfitted(k, distance, normalize);
+        knn.fit(&x, &y)?;
+        Ok(knn)
+    }
+
+    /// Create a KNN without training data, to be trained later with [`Fit::fit`].
+    pub fn unfitted(
+        k: i32,
+        distance: Option<distance::Distance>,
+        normalize: Option<norm::Norm>,
+    ) -> KNN<F> {
+        KNN {
+            k,
+            x: DenseMatrix::new(0),
+            y: Vec::new(),
+            weights: None,
+            classes: Vec::new(),
+            num_labels: 0,
+            distance,
+            custom_distance: None,
+            normalize,
+            standardize: None,
+            weighting: Weighting::Uniform,
+            parallelism: None,
+            seed: None,
+            index: IndexKind::BruteForce,
+            neighbor_index: None,
+            norms: Vec::new(),
+            offsets: Vec::new(),
+            scales: Vec::new(),
+        }
+    }
+
+    /// Set how the nearest neighbors are searched for, building the index if the KNN is fit.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rml::knn::{IndexKind, KNN};
+    ///
+    /// let x: Vec<Vec<f64>> = (0..100).map(|i| vec![i as f64]).collect();
+    /// let y = (0..100).map(|i| i / 50).collect();
+    /// let knn = KNN::new(3, x, y, None, None).unwrap().with_index(IndexKind::BallTree);
+    ///
+    /// assert_eq!(knn.predict(&[80.0]).unwrap(), 1);
+    /// ```
+    pub fn with_index(mut self, index: IndexKind) -> Self {
+        self.index = index;
+        self.build_index();
+        self
+    }
+
+    /// Rebuild the neighbor index over the training data, and cache the norms of the training
+    /// points for cosine distance.
+    fn build_index(&mut self) {
+        let metric = self.metric();
+        self.norms = match metric {
+            distance::Distance::Cosine => self.x.rows().map(norm::l2_norm).collect(),
+            _ => Vec::new(),
+        };
+        self.neighbor_index = match self.index {
+            _ if self.x.is_empty() || self.custom_distance.is_some() => None,
+            _ if metric == distance::Distance::Precomputed => None,
+            IndexKind::BruteForce => None,
+            IndexKind::BallTree if metric == distance::Distance::Cosine => None,
+            IndexKind::BallTree => Some(NeighborIndex::BallTree(BallTree::new(&self.x, metric))),
+            IndexKind::Hnsw {
+                m,
+                ef_construction,
+                ef,
+                seed,
+            } => Some(NeighborIndex::Hnsw(Hnsw::new(
+                &self.x,
+                metric,
+                m,
+                ef_construction,
+                ef,
+                seed,
+            ))),
+        };
+    }
+
+    /// Measure distances with `metric` instead of a built-in distance. Neighbor indexes only
+    /// support the built-in distances, so a KNN with a custom distance always measures the
+    /// distance to every training point.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rml::knn::KNN;
+    ///
+    /// let chebyshev = |p: &[f64], q: &[f64]| {
+    ///     p.iter().zip(q).map(|(a, b)| (a - b).abs()).fold(0.0, f64::max)
+    /// };
+    /// let knn = KNN::new(1, vec![vec![0.0, 0.0], vec![3.0, 3.0]], vec![0, 1], None, None)
+    ///     .unwrap()
+    ///     .custom_distance(chebyshev);
+    ///
+    /// assert_eq!(knn.predict(&[2.0, -1.0]).unwrap(), 0);
+    /// ```
+    pub fn custom_distance<M: distance::Metric<F> + 'static>(mut self, metric: M) -> Self {
+        self.custom_distance = Some(distance::CustomDistance::new(metric));
+        self.build_index();
+        self
+    }
+
+    /// The configured built-in distance.
+    fn metric(&self) -> distance::Distance {
+        self.distance.unwrap_or(distance::Distance::Euclidean)
+    }
+
+    /// Set how the votes of the neighbors are weighed.
+    pub fn weighting(mut self, weighting: Weighting) -> Self {
+        self.weighting = weighting;
+        self
+    }
+
+    /// Set the parallelism of distance computations, overriding the global setting.
+    pub fn parallelism(mut self, parallelism: Parallelism) -> Self {
+        self.parallelism = Some(parallelism);
+        self
+    }
+
+    /// The seed used to shuffle the samples in [`KNN::tune_k`].
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Create a new KNN from a square matrix of precomputed distances between the training
+    /// points, where `distances[i][j]` is the distance from point `i` to point `j`. Each query
+    /// is then the distances from the query point to every training point, in training order.
+    /// Returns an error if `distances` is not square, or under the same conditions as
+    /// [`KNN::new`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use rml::knn::KNN;
+    ///
+    /// let distances = vec![
+    ///     vec![0.0, 1.0, 8.0],
+    ///     vec![1.0, 0.0, 7.0],
+    ///     vec![8.0, 7.0, 0.0],
+    /// ];
+    /// let knn: KNN = KNN::precomputed(1, distances, vec![0, 0, 1]).unwrap();
+    ///
+    /// assert_eq!(knn.predict(&[6.0, 5.0, 2.0]).unwrap(), 1);
+    /// ```
+    pub fn precomputed(k: i32, distances: Vec<Vec<F>>, y: Vec<i32>) -> Result<KNN<F>> {
+        KNN::new(k, distances, y, Some(distance::Distance::Precomputed), None)
+    }
+
+    /// Create a new KNN from a `Dataset`, with optional normalization.
+    pub fn from_dataset(
+        k: i32,
+        data: Dataset<F, i32>,
+        distance: Option<distance::Distance>,
+        normalize: Option<norm::Norm>,
+    ) -> Result<KNN<F>> {
+        KNN::new(k, data.x, data.y, distance, normalize)
+    }
+
+    /// Gets the number of unique labels.
+    /// This function is called when ::new is called. You can access the value using
+    /// the value contained in the KNN struct.
+    pub fn get_num_labels(y: &[i32]) -> usize {
+        let set: HashSet<i32> = y.iter().cloned().collect::<HashSet<_>>();
+        set.len()
+    }
+
+    /// Normalize the data contain in `self` given by the KNN's configured normalization setting.
+    pub fn normalize_data(&mut self) {
+        if let Some(n) = self.normalize {
+            for i in 0..self.x.n_rows() {
+                norm::normalize_vector(self.x.row_mut(i), &n);
+            }
+        }
+    }
+
+    /// Borrow immutable reference to the data.
+    pub fn data(&self) -> (&DenseMatrix<F>, &Vec<i32>) {
+        (&self.x, &self.y)
+    }
+
+    /// Add `x` and `y` to the training data, standardizing and normalizing them with the
+    /// configured settings and updating the classes and any neighbor index. The
+    /// standardization statistics are kept as they are. New points have a weight of 1 if
+    /// the model is weighted. The new points do not need to contain every class.
+    /// Returns an error if `x` and `y` have different lengths, the samples have different
+    /// dimensions than the training data, or the distance is invalid.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rml::knn::KNN;
+    ///
+    /// let mut knn: KNN = KNN::new(1, vec![vec![0.0]], vec![0], None, None).unwrap();
+    /// knn.add_samples(vec![vec![10.0]], vec![1]).unwrap();
+    ///
+    /// assert_eq!(knn.num_labels, 2);
+    /// assert_eq!(knn.predict(&[9.0]).unwrap(), 1);
+    /// ```
+    pub fn add_samples(&mut self, x: Vec<Vec<F>>, y: Vec<i32>) -> Result<()> {
+        check_lengths("Features and labels", x.len(), y.len())?;
+        if self.metric() == distance::Distance::Precomputed {
+            return Err(Error::InvalidInput(
+                "Samples can't be added to precomputed distances; fit the KNN again.".into(),
+            ));
+        }
+        distance::validate(self.metric())?;
+        let n_features = match self.x.is_empty() {
+            true => x.first().map_or(0, Vec::len),
+            false => self.x.n_cols(),
+        };
+        if let Some(row) = x.iter().find(|row| row.len() != n_features) {
+            return Err(Error::ShapeMismatch(format!(
+                "Expected {} features but a sample has {}.",
+                n_features,
+                row.len()
+            )));
+        }
+        let start = self.x.n_rows();
+        if start == 0 {
+            // The first samples of an empty KNN are standardized with their own statistics.
+            for row in x {
+                self.x.push_row(&row)?;
+            }
+            self.standardize_data();
+            self.normalize_data();
+        } else {
+            for mut row in x {
+                self.standardize_vector(&mut row);
+                if let Some(n) = &self.normalize {
+                    norm::normalize_vector(&mut row, n);
+                }
+                self.x.push_row(&row)?;
+            }
+        }
+        self.y.extend(y);
+        if let Some(weights) = &mut self.weights {
+            weights.resize(self.y.len(), 1.0);
+        }
+        self.update_classes();
+        let metric = self.metric();
+        match &mut self.neighbor_index {
+            // New points are inserted into the graph rather than rebuilding it.
+            Some(NeighborIndex::Hnsw(graph))
+                if graph.len() == start && graph.matches(start, metric) =>
+            {
+                graph.extend(&self.x);
+                if metric == distance::Distance::Cosine {
+                    self.norms
+                        .extend(self.x.rows().skip(start).map(norm::l2_norm));
+                }
+            }
+            _ => self.build_index(),
+        }
+        Ok(())
+    }
+
+    /// Remove the training points at `indices`, rebuilding any neighbor index. With
+    /// precomputed distances, the distances to the removed points are removed too. The classes
+    /// become the sorted distinct remaining labels.
+    /// Returns an error if an index is out of range, in which case nothing is removed.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rml::knn::KNN;
+    ///
+    /// let x = vec![vec![0.0], vec![1.0], vec![10.0]];
+    /// let mut knn: KNN = KNN::new(1, x, vec![0, 1, 0], None, None).unwrap();
+    /// knn.remove_samples(&[1]).unwrap();
+    ///
+    /// assert_eq!(knn.y, vec![0, 0]);
+    /// assert_eq!(knn.num_labels, 1);
+    /// ```
+    pub fn remove_samples(&mut self, indices: &[usize]) -> Result<()> {
+        if let Some(&i) = indices.iter().find(|&&i| i >= self.x.n_rows()) {
+            return Err(Error::InvalidInput(format!(
+                "Index {} is out of range for {} training points.",
+                i,
+                self.x.n_rows()
+            )));
+        }
+        let mut keep = vec![true; self.x.n_rows()];
+        indices.iter().for_each(|&i| keep[i] = false);
+        self.x.retain_rows(&keep);
+        if self.metric() == distance::Distance::Precomputed {
+            self.x.retain_columns(&keep);
+        }
+        let mut flags = keep.iter();
+        self.y.retain(|_| *flags.next().unwrap());
+        if let Some(weights) = &mut self.weights {
+            let mut flags = keep.iter();
+            weights.retain(|_| *flags.next().unwrap());
+        }
+        self.update_classes();
+        self.build_index();
+        Ok(())
+    }
+
+    /// Change the label of the training point at `index` to `label`, updating the classes.
+    /// Returns an error if `index` is out of range.
+    pub fn relabel(&mut self, index: usize, label: i32) -> Result<()> {
+        let n = self.y.len();
+        let y = self.y.get_mut(index).ok_or_else(|| {
+            Error::InvalidInput(format!(
+                "Index {} is out of range for {} training points.",
+                index, n
+            ))
+        })?;
+        *y = label;
+        self.update_classes();
+        Ok(())
+    }
+
+    /// Set the classes to the distinct labels of the training data.
+    fn update_classes(&mut self) {
+        let mut classes = self.y.clone();
+        classes.sort_unstable();
+        classes.dedup();
+        self.num_labels = classes.len();
+        self.classes = classes;
+    }
+
+    /// The heap memory of the training data, weights and index, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.x.memory_usage()
+            + (self.norms.capacity() + self.offsets.capacity() + self.scales.capacity())
+                * std::mem::size_of::<F>()
+            + match &self.neighbor_index {
+                Some(NeighborIndex::BallTree(tree)) => tree.memory_usage(),
+                Some(NeighborIndex::Hnsw(graph)) => graph.memory_usage(),
+                None => 0,
+            }
+            + self.y.capacity() * std::mem::size_of::<i32>()
+            + self.weights.as_ref().map_or(0, |w| w.capacity()) * std::mem::size_of::<f64>()
+    }
+
+    /// Calculate the distance from `new_point` to all other points in the set.
+    /// Note: new_point must be the same dimensions as the data passed into ::new, and is not
+    /// normalized.
+    pub fn calculate_distances(&self, new_point: &[F]) -> Vec<Point<F>> {
+        self.distances(new_point)
+            .into_iter()
+            .zip(&self.y)
+            .map(|(distance, y)| Point {
+                class: *y,
+                distance,
+            })
+            .collect()
+    }
+
+    /// The distance from `new_point` to each training point, in training order.
+    fn distances(&self, new_point: &[F]) -> Vec<F> {
+        if let Some(custom) = &self.custom_distance {
+            return parallel::install(self.parallelism, || {
+                self.x
+                    .par_rows()
+                    .map(|x| custom.distance(new_point, x))
+                    .collect()
+            });
+        }
+        if self.metric() == distance::Distance::Precomputed {
+            return new_point.to_vec();
+        }
+        if self.metric() == distance::Distance::Cosine && self.norms.len() == self.x.n_rows() {
+            let norm = norm::l2_norm(new_point);
+            return parallel::install(self.parallelism, || {
+                self.x
+                    .par_rows()
+                    .zip(self.norms.par_iter())
+                    .map(|(x, &x_norm)| {
+                        distance::cosine_from_dot(distance::dot(new_point, x), norm, x_norm)
+                    })
+                    .collect()
+            });
+        }
+        let distance_fn = |p: &[F], q: &[F]| match self.distance {
+            Some(distance::Distance::Manhattan) => distance::manhattan_distance(p, q),
+            Some(distance::Distance::Minkowski(power)) => distance::minkowski_distance(p, q, power),
+            Some(distance::Distance::Hamming) => distance::hamming_distance(p, q),
+            Some(distance::Distance::Cosine) => distance::cosine_distance(p, q),
+            _ => distance::euclidean_distance(p, q),
+        };
+
+        parallel::install(self.parallelism, || {
+            self.x
+                .par_rows()
+                .map(|x| distance_fn(new_point, x))
+                .collect()
+        })
+    }
+
+    /// Predict the class of a point `x`.
+    /// Returns [`Error::NotFitted`] if the KNN has no training data, [`Error::ShapeMismatch`] if
+    /// `x` does not have as many features as the training data, or [`Error::InvalidInput`] if
+    /// there are fewer than `k` training points.
+    pub fn predict(&self, x: &[F]) -> Result<i32> {
+        Ok(self.get_max_value(&self.votes(&self.query(x))?))
+    }
+
+    /// The fraction of the votes of the k nearest neighbors of `x` in each class. With
+    /// [`Weighting::Distance`] nearer neighbors count for more.
+    /// Returns an error under the same conditions as [`KNN::predict`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use rml::knn::KNN;
+    ///
+    /// let knn: KNN = KNN::new(4, vec![vec![0.0], vec![1.0], vec![2.0], vec![9.0]], vec![0, 0, 0, 1], None, None)
+    ///     .unwrap();
+    ///
+    /// assert_eq!(knn.predict_proba(&[8.0]).unwrap(), vec![0.75, 0.25]);
+    /// ```
+    pub fn predict_proba(&self, x: &[F]) -> Result<Vec<f64>> {
+        let votes = self.votes(&self.query(x))?;
+        let total: f64 = votes.iter().sum();
+        Ok(votes.iter().map(|v| v / total).collect())
+    }
+
+    /// Predict the class of each point in `x`, which has already been normalized with the
+    /// configured normalization, skipping the normalization of each query. A standardized KNN
+    /// without normalization still standardizes each query with its learned statistics.
+    /// Returns an error under the same conditions as [`KNN::predict`], or if the KNN both
+    /// standardizes and normalizes, since the queries must be standardized before they are
+    /// normalized.
+    pub fn predict_normalized(&self, x: &[Vec<F>]) -> Result<Vec<i32>> {
+        if !self.offsets.is_empty() && self.normalize.is_some() {
+            return Err(Error::InvalidInput(
+                "Queries of a standardized KNN can't be normalized in advance.".into(),
+            ));
+        }
+        x.iter()
+            .map(|xi| {
+                let mut query = Cow::Borrowed(xi.as_slice());
+                if !self.offsets.is_empty() {
+                    self.standardize_vector(query.to_mut());
+                }
+                Ok(self.get_max_value(&self.votes(&query)?))
+            })
+            .collect()
+    }
+
+    /// Predict the class of each point in `x`, in chunks of `chunk_size` points predicted in
+    /// parallel. After each chunk `progress` is called with the number of points predicted so
+    /// far and the total; it may be called from several threads at once. Return `false` from
+    /// `progress` to cancel the remaining chunks.
+    /// Returns [`Error::Cancelled`] if cancelled, an error if `chunk_size` is 0, or an error
+    /// under the same conditions as [`KNN::predict`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use rml::knn::KNN;
+    ///
+    /// let knn: KNN = KNN::new(1, vec![vec![0.0], vec![10.0]], vec![0, 1], None, None).unwrap();
+    /// let x: Vec<Vec<f64>> = (0..100).map(|i| vec![i as f64 / 10.0]).collect();
+    /// let pred = knn
+    ///     .predict_batch_with(&x, 25, |done, total| {
+    ///         println!("{}/{} predicted", done, total);
+    ///         true
+    ///     })
+    ///     .unwrap();
+    ///
+    /// assert_eq!(pred.iter().filter(|&&p| p == 1).count(), 49);
+    /// ```
+    pub fn predict_batch_with<P>(
+        &self,
+        x: &[Vec<F>],
+        chunk_size: usize,
+        progress: P,
+    ) -> Result<Vec<i32>>
+    where
+        P: Fn(usize, usize) -> bool + Sync,
+    {
+        if chunk_size == 0 {
+            return Err(Error::InvalidInput(
+                "The chunk size must be positive.".into(),
+            ));
+        }
+        let done = AtomicUsize::new(0);
+        let cancelled = AtomicBool::new(false);
+        let chunks: Vec<Vec<i32>> = parallel::install(self.parallelism, || {
+            x.par_chunks(chunk_size)
+                .map(|chunk| {
+                    if cancelled.load(AtomicOrdering::Relaxed) {
+                        return Err(Error::Cancelled);
+                    }
+                    let pred = chunk
+                        .iter()
+                        .map(|xi| KNN::predict(self, xi))
+                        .collect::<Result<Vec<i32>>>()?;
+                    let done = done.fetch_add(chunk.len(), AtomicOrdering::Relaxed) + chunk.len();
+                    if !progress(done, x.len()) {
+                        cancelled.store(true, AtomicOrdering::Relaxed);
+                        return Err(Error::Cancelled);
+                    }
+                    Ok(pred)
+                })
+                .collect::<Result<_>>()
+        })?;
+        Ok(chunks.concat())
+    }
+
+    /// Normalize a query point with the configured normalization, as the training data was.
+    fn query<'a>(&self, x: &'a [F]) -> Cow<'a, [F]> {
+        if self.normalize.is_none() && self.offsets.is_empty() {
+            return Cow::Borrowed(x);
+        }
+        let mut x = x.to_vec();
+        self.standardize_vector(&mut x);
+        if let Some(n) = &self.normalize {
+            norm::normalize_vector(&mut x, n);
+        }
+        Cow::Owned(x)
+    }
+
+    /// Set how each feature is rescaled. The statistics are learned the next time the KNN is
+    /// fit, and applied to the training data and every query before any normalization.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rml::knn::{Standardization, KNN};
+    /// use rml::traits::Fit;
+    ///
+    /// // The second feature is in much larger units, and would decide every distance.
+    /// let x = vec![vec![0.0, 1000.0], vec![0.1, 3000.0], vec![1.0, 1100.0], vec![0.9, 3100.0]];
+    /// let mut knn = KNN::unfitted(1, None, None).standardize(Standardization::MinMax);
+    /// knn.fit(&x, &[0, 0, 1, 1]).unwrap();
+    ///
+    /// assert_eq!(knn.predict(&[0.95, 1500.0]).unwrap(), 1);
+    /// ```
+    pub fn standardize(mut self, standardize: Standardization) -> Self {
+        self.standardize = Some(standardize);
+        self
+    }
+
+    /// Learn the offset and scale of each feature of the training data with the configured
+    /// standardization, and rescale the training data with them.
+    fn standardize_data(&mut self) {
+        let n = F::from_f64(self.x.n_rows() as f64);
+        let (offsets, scales): (Vec<F>, Vec<F>) = match self.standardize {
+            _ if self.x.is_empty() => (Vec::new(), Vec::new()),
+            None => (Vec::new(), Vec::new()),
+            Some(Standardization::ZScore) => (0..self.x.n_cols())
+                .map(|j| {
+                    let mean = self.x.rows().map(|row| row[j]).sum::<F>() / n;
+                    let var = self.x.rows().map(|row| (row[j] - mean).powi(2)).sum::<F>() / n;
+                    (mean, var.sqrt())
+                })
+                .unzip(),
+            Some(Standardization::MinMax) => (0..self.x.n_cols())
+                .map(|j| {
+                    let column = || self.x.rows().map(|row| row[j]);
+                    let min = column().min_by(F::total_cmp).unwrap_or_else(F::zero);
+                    let max = column().max_by(F::total_cmp).unwrap_or_else(F::zero);
+                    (min, max - min)
+                })
+                .unzip(),
+        };
+        // Constant features are only shifted.
+        self.scales = scales
+            .into_iter()
+            .map(|s| if s > F::zero() { s } else { F::from_f64(1.0) })
+            .collect();
+        self.offsets = offsets;
+        for i in 0..self.x.n_rows() {
+            let (offsets, scales) = (&self.offsets, &self.scales);
+            for (v, (&o, &s)) in self.x.row_mut(i).iter_mut().zip(offsets.iter().zip(scales)) {
+                *v = (*v - o) / s;
+            }
+        }
+    }
+
+    /// Rescale each feature of `x` with the learned standardization. Extra features are left
+    /// as they are, so a later shape check still sees them.
+    fn standardize_vector(&self, x: &mut [F]) {
+        for (v, (&o, &s)) in x.iter_mut().zip(self.offsets.iter().zip(&self.scales)) {
+            *v = (*v - o) / s;
+        }
+    }
+
+    /// Explain the prediction for `x`: the k nearest neighbors with their labels, distances,
+    /// vote weights and the contribution of each feature to their distance from the normalized
+    /// `x`, nearest first.
+    /// Returns an error under the same conditions as [`KNN::predict`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use rml::knn::KNN;
+    ///
+    /// let knn: KNN = KNN::new(1, vec![vec![0.0, 0.0], vec![5.0, 5.0]], vec![0, 1], None, None)
+    ///     .unwrap();
+    /// let explanation = knn.explain(&[1.0, 2.0]).unwrap();
+    ///
+    /// assert_eq!(explanation.prediction, 0);
+    /// assert_eq!(explanation.neighbors[0].index, 0);
+    /// assert_eq!(explanation.neighbors[0].contributions, vec![1.0, 4.0]);
+    /// ```
+    pub fn explain(&self, x: &[F]) -> Result<Explanation<F>> {
+        let x = self.query(x);
+        let nearest = self.nearest(&x)?;
+        let weights = self.vote_weights(&nearest);
+        let votes = self.count_votes(&nearest, &weights);
+        let neighbors = nearest
+            .iter()
+            .zip(weights)
+            .map(|(&(index, distance), weight)| Neighbor {
+                index,
+                class: self.y[index],
+                distance,
+                weight,
+                contributions: match self.custom_distance {
+                    Some(_) => Vec::new(),
+                    None if self.metric() == distance::Distance::Precomputed => Vec::new(),
+                    None if self.metric() == distance::Distance::Cosine => {
+                        let norms = norm::l2_norm(&x) * norm::l2_norm(self.x.row(index));
+                        x.iter()
+                            .zip(self.x.row(index))
+                            .map(|(&a, &b)| {
+                                if norms == F::zero() {
+                                    F::zero()
+                                } else {
+                                    a * b / norms
+                                }
+                            })
+                            .collect()
+                    }
+                    None => x
+                        .iter()
+                        .zip(self.x.row(index))
+                        .map(|(&a, &b)| match self.distance {
+                            Some(distance::Distance::Manhattan) => (a - b).abs(),
+                            Some(distance::Distance::Minkowski(power)) => {
+                                F::from_f64((a - b).abs().to_f64().powf(power))
+                            }
+                            Some(distance::Distance::Hamming) => F::from_f64((a != b) as u8 as f64),
+                            _ => (a - b).powi(2),
+                        })
+                        .collect(),
+                },
+            })
+            .collect();
+        Ok(Explanation {
+            prediction: self.get_max_value(&votes),
+            votes,
+            neighbors,
+        })
+    }
+
+    /// Sum the weights of the k nearest neighbors of the normalized `query` in each class.
+    fn votes(&self, query: &[F]) -> Result<Vec<f64>> {
+        let nearest = self.nearest(query)?;
+        let weights = self.vote_weights(&nearest);
+        Ok(self.count_votes(&nearest, &weights))
+    }
+
+    /// Sum the vote weights of the neighbors in each class.
+    fn count_votes(&self, nearest: &[(usize, F)], weights: &[f64]) -> Vec<f64> {
+        let mut votes = vec![0.0; self.num_labels];
+        for (&(i, _), w) in nearest.iter().zip(weights) {
+            if let Ok(c) = self.classes.binary_search(&self.y[i]) {
+                votes[c] += w;
+            }
+        }
+        votes
+    }
+
+    /// The vote weight of each neighbor: its sample weight times its distance weight, or 1 for
+    /// each if they all have a weight of 0.
+    fn vote_weights(&self, nearest: &[(usize, F)]) -> Vec<f64> {
+        let exact = nearest.iter().any(|&(_, d)| d == F::zero());
+        let weights: Vec<f64> = nearest
+            .iter()
+            .map(|&(i, d)| {
+                let sample = self.weights.as_ref().map_or(1.0, |w| w[i]);
+                match self.weighting {
+                    Weighting::Uniform => sample,
+                    Weighting::Distance if exact => {
+                        if d == F::zero() {
+                            sample
+                        } else {
+                            0.0
+                        }
+                    }
+                    Weighting::Distance => sample / d.to_f64(),
+                }
+            })
+            .collect();
+        if weights.iter().all(|&w| w == 0.0) {
+            return vec![1.0; nearest.len()];
+        }
+        weights
+    }
+
+    /// The indices and distances of the k nearest training points to the normalized `query`,
+    /// nearest first.
+    fn nearest(&self, query: &[F]) -> Result<Vec<(usize, F)>> {
+        self.check_k()?;
+        if self.x.n_cols() != query.len() {
+            return Err(Error::ShapeMismatch(format!(
+                "Expected {} features but a sample has {}.",
+                self.x.n_cols(),
+                query.len()
+            )));
+        }
+        let (n, metric, k) = (self.x.n_rows(), self.metric(), self.k as usize);
+        match &self.neighbor_index {
+            _ if self.custom_distance.is_some() => {}
+            Some(NeighborIndex::BallTree(tree)) if tree.matches(n, metric) => {
+                return Ok(tree.query(&self.x, query, k));
+            }
+            Some(NeighborIndex::Hnsw(graph)) if graph.len() == n && graph.matches(n, metric) => {
+                return Ok(graph.query(&self.x, query, k));
+            }
+            _ => {}
+        }
+        let distances = self.distances(query);
+        // Only the k nearest need to be sorted. Ties are broken by training order.
+        let by_distance =
+            |a: &usize, b: &usize| distances[*a].total_cmp(&distances[*b]).then(a.cmp(b));
+        let mut order: Vec<usize> = (0..distances.len()).collect();
+        if k < order.len() {
+            order.select_nth_unstable_by(k - 1, by_distance);
+            order.truncate(k);
+        }
+        order.sort_unstable_by(by_distance);
+        Ok(order.into_iter().map(|i| (i, distances[i])).collect())
+    }
+
+    /// The number of features of the training data, or 0 if there is none.
+    fn n_features(&self) -> usize {
+        match self.x.is_empty() {
+            true => 0,
+            false => self.x.n_cols(),
+        }
+    }
+
+    /// Check that there are enough training points to find k neighbors.
+    fn check_k(&self) -> Result<()> {
+        if self.x.is_empty() {
+            return Err(Error::NotFitted);
+        }
+        if self.k < 1 || self.k as usize > self.x.n_rows() {
+            return Err(Error::InvalidInput(format!(
+                "k is {} but there are {} training points.",
+                self.k,
+                self.x.n_rows()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Get the class with the most votes.
+    fn get_max_value(&self, predictions: &[f64]) -> i32 {
+        predictions
+            .iter()
+            .enumerate() // add index to the iterated items [a, b, c] -> [(0, a), (1, b), (2, c)]
+            .max_by(|(_, a), (_, b)| a.total_cmp(b)) // take maximum by the actual item, not the index
+            .and_then(|(idx, _)| self.classes.get(idx)) // translate the index back to its label
+            .map_or(0, |&class| class)
+    }
+}
+
+impl<F: Float + CacheElement> KNN<F> {
+    /// Write the model to a file at `path`, to be read with [`KNN::load_model`].
+    #[cfg(feature = "fs")]
+    pub fn save_model(&self, path: &str) -> Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        self.write_model(&mut w)?;
+        w.flush()?;
+        Ok(())
+    }
+
+    /// Read a model from a file written by [`KNN::save_model`].
+    /// Returns an error under the same conditions as [`KNN::read_model`].
+    #[cfg(feature = "fs")]
+    pub fn load_model(path: &str) -> Result<KNN<F>> {
+        KNN::read_model(&mut BufReader::new(File::open(path)?))
+    }
+
+    /// Write the model as an artifact, with its training data. The parallelism and index are
+    /// not saved. Returns an error if the model has a custom distance, which cannot be saved.
+    pub fn write_model<W: Write>(&self, w: &mut W) -> Result<()> {
+        if self.custom_distance.is_some() {
+            return Err(Error::InvalidInput(
+                "A KNN with a custom distance cannot be saved.".into(),
+            ));
+        }
+        Header::new(Self::model_kind(), self.n_features(), self.fingerprint()).write_to(w)?;
+        self.k.write_to(w)?;
+        match self.distance {
+            Some(distance::Distance::Euclidean) => Some(0u8),
+            Some(distance::Distance::Manhattan) => Some(1),
+            Some(distance::Distance::Cosine) => Some(2),
+            Some(distance::Distance::Precomputed) => Some(3),
+            Some(distance::Distance::Minkowski(_)) => Some(4),
+            Some(distance::Distance::Hamming) => Some(5),
+            None => None,
+        }
+        .write_to(w)?;
+        if let Some(distance::Distance::Minkowski(power)) = self.distance {
+            power.write_to(w)?;
+        }
+        self.normalize.map(|n| n as u8).write_to(w)?;
+        self.num_labels.write_to(w)?;
+        self.x.write_to(w)?;
+        self.y.write_to(w)?;
+        self.weights.write_to(w)?;
+        (self.weighting as u8).write_to(w)?;
+        self.standardize.map(|s| s as u8).write_to(w)?;
+        self.offsets.write_to(w)?;
+        self.scales.write_to(w)?;
+        Ok(())
+    }
+
+    /// Read a model written by [`KNN::write_model`].
+    /// Returns an error if `r` does not hold a `KNN` with the same float type, uses a different
+    /// format version, or is corrupt.
+    pub fn read_model<R: Read>(r: &mut R) -> Result<KNN<F>> {
+        let header = artifact::read_header(r)?;
+        header.check_kind(&Self::model_kind())?;
+        let corrupt = || Error::Parse("The KNN is corrupt.".into());
+
+        let k = i32::read_from(r)?;
+        let distance = match Option::<u8>::read_from(r)? {
+            None => None,
+            Some(0) => Some(distance::Distance::Euclidean),
+            Some(1) => Some(distance::Distance::Manhattan),
+            Some(2) => Some(distance::Distance::Cosine),
+            Some(3) => Some(distance::Distance::Precomputed),
+            Some(4) => Some(distance::Distance::Minkowski(f64::read_from(r)?)),
+            Some(5) => Some(distance::Distance::Hamming),
+            Some(_) => return Err(corrupt()),
+        };
+        let normalize = match Option::<u8>::read_from(r)? {
+            None => None,
+            Some(0) => Some(norm::Norm::L1),
+            Some(1) => Some(norm::Norm::L2),
+            Some(_) => return Err(corrupt()),
+        };
+        let mut knn = KNN::unfitted(k, distance, normalize);
+        knn.num_labels = usize::read_from(r)?;
+        knn.x = CacheElement::read_from(r)?;
+        knn.y = CacheElement::read_from(r)?;
+        knn.weights = CacheElement::read_from(r)?;
+        knn.weighting = match u8::read_from(r)? {
+            0 => Weighting::Uniform,
+            1 => Weighting::Distance,
+            _ => return Err(corrupt()),
+        };
+        knn.standardize = match Option::<u8>::read_from(r)? {
+            None => None,
+            Some(0) => Some(Standardization::ZScore),
+            Some(1) => Some(Standardization::MinMax),
+            Some(_) => return Err(corrupt()),
+        };
+        knn.offsets = CacheElement::read_from(r)?;
+        knn.scales = CacheElement::read_from(r)?;
+
+        header.check_fingerprint(knn.fingerprint())?;
+        header.check_n_features(knn.n_features())?;
+        check_lengths("Features and labels", knn.x.n_rows(), knn.y.len())?;
+        if let Some(weights) = &knn.weights {
+            check_weights(knn.x.n_rows(), weights)?;
+        }
+        let num_labels = knn.num_labels;
+        knn.update_classes();
+        if knn.num_labels != num_labels {
+            return Err(corrupt());
+        }
+        if knn.offsets.len() != knn.scales.len()
+            || (!knn.offsets.is_empty() && knn.offsets.len() != knn.n_features())
+        {
+            return Err(corrupt());
+        }
+        knn.build_index();
+        Ok(knn)
+    }
+
+    /// The artifact kind of a model of this type.
+    fn model_kind() -> String {
+        format!("KNN<{}>", F::type_name())
+    }
+
+    /// The fingerprint of the configuration and the shape of the training data.
+    fn fingerprint(&self) -> u64 {
+        artifact::fingerprint(&format!(
+            "k={} distance={:?} normalize={:?} standardize={:?} weighting={:?} n_samples={} n_features={} n_classes={} weighted={}",
+            self.k,
+            self.distance,
+            self.normalize,
+            self.standardize,
+            self.weighting,
+            self.x.n_rows(),
+            self.n_features(),
+            self.num_labels,
+            self.weights.is_some()
+        ))
+    }
+}
+
+impl<F: Float> Fit<F, i32> for KNN<F> {
+    /// Replace the training data with `x` and `y`, normalizing it with the configured setting.
+    /// Returns an error under the same conditions as [`KNN::new`].
+    fn fit(&mut self, x: &[Vec<F>], y: &[i32]) -> Result<()> {
+        self.fit_weighted(x, y, None)
+    }
+}
+
+impl<F: Float> FitWeighted<F, i32> for KNN<F> {
+    /// Replace the training data with `x` and `y`, weighing the vote of each training point by
+    /// its weight in `sample_weight`. Returns an error under the same conditions as
+    /// [`KNN::new`], or if the weights are not one finite, non-negative number per sample.
+    fn fit_weighted(
+        &mut self,
+        x: &[Vec<F>],
+        y: &[i32],
+        sample_weight: Option<&[f64]>,
+    ) -> Result<()> {
+        check_lengths("Features and labels", x.len(), y.len())?;
+        if let Some(weights) = sample_weight {
+            check_weights(x.len(), weights)?;
+        }
+        if self.k < 1 || self.k as usize > x.len() {
+            return Err(Error::InvalidInput(format!(
+                "k is {} but there are {} training points.",
+                self.k,
+                x.len()
+            )));
+        }
+        if let Some(metric) = self.distance {
+            distance::validate(metric)?;
+        }
+        let rows = DenseMatrix::from_rows(x)?;
+        if self.metric() == distance::Distance::Precomputed && self.custom_distance.is_none() {
+            check_precomputed(
+                &rows,
+                self.normalize.is_some() || self.standardize.is_some(),
+            )?;
+        }
+        self.x = rows;
+        self.y = y.to_vec();
+        self.weights = sample_weight.map(<[f64]>::to_vec);
+        self.update_classes();
+        self.standardize_data();
+        self.normalize_data();
+        self.build_index();
+        Ok(())
+    }
+}
+
+impl<F: Float> IncrementalFit<F, i32> for KNN<F> {
+    /// Add `x` and `y` to the training data, like [`KNN::add_samples`].
+    fn partial_fit(&mut self, x: &[Vec<F>], y: &[i32]) -> Result<()> {
+        self.add_samples(x.to_vec(), y.to_vec())
+    }
+}
+
+impl<F: Float> Predict<F, i32> for KNN<F> {
+    /// Predict the class of each point in `x`.
+    /// Returns an error under the same conditions as [`KNN::predict`].
+    fn predict(&self, x: &[Vec<F>]) -> Result<Vec<i32>> {
+        x.iter().map(|xi| KNN::predict(self, xi)).collect()
+    }
+}
+
+impl<F: Float> PredictProba<F> for KNN<F> {
+    /// The fraction of the k nearest neighbors of each point in each class, by weight.
+    /// Returns an error under the same conditions as [`KNN::predict`].
+    fn predict_proba(&self, x: &[Vec<F>]) -> Result<Vec<Vec<f64>>> {
+        x.iter().map(|xi| self.predict_proba(xi)).collect()
+    }
+}
+
+impl<F: Float> Describe for KNN<F> {
+    fn describe(&self) -> Summary {
+        let metric = self.metric();
+        let distance: &dyn std::fmt::Debug = match &self.custom_distance {
+            Some(custom) => custom,
+            None => &metric,
+        };
+        let mut summary = Summary::new("KNN")
+            .param("k", self.k)
+            .param("distance", distance)
+            .param("normalize", self.normalize)
+            .param("standardize", self.standardize)
+            .param("weighting", self.weighting)
+            .param("index", self.index)
+            .param("parallelism", self.parallelism)
+            .param("seed", self.seed);
+        if !self.x.is_empty() {
+            summary.n_samples = Some(self.x.n_rows());
+            summary.n_features = Some(self.x.n_cols());
+            summary.n_classes = Some(self.num_labels);
+        }
+        summary.memory = self.memory_usage();
+        summary
+    }
+}
+
+/// Check that a precomputed distance matrix is square and not rescaled.
+fn check_precomputed<F>(x: &DenseMatrix<F>, rescaled: bool) -> Result<()> {
+    if x.n_rows() != x.n_cols() {
+        return Err(Error::ShapeMismatch(format!(
+            "A precomputed distance matrix must be square, but it is {}x{}.",
+            x.n_rows(),
+            x.n_cols()
+        )));
+    }
+    if rescaled {
+        return Err(Error::InvalidInput(
+            "Precomputed distances can't be normalized or standardized.".into(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_data_test() {
+        let p: Vec<Vec<f64>> = vec![vec![2.0, 2.0, 2.0]];
+        let mut knn = KNN::new(1, p, vec![0], None, Some(norm::Norm::L2)).unwrap();
+        knn.normalize_data();
+        assert_eq!(
+            knn.data().0.to_rows(),
+            vec![vec![2.0 / f64::from(12).sqrt(); 3]]
+        );
+    }
+
+    #[test]
+    fn calculate_distances_test() {
+        let p: Vec<Vec<f64>> = vec![vec![2.0, 2.0]];
+        let knn = KNN::new(1, p, vec![0], None, None).unwrap();
+
+        let q = knn.calculate_distances(&(vec![0.0, 0.0] as Vec<f64>));
+        assert_eq!(q[0].distance, f64::from(8).sqrt());
+    }
+
+    #[test]
+    fn from_dataset_test() {
+        let data = Dataset::new(vec![vec![0.0], vec![10.0]], vec![0, 1]).unwrap();
+        let knn = KNN::from_dataset(1, data, None, None).unwrap();
+
+        assert_eq!(knn.predict(&[9.0]).unwrap(), 1);
+    }
+
+    #[cfg(feature = "datasets")]
+    #[test]
+    fn iris_test() {
+        let iris = crate::datasets::load_iris();
+        let knn = KNN::from_dataset(5, iris.clone(), None, None).unwrap();
+
+        let num_correct = iris
+            .x
+            .iter()
+            .zip(&iris.y)
+            .filter(|(x, y)| knn.predict(x).unwrap() == **y)
+            .count();
+        assert!(num_correct >= 140);
+    }
+
+    #[test]
+    fn fit_predict_test() {
+        let mut knn = KNN::unfitted(1, None, None);
+        assert!(Predict::predict(&knn, &[vec![0.0]]).is_err());
+        assert!(knn.fit(&[vec![0.0]], &[0, 1]).is_err());
+
+        knn.fit(&[vec![0.0], vec![10.0]], &[0, 1]).unwrap();
+        assert_eq!(
+            Predict::predict(&knn, &[vec![1.0], vec![8.0]]).unwrap(),
+            vec![0, 1]
+        );
+    }
+
+    #[test]
+    fn nan_distance_test() {
+        let knn = KNN::new(1, vec![vec![0.0], vec![f64::NAN]], vec![0, 1], None, None).unwrap();
+        assert_eq!(knn.predict(&[1.0]).unwrap(), 0);
+    }
+
+    #[test]
+    fn validation_test() {
+        let x = vec![vec![0.0, 1.0], vec![1.0, 0.0]];
+        assert!(matches!(
+            KNN::new(1, x.clone(), vec![0], None, None),
+            Err(Error::ShapeMismatch(_))
+        ));
+        assert!(KNN::new(3, x.clone(), vec![0, 1], None, None).is_err());
+        assert!(KNN::new(1, vec![vec![0.0], vec![1.0, 2.0]], vec![0, 1], None, None).is_err());
+
+        let mut knn = KNN::new(1, x, vec![0, 1], None, None).unwrap();
+        assert!(matches!(knn.predict(&[0.0]), Err(Error::ShapeMismatch(_))));
+        knn.k = 3;
+        assert!(matches!(
+            knn.predict(&[0.0, 0.0]),
+            Err(Error::InvalidInput(_))
+        ));
+        assert!(matches!(
+            KNN::unfitted(1, None, None).predict(&[0.0]),
+            Err(Error::NotFitted)
+        ));
+    }
+
+    #[test]
+    fn arbitrary_labels_test() {
+        let x = vec![vec![0.0], vec![1.0], vec![5.0], vec![6.0], vec![20.0]];
+        let knn = KNN::new(3, x, vec![200, 200, -1, -1, 10], None, None).unwrap();
+
+        assert_eq!(knn.classes, vec![-1, 10, 200]);
+        assert_eq!(knn.num_labels, 3);
+        assert_eq!(knn.predict(&[0.5]).unwrap(), 200);
+        assert_eq!(knn.predict(&[5.5]).unwrap(), -1);
+        assert_eq!(
+            knn.predict_proba(&[5.5]).unwrap(),
+            vec![2.0 / 3.0, 0.0, 1.0 / 3.0]
+        );
+        assert_eq!(knn.explain(&[0.5]).unwrap().prediction, 200);
+
+        let mut bytes = Vec::new();
+        knn.write_model(&mut bytes).unwrap();
+        let read: KNN = KNN::read_model(&mut bytes.as_slice()).unwrap();
+        assert_eq!(read.classes, knn.classes);
+        assert_eq!(read.predict(&[19.0]).unwrap(), -1);
+    }
+
+    #[test]
+    fn parallelism_test() {
+        let x = vec![vec![0.0], vec![1.0], vec![10.0]];
+        let knn = KNN::new(1, x, vec![0, 0, 1], None, None).unwrap();
+        let sequential = knn.clone().parallelism(Parallelism::Sequential);
+
+        assert_eq!(sequential.parallelism, Some(Parallelism::Sequential));
+        assert_eq!(
+            sequential.predict(&[9.0]).unwrap(),
+            knn.predict(&[9.0]).unwrap()
+        );
+    }
+
+    #[test]
+    fn f32_test() {
+        let x: Vec<Vec<f32>> = vec![vec![0.0, 0.0], vec![0.1, 0.0], vec![5.0, 5.0]];
+        let knn = KNN::new(
+            1,
+            x,
+            vec![0, 0, 1],
+            Some(distance::Distance::Manhattan),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(knn.predict(&[4.0, 4.5]).unwrap(), 1);
+        assert_eq!(knn.calculate_distances(&[0.0, 0.0])[2].distance, 10.0f32);
+    }
+
+    #[test]
+    fn predict_proba_test() {
+        let mut knn = KNN::unfitted(3, None, None);
+        knn.fit(
+            &[vec![0.0], vec![1.0], vec![2.0], vec![10.0]],
+            &[0, 0, 1, 1],
+        )
+        .unwrap();
+
+        assert_eq!(
+            PredictProba::predict_proba(&knn, &[vec![0.0], vec![9.0]]).unwrap(),
+            vec![vec![2.0 / 3.0, 1.0 / 3.0], vec![1.0 / 3.0, 2.0 / 3.0]]
+        );
+
+        let knn = knn.weighting(Weighting::Distance);
+        let proba = knn.predict_proba(&[9.0]).unwrap();
+        let total = 1.0 + 1.0 / 7.0 + 1.0 / 8.0;
+        assert!((proba[0] - 1.0 / 8.0 / total).abs() < 1e-12);
+        assert!((proba[0] + proba[1] - 1.0).abs() < 1e-12);
+        // Neighbors at a distance of 0 outvote all others.
+        assert_eq!(knn.predict_proba(&[2.0]).unwrap(), vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn describe_test() {
+        let knn = KNN::new(
+            1,
+            vec![vec![0.0, 1.0], vec![1.0, 0.0]],
+            vec![0, 1],
+            None,
+            None,
+        )
+        .unwrap();
+        let summary = knn.describe();
+
+        assert_eq!(summary.params[0], ("k".to_string(), "1".to_string()));
+        assert_eq!(summary.n_samples, Some(2));
+        assert_eq!(summary.n_classes, Some(2));
+        assert!(summary.memory >= 4 * 8 + 2 * 4);
+        assert_eq!(
+            KNN::<f64>::unfitted(1, None, None).describe().n_samples,
+            None
+        );
+    }
+
+    #[test]
+    fn fit_weighted_test() {
+        let x = vec![vec![0.0], vec![1.0], vec![2.0]];
+        let mut knn = KNN::unfitted(3, None, None);
+        knn.fit_weighted(&x, &[0, 0, 1], Some(&[1.0, 1.0, 4.0]))
+            .unwrap();
+
+        assert_eq!(knn.predict(&[0.0]).unwrap(), 1);
+        assert_eq!(
+            PredictProba::predict_proba(&knn, &[vec![0.0]]).unwrap(),
+            vec![vec![1.0 / 3.0, 2.0 / 3.0]]
+        );
+        assert!(knn.fit_weighted(&x, &[0, 0, 1], Some(&[1.0])).is_err());
+
+        knn.fit_weighted(&x, &[0, 0, 1], Some(&[0.0; 3])).unwrap();
+        assert_eq!(knn.predict(&[0.0]).unwrap(), 0);
+        knn.fit(&x, &[0, 0, 1]).unwrap();
+        assert_eq!(knn.weights, None);
+    }
+
+    #[test]
+    fn partial_fit_test() {
+        let mut knn = KNN::unfitted(2, None, Some(norm::Norm::L1));
+        knn.partial_fit(&[vec![1.0, 0.0]], &[1]).unwrap();
+        assert!(knn.predict(&[1.0, 0.0]).is_err());
+
+        knn.partial_fit(&[vec![0.0, 2.0], vec![0.0, 3.0]], &[0, 0])
+            .unwrap();
+        assert_eq!(knn.num_labels, 2);
+        assert_eq!(knn.x.row(2), &[0.0, 1.0]);
+        assert_eq!(knn.predict(&[0.0, 1.0]).unwrap(), 0);
+        assert!(knn.partial_fit(&[vec![1.0]], &[0]).is_err());
+        knn.partial_fit(&[vec![1.0, 1.0]], &[-1]).unwrap();
+        assert_eq!(knn.classes, vec![-1, 0, 1]);
+    }
+
+    #[test]
+    fn model_round_trip_test() {
+        let x = vec![vec![0.0f32, 1.0], vec![1.0, 0.0], vec![1.0, 1.0]];
+        let mut knn = KNN::unfitted(1, Some(distance::Distance::Manhattan), Some(norm::Norm::L1));
+        knn.fit_weighted(&x, &[0, 1, 1], Some(&[1.0, 2.0, 0.5]))
+            .unwrap();
+        let mut bytes = Vec::new();
+        knn.write_model(&mut bytes).unwrap();
+        let read = KNN::<f32>::read_model(&mut &bytes[..]).unwrap();
+
+        assert_eq!(read.x, knn.x);
+        assert_eq!(read.weights, knn.weights);
+        assert_eq!(read.distance, knn.distance);
+        assert_eq!(read.normalize, knn.normalize);
+        assert_eq!(read.predict(&[0.1, 0.9]).unwrap(), 0);
+        assert!(KNN::<f64>::read_model(&mut &bytes[..]).is_err());
+        assert!(KNN::<f32>::read_model(&mut &bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn explain_test() {
+        let x = vec![vec![0.0, 0.0], vec![3.0, 4.0], vec![10.0, 10.0]];
+        let mut knn = KNN::unfitted(2, Some(distance::Distance::Manhattan), None);
+        knn.fit_weighted(&x, &[0, 1, 1], Some(&[1.0, 3.0, 1.0]))
+            .unwrap();
+        let explanation = knn.explain(&[1.0, 1.0]).unwrap();
+
+        assert_eq!(explanation.prediction, 1);
+        assert_eq!(explanation.votes, vec![1.0, 3.0]);
+        let indices: Vec<usize> = explanation.neighbors.iter().map(|n| n.index).collect();
+        assert_eq!(indices, vec![0, 1]);
+        let second = &explanation.neighbors[1];
+        assert_eq!(
+            (second.class, second.distance, second.weight),
+            (1, 5.0, 3.0)
+        );
+        assert_eq!(second.contributions, vec![2.0, 3.0]);
+        assert!(knn.explain(&[1.0]).is_err());
+    }
+
+    #[test]
+    fn builder_test() {
+        let x: Vec<Vec<f64>> = (0..20).map(|i| vec![i as f64, 1.0]).collect();
+        let y: Vec<i32> = (0..20).map(|i| i / 10).collect();
+        let knn = KNNBuilder::new()
+            .k(3)
+            .distance(distance::Distance::Manhattan)
+            .normalize(norm::Norm::L2)
+            .index(IndexKind::BallTree)
+            .weighting(Weighting::Distance)
+            .build(x.clone(), y.clone())
+            .unwrap();
+
+        assert_eq!(knn.k, 3);
+        assert_eq!(knn.distance, Some(distance::Distance::Manhattan));
+        assert_eq!(knn.normalize, Some(norm::Norm::L2));
+        assert_eq!(knn.index, IndexKind::BallTree);
+        assert_eq!(knn.weighting, Weighting::Distance);
+        assert!(knn.neighbor_index.is_some());
+        assert_eq!(knn.predict(&[18.0, 1.0]).unwrap(), 1);
+        assert!(KNNBuilder::new().k(21).build(x, y).is_err());
+    }
+
+    #[test]
+    fn predict_batch_with_test() {
+        let x: Vec<Vec<f64>> = (0..50).map(|i| vec![i as f64]).collect();
+        let y: Vec<i32> = (0..50).map(|i| i / 25).collect();
+        let knn = KNN::new(3, x.clone(), y.clone(), None, None).unwrap();
+        let calls = std::sync::Mutex::new(Vec::new());
+        let pred = knn
+            .predict_batch_with(&x, 8, |done, total| {
+                calls.lock().unwrap().push((done, total));
+                true
+            })
+            .unwrap();
+
+        assert_eq!(pred, y);
+        let mut calls = calls.into_inner().unwrap();
+        calls.sort_unstable();
+        assert_eq!(calls.len(), 7);
+        assert_eq!(calls.last(), Some(&(50, 50)));
+
+        let knn = knn.parallelism(Parallelism::Sequential);
+        assert!(matches!(
+            knn.predict_batch_with(&x, 8, |done, _| done < 16),
+            Err(Error::Cancelled)
+        ));
+        assert!(knn.predict_batch_with(&x, 0, |_, _| true).is_err());
+        assert!(knn
+            .predict_batch_with(&[vec![1.0, 2.0]], 8, |_, _| true)
+            .is_err());
+    }
+
+    #[test]
+    fn precomputed_test() {
+        let points: Vec<Vec<f64>> = (0..12).map(|i| vec![i as f64, (i % 3) as f64]).collect();
+        let y: Vec<i32> = (0..12).map(|i| i / 6).collect();
+        let pairwise = |a: &[Vec<f64>]| -> Vec<Vec<f64>> {
+            a.iter()
+                .map(|p| {
+                    points
+                        .iter()
+                        .map(|q| distance::euclidean_distance(p, q))
+                        .collect()
+                })
+                .collect()
+        };
+        let mut knn = KNN::precomputed(3, pairwise(&points), y.clone()).unwrap();
+        let plain = KNN::new(3, points.clone(), y.clone(), None, None).unwrap();
+
+        let queries = vec![vec![2.5, 1.0], vec![9.0, 0.0]];
+        for (q, d) in queries.iter().zip(pairwise(&queries)) {
+            assert_eq!(knn.predict(&d).unwrap(), plain.predict(q).unwrap());
+            assert!(knn.explain(&d).unwrap().neighbors[0]
+                .contributions
+                .is_empty());
+        }
+        assert!(knn.predict(&[1.0]).is_err());
+        assert!(knn.add_samples(vec![vec![0.0; 12]], vec![0]).is_err());
+
+        knn.remove_samples(&[0, 11]).unwrap();
+        assert_eq!((knn.x.n_rows(), knn.x.n_cols()), (10, 10));
+        assert_eq!(knn.x.row(0)[0], 0.0);
+        assert!(knn.condense().unwrap() > 0);
+
+        let mut bytes = Vec::new();
+        knn.write_model(&mut bytes).unwrap();
+        let read: KNN = KNN::read_model(&mut bytes.as_slice()).unwrap();
+        assert_eq!(read.distance, Some(distance::Distance::Precomputed));
+
+        assert!(matches!(
+            KNN::precomputed(1, vec![vec![0.0, 1.0]], vec![0]),
+            Err(Error::ShapeMismatch(_))
+        ));
+        assert!(KNN::new(
+            1,
+            vec![vec![0.0]],
+            vec![0],
+            Some(distance::Distance::Precomputed),
+            Some(norm::Norm::L2)
+        )
+        .is_err());
+        let tuned = KNN::precomputed(1, pairwise(&points), y)
+            .unwrap()
+            .seed(1)
+            .tune_k(&[1, 3], 3)
+            .unwrap();
+        assert!(tuned.scores.iter().all(|&(_, s)| s > 0.8));
+    }
+
+    #[test]
+    fn standardize_test() {
+        // The first feature separates the classes, the second is large noise.
+        let x: Vec<Vec<f64>> = (0..20)
+            .map(|i| vec![(i / 10) as f64, (i % 10 * 100 + i / 10 * 50) as f64])
+            .collect();
+        let y: Vec<i32> = (0..20).map(|i| i / 10).collect();
+        let query = [0.9, 0.0];
+
+        let plain = KNN::new(3, x.clone(), y.clone(), None, None).unwrap();
+        assert_eq!(plain.predict(&query).unwrap(), 0);
+        for standardize in [Standardization::ZScore, Standardization::MinMax] {
+            let mut knn = KNN::unfitted(3, None, None).standardize(standardize);
+            knn.fit(&x, &y).unwrap();
+            assert_eq!(knn.predict(&query).unwrap(), 1);
+            assert!(knn.predict(&[0.9, 0.0, 1.0]).is_err());
+            assert_eq!(
+                knn.describe().params[3].1,
+                format!("Some({:?})", standardize)
+            );
+        }
+
+        let mut knn = KNNBuilder::new()
+            .k(1)
+            .standardize(Standardization::MinMax)
+            .build(x.clone(), y.clone())
+            .unwrap();
+        assert_eq!(knn.x.row(19), &[1.0, 1.0]);
+        knn.add_samples(vec![vec![2.0, 475.0]], vec![2]).unwrap();
+        assert_eq!(knn.x.row(20), &[2.0, 0.5]);
+
+        let mut bytes = Vec::new();
+        knn.write_model(&mut bytes).unwrap();
+        let read: KNN = KNN::read_model(&mut bytes.as_slice()).unwrap();
+        assert_eq!(read.predict(&[1.9, 400.0]).unwrap(), 2);
+        assert_eq!(read.standardize, Some(Standardization::MinMax));
+
+        let mut online = KNN::unfitted(1, None, None).standardize(Standardization::MinMax);
+        online.add_samples(x, y).unwrap();
+        assert_eq!(online.x.row(19), &[1.0, 1.0]);
+    }
+
+    #[test]
+    fn normalized_query_test() {
+        let x = vec![vec![1.0, 0.0], vec![0.6, 0.8]];
+        let knn = KNN::new(
+            1,
+            x,
+            vec![0, 1],
+            Some(distance::Distance::Manhattan),
+            Some(norm::Norm::L2),
+        )
+        .unwrap();
+        let query = vec![0.05, 0.1];
+        let mut normalized = query.clone();
+        norm::normalize_vector(&mut normalized, &norm::Norm::L2);
+
+        // Unnormalized, the query is closer to the first point.
+        assert_eq!(knn.predict(&query).unwrap(), 1);
+        assert_eq!(knn.predict_normalized(&[normalized]).unwrap(), vec![1]);
+        assert_eq!(knn.explain(&query).unwrap().neighbors[0].index, 1);
+
+        // Standardized, the query is closer to the second point; raw, to the first.
+        let x = vec![vec![0.0, 0.0], vec![1.0, 100.0], vec![0.0, 100.0]];
+        let mut knn = KNN::unfitted(1, None, None).standardize(Standardization::MinMax);
+        knn.fit(&x, &[0, 1, 2]).unwrap();
+        assert_eq!(knn.predict_normalized(&[vec![0.9, 45.0]]).unwrap(), vec![1]);
+        knn.normalize = Some(norm::Norm::L2);
+        assert!(knn.predict_normalized(&[vec![0.9, 45.0]]).is_err());
+    }
+
+    #[test]
+    fn ball_tree_index_test() {
+        let mut rng = crate::math::random::Rng::new(11);
+        let mut points = |n| -> Vec<Vec<f64>> {
+            (0..n)
+                .map(|_| vec![rng.next_f64() * 20.0, rng.next_f64() * 20.0])
+                .collect()
+        };
+        let x = points(200);
+        let queries = points(30);
+        let y: Vec<i32> = (0..200).map(|i| i % 3).collect();
+        let mut knn = KNN::new(
+            5,
+            x.clone(),
+            y.clone(),
+            Some(distance::Distance::Manhattan),
+            None,
+        )
+        .unwrap()
+        .weighting(Weighting::Distance);
+        let expected = PredictProba::predict_proba(&knn, &queries).unwrap();
+
+        knn = knn.with_index(IndexKind::BallTree);
+        assert_eq!(
+            PredictProba::predict_proba(&knn, &queries).unwrap(),
+            expected
+        );
+        knn.partial_fit(&[vec![7.0, 7.5]], &[3]).unwrap();
+        assert_eq!(knn.predict(&[7.0, 7.5]).unwrap(), 3);
+        // Changing the distance by hand falls back to a brute force search.
+        knn.distance = None;
+        assert_eq!(knn.predict(&[7.0, 7.5]).unwrap(), 3);
+    }
+
+    #[test]
+    fn minkowski_test() {
+        let mut rng = crate::math::random::Rng::new(4);
+        let x: Vec<Vec<f64>> = (0..100)
+            .map(|_| vec![rng.next_f64(), rng.next_f64(), rng.next_f64()])
+            .collect();
+        let y: Vec<i32> = (0..100).map(|i| i % 2).collect();
+        let minkowski = Some(distance::Distance::Minkowski(3.0));
+        let knn = KNN::new(3, x.clone(), y.clone(), minkowski, None).unwrap();
+        let queries = &x[..20];
+        let indexed = knn.clone().with_index(IndexKind::BallTree);
+
+        assert_eq!(
+            PredictProba::predict_proba(&indexed, queries).unwrap(),
+            PredictProba::predict_proba(&knn, queries).unwrap()
+        );
+        let mut bytes = Vec::new();
+        knn.write_model(&mut bytes).unwrap();
+        assert_eq!(
+            KNN::<f64>::read_model(&mut &bytes[..]).unwrap().distance,
+            minkowski
+        );
+        let invalid = Some(distance::Distance::Minkowski(0.5));
+        assert!(KNN::new(3, x, y, invalid, None).is_err());
+    }
+
+    #[test]
+    fn hamming_test() {
+        // Categorical features: the query shares two categories with the first point but only
+        // one with the second, though it is closer to the second in euclidean distance.
+        let x = vec![vec![0.0, 5.0, 9.0], vec![1.0, 5.0, 3.0]];
+        let hamming = Some(distance::Distance::Hamming);
+        let knn = KNN::new(1, x, vec![0, 1], hamming, None).unwrap();
+
+        assert_eq!(knn.predict(&[0.0, 5.0, 2.0]).unwrap(), 0);
+        let explanation = knn.explain(&[0.0, 5.0, 2.0]).unwrap();
+        assert_eq!(explanation.neighbors[0].contributions, vec![0.0, 0.0, 1.0]);
+        let mut bytes = Vec::new();
+        knn.write_model(&mut bytes).unwrap();
+        assert_eq!(
+            KNN::<f64>::read_model(&mut &bytes[..]).unwrap().distance,
+            hamming
+        );
+    }
+
+    #[test]
+    fn hnsw_index_test() {
+        let mut rng = crate::math::random::Rng::new(3);
+        let x: Vec<Vec<f64>> = (0..300)
+            .map(|_| (0..10).map(|_| rng.next_f64()).collect())
+            .collect();
+        let y: Vec<i32> = x.iter().map(|row| (row[0] > 0.5) as i32).collect();
+        let knn = KNN::new(3, x.clone(), y, None, None).unwrap();
+        let mut approximate = knn.clone().with_index(IndexKind::hnsw());
+
+        let agree = x
+            .iter()
+            .filter(|row| knn.predict(row).unwrap() == approximate.predict(row).unwrap())
+            .count();
+        assert!(agree >= 290);
+        assert!(approximate.memory_usage() > knn.memory_usage());
+
+        approximate.partial_fit(&[vec![2.0; 10]], &[2]).unwrap();
+        let explanation = approximate.explain(&[2.0; 10]).unwrap();
+        assert_eq!(explanation.neighbors[0].index, 300);
+    }
+
+    #[test]
+    fn custom_distance_test() {
+        // Only the first feature counts.
+        let first = |p: &[f64], q: &[f64]| (p[0] - q[0]).abs();
+        let x = vec![vec![0.0, 10.0], vec![5.0, 0.0]];
+        let knn = KNN::new(1, x, vec![0, 1], None, None)
+            .unwrap()
+            .with_index(IndexKind::BallTree)
+            .custom_distance(first);
+
+        assert_eq!(knn.predict(&[1.0, 0.0]).unwrap(), 0);
+        assert_eq!(knn.calculate_distances(&[1.0, 0.0])[1].distance, 4.0);
+        assert!(knn.explain(&[1.0, 0.0]).unwrap().neighbors[0]
+            .contributions
+            .is_empty());
+        assert_eq!(knn.describe().params[1].1, "CustomDistance");
+        assert!(knn.write_model(&mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn cosine_distance_test() {
+        let x = vec![vec![1.0, 0.1], vec![10.0, 10.0], vec![0.0, 0.0]];
+        let knn = KNN::new(1, x, vec![0, 1, 2], Some(distance::Distance::Cosine), None)
+            .unwrap()
+            .with_index(IndexKind::BallTree);
+
+        // Far from the second point, but in the same direction.
+        assert_eq!(knn.predict(&[1.0, 1.0]).unwrap(), 1);
+        assert_eq!(knn.predict(&[20.0, 1.0]).unwrap(), 0);
+        let distances = knn.calculate_distances(&[1.0, 1.0]);
+        assert!(distances[1].distance.abs() < 1e-12);
+        assert_eq!(distances[2].distance, 1.0);
+        let contributions = &knn.explain(&[3.0, 3.0]).unwrap().neighbors[0].contributions;
+        assert!((contributions[0] - 0.5).abs() < 1e-12);
+
+        let mut buf = Vec::new();
+        knn.write_model(&mut buf).unwrap();
+        let read: KNN = KNN::read_model(&mut buf.as_slice()).unwrap();
+        assert_eq!(read.predict(&[1.0, 1.0]).unwrap(), 1);
+    }
+
+    #[test]
+    fn add_samples_test() {
+        let mut knn = KNN::new(
+            1,
+            vec![vec![1.0, 0.0], vec![0.0, 1.0]],
+            vec![0, 1],
+            Some(distance::Distance::Cosine),
+            None,
+        )
+        .unwrap()
+        .with_index(IndexKind::hnsw());
+        knn.add_samples(vec![vec![-3.0, -3.0]], vec![2]).unwrap();
+
+        assert_eq!(knn.num_labels, 3);
+        assert_eq!(knn.norms.len(), 3);
+        assert_eq!(knn.predict(&[-1.0, -2.0]).unwrap(), 2);
+        assert!(knn.add_samples(vec![vec![1.0]], vec![0]).is_err());
+        assert!(knn.add_samples(vec![vec![1.0, 1.0]], vec![]).is_err());
+        assert_eq!(knn.x.n_rows(), 3);
+
+        let invalid = Some(distance::Distance::Minkowski(0.5));
+        let mut empty: KNN = KNN::unfitted(1, invalid, None);
+        assert!(empty.add_samples(vec![vec![1.0]], vec![0]).is_err());
+        assert!(empty.x.is_empty());
+    }
+
+    #[test]
+    fn remove_and_relabel_test() {
+        let x = vec![vec![0.0], vec![1.0], vec![5.0], vec![6.0]];
+        let mut knn = KNN::new(1, x, vec![0, 0, 1, 2], None, None)
+            .unwrap()
+            .with_index(IndexKind::BallTree);
+        knn.fit_weighted(&knn.x.to_rows(), &[0, 0, 1, 2], Some(&[1.0, 2.0, 3.0, 4.0]))
+            .unwrap();
+
+        knn.remove_samples(&[3, 0, 3]).unwrap();
+        assert_eq!(knn.x.to_rows(), vec![vec![1.0], vec![5.0]]);
+        assert_eq!(knn.weights, Some(vec![2.0, 3.0]));
+        assert_eq!(knn.num_labels, 2);
+        assert_eq!(knn.predict(&[6.0]).unwrap(), 1);
+        assert!(knn.remove_samples(&[0, 2]).is_err());
+        assert_eq!(knn.x.n_rows(), 2);
+
+        knn.relabel(1, 3).unwrap();
+        assert_eq!(knn.classes, vec![0, 3]);
+        assert_eq!(knn.predict(&[6.0]).unwrap(), 3);
+        assert!(knn.relabel(2, 0).is_err());
+        knn.relabel(0, -1).unwrap();
+        assert_eq!(knn.classes, vec![-1, 3]);
+    }
+
+    #[test]
+    fn top_k_test() {
+        let x: Vec<Vec<f64>> = (0..1000).map(|i| vec![(i % 10) as f64]).collect();
+        let y: Vec<i32> = (0..1000).map(|i| i / 500).collect();
+        let knn = KNN::new(3, x, y, None, None).unwrap();
+        let nearest = knn.nearest(&[3.2]).unwrap();
+
+        // Ties are broken by training order.
+        assert_eq!(
+            nearest.iter().map(|p| p.0).collect::<Vec<_>>(),
+            vec![3, 13, 23]
+        );
+        assert_eq!(knn.predict(&[3.2]).unwrap(), 0);
+        assert_eq!(
+            KNN::new(1, vec![vec![0.0]], vec![0], None, None)
+                .unwrap()
+                .nearest(&[1.0])
+                .unwrap(),
+            vec![(0, 1.0)]
+        );
+    }
+}
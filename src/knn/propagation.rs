@@ -0,0 +1,514 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Semi-supervised classification by spreading labels over a nearest neighbor graph.
+
+use super::ball_tree::BallTree;
+use crate::describe::{Describe, Summary};
+use crate::error::{check_lengths, Error, Result};
+use crate::math::dense::DenseMatrix;
+use crate::math::distance::{self, Distance};
+use crate::math::Float;
+use crate::parallel::{self, Parallelism};
+use crate::traits::{Fit, Predict, PredictProba};
+use rayon::prelude::*;
+
+/// Labels the unlabeled training points, given as `None`, by propagating the labels of the
+/// others along a graph that links each point to its `k` nearest neighbors.
+///
+/// By default the labeled points keep their labels and every other point repeatedly takes the
+/// average label distribution of its neighbors. With [`LabelPropagation::spreading`] the graph is
+/// symmetrically normalized and each point keeps only part of its initial label, so some
+/// mislabeled points can be corrected. New points are classified by the label distributions of
+/// their `k` nearest training points.
+///
+/// # Example
+/// ```rust
+/// use rml::knn::LabelPropagation;
+/// use rml::traits::Fit;
+///
+/// let x: Vec<Vec<f64>> = (0..10).map(|i| vec![i as f64 + (i / 5) as f64 * 10.0]).collect();
+/// let mut y = vec![None; 10];
+/// y[0] = Some(0);
+/// y[9] = Some(1);
+/// let mut model: LabelPropagation = LabelPropagation::new(2);
+/// model.fit(&x, &y).unwrap();
+///
+/// assert_eq!(model.transduction, vec![0, 0, 0, 0, 0, 1, 1, 1, 1, 1]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct LabelPropagation<F = f64> {
+    /// The number of neighbors each point is linked to.
+    pub k: usize,
+    /// Type of distance to use, euclidean by default.
+    pub distance: Option<Distance>,
+    /// The fraction of its neighbors' labels each point takes per iteration when spreading, or
+    /// None to clamp the labeled points.
+    pub alpha: Option<f64>,
+    /// The maximum number of iterations.
+    pub max_iter: usize,
+    /// The largest change of a label probability below which the iterations stop.
+    pub tol: f64,
+    /// The parallelism of neighbor searches, or None for the global setting.
+    pub parallelism: Option<Parallelism>,
+    /// Features, one row per training point.
+    pub x: DenseMatrix<F>,
+    /// The distinct given labels, in sorted order. Distributions are in this order.
+    pub classes: Vec<i32>,
+    /// The probability of each class for each training point, once fit.
+    pub label_distributions: Vec<Vec<f64>>,
+    /// The most likely label of each training point, once fit.
+    pub transduction: Vec<i32>,
+    /// The number of iterations the last fit ran.
+    pub n_iter: usize,
+    tree: Option<BallTree<F>>,
+}
+
+impl<F: Float> LabelPropagation<F> {
+    /// Create an unfitted model linking each point to its `k` nearest neighbors, clamping the
+    /// labeled points, with at most 1000 iterations.
+    pub fn new(k: usize) -> LabelPropagation<F> {
+        LabelPropagation {
+            k,
+            distance: None,
+            alpha: None,
+            max_iter: 1000,
+            tol: 1e-3,
+            parallelism: None,
+            x: DenseMatrix::new(0),
+            classes: Vec::new(),
+            label_distributions: Vec::new(),
+            transduction: Vec::new(),
+            n_iter: 0,
+            tree: None,
+        }
+    }
+
+    /// Set the distance between points. With `Distance::Precomputed` row `i` of the data is the
+    /// distances from point `i` to every training point.
+    pub fn distance(mut self, distance: Distance) -> Self {
+        self.distance = Some(distance);
+        self
+    }
+
+    /// Spread labels instead of clamping them, with each point taking `alpha` of its neighbors'
+    /// labels and keeping `1 - alpha` of its initial label per iteration.
+    pub fn spreading(mut self, alpha: f64) -> Self {
+        self.alpha = Some(alpha);
+        self
+    }
+
+    /// Set the maximum number of iterations.
+    pub fn max_iter(mut self, max_iter: usize) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    /// Set the change below which the iterations stop.
+    pub fn tol(mut self, tol: f64) -> Self {
+        self.tol = tol;
+        self
+    }
+
+    /// Set the parallelism of neighbor searches, overriding the global setting.
+    pub fn parallelism(mut self, parallelism: Parallelism) -> Self {
+        self.parallelism = Some(parallelism);
+        self
+    }
+
+    /// The heap memory of the training data and label distributions, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.x.memory_usage()
+            + self.tree.as_ref().map_or(0, |t| t.memory_usage())
+            + self.label_distributions.len() * self.classes.len() * std::mem::size_of::<f64>()
+            + self.transduction.capacity() * std::mem::size_of::<i32>()
+    }
+
+    /// The class probabilities of a point `x`, averaged over its `k` nearest training points.
+    /// Returns [`Error::NotFitted`] if the model is not fit, or [`Error::ShapeMismatch`] if `x`
+    /// does not have as many features as the training data.
+    pub fn predict_proba(&self, x: &[F]) -> Result<Vec<f64>> {
+        if self.transduction.is_empty() {
+            return Err(Error::NotFitted);
+        }
+        check_lengths("Features and training features", x.len(), self.x.n_cols())?;
+        let mut proba = vec![0.0; self.classes.len()];
+        for (i, _) in self.neighbors(x, self.k) {
+            proba
+                .iter_mut()
+                .zip(&self.label_distributions[i])
+                .for_each(|(p, &d)| *p += d);
+        }
+        normalize(&mut proba);
+        Ok(proba)
+    }
+
+    /// Predict the class of a point `x`.
+    /// Returns an error under the same conditions as [`LabelPropagation::predict_proba`].
+    pub fn predict(&self, x: &[F]) -> Result<i32> {
+        let proba = self.predict_proba(x)?;
+        Ok(self.classes[argmax(&proba)])
+    }
+
+    /// The indices and distances of the `k` training points nearest to `q`, nearest first. Without
+    /// a tree the distances are precomputed or cosine distances.
+    fn neighbors(&self, q: &[F], k: usize) -> Vec<(usize, F)> {
+        match &self.tree {
+            Some(tree) => tree.query(&self.x, q, k),
+            None => {
+                let mut all: Vec<(usize, F)> = match self.distance {
+                    Some(Distance::Precomputed) => q.iter().cloned().enumerate().collect(),
+                    _ => self
+                        .x
+                        .rows()
+                        .map(|row| distance::cosine_distance(q, row))
+                        .enumerate()
+                        .collect(),
+                };
+                all.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+                all.truncate(k);
+                all
+            }
+        }
+    }
+
+    /// The symmetric k nearest neighbor graph of the training points, as the neighbors of each
+    /// point and the weights of the edges to them.
+    fn graph(&self) -> Vec<Vec<(usize, f64)>> {
+        let n = self.x.n_rows();
+        let nearest: Vec<Vec<usize>> = parallel::install(self.parallelism, || {
+            (0..n)
+                .into_par_iter()
+                .map(|i| {
+                    self.neighbors(self.x.row(i), self.k + 1)
+                        .into_iter()
+                        .map(|(j, _)| j)
+                        .filter(|&j| j != i)
+                        .take(self.k)
+                        .collect()
+                })
+                .collect()
+        });
+        let mut graph = vec![Vec::new(); n];
+        for (i, neighbors) in nearest.into_iter().enumerate() {
+            for j in neighbors {
+                graph[i].push((j, 0.5));
+                graph[j].push((i, 0.5));
+            }
+        }
+        for edges in &mut graph {
+            edges.sort_by_key(|&(j, _)| j);
+            edges.dedup_by(|a, b| {
+                let same = a.0 == b.0;
+                if same {
+                    b.1 += a.1;
+                }
+                same
+            });
+        }
+        graph
+    }
+}
+
+impl<F: Float> Fit<F, Option<i32>> for LabelPropagation<F> {
+    /// Label every point of `x` from the labels of `y`, where unlabeled points are None.
+    /// Returns an error if `x` and `y` have different lengths, the samples have different
+    /// dimensions, no point is labeled, `k` is not in `1..n` for `n` points, `alpha` is not in
+    /// `[0, 1)`, the distance is invalid, or a precomputed distance matrix is not square.
+    fn fit(&mut self, x: &[Vec<F>], y: &[Option<i32>]) -> Result<()> {
+        check_lengths("Features and labels", x.len(), y.len())?;
+        let n = x.len();
+        if self.k == 0 || self.k >= n {
+            return Err(Error::InvalidInput(format!(
+                "k is {} but there are {} points.",
+                self.k, n
+            )));
+        }
+        if let Some(alpha) = self.alpha.filter(|a| !(0.0..1.0).contains(a)) {
+            return Err(Error::InvalidInput(format!(
+                "alpha is {} but must be in [0, 1).",
+                alpha
+            )));
+        }
+        let mut classes: Vec<i32> = y.iter().flatten().cloned().collect();
+        classes.sort_unstable();
+        classes.dedup();
+        if classes.is_empty() {
+            return Err(Error::InvalidInput("There are no labeled points.".into()));
+        }
+        let distance = self.distance.unwrap_or(Distance::Euclidean);
+        distance::validate(distance)?;
+        self.x = DenseMatrix::from_rows(x)?;
+        self.tree = match distance {
+            Distance::Precomputed if self.x.n_cols() != n => {
+                return Err(Error::ShapeMismatch(format!(
+                    "A precomputed distance matrix must be square, but it is {}x{}.",
+                    n,
+                    self.x.n_cols()
+                )))
+            }
+            Distance::Precomputed => None,
+            // Cosine distance breaks the triangle inequality the tree prunes with.
+            Distance::Cosine => None,
+            distance => Some(BallTree::new(&self.x, distance)),
+        };
+        self.classes = classes;
+
+        let c = self.classes.len();
+        let initial: Vec<Vec<f64>> = y
+            .iter()
+            .map(|label| {
+                let mut row = vec![0.0; c];
+                if let Some(label) = label {
+                    row[self.classes.binary_search(label).unwrap_or(0)] = 1.0;
+                }
+                row
+            })
+            .collect();
+        let mut graph = self.graph();
+        let degrees: Vec<f64> = graph
+            .iter()
+            .map(|edges| edges.iter().map(|&(_, w)| w).sum())
+            .collect();
+        for (i, edges) in graph.iter_mut().enumerate() {
+            for (j, w) in edges.iter_mut() {
+                *w /= match self.alpha {
+                    Some(_) => (degrees[i] * degrees[*j]).sqrt(),
+                    None => degrees[i],
+                };
+            }
+        }
+
+        let mut current = initial.clone();
+        self.n_iter = 0;
+        while self.n_iter < self.max_iter {
+            let next: Vec<Vec<f64>> = parallel::install(self.parallelism, || {
+                (0..n)
+                    .into_par_iter()
+                    .map(|i| {
+                        if self.alpha.is_none() && y[i].is_some() {
+                            return initial[i].clone();
+                        }
+                        let mut row = vec![0.0; c];
+                        for &(j, w) in &graph[i] {
+                            row.iter_mut()
+                                .zip(&current[j])
+                                .for_each(|(r, &v)| *r += w * v);
+                        }
+                        if let Some(alpha) = self.alpha {
+                            row.iter_mut()
+                                .zip(&initial[i])
+                                .for_each(|(r, &v)| *r = alpha * *r + (1.0 - alpha) * v);
+                        }
+                        row
+                    })
+                    .collect()
+            });
+            let change = next
+                .iter()
+                .flatten()
+                .zip(current.iter().flatten())
+                .map(|(a, b)| (a - b).abs())
+                .fold(0.0, f64::max);
+            current = next;
+            self.n_iter += 1;
+            if change < self.tol {
+                break;
+            }
+        }
+
+        current.iter_mut().for_each(|row| normalize(row));
+        self.transduction = current
+            .iter()
+            .map(|row| self.classes[argmax(row)])
+            .collect();
+        self.label_distributions = current;
+        Ok(())
+    }
+}
+
+impl<F: Float> Predict<F, i32> for LabelPropagation<F> {
+    /// Predict the class of each point in `x`.
+    /// Returns an error under the same conditions as [`LabelPropagation::predict_proba`].
+    fn predict(&self, x: &[Vec<F>]) -> Result<Vec<i32>> {
+        x.iter()
+            .map(|xi| LabelPropagation::predict(self, xi))
+            .collect()
+    }
+}
+
+impl<F: Float> PredictProba<F> for LabelPropagation<F> {
+    /// The class probabilities of each point in `x`.
+    /// Returns an error under the same conditions as [`LabelPropagation::predict_proba`].
+    fn predict_proba(&self, x: &[Vec<F>]) -> Result<Vec<Vec<f64>>> {
+        x.iter().map(|xi| self.predict_proba(xi)).collect()
+    }
+}
+
+/// Scale `row` to sum to 1, unless it is all zeros.
+fn normalize(row: &mut [f64]) {
+    let total: f64 = row.iter().sum();
+    if total > 0.0 {
+        row.iter_mut().for_each(|v| *v /= total);
+    }
+}
+
+/// The index of the largest value, the first on ties.
+fn argmax(row: &[f64]) -> usize {
+    (0..row.len()).fold(0, |best, i| if row[i] > row[best] { i } else { best })
+}
+
+impl<F: Float> Describe for LabelPropagation<F> {
+    fn describe(&self) -> Summary {
+        let mut summary = Summary::new("LabelPropagation")
+            .param("k", self.k)
+            .param("distance", self.distance.unwrap_or(Distance::Euclidean))
+            .param("alpha", self.alpha)
+            .param("max_iter", self.max_iter)
+            .param("tol", self.tol)
+            .param("parallelism", self.parallelism);
+        if !self.transduction.is_empty() {
+            summary.n_samples = Some(self.x.n_rows());
+            summary.n_features = Some(self.x.n_cols());
+            summary.n_classes = Some(self.classes.len());
+        }
+        summary.memory = self.memory_usage();
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::random::Rng;
+
+    /// Two noisy concentric rings with one labeled point each.
+    fn rings() -> (Vec<Vec<f64>>, Vec<i32>, Vec<Option<i32>>) {
+        let mut rng = Rng::new(5);
+        let mut x = Vec::new();
+        let mut truth = Vec::new();
+        for i in 0..200 {
+            let radius = if i % 2 == 0 { 1.0 } else { 3.0 };
+            let angle = rng.next_f64() * std::f64::consts::TAU;
+            let r = radius + 0.1 * rng.next_normal();
+            x.push(vec![r * angle.cos(), r * angle.sin()]);
+            truth.push(i % 2);
+        }
+        let mut y = vec![None; 200];
+        y[0] = Some(0);
+        y[1] = Some(1);
+        (x, truth, y)
+    }
+
+    #[test]
+    fn propagation_test() {
+        let (x, truth, y) = rings();
+        let mut model = LabelPropagation::new(7);
+        model.fit(&x, &y).unwrap();
+
+        assert_eq!(model.classes, vec![0, 1]);
+        assert_eq!(model.transduction, truth);
+        assert_eq!(
+            Predict::predict(&model, &[vec![0.0, 1.0], vec![-3.0, 0.0]]).unwrap(),
+            vec![0, 1]
+        );
+        let proba = LabelPropagation::predict_proba(&model, &[0.0, 1.0]).unwrap();
+        assert!((proba.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!(model.n_iter > 1 && model.n_iter < model.max_iter);
+    }
+
+    #[test]
+    fn spreading_test() {
+        let (x, truth, _) = rings();
+        // One of the labeled points is mislabeled, and is outvoted by its neighbors.
+        let mut y: Vec<Option<i32>> = (0..200)
+            .map(|i| Some(truth[i]).filter(|_| i < 40))
+            .collect();
+        y[2] = Some(1);
+        let mut model = LabelPropagation::new(7).spreading(0.9).tol(1e-6);
+        model.fit(&x, &y).unwrap();
+
+        assert_eq!(model.transduction[2], 0);
+        let correct = model
+            .transduction
+            .iter()
+            .zip(&truth)
+            .filter(|(a, b)| a == b)
+            .count();
+        assert!(correct > 190, "{}", correct);
+        assert!(model
+            .label_distributions
+            .iter()
+            .all(|row| (row.iter().sum::<f64>() - 1.0).abs() < 1e-12));
+    }
+
+    #[test]
+    fn cosine_test() {
+        // Two rays from the origin: near it, points of different rays are closer in euclidean
+        // distance than points of the same ray, but never in cosine distance.
+        let mut rng = Rng::new(8);
+        let x: Vec<Vec<f64>> = (0..100)
+            .map(|i| {
+                let angle = (i % 2) as f64 + 0.05 * rng.next_normal();
+                let r = 0.1 + (i / 2) as f64;
+                vec![r * angle.cos(), r * angle.sin()]
+            })
+            .collect();
+        let truth: Vec<i32> = (0..100).map(|i| i % 2).collect();
+        let mut y = vec![None; 100];
+        y[98] = Some(0);
+        y[99] = Some(1);
+        let mut model = LabelPropagation::new(5).distance(Distance::Cosine);
+        model.fit(&x, &y).unwrap();
+
+        assert!(model.tree.is_none());
+        assert_eq!(model.transduction, truth);
+    }
+
+    #[test]
+    fn precomputed_test() {
+        let x: Vec<Vec<f64>> = (0..10)
+            .map(|i| vec![i as f64 + (i / 5) as f64 * 10.0])
+            .collect();
+        let d: Vec<Vec<f64>> = x
+            .iter()
+            .map(|p| x.iter().map(|q| (p[0] - q[0]).abs()).collect())
+            .collect();
+        let mut y = vec![None; 10];
+        y[0] = Some(3);
+        y[9] = Some(-1);
+        let mut direct = LabelPropagation::new(2);
+        let mut precomputed = LabelPropagation::new(2).distance(Distance::Precomputed);
+        direct.fit(&x, &y).unwrap();
+        precomputed.fit(&d, &y).unwrap();
+
+        assert_eq!(direct.transduction, precomputed.transduction);
+        assert_eq!(precomputed.predict(&d[1]).unwrap(), 3);
+        assert!(precomputed.fit(&x, &y).is_err());
+        assert!(LabelPropagation::new(10).fit(&x, &y).is_err());
+        assert!(LabelPropagation::new(2)
+            .distance(Distance::Minkowski(0.5))
+            .fit(&x, &y)
+            .is_err());
+        assert!(LabelPropagation::new(2).fit(&x, &[None; 10]).is_err());
+        assert!(LabelPropagation::new(2).spreading(1.0).fit(&x, &y).is_err());
+        assert!(matches!(
+            LabelPropagation::<f64>::new(2).predict(&[0.0]),
+            Err(Error::NotFitted)
+        ));
+    }
+}
@@ -0,0 +1,465 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Classification by the training points within a fixed radius.
+
+use super::{check_precomputed, Weighting};
+use crate::describe::{Describe, Summary};
+use crate::error::{check_lengths, Error, Result};
+use crate::math::dense::DenseMatrix;
+use crate::math::distance::{self, Distance};
+use crate::math::norm;
+use crate::math::Float;
+use crate::parallel::{self, Parallelism};
+use crate::traits::{Fit, Predict, PredictProba};
+use rayon::prelude::*;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+/// Predicts the class of a point by a vote of every training point within `radius` of it.
+///
+/// Unlike a [`KNN`](super::KNN), the number of voters follows the density of the training data, so sparse
+/// regions are not outvoted by distant points of a dense class. A point with no training points
+/// within the radius is given the outlier label if one is set, and is an error otherwise.
+///
+/// # Example
+/// ```rust
+/// use rml::knn::RadiusNeighborsClassifier;
+///
+/// let x = vec![vec![0.0], vec![0.5], vec![10.0]];
+/// let knn: RadiusNeighborsClassifier = RadiusNeighborsClassifier::new(1.0, x, vec![0, 0, 1], None, None)
+///     .unwrap()
+///     .outlier_label(2);
+///
+/// assert_eq!(knn.predict(&[0.2]).unwrap(), 0);
+/// assert_eq!(knn.predict(&[9.5]).unwrap(), 1);
+/// assert_eq!(knn.predict(&[5.0]).unwrap(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RadiusNeighborsClassifier<F = f64> {
+    /// The distance within which training points vote.
+    pub radius: F,
+    /// Features, one row per training point.
+    pub x: DenseMatrix<F>,
+    /// Class labels for each feature.
+    pub y: Vec<i32>,
+    /// The distinct labels in `y`, in sorted order. Votes and probabilities are in this order.
+    pub classes: Vec<i32>,
+    /// Number of labels.
+    pub num_labels: usize,
+    /// Type of distance to use.
+    pub distance: Option<Distance>,
+    /// The type of normalization, or None.
+    pub normalize: Option<norm::Norm>,
+    /// How the votes of the neighbors are weighed.
+    pub weighting: Weighting,
+    /// The label of points with no training points within the radius, or None to return an
+    /// error for them.
+    pub outlier_label: Option<i32>,
+    /// The parallelism of distance computations, or None for the global setting.
+    pub parallelism: Option<Parallelism>,
+}
+
+impl<F: Float> RadiusNeighborsClassifier<F> {
+    /// Create a new classifier with optional normalization.
+    /// Returns an error if `x` and `y` have different lengths or are empty, the samples have
+    /// different dimensions, `radius` is not positive, or the distance is invalid.
+    pub fn new(
+        radius: F,
+        x: Vec<Vec<F>>,
+        y: Vec<i32>,
+        distance: Option<Distance>,
+        normalize: Option<norm::Norm>,
+    ) -> Result<RadiusNeighborsClassifier<F>> {
+        let mut knn = RadiusNeighborsClassifier::unfitted(radius, distance, normalize);
+        knn.fit(&x, &y)?;
+        Ok(knn)
+    }
+
+    /// Create a classifier without training data, to be trained later with [`Fit::fit`].
+    pub fn unfitted(
+        radius: F,
+        distance: Option<Distance>,
+        normalize: Option<norm::Norm>,
+    ) -> RadiusNeighborsClassifier<F> {
+        RadiusNeighborsClassifier {
+            radius,
+            x: DenseMatrix::new(0),
+            y: Vec::new(),
+            classes: Vec::new(),
+            num_labels: 0,
+            distance,
+            normalize,
+            weighting: Weighting::Uniform,
+            outlier_label: None,
+            parallelism: None,
+        }
+    }
+
+    /// Give points with no training points within the radius the label `label`.
+    pub fn outlier_label(mut self, label: i32) -> Self {
+        self.outlier_label = Some(label);
+        self
+    }
+
+    /// Set how the votes of the neighbors are weighed.
+    pub fn weighting(mut self, weighting: Weighting) -> Self {
+        self.weighting = weighting;
+        self
+    }
+
+    /// Set the parallelism of distance computations, overriding the global setting.
+    pub fn parallelism(mut self, parallelism: Parallelism) -> Self {
+        self.parallelism = Some(parallelism);
+        self
+    }
+
+    /// The heap memory of the training data, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.x.memory_usage()
+            + (self.y.capacity() + self.classes.capacity()) * std::mem::size_of::<i32>()
+    }
+
+    /// Predict the class of a point `x`.
+    /// Returns [`Error::NotFitted`] if the classifier has no training data,
+    /// [`Error::ShapeMismatch`] if `x` does not have as many features as the training data, or
+    /// [`Error::InvalidInput`] if no training point is within the radius and there is no outlier
+    /// label.
+    pub fn predict(&self, x: &[F]) -> Result<i32> {
+        match self.votes(x)? {
+            Some(votes) => Ok(votes
+                .iter()
+                .enumerate()
+                .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map_or(0, |(i, _)| self.classes[i])),
+            None => self.outlier(),
+        }
+    }
+
+    /// The fraction of the votes of the training points within the radius of `x` in each class.
+    /// A point with no training points within the radius gets a probability of 1 for the outlier
+    /// label.
+    /// Returns an error under the same conditions as [`RadiusNeighborsClassifier::predict`], or
+    /// [`Error::InvalidInput`] if such a point's outlier label is not a known class.
+    pub fn predict_proba(&self, x: &[F]) -> Result<Vec<f64>> {
+        match self.votes(x)? {
+            Some(votes) => {
+                let total: f64 = votes.iter().sum();
+                Ok(votes.iter().map(|v| v / total).collect())
+            }
+            None => {
+                let label = self.outlier()?;
+                if self.classes.binary_search(&label).is_err() {
+                    return Err(Error::InvalidInput(format!(
+                        "The outlier label {} has no probability column; it is not a known class.",
+                        label
+                    )));
+                }
+                Ok(self
+                    .classes
+                    .iter()
+                    .map(|&c| if c == label { 1.0 } else { 0.0 })
+                    .collect())
+            }
+        }
+    }
+
+    /// The indices and distances of the training points within the radius of `x`, nearest first.
+    /// Returns an error if the classifier is not fit or `x` has the wrong number of features.
+    pub fn neighbors(&self, x: &[F]) -> Result<Vec<(usize, F)>> {
+        if self.x.is_empty() {
+            return Err(Error::NotFitted);
+        }
+        if self.x.n_cols() != x.len() {
+            return Err(Error::ShapeMismatch(format!(
+                "Expected {} features but a sample has {}.",
+                self.x.n_cols(),
+                x.len()
+            )));
+        }
+        let query = self.query(x);
+        let distance_fn = |p: &[F], q: &[F]| match self.distance {
+            Some(Distance::Manhattan) => distance::manhattan_distance(p, q),
+            Some(Distance::Minkowski(power)) => distance::minkowski_distance(p, q, power),
+            Some(Distance::Hamming) => distance::hamming_distance(p, q),
+            Some(Distance::Cosine) => distance::cosine_distance(p, q),
+            _ => distance::euclidean_distance(p, q),
+        };
+        let mut neighbors: Vec<(usize, F)> = match self.distance {
+            Some(Distance::Precomputed) => query
+                .iter()
+                .cloned()
+                .enumerate()
+                .filter(|&(_, d)| d <= self.radius)
+                .collect(),
+            _ => parallel::install(self.parallelism, || {
+                self.x
+                    .par_rows()
+                    .map(|row| distance_fn(&query, row))
+                    .enumerate()
+                    .filter(|&(_, d)| d <= self.radius)
+                    .collect()
+            }),
+        };
+        neighbors.sort_unstable_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        Ok(neighbors)
+    }
+
+    /// The weighted votes for each class, or None if no training point is within the radius.
+    fn votes(&self, x: &[F]) -> Result<Option<Vec<f64>>> {
+        let neighbors = self.neighbors(x)?;
+        if neighbors.is_empty() {
+            return Ok(None);
+        }
+        let exact = neighbors.iter().any(|&(_, d)| d == F::zero());
+        let mut votes = vec![0.0; self.num_labels];
+        for &(i, d) in &neighbors {
+            let c = self.classes.binary_search(&self.y[i]).unwrap_or(0);
+            votes[c] += match self.weighting {
+                Weighting::Uniform => 1.0,
+                Weighting::Distance if exact => {
+                    if d == F::zero() {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                }
+                Weighting::Distance => 1.0 / d.to_f64(),
+            };
+        }
+        Ok(Some(votes))
+    }
+
+    /// The outlier label, or an error if there is none.
+    fn outlier(&self) -> Result<i32> {
+        self.outlier_label.ok_or_else(|| {
+            Error::InvalidInput(format!(
+                "No training points are within a radius of {}; set an outlier label.",
+                self.radius
+            ))
+        })
+    }
+
+    /// Normalize a query point with the configured normalization, as the training data was.
+    fn query<'a>(&self, x: &'a [F]) -> Cow<'a, [F]> {
+        match &self.normalize {
+            Some(n) => {
+                let mut x = x.to_vec();
+                norm::normalize_vector(&mut x, n);
+                Cow::Owned(x)
+            }
+            None => Cow::Borrowed(x),
+        }
+    }
+}
+
+impl<F: Float> Fit<F, i32> for RadiusNeighborsClassifier<F> {
+    /// Replace the training data with `x` and `y`, normalizing it with the configured setting.
+    /// Returns an error under the same conditions as [`RadiusNeighborsClassifier::new`].
+    fn fit(&mut self, x: &[Vec<F>], y: &[i32]) -> Result<()> {
+        check_lengths("Features and labels", x.len(), y.len())?;
+        if x.is_empty() {
+            return Err(Error::InvalidInput("There are no training points.".into()));
+        }
+        if self.radius.partial_cmp(&F::zero()) != Some(Ordering::Greater) {
+            return Err(Error::InvalidInput(format!(
+                "The radius is {} but must be positive.",
+                self.radius
+            )));
+        }
+        if let Some(metric) = self.distance {
+            distance::validate(metric)?;
+        }
+        let mut rows = DenseMatrix::from_rows(x)?;
+        if self.distance == Some(Distance::Precomputed) {
+            check_precomputed(&rows, self.normalize.is_some())?;
+        }
+        if let Some(n) = self.normalize {
+            for i in 0..rows.n_rows() {
+                norm::normalize_vector(rows.row_mut(i), &n);
+            }
+        }
+        self.x = rows;
+        self.y = y.to_vec();
+        let mut classes = self.y.clone();
+        classes.sort_unstable();
+        classes.dedup();
+        self.num_labels = classes.len();
+        self.classes = classes;
+        Ok(())
+    }
+}
+
+impl<F: Float> Predict<F, i32> for RadiusNeighborsClassifier<F> {
+    /// Predict the class of each point in `x`.
+    /// Returns an error under the same conditions as [`RadiusNeighborsClassifier::predict`].
+    fn predict(&self, x: &[Vec<F>]) -> Result<Vec<i32>> {
+        x.iter()
+            .map(|xi| RadiusNeighborsClassifier::predict(self, xi))
+            .collect()
+    }
+}
+
+impl<F: Float> PredictProba<F> for RadiusNeighborsClassifier<F> {
+    /// The fraction of the votes within the radius of each point in each class.
+    /// Returns an error under the same conditions as
+    /// [`RadiusNeighborsClassifier::predict_proba`].
+    fn predict_proba(&self, x: &[Vec<F>]) -> Result<Vec<Vec<f64>>> {
+        x.iter().map(|xi| self.predict_proba(xi)).collect()
+    }
+}
+
+impl<F: Float> Describe for RadiusNeighborsClassifier<F> {
+    fn describe(&self) -> Summary {
+        let mut summary = Summary::new("RadiusNeighborsClassifier")
+            .param("radius", self.radius)
+            .param("distance", self.distance.unwrap_or(Distance::Euclidean))
+            .param("normalize", self.normalize)
+            .param("weighting", self.weighting)
+            .param("outlier_label", self.outlier_label)
+            .param("parallelism", self.parallelism);
+        if !self.x.is_empty() {
+            summary.n_samples = Some(self.x.n_rows());
+            summary.n_features = Some(self.x.n_cols());
+            summary.n_classes = Some(self.num_labels);
+        }
+        summary.memory = self.memory_usage();
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knn::KNN;
+
+    fn uneven() -> (Vec<Vec<f64>>, Vec<i32>) {
+        // A dense cluster of class 0 next to a sparse cluster of class 1.
+        let mut x: Vec<Vec<f64>> = (0..20).map(|i| vec![i as f64 * 0.05]).collect();
+        let mut y = vec![0; 20];
+        x.extend(vec![vec![3.0], vec![4.0], vec![5.0]]);
+        y.extend(vec![1, 1, 1]);
+        (x, y)
+    }
+
+    #[test]
+    fn radius_test() {
+        let (x, y) = uneven();
+        let knn = RadiusNeighborsClassifier::new(1.5, x.clone(), y.clone(), None, None).unwrap();
+
+        assert_eq!(knn.predict(&[0.5]).unwrap(), 0);
+        assert_eq!(knn.predict(&[4.2]).unwrap(), 1);
+        assert_eq!(knn.neighbors(&[4.2]).unwrap()[0].0, 21);
+        // With k = 7 the dense cluster outvotes the sparse one at the same point.
+        let knn7 = KNN::new(7, x, y, None, None).unwrap();
+        assert_eq!(knn7.predict(&[2.6]).unwrap(), 0);
+        assert_eq!(knn.predict(&[2.6]).unwrap(), 1);
+        assert_eq!(knn.predict_proba(&[4.2]).unwrap(), vec![0.0, 1.0]);
+        assert_eq!(knn.describe().n_samples, Some(23));
+    }
+
+    #[test]
+    fn outlier_test() {
+        let (x, y) = uneven();
+        let knn = RadiusNeighborsClassifier::new(0.5, x, y, None, None).unwrap();
+
+        assert!(matches!(knn.predict(&[10.0]), Err(Error::InvalidInput(_))));
+        assert!(matches!(
+            knn.predict(&[1.0, 2.0]),
+            Err(Error::ShapeMismatch(_))
+        ));
+        let knn = knn.outlier_label(1);
+        assert_eq!(knn.predict(&[10.0]).unwrap(), 1);
+        assert_eq!(knn.predict_proba(&[10.0]).unwrap(), vec![0.0, 1.0]);
+        let knn = knn.outlier_label(-1);
+        assert_eq!(knn.predict(&[10.0]).unwrap(), -1);
+        assert!(matches!(
+            knn.predict_proba(&[10.0]),
+            Err(Error::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn labels_test() {
+        let x = vec![vec![0.0], vec![0.5], vec![5.0], vec![10.0]];
+        let knn = RadiusNeighborsClassifier::new(1.0, x, vec![200, 200, -1, 10], None, None)
+            .unwrap()
+            .outlier_label(10);
+
+        assert_eq!(knn.classes, vec![-1, 10, 200]);
+        assert_eq!(knn.predict(&[0.2]).unwrap(), 200);
+        assert_eq!(knn.predict(&[5.5]).unwrap(), -1);
+        assert_eq!(knn.predict_proba(&[9.5]).unwrap(), vec![0.0, 1.0, 0.0]);
+        assert_eq!(knn.predict_proba(&[20.0]).unwrap(), vec![0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn validation_test() {
+        let x = vec![vec![0.0], vec![1.0]];
+        assert!(RadiusNeighborsClassifier::new(0.0, x.clone(), vec![0, 1], None, None).is_err());
+        assert!(RadiusNeighborsClassifier::new(1.0, x.clone(), vec![0], None, None).is_err());
+        let invalid = Some(Distance::Minkowski(f64::INFINITY));
+        assert!(RadiusNeighborsClassifier::new(1.0, x, vec![0, 1], invalid, None).is_err());
+        assert!(RadiusNeighborsClassifier::<f64>::new(1.0, vec![], vec![], None, None).is_err());
+        assert!(matches!(
+            RadiusNeighborsClassifier::unfitted(1.0, None, None).predict(&[0.0]),
+            Err(Error::NotFitted)
+        ));
+    }
+
+    #[test]
+    fn precomputed_test() {
+        let distances = vec![
+            vec![0.0, 1.0, 9.0],
+            vec![1.0, 0.0, 8.0],
+            vec![9.0, 8.0, 0.0],
+        ];
+        let knn = RadiusNeighborsClassifier::new(
+            2.0,
+            distances,
+            vec![0, 0, 1],
+            Some(Distance::Precomputed),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            knn.neighbors(&[1.5, 0.5, 7.0]).unwrap(),
+            vec![(1, 0.5), (0, 1.5)]
+        );
+        assert_eq!(knn.predict(&[8.0, 7.0, 1.0]).unwrap(), 1);
+        assert!(RadiusNeighborsClassifier::new(
+            2.0,
+            vec![vec![0.0, 1.0]],
+            vec![0],
+            Some(Distance::Precomputed),
+            None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn distance_weighting_test() {
+        let x = vec![vec![0.0], vec![0.9], vec![1.0]];
+        let knn = RadiusNeighborsClassifier::new(1.0, x, vec![0, 1, 1], None, None).unwrap();
+        assert_eq!(knn.predict(&[0.1]).unwrap(), 1);
+
+        let knn = knn.weighting(Weighting::Distance);
+        assert_eq!(knn.predict(&[0.1]).unwrap(), 0);
+        assert_eq!(knn.predict(&[0.9]).unwrap(), 1);
+    }
+}
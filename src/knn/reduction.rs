@@ -0,0 +1,190 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Reduction of the training set of a KNN.
+
+use super::KNN;
+use crate::error::{Error, Result};
+use crate::math::distance;
+use crate::math::Float;
+
+impl<F: Float> KNN<F> {
+    /// Condense the training data with Hart's condensed nearest neighbor rule: keep a subset
+    /// that classifies every removed training point correctly by its single nearest neighbor.
+    /// Redundant points deep inside a class are dropped and points near the class boundaries
+    /// kept. If fewer than `k` points remain, `k` is lowered to their number.
+    /// Returns the number of training points removed, or an error if the KNN is not fit.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rml::knn::KNN;
+    ///
+    /// let x: Vec<Vec<f64>> = (0..20).map(|i| vec![i as f64]).collect();
+    /// let y = (0..20).map(|i| i / 10).collect();
+    /// let mut knn = KNN::new(1, x, y, None, None).unwrap();
+    ///
+    /// assert_eq!(knn.condense().unwrap(), 16);
+    /// assert_eq!(knn.predict(&[2.0]).unwrap(), 0);
+    /// assert_eq!(knn.predict(&[17.0]).unwrap(), 1);
+    /// ```
+    pub fn condense(&mut self) -> Result<usize> {
+        let n = self.x.n_rows();
+        if n == 0 {
+            return Err(Error::NotFitted);
+        }
+        let mut keep = vec![false; n];
+        let mut store = vec![0];
+        keep[0] = true;
+        loop {
+            let mut changed = false;
+            for (i, kept) in keep.iter_mut().enumerate() {
+                if *kept {
+                    continue;
+                }
+                let nearest = store
+                    .iter()
+                    .map(|&j| (j, self.pair_distance(i, j)))
+                    .min_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)))
+                    .map(|(j, _)| j);
+                if nearest.is_some_and(|j| self.y[j] != self.y[i]) {
+                    store.push(i);
+                    *kept = true;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        self.keep_only(&keep)
+    }
+
+    /// Edit the training data with Wilson's edited nearest neighbor rule: remove every training
+    /// point whose class differs from the prediction of its k nearest other training points.
+    /// Noisy and mislabeled points are dropped, smoothing the class boundaries. If fewer than `k`
+    /// points remain, `k` is lowered to their number.
+    /// Returns the number of training points removed, or an error if the KNN is not fit or has
+    /// at most `k` training points.
+    pub fn edit(&mut self) -> Result<usize> {
+        let n = self.x.n_rows();
+        if n == 0 {
+            return Err(Error::NotFitted);
+        }
+        let k = self.k as usize;
+        if k >= n {
+            return Err(Error::InvalidInput(format!(
+                "k is {} but there are {} training points; editing needs more than k.",
+                self.k, n
+            )));
+        }
+        let keep: Vec<bool> = (0..n)
+            .map(|i| {
+                let distances = self.distances(self.x.row(i));
+                let by_distance =
+                    |a: &usize, b: &usize| distances[*a].total_cmp(&distances[*b]).then(a.cmp(b));
+                let mut order: Vec<usize> = (0..n).filter(|&j| j != i).collect();
+                if k < order.len() {
+                    order.select_nth_unstable_by(k - 1, by_distance);
+                    order.truncate(k);
+                }
+                let nearest: Vec<(usize, F)> =
+                    order.into_iter().map(|j| (j, distances[j])).collect();
+                let weights = self.vote_weights(&nearest);
+                self.get_max_value(&self.count_votes(&nearest, &weights)) == self.y[i]
+            })
+            .collect();
+        self.keep_only(&keep)
+    }
+
+    /// The distance between the training points `i` and `j`.
+    fn pair_distance(&self, i: usize, j: usize) -> F {
+        let (p, q) = (self.x.row(i), self.x.row(j));
+        match (&self.custom_distance, self.metric()) {
+            (Some(custom), _) => custom.distance(p, q),
+            (None, distance::Distance::Manhattan) => distance::manhattan_distance(p, q),
+            (None, distance::Distance::Minkowski(power)) => {
+                distance::minkowski_distance(p, q, power)
+            }
+            (None, distance::Distance::Hamming) => distance::hamming_distance(p, q),
+            (None, distance::Distance::Cosine) => distance::cosine_distance(p, q),
+            (None, distance::Distance::Precomputed) => p[j],
+            (None, distance::Distance::Euclidean) => distance::euclidean_distance(p, q),
+        }
+    }
+
+    /// Remove the training points not flagged in `keep`, lowering `k` to the number left if
+    /// needed, and return the number removed.
+    fn keep_only(&mut self, keep: &[bool]) -> Result<usize> {
+        let removed: Vec<usize> = (0..keep.len()).filter(|&i| !keep[i]).collect();
+        if removed.len() == keep.len() {
+            return Err(Error::InvalidInput(
+                "Every training point would be removed.".into(),
+            ));
+        }
+        self.remove_samples(&removed)?;
+        self.k = self.k.min(self.x.n_rows() as i32);
+        Ok(removed.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn condense_test() {
+        let x: Vec<Vec<f64>> = (0..30)
+            .map(|i| vec![(i % 10) as f64, (i / 10) as f64 * 10.0])
+            .collect();
+        let y: Vec<i32> = (0..30).map(|i| i / 10).collect();
+        let mut knn = KNN::new(3, x.clone(), y.clone(), None, None).unwrap();
+        let removed = knn.condense().unwrap();
+
+        assert!(removed > 20);
+        assert_eq!(knn.x.n_rows(), 30 - removed);
+        assert_eq!(knn.k, 3.min(30 - removed as i32));
+        let one = KNN::new(1, knn.x.to_rows(), knn.y.clone(), None, None).unwrap();
+        for (xi, &yi) in x.iter().zip(&y) {
+            assert_eq!(one.predict(xi).unwrap(), yi);
+        }
+    }
+
+    #[test]
+    fn edit_test() {
+        let mut x: Vec<Vec<f64>> = (0..10).map(|i| vec![i as f64]).collect();
+        let mut y = vec![0; 10];
+        x.extend((20..30).map(|i| vec![i as f64]));
+        y.extend(vec![1; 10]);
+        // A mislabeled point inside class 0.
+        y[4] = 1;
+        let mut knn = KNN::new(3, x, y, None, None).unwrap();
+
+        assert_eq!(knn.edit().unwrap(), 1);
+        assert_eq!(knn.x.n_rows(), 19);
+        assert_eq!(knn.predict(&[4.0]).unwrap(), 0);
+        assert!(
+            KNN::new(2, vec![vec![0.0], vec![1.0]], vec![0, 1], None, None)
+                .unwrap()
+                .edit()
+                .is_err()
+        );
+        assert!(matches!(
+            KNN::<f64>::unfitted(1, None, None).condense(),
+            Err(Error::NotFitted)
+        ));
+    }
+}
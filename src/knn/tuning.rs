@@ -0,0 +1,245 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Selection of k by cross-validation.
+
+use super::KNN;
+use crate::error::{Error, Result};
+use crate::math::distance;
+use crate::math::random;
+use crate::math::Float;
+use crate::model_selection::KFold;
+use std::borrow::Cow;
+
+/// The result of [`KNN::tune_k`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct KSelection {
+    /// The candidate with the highest accuracy.
+    pub best_k: i32,
+    /// The mean cross-validated accuracy of each candidate, in the order given.
+    pub scores: Vec<(i32, f64)>,
+}
+
+impl<F: Float> KNN<F> {
+    /// Choose k from `candidate_ks` by `folds`-fold cross-validation over the training data,
+    /// with every other setting of this KNN. The samples are shuffled with [`KNN::seed`], or the
+    /// global seed if it is None, before splitting, and ties go to the smaller k. The
+    /// standardization statistics are learned again on each training fold. The KNN itself is
+    /// not changed; set `knn.k = selection.best_k` to use the result.
+    /// Returns an error if the KNN is not fit, there are fewer than 2 folds or more folds than
+    /// training points, a candidate is not in `1..=n` for the smallest training fold of `n`
+    /// points, or the KNN both standardizes and normalizes, since the training data can then
+    /// not be standardized again.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rml::knn::KNN;
+    ///
+    /// let x: Vec<Vec<f64>> = (0..40).map(|i| vec![(i % 20) as f64]).collect();
+    /// let y = (0..40).map(|i| (i % 20) / 10).collect();
+    /// let knn = KNN::new(1, x, y, None, None).unwrap().seed(7);
+    /// let selection = knn.tune_k(&[1, 3, 5], 5).unwrap();
+    ///
+    /// assert_eq!(selection.scores.len(), 3);
+    /// assert!(selection.scores.iter().all(|&(_, accuracy)| accuracy > 0.9));
+    /// ```
+    pub fn tune_k(&self, candidate_ks: &[i32], folds: usize) -> Result<KSelection> {
+        let n = self.x.n_rows();
+        if n == 0 {
+            return Err(Error::NotFitted);
+        }
+        let standardized = !self.offsets.is_empty();
+        if standardized && self.normalize.is_some() {
+            return Err(Error::InvalidInput(
+                "k can't be tuned for a KNN that both standardizes and normalizes.".into(),
+            ));
+        }
+        let folds = KFold::new(folds)?
+            .shuffle(self.seed.unwrap_or_else(random::global_seed))
+            .split(n)?;
+        let smallest = folds
+            .iter()
+            .map(|(train, _)| train.len())
+            .min()
+            .unwrap_or(0);
+        let max_k = match candidate_ks.iter().max() {
+            Some(&k) => k,
+            None => return Err(Error::InvalidInput("There are no candidate ks.".into())),
+        };
+        if let Some(k) = candidate_ks
+            .iter()
+            .find(|&&k| k < 1 || k as usize > smallest)
+        {
+            return Err(Error::InvalidInput(format!(
+                "k is {} but the smallest training fold has {} points.",
+                k, smallest
+            )));
+        }
+
+        // Undo the standardization, so each fold learns its own from its training points.
+        let raw = |i: usize| -> Vec<F> {
+            self.x
+                .row(i)
+                .iter()
+                .zip(self.offsets.iter().zip(&self.scales))
+                .map(|(&v, (&o, &s))| v * s + o)
+                .collect()
+        };
+        let mut correct = vec![0usize; candidate_ks.len()];
+        let precomputed = self.metric() == distance::Distance::Precomputed;
+        for (train, validation) in &folds {
+            let mut model = self.clone();
+            model.remove_samples(validation)?;
+            model.k = max_k;
+            // The training points keep their order, so precomputed distances are reordered too.
+            let mut columns = train.clone();
+            columns.sort_unstable();
+            if standardized {
+                for (row, &i) in columns.iter().enumerate() {
+                    model.x.row_mut(row).copy_from_slice(&raw(i));
+                }
+                model.standardize_data();
+                model.build_index();
+            }
+            for &i in validation {
+                // The training data is already normalized.
+                let query: Cow<[F]> = match precomputed {
+                    true => Cow::Owned(columns.iter().map(|&j| self.x.row(i)[j]).collect()),
+                    false if standardized => {
+                        let mut query = raw(i);
+                        model.standardize_vector(&mut query);
+                        Cow::Owned(query)
+                    }
+                    false => Cow::Borrowed(self.x.row(i)),
+                };
+                let nearest = model.nearest(&query)?;
+                for (count, &k) in correct.iter_mut().zip(candidate_ks) {
+                    let nearest = &nearest[..k as usize];
+                    let weights = model.vote_weights(nearest);
+                    let votes = model.count_votes(nearest, &weights);
+                    if model.get_max_value(&votes) == self.y[i] {
+                        *count += 1;
+                    }
+                }
+            }
+        }
+
+        let scores: Vec<(i32, f64)> = candidate_ks
+            .iter()
+            .zip(correct)
+            .map(|(&k, c)| (k, c as f64 / n as f64))
+            .collect();
+        let best_k = scores
+            .iter()
+            .fold(None, |best: Option<(i32, f64)>, &(k, score)| match best {
+                Some((best_k, best_score))
+                    if best_score > score || (best_score == score && best_k < k) =>
+                {
+                    best
+                }
+                _ => Some((k, score)),
+            })
+            .map_or(max_k, |(k, _)| k);
+        Ok(KSelection { best_k, scores })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knn::{KNNBuilder, Standardization};
+    use crate::math::norm::Norm;
+    use crate::math::random::Rng;
+
+    #[test]
+    fn tune_k_test() {
+        // Two classes along a line, with every fifth label flipped, so more neighbors help.
+        let mut rng = Rng::new(3);
+        let x: Vec<Vec<f64>> = (0..200).map(|_| vec![rng.next_f64()]).collect();
+        let y: Vec<i32> = x
+            .iter()
+            .enumerate()
+            .map(|(i, xi)| (xi[0] > 0.5) as i32 ^ (i % 5 == 0) as i32)
+            .collect();
+        let knn = KNN::new(1, x, y, None, None).unwrap().seed(2);
+        let selection = knn.tune_k(&[1, 15], 4).unwrap();
+
+        assert_eq!(selection.best_k, 15);
+        assert!(selection.scores[1].1 > selection.scores[0].1);
+        assert_eq!(knn.k, 1);
+        assert_eq!(knn.tune_k(&[1, 15], 4).unwrap(), selection);
+    }
+
+    #[test]
+    fn standardized_test() {
+        // Each fold is standardized with the statistics of its own training points, as if a
+        // fresh KNN were fit to it.
+        let mut rng = Rng::new(8);
+        let mut x: Vec<Vec<f64>> = (0..60)
+            .map(|_| vec![rng.next_f64(), 100.0 * rng.next_f64()])
+            .collect();
+        // An outlier stretches the second feature only in the folds that train on it.
+        x[0][1] = 1e4;
+        let y: Vec<i32> = x.iter().map(|r| (r[0] * 100.0 > r[1]) as i32).collect();
+        let build = || KNNBuilder::new().standardize(Standardization::ZScore);
+        let knn = build().k(1).build(x.clone(), y.clone()).unwrap().seed(4);
+        let selection = knn.tune_k(&[1, 3], 3).unwrap();
+
+        let mut correct = [0; 2];
+        for (train, validation) in KFold::new(3).unwrap().shuffle(4).split(60).unwrap() {
+            let mut train = train;
+            train.sort_unstable();
+            for (count, k) in correct.iter_mut().zip([1, 3]) {
+                let fold = build()
+                    .k(k)
+                    .build(
+                        train.iter().map(|&i| x[i].clone()).collect(),
+                        train.iter().map(|&i| y[i]).collect(),
+                    )
+                    .unwrap();
+                *count += validation
+                    .iter()
+                    .filter(|&&i| fold.predict(&x[i]).unwrap() == y[i])
+                    .count();
+            }
+        }
+        assert_eq!(
+            selection.scores,
+            vec![(1, correct[0] as f64 / 60.0), (3, correct[1] as f64 / 60.0)]
+        );
+
+        let normalized = build().normalize(Norm::L2).build(x, y).unwrap();
+        assert!(normalized.tune_k(&[1], 3).is_err());
+    }
+
+    #[test]
+    fn tune_k_validation_test() {
+        let x: Vec<Vec<f64>> = (0..10).map(|i| vec![i as f64]).collect();
+        let knn = KNN::new(1, x, vec![0; 10], None, None).unwrap();
+
+        assert_eq!(knn.tune_k(&[3, 1], 2).unwrap().best_k, 1);
+        assert!(knn.tune_k(&[], 2).is_err());
+        assert!(knn.tune_k(&[0], 2).is_err());
+        assert!(knn.tune_k(&[6], 2).is_err());
+        assert!(knn.tune_k(&[1], 1).is_err());
+        assert!(knn.tune_k(&[1], 11).is_err());
+        assert!(matches!(
+            KNN::<f64>::unfitted(1, None, None).tune_k(&[1], 2),
+            Err(Error::NotFitted)
+        ));
+    }
+}
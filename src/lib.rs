@@ -26,5 +26,36 @@ See the provided examples directory for how to use the corresponding algorithms
 
 !*/
 
+pub mod artifact;
+pub mod association;
+pub mod calibration;
+pub mod cluster;
+pub mod datasets;
+pub mod density;
+pub mod describe;
+pub mod early_stopping;
+mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod inspection;
 pub mod knn;
+pub mod linear;
 pub mod math;
+pub mod metrics;
+pub mod model_selection;
+pub mod monitor;
+pub mod multioutput;
+pub mod observer;
+pub mod parallel;
+pub mod pipeline;
+#[cfg(feature = "pmml")]
+pub mod pmml;
+pub mod preprocessing;
+pub mod registry;
+pub mod sequence;
+pub mod svm;
+pub mod text;
+pub mod traits;
+
+pub use error::{Error, Result};
+pub use math::random::set_global_seed;
@@ -0,0 +1,332 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Bayesian ridge regression.
+
+use crate::describe::{Describe, Summary};
+use crate::error::{check_lengths, Error, Result};
+use crate::math::dense::DenseMatrix;
+use crate::math::linalg::inverse;
+use crate::math::Float;
+use crate::traits::{Fit, Predict};
+
+/// The shape and rate of the weak Gamma priors on both precisions, which keep them finite when
+/// the data is fit exactly.
+const PRIOR: f64 = 1e-6;
+
+/// Linear regression with a Gaussian prior on the coefficients, whose precision is estimated
+/// from the data along with the precision of the noise, by maximizing the evidence.
+///
+/// The fit alternates between the posterior of the coefficients given both precisions and the
+/// precisions given the posterior, so unlike [`Ridge`](super::Ridge) there is no penalty to
+/// tune: it is `weight_precision / noise_precision`. The posterior covariance gives the
+/// uncertainty of each prediction, see [`BayesianRidge::predict_std`].
+///
+/// # Example
+/// ```rust
+/// use rml::linear::BayesianRidge;
+/// use rml::traits::Fit;
+///
+/// let x: Vec<Vec<f64>> = (0..20).map(|i| vec![i as f64]).collect();
+/// let y: Vec<f64> = (0..20).map(|i| 2.0 * i as f64 + 1.0 + (i % 3) as f64 - 1.0).collect();
+/// let mut model = BayesianRidge::new();
+/// model.fit(&x, &y).unwrap();
+/// let (mean, std) = model.predict_std(&[vec![10.0], vec![100.0]]).unwrap();
+///
+/// assert!((mean[0] - 21.0).abs() < 0.5);
+/// assert!(std[1] > std[0]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct BayesianRidge {
+    /// The maximum number of updates of the precisions.
+    pub max_iter: usize,
+    /// The largest change of a coefficient below which the updates stop.
+    pub tol: f64,
+    /// Whether to fit an intercept. If false the data is assumed to be centered.
+    pub fit_intercept: bool,
+    /// The posterior mean of the coefficient of each feature, once fit.
+    pub coef: Vec<f64>,
+    /// The intercept, once fit.
+    pub intercept: f64,
+    /// The estimated precision of the noise, once fit.
+    pub noise_precision: f64,
+    /// The estimated precision of the prior on the coefficients, once fit.
+    pub weight_precision: f64,
+    /// The posterior covariance of the coefficients, once fit.
+    pub covariance: Vec<Vec<f64>>,
+    /// The number of updates the last fit ran.
+    pub n_iter: usize,
+    x_mean: Vec<f64>,
+}
+
+impl BayesianRidge {
+    /// Create an unfitted BayesianRidge with an intercept and at most 300 updates.
+    pub fn new() -> BayesianRidge {
+        BayesianRidge {
+            max_iter: 300,
+            tol: 1e-3,
+            fit_intercept: true,
+            coef: Vec::new(),
+            intercept: 0.0,
+            noise_precision: 0.0,
+            weight_precision: 0.0,
+            covariance: Vec::new(),
+            n_iter: 0,
+            x_mean: Vec::new(),
+        }
+    }
+
+    /// Set the maximum number of updates.
+    pub fn max_iter(mut self, max_iter: usize) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    /// Set the change below which the updates stop.
+    pub fn tol(mut self, tol: f64) -> Self {
+        self.tol = tol;
+        self
+    }
+
+    /// Set whether to fit an intercept.
+    pub fn fit_intercept(mut self, fit_intercept: bool) -> Self {
+        self.fit_intercept = fit_intercept;
+        self
+    }
+
+    /// The mean and standard deviation of the predictive distribution of each sample in `x`,
+    /// which includes both the noise and the uncertainty of the coefficients. The intercept is
+    /// taken as known.
+    /// Returns an error under the same conditions as [`BayesianRidge::predict`].
+    pub fn predict_std<F: Float>(&self, x: &[Vec<F>]) -> Result<(Vec<f64>, Vec<f64>)> {
+        let mean = self.predict(x)?;
+        let std = x
+            .iter()
+            .map(|row| {
+                let centered: Vec<f64> = row
+                    .iter()
+                    .zip(&self.x_mean)
+                    .map(|(v, m)| v.to_f64() - m)
+                    .collect();
+                let spread: f64 = self
+                    .covariance
+                    .iter()
+                    .zip(&centered)
+                    .map(|(cov, a)| a * cov.iter().zip(&centered).map(|(c, b)| c * b).sum::<f64>())
+                    .sum();
+                (1.0 / self.noise_precision + spread).sqrt()
+            })
+            .collect();
+        Ok((mean, std))
+    }
+}
+
+impl Default for BayesianRidge {
+    fn default() -> Self {
+        BayesianRidge::new()
+    }
+}
+
+impl<F: Float> Fit<F, f64> for BayesianRidge {
+    /// Fit the precisions and the posterior of the coefficients to `x` and `y`.
+    /// Returns an error if there are no samples, the samples have different dimensions, or `x`
+    /// and `y` have different lengths.
+    fn fit(&mut self, x: &[Vec<F>], y: &[f64]) -> Result<()> {
+        check_lengths("Features and targets", x.len(), y.len())?;
+        if x.is_empty() {
+            return Err(Error::InvalidInput("There are no samples.".into()));
+        }
+        let x = DenseMatrix::from_rows(x)?;
+        let (n, p) = (x.n_rows(), x.n_cols());
+
+        let mut x_mean = vec![0.0; p];
+        let mut y_mean = 0.0;
+        if self.fit_intercept {
+            for (row, t) in x.rows().zip(y) {
+                for (m, v) in x_mean.iter_mut().zip(row) {
+                    *m += v.to_f64() / n as f64;
+                }
+                y_mean += t / n as f64;
+            }
+        }
+        let xc: Vec<Vec<f64>> = x
+            .rows()
+            .map(|row| {
+                row.iter()
+                    .zip(&x_mean)
+                    .map(|(v, m)| v.to_f64() - m)
+                    .collect()
+            })
+            .collect();
+        let yc: Vec<f64> = y.iter().map(|v| v - y_mean).collect();
+        let mut gram = vec![vec![0.0; p]; p];
+        let mut xty = vec![0.0; p];
+        for (row, t) in xc.iter().zip(&yc) {
+            for r in 0..p {
+                xty[r] += row[r] * t;
+                for (c, v) in gram[r].iter_mut().enumerate() {
+                    *v += row[r] * row[c];
+                }
+            }
+        }
+
+        let variance = yc.iter().map(|v| v * v).sum::<f64>() / n as f64;
+        let mut noise = 1.0 / (variance + f64::EPSILON);
+        let mut weight = 1.0;
+        let mut coef = vec![0.0; p];
+        let mut covariance = Vec::new();
+        self.n_iter = 0;
+        while self.n_iter < self.max_iter {
+            // The posterior covariance (weight I + noise X'X)^-1 and mean noise Σ X'y.
+            let mut a: Vec<Vec<f64>> = gram
+                .iter()
+                .map(|row| row.iter().map(|v| noise * v).collect())
+                .collect();
+            (0..p).for_each(|r| a[r][r] += weight);
+            covariance = inverse(a).ok_or_else(|| {
+                Error::InvalidInput("The posterior precision is singular.".into())
+            })?;
+            let next: Vec<f64> = covariance
+                .iter()
+                .map(|row| noise * row.iter().zip(&xty).map(|(c, b)| c * b).sum::<f64>())
+                .collect();
+
+            // The number of coefficients the data determines, then the precisions it implies.
+            let trace: f64 = (0..p).map(|r| covariance[r][r]).sum();
+            let gamma = p as f64 - weight * trace;
+            let norm: f64 = next.iter().map(|c| c * c).sum();
+            let rss: f64 = xc
+                .iter()
+                .zip(&yc)
+                .map(|(row, t)| {
+                    let fit: f64 = row.iter().zip(&next).map(|(v, c)| v * c).sum();
+                    (t - fit) * (t - fit)
+                })
+                .sum();
+            weight = (gamma + 2.0 * PRIOR) / (norm + 2.0 * PRIOR);
+            noise = (n as f64 - gamma + 2.0 * PRIOR) / (rss + 2.0 * PRIOR);
+
+            let change = next
+                .iter()
+                .zip(&coef)
+                .map(|(a, b)| (a - b).abs())
+                .fold(0.0, f64::max);
+            coef = next;
+            self.n_iter += 1;
+            if self.n_iter > 1 && change < self.tol {
+                break;
+            }
+        }
+
+        self.intercept = y_mean - coef.iter().zip(&x_mean).map(|(c, m)| c * m).sum::<f64>();
+        self.coef = coef;
+        self.covariance = covariance;
+        self.noise_precision = noise;
+        self.weight_precision = weight;
+        self.x_mean = x_mean;
+        Ok(())
+    }
+}
+
+impl<F: Float> Predict<F, f64> for BayesianRidge {
+    /// Predict the mean target of each sample in `x`.
+    /// Returns an error if the BayesianRidge is not fit or a sample has the wrong number of
+    /// features.
+    fn predict(&self, x: &[Vec<F>]) -> Result<Vec<f64>> {
+        if self.coef.is_empty() {
+            return Err(Error::NotFitted);
+        }
+        x.iter()
+            .map(|row| {
+                check_lengths("Features and coefficients", row.len(), self.coef.len())?;
+                Ok(self.intercept
+                    + row
+                        .iter()
+                        .zip(&self.coef)
+                        .map(|(v, c)| v.to_f64() * c)
+                        .sum::<f64>())
+            })
+            .collect()
+    }
+}
+
+impl Describe for BayesianRidge {
+    fn describe(&self) -> Summary {
+        let mut summary = Summary::new("BayesianRidge")
+            .param("max_iter", self.max_iter)
+            .param("tol", self.tol)
+            .param("fit_intercept", self.fit_intercept);
+        if !self.coef.is_empty() {
+            summary.n_features = Some(self.coef.len());
+        }
+        summary.memory = (self.coef.capacity() + self.x_mean.capacity())
+            * std::mem::size_of::<f64>()
+            + self.coef.len() * self.coef.len() * std::mem::size_of::<f64>();
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::random::Rng;
+
+    #[test]
+    fn bayesian_ridge_test() {
+        // y = 2 x0 - x1 + 3 with noise of standard deviation 0.5, and an irrelevant feature.
+        let mut rng = Rng::new(13);
+        let x: Vec<Vec<f64>> = (0..200)
+            .map(|_| (0..3).map(|_| rng.next_normal()).collect())
+            .collect();
+        let y: Vec<f64> = x
+            .iter()
+            .map(|r| 2.0 * r[0] - r[1] + 3.0 + 0.5 * rng.next_normal())
+            .collect();
+        let mut model = BayesianRidge::new();
+        model.fit(&x, &y).unwrap();
+
+        assert!((model.coef[0] - 2.0).abs() < 0.1);
+        assert!((model.coef[1] + 1.0).abs() < 0.1);
+        assert!(model.coef[2].abs() < 0.1);
+        assert!((model.intercept - 3.0).abs() < 0.1);
+        assert!((model.noise_precision - 4.0).abs() < 1.0);
+        assert!(model.n_iter < model.max_iter);
+        assert_eq!(model.describe().n_features, Some(3));
+    }
+
+    #[test]
+    fn predict_std_test() {
+        let x: Vec<Vec<f64>> = (0..30).map(|i| vec![i as f64 / 10.0]).collect();
+        let y: Vec<f64> = (0..30)
+            .map(|i| i as f64 / 10.0 + 0.1 * ((i % 5) as f64 - 2.0))
+            .collect();
+        let mut model = BayesianRidge::new();
+        model.fit(&x, &y).unwrap();
+        let (mean, std) = model.predict_std(&[vec![1.5], vec![50.0]]).unwrap();
+
+        assert!((mean[0] - 1.5).abs() < 0.1);
+        // The noise alone, and more far from the data.
+        assert!(std[0] >= 1.0 / model.noise_precision.sqrt());
+        assert!(std[1] > 2.0 * std[0]);
+        assert!(model.predict_std(&[vec![1.0, 2.0]]).is_err());
+        assert!(matches!(
+            BayesianRidge::new().predict_std(&x),
+            Err(Error::NotFitted)
+        ));
+        assert!(BayesianRidge::new().fit(&x, &y[..3]).is_err());
+    }
+}
@@ -0,0 +1,467 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Multinomial logistic regression.
+
+use crate::describe::{Describe, Summary};
+use crate::error::{check_lengths, check_weights, Error, Result};
+use crate::math::dense::DenseMatrix;
+use crate::math::Float;
+use crate::traits::{Fit, FitWeighted, Predict, PredictProba};
+
+/// The number of past steps L-BFGS keeps to approximate the curvature.
+const HISTORY: usize = 10;
+
+/// A linear classifier with one weight vector per class, whose scores are turned into class
+/// probabilities by the softmax function.
+///
+/// The fit minimizes the mean cross-entropy of the training labels plus an L2 penalty of
+/// `alpha / 2` times the squared norm of the coefficients, with L-BFGS. Since every class is
+/// fit jointly rather than one against the rest, the probabilities are consistent and need no
+/// renormalization.
+///
+/// # Example
+/// ```rust
+/// use rml::linear::LogisticRegression;
+/// use rml::traits::{Fit, Predict, PredictProba};
+///
+/// let x = vec![vec![0.0], vec![1.0], vec![5.0], vec![6.0], vec![10.0], vec![11.0]];
+/// let y = vec![0, 0, 1, 1, 2, 2];
+/// let mut model = LogisticRegression::new();
+/// model.fit(&x, &y).unwrap();
+///
+/// assert_eq!(model.predict(&[vec![0.5], vec![5.5], vec![12.0]]).unwrap(), vec![0, 1, 2]);
+/// let proba = model.predict_proba(&[vec![5.5]]).unwrap();
+/// assert!((proba[0].iter().sum::<f64>() - 1.0).abs() < 1e-12);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogisticRegression {
+    /// The strength of the L2 penalty. The intercepts are not penalized.
+    pub alpha: f64,
+    /// The maximum number of L-BFGS iterations.
+    pub max_iter: usize,
+    /// The largest gradient component below which the fit stops.
+    pub tol: f64,
+    /// Whether to fit an intercept.
+    pub fit_intercept: bool,
+    /// The sorted distinct labels, once fit. Coefficients and probabilities are in this order.
+    pub classes: Vec<i32>,
+    /// The weights of each class, once fit.
+    pub coef: Vec<Vec<f64>>,
+    /// The intercept of each class, once fit.
+    pub intercept: Vec<f64>,
+    /// The number of iterations the last fit ran.
+    pub n_iter: usize,
+}
+
+impl LogisticRegression {
+    /// Create an unfitted LogisticRegression with a penalty of `1e-4`, at most 100 iterations
+    /// and an intercept.
+    pub fn new() -> LogisticRegression {
+        LogisticRegression {
+            alpha: 1e-4,
+            max_iter: 100,
+            tol: 1e-5,
+            fit_intercept: true,
+            classes: Vec::new(),
+            coef: Vec::new(),
+            intercept: Vec::new(),
+            n_iter: 0,
+        }
+    }
+
+    /// Set the strength of the penalty.
+    pub fn alpha(mut self, alpha: f64) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Set the maximum number of iterations.
+    pub fn max_iter(mut self, max_iter: usize) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    /// Set the gradient size below which the fit stops.
+    pub fn tol(mut self, tol: f64) -> Self {
+        self.tol = tol;
+        self
+    }
+
+    /// Set whether to fit an intercept.
+    pub fn fit_intercept(mut self, fit_intercept: bool) -> Self {
+        self.fit_intercept = fit_intercept;
+        self
+    }
+
+    /// The score of each sample of `x` for each class, in the order of `classes`.
+    /// Returns an error if the LogisticRegression is not fit or a sample has the wrong number
+    /// of features.
+    pub fn decision_function<F: Float>(&self, x: &[Vec<F>]) -> Result<Vec<Vec<f64>>> {
+        if self.coef.is_empty() {
+            return Err(Error::NotFitted);
+        }
+        x.iter()
+            .map(|row| {
+                check_lengths("Features and coefficients", row.len(), self.coef[0].len())?;
+                Ok(self
+                    .coef
+                    .iter()
+                    .zip(&self.intercept)
+                    .map(|(w, b)| b + row.iter().zip(w).map(|(v, w)| v.to_f64() * w).sum::<f64>())
+                    .collect())
+            })
+            .collect()
+    }
+}
+
+impl Default for LogisticRegression {
+    fn default() -> Self {
+        LogisticRegression::new()
+    }
+}
+
+/// Replace `scores` by their softmax, returning the log of the sum of their exponentials.
+fn softmax(scores: &mut [f64]) -> f64 {
+    let max = scores.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    scores.iter_mut().for_each(|s| *s = (*s - max).exp());
+    let total: f64 = scores.iter().sum();
+    scores.iter_mut().for_each(|s| *s /= total);
+    max + total.ln()
+}
+
+/// Minimize `f`, which returns the value and gradient at a point, from `x` by L-BFGS with a
+/// backtracking line search. Returns the number of iterations run.
+fn lbfgs<G>(f: G, x: &mut Vec<f64>, max_iter: usize, tol: f64) -> usize
+where
+    G: Fn(&[f64]) -> (f64, Vec<f64>),
+{
+    let dot = |a: &[f64], b: &[f64]| a.iter().zip(b).map(|(a, b)| a * b).sum::<f64>();
+    let (mut value, mut gradient) = f(x);
+    let mut history: Vec<(Vec<f64>, Vec<f64>, f64)> = Vec::with_capacity(HISTORY);
+    for iter in 0..max_iter {
+        if gradient.iter().all(|g| g.abs() < tol) {
+            return iter;
+        }
+        // The two-loop recursion for the direction -H g.
+        let mut direction: Vec<f64> = gradient.iter().map(|g| -g).collect();
+        let mut alphas = Vec::with_capacity(history.len());
+        for (s, y, rho) in history.iter().rev() {
+            let a = rho * dot(s, &direction);
+            direction.iter_mut().zip(y).for_each(|(d, y)| *d -= a * y);
+            alphas.push(a);
+        }
+        if let Some((s, y, _)) = history.last() {
+            let scale = dot(s, y) / dot(y, y);
+            direction.iter_mut().for_each(|d| *d *= scale);
+        }
+        for ((s, y, rho), a) in history.iter().zip(alphas.iter().rev()) {
+            let b = rho * dot(y, &direction);
+            direction
+                .iter_mut()
+                .zip(s)
+                .for_each(|(d, s)| *d += (a - b) * s);
+        }
+
+        let slope = dot(&gradient, &direction);
+        let mut step = match history.is_empty() {
+            true => 1.0 / dot(&gradient, &gradient).sqrt().max(1.0),
+            false => 1.0,
+        };
+        let (next, next_value, next_gradient) = loop {
+            let next: Vec<f64> = x
+                .iter()
+                .zip(&direction)
+                .map(|(x, d)| x + step * d)
+                .collect();
+            let (next_value, next_gradient) = f(&next);
+            if next_value <= value + 1e-4 * step * slope || step < 1e-12 {
+                break (next, next_value, next_gradient);
+            }
+            step /= 2.0;
+        };
+        let s: Vec<f64> = next.iter().zip(x.iter()).map(|(a, b)| a - b).collect();
+        let y: Vec<f64> = next_gradient
+            .iter()
+            .zip(&gradient)
+            .map(|(a, b)| a - b)
+            .collect();
+        let sy = dot(&s, &y);
+        if sy > 1e-12 {
+            if history.len() == HISTORY {
+                history.remove(0);
+            }
+            history.push((s, y, 1.0 / sy));
+        }
+        *x = next;
+        if value - next_value <= f64::EPSILON * value.abs() {
+            return iter + 1;
+        }
+        value = next_value;
+        gradient = next_gradient;
+    }
+    max_iter
+}
+
+impl<F: Float> Fit<F, i32> for LogisticRegression {
+    /// Fit the coefficients of every class to `x` and `y`.
+    /// Returns an error if there are no samples, the samples have different dimensions, `x` and
+    /// `y` have different lengths, there are fewer than two classes, or `alpha` is negative.
+    fn fit(&mut self, x: &[Vec<F>], y: &[i32]) -> Result<()> {
+        self.fit_weighted(x, y, None)
+    }
+}
+
+impl<F: Float> FitWeighted<F, i32> for LogisticRegression {
+    /// Fit the coefficients of every class to `x` and `y`, weighing the cross-entropy of each
+    /// sample by its weight in `sample_weight`. Returns an error under the same conditions as
+    /// [`LogisticRegression::fit`], or if the weights are not one finite, non-negative number
+    /// per sample with a positive sum.
+    fn fit_weighted(
+        &mut self,
+        x: &[Vec<F>],
+        y: &[i32],
+        sample_weight: Option<&[f64]>,
+    ) -> Result<()> {
+        check_lengths("Features and labels", x.len(), y.len())?;
+        if x.is_empty() {
+            return Err(Error::InvalidInput("There are no samples.".into()));
+        }
+        if !(self.alpha >= 0.0 && self.alpha.is_finite()) {
+            return Err(Error::InvalidInput(format!(
+                "The penalty {} is not a finite, non-negative number.",
+                self.alpha
+            )));
+        }
+        let weights = match sample_weight {
+            Some(weights) => {
+                check_weights(x.len(), weights)?;
+                weights.to_vec()
+            }
+            None => vec![1.0; x.len()],
+        };
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return Err(Error::InvalidInput("The sample weights sum to 0.".into()));
+        }
+        let mut classes = y.to_vec();
+        classes.sort_unstable();
+        classes.dedup();
+        if classes.len() < 2 {
+            return Err(Error::InvalidInput(
+                "There must be at least two classes.".into(),
+            ));
+        }
+        let x = DenseMatrix::from_rows(x)?;
+        let labels: Vec<usize> = y
+            .iter()
+            .map(|l| classes.binary_search(l).unwrap_or(0))
+            .collect();
+        let (k, p) = (classes.len(), x.n_cols());
+        // The parameters of class c are its p weights followed by its intercept.
+        let width = p + 1;
+
+        let objective = |params: &[f64]| {
+            let mut value = 0.0;
+            let mut gradient = vec![0.0; params.len()];
+            let mut scores = vec![0.0; k];
+            for (i, row) in x.rows().enumerate() {
+                let w = weights[i] / total;
+                if w == 0.0 {
+                    continue;
+                }
+                for (c, s) in scores.iter_mut().enumerate() {
+                    let theta = &params[c * width..(c + 1) * width];
+                    *s = theta[p]
+                        + row
+                            .iter()
+                            .zip(theta)
+                            .map(|(v, t)| v.to_f64() * t)
+                            .sum::<f64>();
+                }
+                let score = scores[labels[i]];
+                value += w * (softmax(&mut scores) - score);
+                scores[labels[i]] -= 1.0;
+                for (c, &d) in scores.iter().enumerate() {
+                    let g = &mut gradient[c * width..(c + 1) * width];
+                    g.iter_mut()
+                        .zip(row)
+                        .for_each(|(g, v)| *g += w * d * v.to_f64());
+                    if self.fit_intercept {
+                        g[p] += w * d;
+                    }
+                }
+            }
+            for c in 0..k {
+                for j in c * width..c * width + p {
+                    value += 0.5 * self.alpha * params[j] * params[j];
+                    gradient[j] += self.alpha * params[j];
+                }
+            }
+            (value, gradient)
+        };
+        let mut params = vec![0.0; k * width];
+        self.n_iter = lbfgs(objective, &mut params, self.max_iter, self.tol);
+
+        self.coef = params.chunks(width).map(|t| t[..p].to_vec()).collect();
+        self.intercept = params.chunks(width).map(|t| t[p]).collect();
+        self.classes = classes;
+        Ok(())
+    }
+}
+
+impl<F: Float> Predict<F, i32> for LogisticRegression {
+    /// Predict the most likely class of each sample in `x`.
+    /// Returns an error under the same conditions as
+    /// [`LogisticRegression::decision_function`].
+    fn predict(&self, x: &[Vec<F>]) -> Result<Vec<i32>> {
+        Ok(self
+            .decision_function(x)?
+            .into_iter()
+            .map(|scores| {
+                let best = (0..scores.len())
+                    .max_by(|&a, &b| scores[a].total_cmp(&scores[b]).then(b.cmp(&a)))
+                    .unwrap_or(0);
+                self.classes[best]
+            })
+            .collect())
+    }
+}
+
+impl<F: Float> PredictProba<F> for LogisticRegression {
+    /// The probability of each class for each sample in `x`, the softmax of the scores.
+    /// Returns an error under the same conditions as
+    /// [`LogisticRegression::decision_function`].
+    fn predict_proba(&self, x: &[Vec<F>]) -> Result<Vec<Vec<f64>>> {
+        let mut scores = self.decision_function(x)?;
+        scores.iter_mut().for_each(|s| {
+            softmax(s);
+        });
+        Ok(scores)
+    }
+}
+
+impl Describe for LogisticRegression {
+    fn describe(&self) -> Summary {
+        let mut summary = Summary::new("LogisticRegression")
+            .param("alpha", self.alpha)
+            .param("max_iter", self.max_iter)
+            .param("tol", self.tol)
+            .param("fit_intercept", self.fit_intercept);
+        if !self.coef.is_empty() {
+            summary.n_features = Some(self.coef[0].len());
+            summary.n_classes = Some(self.classes.len());
+        }
+        summary.memory = self.coef.len()
+            * (self.coef.first().map_or(0, Vec::len) + 1)
+            * std::mem::size_of::<f64>();
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::random::Rng;
+
+    /// Three overlapping blobs in the plane.
+    fn blobs(rng: &mut Rng, n: usize) -> (Vec<Vec<f64>>, Vec<i32>) {
+        let centers = [[0.0, 0.0], [3.0, 0.0], [0.0, 3.0]];
+        (0..n)
+            .map(|i| {
+                let c = centers[i % 3];
+                (
+                    vec![c[0] + rng.next_normal(), c[1] + rng.next_normal()],
+                    (i % 3) as i32,
+                )
+            })
+            .unzip()
+    }
+
+    #[test]
+    fn logistic_test() {
+        let mut rng = Rng::new(17);
+        let (x, y) = blobs(&mut rng, 600);
+        let mut model = LogisticRegression::new();
+        model.fit(&x, &y).unwrap();
+
+        let (test_x, test_y) = blobs(&mut rng, 3000);
+        let predicted = model.predict(&test_x).unwrap();
+        let correct = predicted
+            .iter()
+            .zip(&test_y)
+            .filter(|(a, b)| a == b)
+            .count();
+        assert!(correct as f64 / 3000.0 > 0.8);
+        assert!(model.n_iter < model.max_iter);
+
+        // The mean probability of each class matches its frequency, about 1/3.
+        let proba = model.predict_proba(&test_x).unwrap();
+        for c in 0..3 {
+            let mean = proba.iter().map(|p| p[c]).sum::<f64>() / 3000.0;
+            assert!((mean - 1.0 / 3.0).abs() < 0.02, "{}", mean);
+        }
+        assert_eq!(model.describe().n_classes, Some(3));
+    }
+
+    #[test]
+    fn binary_test() {
+        // For two classes the score difference is the log-odds of a logistic regression: here
+        // the labels are drawn with P(1) = sigmoid(2 x - 1).
+        let mut rng = Rng::new(2);
+        let x: Vec<Vec<f64>> = (0..4000).map(|_| vec![rng.next_normal()]).collect();
+        let y: Vec<i32> = x
+            .iter()
+            .map(|r| (rng.next_f64() < 1.0 / (1.0 + (1.0 - 2.0 * r[0]).exp())) as i32)
+            .collect();
+        let mut model = LogisticRegression::new().alpha(0.0);
+        model.fit(&x, &y).unwrap();
+
+        assert!((model.coef[1][0] - model.coef[0][0] - 2.0).abs() < 0.2);
+        assert!((model.intercept[1] - model.intercept[0] + 1.0).abs() < 0.2);
+    }
+
+    #[test]
+    fn weighted_test() {
+        // Weighing a sample by 2 is the same as repeating it.
+        let x = vec![vec![0.0], vec![1.0], vec![2.0], vec![3.0], vec![1.5]];
+        let y = vec![0, 0, 1, 1, 0];
+        let mut weighted = LogisticRegression::new().alpha(0.1).tol(1e-9);
+        weighted
+            .fit_weighted(&x, &y, Some(&[1.0, 1.0, 1.0, 1.0, 2.0]))
+            .unwrap();
+        let mut repeated = LogisticRegression::new().alpha(0.1).tol(1e-9);
+        let mut x2 = x.clone();
+        x2.push(vec![1.5]);
+        repeated.fit(&x2, &[0, 0, 1, 1, 0, 0]).unwrap();
+
+        for (a, b) in weighted
+            .coef
+            .iter()
+            .flatten()
+            .zip(repeated.coef.iter().flatten())
+        {
+            assert!((a - b).abs() < 1e-4);
+        }
+        assert!(LogisticRegression::new().fit(&x, &[0; 5]).is_err());
+        assert!(LogisticRegression::new().alpha(-1.0).fit(&x, &y).is_err());
+        assert!(matches!(
+            LogisticRegression::new().predict_proba(&x),
+            Err(Error::NotFitted)
+        ));
+    }
+}
@@ -0,0 +1,49 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Linear models.
+
+/*!
+`Ridge` fits a linear regression with an L2 penalty on the coefficients, and `RidgeCV` chooses
+the penalty by cross-validation. `BayesianRidge` estimates the penalty from the data instead, and
+gives the uncertainty of its predictions. `LogisticRegression` fits a multinomial classifier whose
+softmax scores are class probabilities. `SGDClassifier` and `SGDRegressor` train linear models by
+stochastic gradient descent on a choice of loss and penalty, and can learn from streamed batches.
+
+# Example
+```rust
+use rml::linear::Ridge;
+use rml::traits::{Fit, Predict};
+
+let x = vec![vec![0.0], vec![1.0], vec![2.0], vec![3.0]];
+let y = vec![1.0, 3.0, 5.0, 7.0];
+let mut ridge = Ridge::new(0.0);
+ridge.fit(&x, &y).unwrap();
+
+assert!((ridge.predict(&[vec![4.0]]).unwrap()[0] - 9.0).abs() < 1e-9);
+```
+!*/
+
+mod bayesian;
+mod logistic;
+mod ridge;
+mod sgd;
+
+pub use bayesian::BayesianRidge;
+pub use logistic::LogisticRegression;
+pub use ridge::{Ridge, RidgeCV};
+pub use sgd::{Loss, Penalty, SGDClassifier, SGDRegressor};
@@ -0,0 +1,434 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Ridge regression.
+
+use crate::describe::{Describe, Summary};
+use crate::error::{check_lengths, check_weights, Error, Result};
+use crate::math::dense::DenseMatrix;
+use crate::math::linalg::solve;
+use crate::math::random;
+use crate::math::Float;
+use crate::metrics::regression::mean_squared_error;
+use crate::model_selection::{cross_val_score, KFold};
+use crate::traits::{Fit, FitWeighted, Predict};
+
+/// Linear regression with an L2 penalty of `alpha` times the squared norm of the coefficients.
+/// The intercept is not penalized.
+///
+/// The fit solves the normal equations directly. With more features than samples it solves
+/// the equivalent system over the samples instead, so wide data such as text vectors stays
+/// cheap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ridge {
+    /// The strength of the penalty. 0 is ordinary least squares.
+    pub alpha: f64,
+    /// Whether to fit an intercept. If false the data is assumed to be centered.
+    pub fit_intercept: bool,
+    /// The coefficient of each feature, once fit.
+    pub coef: Vec<f64>,
+    /// The intercept, once fit.
+    pub intercept: f64,
+}
+
+impl Ridge {
+    /// Create an unfitted Ridge with the penalty `alpha` and an intercept.
+    pub fn new(alpha: f64) -> Ridge {
+        Ridge {
+            alpha,
+            fit_intercept: true,
+            coef: Vec::new(),
+            intercept: 0.0,
+        }
+    }
+
+    /// Set whether to fit an intercept.
+    pub fn fit_intercept(mut self, fit_intercept: bool) -> Self {
+        self.fit_intercept = fit_intercept;
+        self
+    }
+}
+
+impl<F: Float> Fit<F, f64> for Ridge {
+    /// Fit the coefficients to `x` and `y`.
+    /// Returns an error if there are no samples, the samples have different dimensions, `x` and
+    /// `y` have different lengths, `alpha` is negative, or the system is singular, which needs
+    /// `alpha` to be 0.
+    fn fit(&mut self, x: &[Vec<F>], y: &[f64]) -> Result<()> {
+        self.fit_weighted(x, y, None)
+    }
+}
+
+impl<F: Float> FitWeighted<F, f64> for Ridge {
+    /// Fit the coefficients to `x` and `y`, weighing the squared error of each sample by its
+    /// weight in `sample_weight`. Returns an error under the same conditions as
+    /// [`Ridge::fit`], or if the weights are not one finite, non-negative number per sample
+    /// with a positive sum.
+    fn fit_weighted(
+        &mut self,
+        x: &[Vec<F>],
+        y: &[f64],
+        sample_weight: Option<&[f64]>,
+    ) -> Result<()> {
+        check_lengths("Features and targets", x.len(), y.len())?;
+        if x.is_empty() {
+            return Err(Error::InvalidInput("There are no samples.".into()));
+        }
+        if !(self.alpha >= 0.0 && self.alpha.is_finite()) {
+            return Err(Error::InvalidInput(format!(
+                "Ridge penalty {} is not a finite, non-negative number.",
+                self.alpha
+            )));
+        }
+        let weights = match sample_weight {
+            Some(weights) => {
+                check_weights(x.len(), weights)?;
+                weights.to_vec()
+            }
+            None => vec![1.0; x.len()],
+        };
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return Err(Error::InvalidInput("The sample weights sum to 0.".into()));
+        }
+        let x = DenseMatrix::from_rows(x)?;
+        let (n, p) = (x.n_rows(), x.n_cols());
+
+        let mut x_mean = vec![0.0; p];
+        let mut y_mean = 0.0;
+        if self.fit_intercept {
+            for (i, row) in x.rows().enumerate() {
+                for (m, v) in x_mean.iter_mut().zip(row) {
+                    *m += weights[i] * v.to_f64() / total;
+                }
+                y_mean += weights[i] * y[i] / total;
+            }
+        }
+        // The centered samples and targets, scaled by the square root of their weights.
+        let scale: Vec<f64> = weights.iter().map(|w| w.sqrt()).collect();
+        let xc: Vec<Vec<f64>> = x
+            .rows()
+            .zip(&scale)
+            .map(|(row, s)| {
+                row.iter()
+                    .zip(&x_mean)
+                    .map(|(v, m)| s * (v.to_f64() - m))
+                    .collect()
+            })
+            .collect();
+        let yc: Vec<f64> = y
+            .iter()
+            .zip(&scale)
+            .map(|(v, s)| s * (v - y_mean))
+            .collect();
+        let dot = |a: &[f64], b: &[f64]| a.iter().zip(b).map(|(a, b)| a * b).sum::<f64>();
+
+        let coef = if p <= n {
+            // (Xc' Xc + alpha I) coef = Xc' yc
+            let mut a = vec![vec![0.0; p]; p];
+            let mut b = vec![0.0; p];
+            for (row, t) in xc.iter().zip(&yc) {
+                for r in 0..p {
+                    b[r] += row[r] * t;
+                    for (c, v) in a[r].iter_mut().enumerate() {
+                        *v += row[r] * row[c];
+                    }
+                }
+            }
+            (0..p).for_each(|r| a[r][r] += self.alpha);
+            solve(a, b)
+        } else {
+            // (Xc Xc' + alpha I) dual = yc, and coef = Xc' dual.
+            let mut a: Vec<Vec<f64>> = xc
+                .iter()
+                .map(|r| xc.iter().map(|c| dot(r, c)).collect())
+                .collect();
+            (0..n).for_each(|r| a[r][r] += self.alpha);
+            solve(a, yc).map(|dual| {
+                (0..p)
+                    .map(|j| xc.iter().zip(&dual).map(|(row, d)| row[j] * d).sum())
+                    .collect()
+            })
+        };
+        let coef = coef.ok_or_else(|| {
+            Error::InvalidInput("The system is singular; use a positive ridge penalty.".into())
+        })?;
+        self.intercept = y_mean - dot(&coef, &x_mean);
+        self.coef = coef;
+        Ok(())
+    }
+}
+
+impl<F: Float> Predict<F, f64> for Ridge {
+    /// Predict the target of each sample in `x`.
+    /// Returns an error if the Ridge is not fit or a sample has the wrong number of features.
+    fn predict(&self, x: &[Vec<F>]) -> Result<Vec<f64>> {
+        if self.coef.is_empty() {
+            return Err(Error::NotFitted);
+        }
+        x.iter()
+            .map(|row| {
+                check_lengths("Features and coefficients", row.len(), self.coef.len())?;
+                Ok(self.intercept
+                    + row
+                        .iter()
+                        .zip(&self.coef)
+                        .map(|(v, c)| v.to_f64() * c)
+                        .sum::<f64>())
+            })
+            .collect()
+    }
+}
+
+impl Describe for Ridge {
+    fn describe(&self) -> Summary {
+        let mut summary = Summary::new("Ridge")
+            .param("alpha", self.alpha)
+            .param("fit_intercept", self.fit_intercept);
+        if !self.coef.is_empty() {
+            summary.n_features = Some(self.coef.len());
+        }
+        summary.memory = self.coef.capacity() * std::mem::size_of::<f64>();
+        summary
+    }
+}
+
+/// Ridge regression that chooses `alpha` from a list of candidates by the mean squared error of
+/// `folds`-fold cross-validation, then refits on all the data with the best one.
+///
+/// The samples are shuffled before splitting, and ties go to the larger penalty.
+///
+/// # Example
+/// ```rust
+/// use rml::linear::RidgeCV;
+/// use rml::traits::{Fit, Predict};
+///
+/// let x: Vec<Vec<f64>> = (0..20).map(|i| vec![i as f64]).collect();
+/// let y: Vec<f64> = (0..20).map(|i| 2.0 * i as f64 + 1.0).collect();
+/// let mut ridge = RidgeCV::new(vec![0.1, 10.0, 1000.0]);
+/// ridge.fit(&x, &y).unwrap();
+///
+/// assert_eq!(ridge.alpha, 0.1);
+/// assert!((ridge.predict(&[vec![20.0]]).unwrap()[0] - 41.0).abs() < 0.1);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RidgeCV {
+    /// The candidate penalties.
+    pub alphas: Vec<f64>,
+    /// The number of cross-validation folds.
+    pub folds: usize,
+    /// Whether to fit an intercept.
+    pub fit_intercept: bool,
+    /// The seed used to shuffle the samples before splitting, or None for the global seed.
+    pub seed: Option<u64>,
+    /// The chosen penalty, once fit.
+    pub alpha: f64,
+    /// The mean cross-validated squared error of each candidate, in the order given.
+    pub scores: Vec<(f64, f64)>,
+    /// The Ridge refit with the chosen penalty.
+    pub ridge: Ridge,
+}
+
+impl RidgeCV {
+    /// Create an unfitted RidgeCV choosing from `alphas` by 5-fold cross-validation.
+    pub fn new(alphas: Vec<f64>) -> RidgeCV {
+        RidgeCV {
+            alphas,
+            folds: 5,
+            fit_intercept: true,
+            seed: None,
+            alpha: 0.0,
+            scores: Vec::new(),
+            ridge: Ridge::new(0.0),
+        }
+    }
+
+    /// Set the number of cross-validation folds.
+    pub fn folds(mut self, folds: usize) -> Self {
+        self.folds = folds;
+        self
+    }
+
+    /// Set whether to fit an intercept.
+    pub fn fit_intercept(mut self, fit_intercept: bool) -> Self {
+        self.fit_intercept = fit_intercept;
+        self
+    }
+
+    /// The seed used to shuffle the samples before splitting.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+impl<F: Float> Fit<F, f64> for RidgeCV {
+    /// Choose the penalty and fit the coefficients to `x` and `y`.
+    /// Returns an error if there are no candidates, fewer than 2 folds or more folds than
+    /// samples, or under the conditions of [`Ridge::fit`] for any candidate.
+    fn fit(&mut self, x: &[Vec<F>], y: &[f64]) -> Result<()> {
+        if self.alphas.is_empty() {
+            return Err(Error::InvalidInput(
+                "There are no candidate penalties.".into(),
+            ));
+        }
+        let cv = KFold::new(self.folds)?.shuffle(self.seed.unwrap_or_else(random::global_seed));
+        self.scores = self
+            .alphas
+            .iter()
+            .map(|&alpha| {
+                let ridge = Ridge::new(alpha).fit_intercept(self.fit_intercept);
+                let errors = cross_val_score(&ridge, x, y, &cv, mean_squared_error)?;
+                Ok((alpha, errors.iter().sum::<f64>() / errors.len() as f64))
+            })
+            .collect::<Result<_>>()?;
+        self.alpha = self
+            .scores
+            .iter()
+            .fold(
+                None,
+                |best: Option<(f64, f64)>, &(alpha, error)| match best {
+                    Some((best_alpha, best_error))
+                        if best_error < error || (best_error == error && best_alpha > alpha) =>
+                    {
+                        best
+                    }
+                    _ => Some((alpha, error)),
+                },
+            )
+            .map_or(0.0, |(alpha, _)| alpha);
+        self.ridge = Ridge::new(self.alpha).fit_intercept(self.fit_intercept);
+        self.ridge.fit(x, y)
+    }
+}
+
+impl<F: Float> Predict<F, f64> for RidgeCV {
+    /// Predict the target of each sample in `x` with the refit Ridge.
+    /// Returns an error under the same conditions as [`Ridge::predict`].
+    fn predict(&self, x: &[Vec<F>]) -> Result<Vec<f64>> {
+        self.ridge.predict(x)
+    }
+}
+
+impl Describe for RidgeCV {
+    fn describe(&self) -> Summary {
+        Summary::new("RidgeCV")
+            .param("alphas", &self.alphas)
+            .param("folds", self.folds)
+            .param("fit_intercept", self.fit_intercept)
+            .param("seed", self.seed)
+            .part(self.ridge.describe())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ridge_test() {
+        // y = 3 x0 - 2 x1 + 1
+        let x = vec![
+            vec![0.0, 1.0],
+            vec![1.0, 0.0],
+            vec![2.0, 3.0],
+            vec![3.0, 1.0],
+            vec![4.0, 5.0],
+        ];
+        let y: Vec<f64> = x.iter().map(|r| 3.0 * r[0] - 2.0 * r[1] + 1.0).collect();
+        let mut ols = Ridge::new(0.0);
+        ols.fit(&x, &y).unwrap();
+
+        assert!((ols.coef[0] - 3.0).abs() < 1e-9);
+        assert!((ols.coef[1] + 2.0).abs() < 1e-9);
+        assert!((ols.intercept - 1.0).abs() < 1e-9);
+
+        let mut ridge = Ridge::new(10.0);
+        ridge.fit(&x, &y).unwrap();
+        let norm = |c: &[f64]| c.iter().map(|v| v * v).sum::<f64>();
+        assert!(norm(&ridge.coef) < norm(&ols.coef));
+        assert_eq!(ridge.describe().n_features, Some(2));
+    }
+
+    #[test]
+    fn wide_test() {
+        // More features than samples, so the dual system is solved.
+        let x = vec![vec![1.0, 0.0, 2.0], vec![0.0, 1.0, 1.0]];
+        let y = vec![1.0, -1.0];
+        let mut ridge = Ridge::new(0.5).fit_intercept(false);
+        ridge.fit(&x, &y).unwrap();
+
+        // The primal solution (X'X + alpha I)^-1 X'y, computed directly.
+        let mut a = vec![vec![0.0; 3]; 3];
+        for row in &x {
+            for r in 0..3 {
+                for c in 0..3 {
+                    a[r][c] += row[r] * row[c];
+                }
+            }
+        }
+        (0..3).for_each(|r| a[r][r] += 0.5);
+        let b: Vec<f64> = (0..3).map(|j| x[0][j] * y[0] + x[1][j] * y[1]).collect();
+        let expected = solve(a, b).unwrap();
+        for (c, e) in ridge.coef.iter().zip(&expected) {
+            assert!((c - e).abs() < 1e-9);
+        }
+        assert_eq!(ridge.intercept, 0.0);
+    }
+
+    #[test]
+    fn weighted_test() {
+        let x = vec![vec![0.0], vec![1.0], vec![2.0], vec![2.0]];
+        let y = vec![0.0, 1.0, 2.0, 100.0];
+        let mut ridge = Ridge::new(0.0);
+        ridge
+            .fit_weighted(&x, &y, Some(&[1.0, 1.0, 1.0, 0.0]))
+            .unwrap();
+
+        assert!((ridge.coef[0] - 1.0).abs() < 1e-9);
+        assert!(ridge.intercept.abs() < 1e-9);
+        assert!(ridge.fit_weighted(&x, &y, Some(&[0.0; 4])).is_err());
+        assert!(Ridge::new(-1.0).fit(&x, &y).is_err());
+        assert!(Ridge::new(0.0).fit(&x, &y[..3]).is_err());
+        assert!(matches!(
+            Predict::<f64, f64>::predict(&Ridge::new(1.0), &x),
+            Err(Error::NotFitted)
+        ));
+        assert!(ridge.predict(&[vec![1.0, 2.0]]).is_err());
+    }
+
+    #[test]
+    fn ridge_cv_test() {
+        // Noisy targets of a single informative feature among many noise features.
+        let mut rng = random::Rng::new(11);
+        let x: Vec<Vec<f64>> = (0..40)
+            .map(|_| (0..10).map(|_| rng.next_normal()).collect())
+            .collect();
+        let y: Vec<f64> = x.iter().map(|r| r[0] + rng.next_normal()).collect();
+        let mut ridge = RidgeCV::new(vec![1e-6, 10.0, 1e6]).seed(3);
+        ridge.fit(&x, &y).unwrap();
+        let mut same = RidgeCV::new(vec![1e-6, 10.0, 1e6]).seed(3);
+        same.fit(&x, &y).unwrap();
+
+        assert_eq!(ridge.alpha, 10.0);
+        assert_eq!(ridge.scores, same.scores);
+        assert_eq!(ridge.scores.len(), 3);
+        assert_eq!(ridge.ridge.alpha, 10.0);
+        assert!(Fit::<f64, f64>::fit(&mut RidgeCV::new(vec![]), &x, &y).is_err());
+        assert!(Fit::<f64, f64>::fit(&mut RidgeCV::new(vec![1.0]).folds(1), &x, &y).is_err());
+    }
+}
@@ -0,0 +1,793 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Linear models trained by stochastic gradient descent.
+
+use crate::describe::{Describe, Summary};
+use crate::error::{check_lengths, Error, Result};
+use crate::math::dense::DenseMatrix;
+use crate::math::random::Rng;
+use crate::math::Float;
+use crate::parallel::{self, Parallelism};
+use crate::traits::{Fit, IncrementalFit, Predict, PredictProba};
+use rayon::prelude::*;
+use std::mem;
+
+/// The loss minimized by stochastic gradient descent. Classifiers use the targets -1 and 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Loss {
+    /// The hinge loss `max(0, 1 - y z)` of a linear support vector machine. Classification only.
+    Hinge,
+    /// The logistic loss `ln(1 + exp(-y z))` of logistic regression. Classification only.
+    Log,
+    /// The squared error `(z - y)² / 2` of least squares.
+    Squared,
+    /// The Huber loss, which is squared within the given distance of the target and linear
+    /// beyond it, so outliers pull less.
+    Huber(f64),
+}
+
+/// The penalty on the coefficients. The intercept is never penalized.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Penalty {
+    /// No penalty.
+    None,
+    /// Half the squared L2 norm.
+    L2,
+    /// The L1 norm, which shrinks the coefficients of irrelevant features to about 0.
+    L1,
+    /// A mix of the L1 penalty, with the given weight in `[0, 1]`, and the L2 penalty.
+    ElasticNet(f64),
+}
+
+/// The settings shared by [`SGDClassifier`] and [`SGDRegressor`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Settings {
+    loss: Loss,
+    penalty: Penalty,
+    alpha: f64,
+    eta0: f64,
+    fit_intercept: bool,
+}
+
+impl Settings {
+    /// Check the penalty and learning rate.
+    fn check(&self) -> Result<()> {
+        if !(self.alpha >= 0.0 && self.alpha.is_finite()) {
+            return Err(Error::InvalidInput(format!(
+                "The penalty {} is not a finite, non-negative number.",
+                self.alpha
+            )));
+        }
+        if !(self.eta0 > 0.0 && self.eta0.is_finite()) {
+            return Err(Error::InvalidInput(format!(
+                "The learning rate {} is not a finite, positive number.",
+                self.eta0
+            )));
+        }
+        if let Penalty::ElasticNet(ratio) = self.penalty {
+            if !(0.0..=1.0).contains(&ratio) {
+                return Err(Error::InvalidInput(format!(
+                    "The L1 ratio {} is not in [0, 1].",
+                    ratio
+                )));
+            }
+        }
+        if let Loss::Huber(delta) = self.loss {
+            if !(delta > 0.0 && delta.is_finite()) {
+                return Err(Error::InvalidInput(format!(
+                    "The Huber distance {} is not a finite, positive number.",
+                    delta
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// The derivative of the loss with respect to the score `z` of a sample with target `y`.
+    fn gradient(&self, z: f64, y: f64) -> f64 {
+        match self.loss {
+            Loss::Hinge if y * z < 1.0 => -y,
+            Loss::Hinge => 0.0,
+            Loss::Log => -y / (1.0 + (y * z).exp()),
+            Loss::Squared => z - y,
+            Loss::Huber(delta) => (z - y).clamp(-delta, delta),
+        }
+    }
+
+    /// One gradient step of the weights `w` and intercept `b` on the sample `row` with target
+    /// `y`. `t` is the number of steps taken before.
+    fn step<F: Float>(&self, w: &mut [f64], b: &mut f64, row: &[F], y: f64, t: usize) {
+        let eta = self.eta0 / (1.0 + self.eta0 * self.alpha * t as f64);
+        let z = *b
+            + row
+                .iter()
+                .zip(w.iter())
+                .map(|(v, w)| v.to_f64() * w)
+                .sum::<f64>();
+        let g = self.gradient(z, y);
+        let (l1, l2) = match self.penalty {
+            Penalty::None => (0.0, 0.0),
+            Penalty::L2 => (0.0, 1.0),
+            Penalty::L1 => (1.0, 0.0),
+            Penalty::ElasticNet(ratio) => (ratio, 1.0 - ratio),
+        };
+        let shrink = 1.0 - eta * self.alpha * l2;
+        let clip = eta * self.alpha * l1;
+        for (w, v) in w.iter_mut().zip(row) {
+            *w = *w * shrink - eta * g * v.to_f64();
+            // Truncate towards 0 for the L1 part, without crossing it.
+            if clip > 0.0 {
+                *w = w.signum() * (w.abs() - clip).max(0.0);
+            }
+        }
+        if self.fit_intercept {
+            *b -= eta * g;
+        }
+    }
+}
+
+/// The score of `row` under the weights `w` and intercept `b`.
+fn score<F: Float>(w: &[f64], b: f64, row: &[F]) -> f64 {
+    b + row.iter().zip(w).map(|(v, w)| v.to_f64() * w).sum::<f64>()
+}
+
+/// A shuffled order of `n` samples for each of `passes` passes.
+fn orders(n: usize, passes: usize, rng: &mut Rng) -> Vec<Vec<usize>> {
+    (0..passes)
+        .map(|_| {
+            let mut order: Vec<usize> = (0..n).collect();
+            rng.shuffle(&mut order);
+            order
+        })
+        .collect()
+}
+
+/// A linear classifier trained by stochastic gradient descent on a configurable loss and
+/// penalty.
+///
+/// The learning rate of step `t` is `eta0 / (1 + eta0 * alpha * t)`. With more than two classes
+/// one classifier is trained per class against the rest, in parallel, and the class with the
+/// highest score wins. [`IncrementalFit::partial_fit`] makes a single pass over a batch, so the
+/// classifier can learn from data that doesn't fit in memory.
+///
+/// # Example
+/// ```rust
+/// use rml::linear::{Loss, SGDClassifier};
+/// use rml::traits::{IncrementalFit, Predict};
+///
+/// let mut sgd = SGDClassifier::new(Loss::Log).classes(vec![0, 1]).seed(1);
+/// for _ in 0..20 {
+///     sgd.partial_fit(&[vec![-1.0], vec![1.0]], &[0, 1]).unwrap();
+/// }
+///
+/// assert_eq!(sgd.predict(&[vec![-2.0], vec![0.5]]).unwrap(), vec![0, 1]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SGDClassifier {
+    /// The loss minimized.
+    pub loss: Loss,
+    /// The penalty on the coefficients.
+    pub penalty: Penalty,
+    /// The strength of the penalty.
+    pub alpha: f64,
+    /// The initial learning rate.
+    pub eta0: f64,
+    /// The number of passes over the training data in a fit.
+    pub max_iter: usize,
+    /// Whether to fit an intercept.
+    pub fit_intercept: bool,
+    /// The seed used to shuffle the samples on each pass, or None for the global seed.
+    pub seed: Option<u64>,
+    /// The parallelism of training the per-class classifiers, or None for the global setting.
+    pub parallelism: Option<Parallelism>,
+    /// The sorted distinct labels. Set them before streaming with `partial_fit` if the first
+    /// batch may not contain every class; otherwise they are taken from the data. `fit` always
+    /// takes them from the data.
+    pub classes: Vec<i32>,
+    /// The weights of each classifier, once fit. A binary problem has a single classifier for
+    /// the larger label; otherwise there is one per class.
+    pub coef: Vec<Vec<f64>>,
+    /// The intercept of each classifier, once fit.
+    pub intercept: Vec<f64>,
+    /// The number of gradient steps taken by each classifier.
+    t: usize,
+}
+
+impl SGDClassifier {
+    /// Create an unfitted SGDClassifier with the given loss, an L2 penalty of `1e-4`, an
+    /// initial learning rate of `0.01`, 5 passes and an intercept.
+    pub fn new(loss: Loss) -> SGDClassifier {
+        SGDClassifier {
+            loss,
+            penalty: Penalty::L2,
+            alpha: 1e-4,
+            eta0: 0.01,
+            max_iter: 5,
+            fit_intercept: true,
+            seed: None,
+            parallelism: None,
+            classes: Vec::new(),
+            coef: Vec::new(),
+            intercept: Vec::new(),
+            t: 0,
+        }
+    }
+
+    /// Set the penalty on the coefficients.
+    pub fn penalty(mut self, penalty: Penalty) -> Self {
+        self.penalty = penalty;
+        self
+    }
+
+    /// Set the strength of the penalty.
+    pub fn alpha(mut self, alpha: f64) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Set the initial learning rate.
+    pub fn eta0(mut self, eta0: f64) -> Self {
+        self.eta0 = eta0;
+        self
+    }
+
+    /// Set the number of passes over the training data in a fit.
+    pub fn max_iter(mut self, max_iter: usize) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    /// Set whether to fit an intercept.
+    pub fn fit_intercept(mut self, fit_intercept: bool) -> Self {
+        self.fit_intercept = fit_intercept;
+        self
+    }
+
+    /// The seed used to shuffle the samples.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Set the parallelism of training, overriding the global setting.
+    pub fn parallelism(mut self, parallelism: Parallelism) -> Self {
+        self.parallelism = Some(parallelism);
+        self
+    }
+
+    /// Set the labels to expect, for streaming with `partial_fit`.
+    pub fn classes(mut self, mut classes: Vec<i32>) -> Self {
+        classes.sort_unstable();
+        classes.dedup();
+        self.classes = classes;
+        self
+    }
+
+    /// The score of each sample of `x` under each classifier, in the order of `coef`.
+    /// Returns an error if the SGDClassifier is not fit or a sample has the wrong number of
+    /// features.
+    pub fn decision_function<F: Float>(&self, x: &[Vec<F>]) -> Result<Vec<Vec<f64>>> {
+        if self.coef.is_empty() {
+            return Err(Error::NotFitted);
+        }
+        x.iter()
+            .map(|row| {
+                check_lengths("Features and coefficients", row.len(), self.coef[0].len())?;
+                Ok(self
+                    .coef
+                    .iter()
+                    .zip(&self.intercept)
+                    .map(|(w, &b)| score(w, b, row))
+                    .collect())
+            })
+            .collect()
+    }
+
+    fn settings(&self) -> Settings {
+        Settings {
+            loss: self.loss,
+            penalty: self.penalty,
+            alpha: self.alpha,
+            eta0: self.eta0,
+            fit_intercept: self.fit_intercept,
+        }
+    }
+
+    /// Check the settings and the samples, learning the classes and creating zero weights if
+    /// there are none yet.
+    fn prepare<F: Float>(&mut self, x: &[Vec<F>], y: &[i32]) -> Result<DenseMatrix<F>> {
+        check_lengths("Features and labels", x.len(), y.len())?;
+        if x.is_empty() {
+            return Err(Error::InvalidInput("There are no samples.".into()));
+        }
+        self.settings().check()?;
+        let mut classes = self.classes.clone();
+        if classes.is_empty() {
+            classes = y.to_vec();
+            classes.sort_unstable();
+            classes.dedup();
+        }
+        if classes.len() < 2 {
+            return Err(Error::InvalidInput(
+                "There must be at least two classes.".into(),
+            ));
+        }
+        if let Some(label) = y.iter().find(|l| classes.binary_search(l).is_err()) {
+            return Err(Error::InvalidInput(format!(
+                "Label {} is not one of the classes {:?}.",
+                label, classes
+            )));
+        }
+        let x = DenseMatrix::from_rows(x)?;
+        self.classes = classes;
+        if self.coef.is_empty() {
+            let n_models = if self.classes.len() == 2 {
+                1
+            } else {
+                self.classes.len()
+            };
+            self.coef = vec![vec![0.0; x.n_cols()]; n_models];
+            self.intercept = vec![0.0; n_models];
+            self.t = 0;
+        } else {
+            check_lengths("Features and coefficients", x.n_cols(), self.coef[0].len())?;
+        }
+        Ok(x)
+    }
+
+    /// Train every classifier on the samples of `x` in each of `orders` in turn.
+    fn train<F: Float>(&mut self, x: &DenseMatrix<F>, y: &[i32], orders: &[Vec<usize>]) {
+        let settings = self.settings();
+        let t = self.t;
+        // A binary problem trains the single classifier of the larger label.
+        let offset = self.classes.len() - self.coef.len();
+        let classes = &self.classes[offset..];
+        let coef = &mut self.coef;
+        let intercept = &mut self.intercept;
+        parallel::install(self.parallelism, || {
+            coef.par_iter_mut()
+                .zip(intercept.par_iter_mut())
+                .zip(classes.par_iter())
+                .for_each(|((w, b), &class)| {
+                    for (step, &i) in orders.iter().flatten().enumerate() {
+                        let target = if y[i] == class { 1.0 } else { -1.0 };
+                        settings.step(w, b, x.row(i), target, t + step);
+                    }
+                })
+        });
+        self.t += orders.iter().map(Vec::len).sum::<usize>();
+    }
+}
+
+impl<F: Float> Fit<F, i32> for SGDClassifier {
+    /// Train on the samples `x` and their labels `y`, replacing anything learned before. The
+    /// classes are learned from `y`, replacing any set with [`SGDClassifier::classes`].
+    /// Returns an error if there are no samples, the samples have different dimensions, `x`
+    /// and `y` have different lengths, there are fewer than two classes, or the settings are
+    /// invalid.
+    fn fit(&mut self, x: &[Vec<F>], y: &[i32]) -> Result<()> {
+        self.classes.clear();
+        self.coef.clear();
+        let x = self.prepare(x, y)?;
+        let orders = orders(x.n_rows(), self.max_iter, &mut Rng::from_seed(self.seed));
+        self.train(&x, y, &orders);
+        Ok(())
+    }
+}
+
+impl<F: Float> IncrementalFit<F, i32> for SGDClassifier {
+    /// Make one pass over the samples `x` and their labels `y`, in order, keeping what was
+    /// learned before. Returns an error under the same conditions as [`SGDClassifier::fit`],
+    /// or if a label is not one of the classes or `x` has the wrong number of features.
+    fn partial_fit(&mut self, x: &[Vec<F>], y: &[i32]) -> Result<()> {
+        let x = self.prepare(x, y)?;
+        self.train(&x, y, &[(0..x.n_rows()).collect()]);
+        Ok(())
+    }
+}
+
+impl<F: Float> Predict<F, i32> for SGDClassifier {
+    /// Predict the class of each sample in `x`.
+    /// Returns an error under the same conditions as [`SGDClassifier::decision_function`].
+    fn predict(&self, x: &[Vec<F>]) -> Result<Vec<i32>> {
+        Ok(self
+            .decision_function(x)?
+            .into_iter()
+            .map(|scores| match scores.len() {
+                1 => self.classes[(scores[0] > 0.0) as usize],
+                _ => {
+                    let best = (0..scores.len())
+                        .max_by(|&a, &b| scores[a].total_cmp(&scores[b]).then(b.cmp(&a)))
+                        .unwrap_or(0);
+                    self.classes[best]
+                }
+            })
+            .collect())
+    }
+}
+
+impl<F: Float> PredictProba<F> for SGDClassifier {
+    /// The probability of each class for each sample in `x`, from the logistic function of the
+    /// scores. With more than two classes the one-vs-rest probabilities are normalized to sum
+    /// to 1. Returns an error if the loss is not [`Loss::Log`], or under the same conditions
+    /// as [`SGDClassifier::decision_function`].
+    fn predict_proba(&self, x: &[Vec<F>]) -> Result<Vec<Vec<f64>>> {
+        if self.loss != Loss::Log {
+            return Err(Error::InvalidInput(
+                "Probabilities need the logistic loss.".into(),
+            ));
+        }
+        let sigmoid = |z: f64| 1.0 / (1.0 + (-z).exp());
+        Ok(self
+            .decision_function(x)?
+            .into_iter()
+            .map(|scores| match scores.len() {
+                1 => {
+                    let p = sigmoid(scores[0]);
+                    vec![1.0 - p, p]
+                }
+                _ => {
+                    let p: Vec<f64> = scores.into_iter().map(sigmoid).collect();
+                    let total: f64 = p.iter().sum();
+                    p.into_iter().map(|p| p / total).collect()
+                }
+            })
+            .collect())
+    }
+}
+
+impl Describe for SGDClassifier {
+    fn describe(&self) -> Summary {
+        let mut summary = Summary::new("SGDClassifier")
+            .param("loss", self.loss)
+            .param("penalty", self.penalty)
+            .param("alpha", self.alpha)
+            .param("eta0", self.eta0)
+            .param("max_iter", self.max_iter)
+            .param("fit_intercept", self.fit_intercept)
+            .param("seed", self.seed)
+            .param("parallelism", self.parallelism);
+        if let Some(w) = self.coef.first() {
+            summary.n_features = Some(w.len());
+            summary.n_classes = Some(self.classes.len());
+        }
+        let weights =
+            self.coef.iter().map(Vec::capacity).sum::<usize>() + self.intercept.capacity();
+        summary.memory =
+            weights * mem::size_of::<f64>() + self.classes.capacity() * mem::size_of::<i32>();
+        summary
+    }
+}
+
+/// A linear regression trained by stochastic gradient descent on the squared or Huber loss and
+/// a configurable penalty.
+///
+/// The learning rate of step `t` is `eta0 / (1 + eta0 * alpha * t)`.
+/// [`IncrementalFit::partial_fit`] makes a single pass over a batch, so the regression can learn
+/// from data that doesn't fit in memory.
+///
+/// # Example
+/// ```rust
+/// use rml::linear::{Loss, SGDRegressor};
+/// use rml::traits::{Fit, Predict};
+///
+/// let x: Vec<Vec<f64>> = (0..50).map(|i| vec![i as f64 / 50.0]).collect();
+/// let y: Vec<f64> = x.iter().map(|r| 2.0 * r[0] + 1.0).collect();
+/// let mut sgd = SGDRegressor::new(Loss::Squared).eta0(0.1).max_iter(200).seed(1);
+/// sgd.fit(&x, &y).unwrap();
+///
+/// assert!((sgd.predict(&[vec![0.5]]).unwrap()[0] - 2.0).abs() < 0.05);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SGDRegressor {
+    /// The loss minimized, [`Loss::Squared`] or [`Loss::Huber`].
+    pub loss: Loss,
+    /// The penalty on the coefficients.
+    pub penalty: Penalty,
+    /// The strength of the penalty.
+    pub alpha: f64,
+    /// The initial learning rate.
+    pub eta0: f64,
+    /// The number of passes over the training data in a fit.
+    pub max_iter: usize,
+    /// Whether to fit an intercept.
+    pub fit_intercept: bool,
+    /// The seed used to shuffle the samples on each pass, or None for the global seed.
+    pub seed: Option<u64>,
+    /// The coefficient of each feature, once fit.
+    pub coef: Vec<f64>,
+    /// The intercept, once fit.
+    pub intercept: f64,
+    /// The number of gradient steps taken.
+    t: usize,
+}
+
+impl SGDRegressor {
+    /// Create an unfitted SGDRegressor with the given loss, an L2 penalty of `1e-4`, an initial
+    /// learning rate of `0.01`, 5 passes and an intercept.
+    pub fn new(loss: Loss) -> SGDRegressor {
+        SGDRegressor {
+            loss,
+            penalty: Penalty::L2,
+            alpha: 1e-4,
+            eta0: 0.01,
+            max_iter: 5,
+            fit_intercept: true,
+            seed: None,
+            coef: Vec::new(),
+            intercept: 0.0,
+            t: 0,
+        }
+    }
+
+    /// Set the penalty on the coefficients.
+    pub fn penalty(mut self, penalty: Penalty) -> Self {
+        self.penalty = penalty;
+        self
+    }
+
+    /// Set the strength of the penalty.
+    pub fn alpha(mut self, alpha: f64) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Set the initial learning rate.
+    pub fn eta0(mut self, eta0: f64) -> Self {
+        self.eta0 = eta0;
+        self
+    }
+
+    /// Set the number of passes over the training data in a fit.
+    pub fn max_iter(mut self, max_iter: usize) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    /// Set whether to fit an intercept.
+    pub fn fit_intercept(mut self, fit_intercept: bool) -> Self {
+        self.fit_intercept = fit_intercept;
+        self
+    }
+
+    /// The seed used to shuffle the samples.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    fn settings(&self) -> Settings {
+        Settings {
+            loss: self.loss,
+            penalty: self.penalty,
+            alpha: self.alpha,
+            eta0: self.eta0,
+            fit_intercept: self.fit_intercept,
+        }
+    }
+
+    /// Check the settings and the samples, creating zero weights if there are none yet.
+    fn prepare<F: Float>(&mut self, x: &[Vec<F>], y: &[f64]) -> Result<DenseMatrix<F>> {
+        check_lengths("Features and targets", x.len(), y.len())?;
+        if matches!(self.loss, Loss::Hinge | Loss::Log) {
+            return Err(Error::InvalidInput(format!(
+                "The {:?} loss is for classification.",
+                self.loss
+            )));
+        }
+        self.settings().check()?;
+        let x = DenseMatrix::from_rows(x)?;
+        if self.coef.is_empty() {
+            self.coef = vec![0.0; x.n_cols()];
+            self.intercept = 0.0;
+            self.t = 0;
+        } else if !x.is_empty() {
+            check_lengths("Features and coefficients", x.n_cols(), self.coef.len())?;
+        }
+        Ok(x)
+    }
+
+    /// Train on the samples of `x` in each of `orders` in turn.
+    fn train<F: Float>(&mut self, x: &DenseMatrix<F>, y: &[f64], orders: &[Vec<usize>]) {
+        let settings = self.settings();
+        for &i in orders.iter().flatten() {
+            settings.step(&mut self.coef, &mut self.intercept, x.row(i), y[i], self.t);
+            self.t += 1;
+        }
+    }
+}
+
+impl<F: Float> Fit<F, f64> for SGDRegressor {
+    /// Train on the samples `x` and their targets `y`, replacing anything learned before.
+    /// Returns an error if the samples have different dimensions, `x` and `y` have different
+    /// lengths, the loss is for classification, or the settings are invalid.
+    fn fit(&mut self, x: &[Vec<F>], y: &[f64]) -> Result<()> {
+        self.coef.clear();
+        let x = self.prepare(x, y)?;
+        let orders = orders(x.n_rows(), self.max_iter, &mut Rng::from_seed(self.seed));
+        self.train(&x, y, &orders);
+        Ok(())
+    }
+}
+
+impl<F: Float> IncrementalFit<F, f64> for SGDRegressor {
+    /// Make one pass over the samples `x` and their targets `y`, in order, keeping what was
+    /// learned before. Returns an error under the same conditions as [`SGDRegressor::fit`], or
+    /// if `x` has the wrong number of features.
+    fn partial_fit(&mut self, x: &[Vec<F>], y: &[f64]) -> Result<()> {
+        let x = self.prepare(x, y)?;
+        self.train(&x, y, &[(0..x.n_rows()).collect()]);
+        Ok(())
+    }
+}
+
+impl<F: Float> Predict<F, f64> for SGDRegressor {
+    /// Predict the target of each sample in `x`.
+    /// Returns an error if the SGDRegressor is not fit or a sample has the wrong number of
+    /// features.
+    fn predict(&self, x: &[Vec<F>]) -> Result<Vec<f64>> {
+        if self.coef.is_empty() {
+            return Err(Error::NotFitted);
+        }
+        x.iter()
+            .map(|row| {
+                check_lengths("Features and coefficients", row.len(), self.coef.len())?;
+                Ok(score(&self.coef, self.intercept, row))
+            })
+            .collect()
+    }
+}
+
+impl Describe for SGDRegressor {
+    fn describe(&self) -> Summary {
+        let mut summary = Summary::new("SGDRegressor")
+            .param("loss", self.loss)
+            .param("penalty", self.penalty)
+            .param("alpha", self.alpha)
+            .param("eta0", self.eta0)
+            .param("max_iter", self.max_iter)
+            .param("fit_intercept", self.fit_intercept)
+            .param("seed", self.seed);
+        if !self.coef.is_empty() {
+            summary.n_features = Some(self.coef.len());
+        }
+        summary.memory = self.coef.capacity() * mem::size_of::<f64>();
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::accuracy;
+
+    /// Gaussian blobs of 50 points around each center, labeled by center.
+    fn blobs(centers: &[(f64, f64)]) -> (Vec<Vec<f64>>, Vec<i32>) {
+        let mut rng = Rng::new(8);
+        let mut x = Vec::new();
+        let mut y = Vec::new();
+        for (label, &(cx, cy)) in centers.iter().enumerate() {
+            for _ in 0..50 {
+                x.push(vec![cx + rng.next_normal(), cy + rng.next_normal()]);
+                y.push(label as i32);
+            }
+        }
+        (x, y)
+    }
+
+    #[test]
+    fn classifier_test() {
+        let (x, y) = blobs(&[(0.0, 4.0), (-4.0, -2.0), (4.0, -2.0)]);
+        for &loss in [Loss::Hinge, Loss::Log, Loss::Squared, Loss::Huber(1.0)].iter() {
+            let mut sgd = SGDClassifier::new(loss).max_iter(10).seed(2);
+            sgd.fit(&x, &y).unwrap();
+            assert_eq!(sgd.coef.len(), 3);
+            assert!(accuracy(&y, &sgd.predict(&x).unwrap()).unwrap() > 0.9);
+        }
+
+        let mut sgd = SGDClassifier::new(Loss::Log).eta0(0.1).seed(2);
+        sgd.fit(&x, &y).unwrap();
+        let proba = sgd.predict_proba(&x).unwrap();
+        assert!((proba[0].iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!(proba[0][0] > 0.5);
+        assert!(SGDClassifier::new(Loss::Hinge).predict_proba(&x).is_err());
+    }
+
+    #[test]
+    fn partial_fit_test() {
+        let (x, y) = blobs(&[(-3.0, 0.0), (3.0, 0.0)]);
+        let mut sgd = SGDClassifier::new(Loss::Hinge)
+            .classes(vec![0, 1])
+            .eta0(0.1);
+        // Each batch holds a single class, so the classes must be known up front.
+        for _ in 0..5 {
+            for batch in 0..10 {
+                let rows: Vec<usize> = (0..10).map(|i| (i * 10 + batch) % 100).collect();
+                let xb: Vec<Vec<f64>> = rows.iter().map(|&i| x[i].clone()).collect();
+                let yb: Vec<i32> = rows.iter().map(|&i| y[i]).collect();
+                sgd.partial_fit(&xb, &yb).unwrap();
+            }
+        }
+        assert_eq!(sgd.coef.len(), 1);
+        assert!(accuracy(&y, &sgd.predict(&x).unwrap()).unwrap() > 0.95);
+        assert!(sgd.partial_fit(&[vec![0.0, 0.0]], &[2]).is_err());
+        assert!(sgd.partial_fit(&[vec![0.0]], &[1]).is_err());
+
+        // A rejected single-class batch leaves the classes unset for the next one.
+        let mut sgd = SGDClassifier::new(Loss::Hinge);
+        assert!(sgd.partial_fit(&x[..1], &y[..1]).is_err());
+        assert!(sgd.classes.is_empty());
+        sgd.partial_fit(&x, &y).unwrap();
+        assert_eq!(sgd.classes, vec![0, 1]);
+
+        // An empty first batch doesn't fix the number of features.
+        let mut sgd = SGDClassifier::new(Loss::Hinge).classes(vec![0, 1]);
+        assert!(IncrementalFit::<f64, i32>::partial_fit(&mut sgd, &[], &[]).is_err());
+        assert!(sgd.coef.is_empty());
+        sgd.partial_fit(&x, &y).unwrap();
+    }
+
+    #[test]
+    fn regressor_test() {
+        let mut rng = Rng::new(6);
+        let x: Vec<Vec<f64>> = (0..200)
+            .map(|_| vec![rng.next_f64(), rng.next_f64(), rng.next_f64()])
+            .collect();
+        let y: Vec<f64> = x.iter().map(|r| 3.0 * r[0] - r[1] + 0.5).collect();
+        let mut sgd = SGDRegressor::new(Loss::Squared)
+            .penalty(Penalty::None)
+            .eta0(0.1)
+            .max_iter(100)
+            .seed(3);
+        sgd.fit(&x, &y).unwrap();
+
+        assert!((sgd.coef[0] - 3.0).abs() < 0.05);
+        assert!((sgd.coef[1] + 1.0).abs() < 0.05);
+        assert!(sgd.coef[2].abs() < 0.05);
+        assert!((sgd.intercept - 0.5).abs() < 0.05);
+
+        // The L1 penalty shrinks the irrelevant feature to about 0.
+        let mut lasso = SGDRegressor::new(Loss::Huber(1.0))
+            .penalty(Penalty::L1)
+            .alpha(0.01)
+            .eta0(0.1)
+            .max_iter(100)
+            .seed(3);
+        lasso.fit(&x, &y).unwrap();
+        assert!(lasso.coef[2].abs() < 1e-3);
+    }
+
+    #[test]
+    fn validation_test() {
+        let x = vec![vec![0.0], vec![1.0]];
+        assert!(SGDRegressor::new(Loss::Hinge).fit(&x, &[0.0, 1.0]).is_err());
+        assert!(SGDRegressor::new(Loss::Squared)
+            .penalty(Penalty::ElasticNet(2.0))
+            .fit(&x, &[0.0, 1.0])
+            .is_err());
+        assert!(SGDRegressor::new(Loss::Squared)
+            .eta0(0.0)
+            .fit(&x, &[0.0, 1.0])
+            .is_err());
+        assert!(SGDClassifier::new(Loss::Log).fit(&x, &[1, 1]).is_err());
+        assert!(matches!(
+            Predict::<f64, f64>::predict(&SGDRegressor::new(Loss::Squared), &x),
+            Err(Error::NotFitted)
+        ));
+    }
+}
@@ -0,0 +1,219 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A dense matrix stored in one contiguous buffer.
+
+/*!
+Rows are stored one after another in a single `Vec`, so iterating over them reads contiguous
+memory, unlike a `Vec<Vec<T>>` whose rows are scattered across the heap.
+
+# Example
+```rust
+use rml::math::dense::DenseMatrix;
+
+let mut m = DenseMatrix::from_rows(&[vec![1.0, 2.0], vec![3.0, 4.0]]).unwrap();
+m.push_row(&[5.0, 6.0]).unwrap();
+
+assert_eq!(m.n_rows(), 3);
+assert_eq!(m.row(1), &[3.0, 4.0]);
+assert_eq!(m.as_slice(), &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+```
+!*/
+
+use crate::error::{Error, Result};
+use rayon::prelude::*;
+
+/// A matrix stored in row-major order.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DenseMatrix<T> {
+    data: Vec<T>,
+    n_rows: usize,
+    n_cols: usize,
+}
+
+impl<T> DenseMatrix<T> {
+    /// Create an empty matrix with no rows and `n_cols` columns.
+    pub fn new(n_cols: usize) -> DenseMatrix<T> {
+        DenseMatrix {
+            data: Vec::new(),
+            n_rows: 0,
+            n_cols,
+        }
+    }
+
+    /// The heap memory of the stored values, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.data.capacity() * std::mem::size_of::<T>()
+    }
+
+    /// The number of rows.
+    pub fn n_rows(&self) -> usize {
+        self.n_rows
+    }
+
+    /// The number of columns.
+    pub fn n_cols(&self) -> usize {
+        self.n_cols
+    }
+
+    /// Whether the matrix has no rows.
+    pub fn is_empty(&self) -> bool {
+        self.n_rows == 0
+    }
+
+    /// Row `i`.
+    ///
+    /// # Panics
+    /// If `i` is out of range.
+    pub fn row(&self, i: usize) -> &[T] {
+        assert!(i < self.n_rows, "Row {} is out of range.", i);
+        &self.data[i * self.n_cols..(i + 1) * self.n_cols]
+    }
+
+    /// Row `i`, mutably.
+    ///
+    /// # Panics
+    /// If `i` is out of range.
+    pub fn row_mut(&mut self, i: usize) -> &mut [T] {
+        assert!(i < self.n_rows, "Row {} is out of range.", i);
+        &mut self.data[i * self.n_cols..(i + 1) * self.n_cols]
+    }
+
+    /// Iterate over the rows.
+    pub fn rows(&self) -> impl ExactSizeIterator<Item = &[T]> + '_ {
+        (0..self.n_rows).map(move |i| self.row(i))
+    }
+
+    /// The values of all rows, one row after another.
+    pub fn as_slice(&self) -> &[T] {
+        &self.data
+    }
+
+    /// Keep the rows `i` for which `keep[i]` is true.
+    ///
+    /// # Panics
+    /// If `keep` does not have one flag per row.
+    pub fn retain_rows(&mut self, keep: &[bool]) {
+        assert_eq!(keep.len(), self.n_rows, "Expected one flag per row.");
+        let n_cols = self.n_cols;
+        let mut i = 0;
+        self.data.retain(|_| {
+            let kept = keep[i / n_cols];
+            i += 1;
+            kept
+        });
+        self.n_rows = keep.iter().filter(|&&k| k).count();
+    }
+
+    /// Keep the columns `j` for which `keep[j]` is true.
+    ///
+    /// # Panics
+    /// If `keep` does not have one flag per column.
+    pub fn retain_columns(&mut self, keep: &[bool]) {
+        assert_eq!(keep.len(), self.n_cols, "Expected one flag per column.");
+        let n_cols = self.n_cols;
+        let mut j = 0;
+        self.data.retain(|_| {
+            let kept = keep[j % n_cols];
+            j += 1;
+            kept
+        });
+        self.n_cols = keep.iter().filter(|&&k| k).count();
+    }
+}
+
+impl<T: Sync> DenseMatrix<T> {
+    /// Iterate over the rows in parallel.
+    pub fn par_rows(&self) -> impl IndexedParallelIterator<Item = &[T]> + '_ {
+        (0..self.n_rows).into_par_iter().map(move |i| self.row(i))
+    }
+}
+
+impl<T: Clone> DenseMatrix<T> {
+    /// Copy the rows of a `Vec<Vec<T>>`. An empty slice gives a matrix with no rows or
+    /// columns. Returns an error if the rows have different lengths.
+    pub fn from_rows(rows: &[Vec<T>]) -> Result<DenseMatrix<T>> {
+        let mut m = DenseMatrix::new(rows.first().map_or(0, Vec::len));
+        m.data.reserve(m.n_cols * rows.len());
+        for row in rows {
+            m.push_row(row)?;
+        }
+        Ok(m)
+    }
+
+    /// Add a row to the end of the matrix. The first row of a matrix with no rows sets the
+    /// number of columns. Returns an error if the row has the wrong number of columns.
+    pub fn push_row(&mut self, row: &[T]) -> Result<()> {
+        if self.n_rows == 0 {
+            self.n_cols = row.len();
+        } else if row.len() != self.n_cols {
+            return Err(Error::ShapeMismatch(format!(
+                "Expected {} features but a sample has {}.",
+                self.n_cols,
+                row.len()
+            )));
+        }
+        self.data.extend_from_slice(row);
+        self.n_rows += 1;
+        Ok(())
+    }
+
+    /// Copy the rows into a `Vec<Vec<T>>`.
+    pub fn to_rows(&self) -> Vec<Vec<T>> {
+        self.rows().map(<[T]>::to_vec).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dense_matrix_test() {
+        let rows = vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]];
+        let mut m = DenseMatrix::from_rows(&rows).unwrap();
+
+        assert_eq!((m.n_rows(), m.n_cols()), (3, 3));
+        assert_eq!(m.to_rows(), rows);
+        assert_eq!(
+            m.par_rows().map(|r| r[0]).collect::<Vec<_>>(),
+            vec![1, 4, 7]
+        );
+        assert!(m.push_row(&[1]).is_err());
+        assert!(DenseMatrix::from_rows(&[vec![1], vec![]]).is_err());
+
+        m.row_mut(0)[0] = 0;
+        m.retain_rows(&[true, false, true]);
+        assert_eq!(m.to_rows(), vec![vec![0, 2, 3], vec![7, 8, 9]]);
+        m.retain_columns(&[false, true, true]);
+        assert_eq!(m.to_rows(), vec![vec![2, 3], vec![8, 9]]);
+    }
+
+    #[test]
+    fn empty_test() {
+        let mut m: DenseMatrix<f64> = DenseMatrix::from_rows(&[]).unwrap();
+        assert!(m.is_empty());
+
+        m.push_row(&[1.0, 2.0]).unwrap();
+        assert_eq!(m.n_cols(), 2);
+
+        let mut no_columns = DenseMatrix::from_rows(&[vec![0.0; 0], vec![]]).unwrap();
+        assert_eq!(no_columns.rows().count(), 2);
+        no_columns.retain_rows(&[false, true]);
+        assert_eq!(no_columns.n_rows(), 1);
+    }
+}
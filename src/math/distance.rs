@@ -17,27 +17,63 @@
 
 //! Computes distances needed for KNN.
 
-//! Supports both euclidean and manhattan distances.
+//! Supports euclidean, manhattan, Minkowski, Hamming, Jaccard and cosine distances, and custom distances through `Metric`.
+//! Text vectors are usually compared by `cosine_similarity`, which ignores their lengths.
 
-/// An enum which describes the two available types of distance calculations.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use super::norm::l2_norm;
+use super::Float;
+use crate::error::{Error, Result};
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::Hash;
+use std::sync::Arc;
+
+/// An enum which describes the available types of distance calculations.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Distance {
     Euclidean,
     Manhattan,
+    /// The Minkowski distance of the given power, at least 1. A power of 1 is the Manhattan
+    /// distance and 2 the euclidean distance; larger powers weigh the largest difference more.
+    Minkowski(f64),
+    /// The number of features that differ, for categorical, one-hot or binarized features.
+    Hamming,
+    /// One minus the cosine similarity. Not a metric, as it breaks the triangle inequality.
+    Cosine,
+    /// The features already are distances: each training point is its row of a square matrix
+    /// of distances between the training points, and each query is its distances to every
+    /// training point, in training order.
+    Precomputed,
+}
+
+/// Check the parameters of `distance`.
+/// Returns an error if it is Minkowski with a power that is not a finite number of at least 1.
+pub fn validate(distance: Distance) -> Result<()> {
+    match distance {
+        Distance::Minkowski(power) if !(power >= 1.0 && power.is_finite()) => {
+            Err(Error::InvalidInput(format!(
+                "The Minkowski power {} is not a finite number of at least 1.",
+                power
+            )))
+        }
+        _ => Ok(()),
+    }
 }
 
 /// Calculate the euclidean distance between two points.
 /// # Example
 /// ```rust
+/// use rml::math::distance::euclidean_distance;
+///
 /// let x: Vec<f64> = vec![5.0, 6.0];
 /// let y: Vec<f64> = vec![-7.0, 11.0];
 /// println!("{}", euclidean_distance(&x, &y))
 /// ```
-pub fn euclidean_distance(p: &[f64], q: &[f64]) -> f64 {
-    let distance: f64 = q.iter().zip(p).map(|(&q, &p)| (f64::powi(q - p, 2))).sum();
+pub fn euclidean_distance<F: Float>(p: &[F], q: &[F]) -> F {
+    let distance: F = q.iter().zip(p).map(|(&q, &p)| (q - p).powi(2)).sum();
 
-    if distance == 0.0 {
-        0.0
+    if distance == F::zero() {
+        F::zero()
     } else {
         distance.sqrt()
     }
@@ -46,16 +82,237 @@ pub fn euclidean_distance(p: &[f64], q: &[f64]) -> f64 {
 /// Calculate the Manhattan distance between two points.
 /// # Example
 /// ```rust
+/// use rml::math::distance::manhattan_distance;
+///
 /// let x: Vec<f64> = vec![5.0, 6.0];
 /// let y: Vec<f64> = vec![-7.0, 11.0];
 /// println!("{}", manhattan_distance(&x, &y))
 /// ```
-pub fn manhattan_distance(p: &[f64], q: &[f64]) -> f64 {
-    let distance: f64 = p.iter().zip(q).map(|(&p, &q)| (p - q).abs()).sum();
+pub fn manhattan_distance<F: Float>(p: &[F], q: &[F]) -> F {
+    let distance: F = p.iter().zip(q).map(|(&p, &q)| (p - q).abs()).sum();
 
     distance
 }
 
+/// Calculate the Minkowski distance of order `power` between two points: the `power`-th root
+/// of the sum of the `power`-th powers of the absolute differences. It is a metric for powers
+/// of at least 1, and tends to the largest absolute difference as the power grows.
+/// # Example
+/// ```rust
+/// use rml::math::distance::minkowski_distance;
+///
+/// let x: Vec<f64> = vec![5.0, 6.0];
+/// let y: Vec<f64> = vec![-7.0, 11.0];
+/// println!("{}", minkowski_distance(&x, &y, 3.0))
+/// ```
+pub fn minkowski_distance<F: Float>(p: &[F], q: &[F], power: f64) -> F {
+    let distance: f64 = p
+        .iter()
+        .zip(q)
+        .map(|(&p, &q)| (p - q).abs().to_f64().powf(power))
+        .sum();
+
+    F::from_f64(distance.powf(1.0 / power))
+}
+
+/// Calculate the Hamming distance between two points: the number of positions where they
+/// differ.
+/// # Example
+/// ```rust
+/// use rml::math::distance::hamming_distance;
+///
+/// let x: Vec<f64> = vec![1.0, 0.0, 2.0];
+/// let y: Vec<f64> = vec![1.0, 1.0, 0.0];
+/// println!("{}", hamming_distance(&x, &y))
+/// ```
+pub fn hamming_distance<F: Float>(p: &[F], q: &[F]) -> F {
+    let count = p.iter().zip(q).filter(|(&p, &q)| p != q).count();
+
+    F::from_f64(count as f64)
+}
+
+/// Calculate the Hamming distance between two binary vectors packed 64 bits to a word, as by
+/// `pack_bits`: the number of bits where they differ.
+/// # Example
+/// ```rust
+/// use rml::math::distance::{pack_bits, packed_hamming_distance};
+///
+/// let x = pack_bits(&[1.0, 0.0, 1.0, 1.0]);
+/// let y = pack_bits(&[1.0, 1.0, 0.0, 1.0]);
+/// assert_eq!(packed_hamming_distance(&x, &y), 2);
+/// ```
+pub fn packed_hamming_distance(p: &[u64], q: &[u64]) -> usize {
+    p.iter()
+        .zip(q)
+        .map(|(p, q)| (p ^ q).count_ones() as usize)
+        .sum()
+}
+
+/// Pack a binary vector into 64-bit words, the first feature in the lowest bit of the first
+/// word. Every non-zero feature is a 1.
+pub fn pack_bits<F: Float>(x: &[F]) -> Vec<u64> {
+    x.chunks(64)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .filter(|(_, &v)| v != F::zero())
+                .fold(0, |word, (i, _)| word | 1 << i)
+        })
+        .collect()
+}
+
+/// Calculate the Jaccard distance between two binary vectors, where every non-zero feature is
+/// present: one minus the number of features present in both over the number present in
+/// either. Two vectors with no features present are at distance 0.
+/// # Example
+/// ```rust
+/// use rml::math::distance::jaccard_distance;
+///
+/// let x: Vec<f64> = vec![1.0, 1.0, 0.0, 1.0];
+/// let y: Vec<f64> = vec![1.0, 0.0, 1.0, 1.0];
+/// println!("{}", jaccard_distance(&x, &y))
+/// ```
+pub fn jaccard_distance<F: Float>(p: &[F], q: &[F]) -> F {
+    let (both, either) = p.iter().zip(q).fold((0, 0), |(both, either), (&p, &q)| {
+        let (p, q) = (p != F::zero(), q != F::zero());
+        (both + (p && q) as usize, either + (p || q) as usize)
+    });
+
+    F::from_f64(jaccard_from_counts(both, either))
+}
+
+/// Calculate the Jaccard distance between two sets, such as the tokens of two documents: one
+/// minus the size of their intersection over the size of their union. Two empty sets are at
+/// distance 0. Near-duplicate documents are at a distance close to 0.
+/// # Example
+/// ```rust
+/// use rml::math::distance::jaccard_set_distance;
+/// use std::collections::HashSet;
+///
+/// let x: HashSet<String> = ["the", "cat", "sat"].iter().map(|s| s.to_string()).collect();
+/// let y: HashSet<String> = ["the", "cat", "ran"].iter().map(|s| s.to_string()).collect();
+/// assert_eq!(jaccard_set_distance(&x, &y), 0.5);
+/// ```
+pub fn jaccard_set_distance<T: Eq + Hash>(p: &HashSet<T>, q: &HashSet<T>) -> f64 {
+    let both = p.intersection(q).count();
+
+    jaccard_from_counts(both, p.len() + q.len() - both)
+}
+
+/// The Jaccard distance given the sizes of the intersection and union.
+fn jaccard_from_counts(both: usize, either: usize) -> f64 {
+    if either == 0 {
+        0.0
+    } else {
+        1.0 - both as f64 / either as f64
+    }
+}
+
+/// Calculate the cosine distance between two points: one minus the cosine of the angle between
+/// them, from 0 for points in the same direction to 2 for opposite ones. The distance from the
+/// origin to any point is 1.
+/// # Example
+/// ```rust
+/// use rml::math::distance::cosine_distance;
+///
+/// let x: Vec<f64> = vec![1.0, 0.0];
+/// let y: Vec<f64> = vec![5.0, 5.0];
+/// println!("{}", cosine_distance(&x, &y))
+/// ```
+pub fn cosine_distance<F: Float>(p: &[F], q: &[F]) -> F {
+    cosine_from_dot(dot(p, q), l2_norm(p), l2_norm(q))
+}
+
+/// Calculate the cosine similarity between two points: the cosine of the angle between them,
+/// from 1 for points in the same direction to -1 for opposite ones. The origin has no
+/// direction, so its similarity to any point is 0, and `cosine_distance` is always one minus
+/// the similarity.
+/// # Example
+/// ```rust
+/// use rml::math::distance::cosine_similarity;
+///
+/// let x: Vec<f64> = vec![1.0, 0.0];
+/// let y: Vec<f64> = vec![5.0, 5.0];
+/// println!("{}", cosine_similarity(&x, &y))
+/// ```
+pub fn cosine_similarity<F: Float>(p: &[F], q: &[F]) -> F {
+    F::from_f64(1.0) - cosine_distance(p, q)
+}
+
+/// The dot product of two points.
+pub(crate) fn dot<F: Float>(p: &[F], q: &[F]) -> F {
+    p.iter().zip(q).map(|(&p, &q)| p * q).sum()
+}
+
+/// The cosine distance between two points, given their dot product and L2 norms.
+pub(crate) fn cosine_from_dot<F: Float>(dot: F, p_norm: F, q_norm: F) -> F {
+    if p_norm == F::zero() || q_norm == F::zero() {
+        return F::from_f64(1.0);
+    }
+    let distance = F::from_f64(1.0) - dot / (p_norm * q_norm);
+    // Rounding can push the distance between parallel points just below 0.
+    if distance > F::zero() {
+        distance
+    } else {
+        F::zero()
+    }
+}
+
+/// A distance between two points of type `F`, for domain-specific distances. Implemented for
+/// every thread-safe `Fn(&[F], &[F]) -> F`.
+pub trait Metric<F>: Send + Sync {
+    /// The distance between `p` and `q`.
+    fn distance(&self, p: &[F], q: &[F]) -> F;
+}
+
+impl<F, M> Metric<F> for M
+where
+    M: Fn(&[F], &[F]) -> F + Send + Sync,
+{
+    fn distance(&self, p: &[F], q: &[F]) -> F {
+        self(p, q)
+    }
+}
+
+/// A shared, type-erased `Metric`.
+///
+/// # Example
+/// ```rust
+/// use rml::math::distance::CustomDistance;
+///
+/// let chebyshev = CustomDistance::new(|p: &[f64], q: &[f64]| {
+///     p.iter().zip(q).map(|(a, b)| (a - b).abs()).fold(0.0, f64::max)
+/// });
+///
+/// assert_eq!(chebyshev.distance(&[0.0, 0.0], &[3.0, -4.0]), 4.0);
+/// ```
+pub struct CustomDistance<F>(Arc<dyn Metric<F>>);
+
+impl<F> CustomDistance<F> {
+    /// Wrap a metric.
+    pub fn new<M: Metric<F> + 'static>(metric: M) -> CustomDistance<F> {
+        CustomDistance(Arc::new(metric))
+    }
+
+    /// The distance between `p` and `q`.
+    pub fn distance(&self, p: &[F], q: &[F]) -> F {
+        self.0.distance(p, q)
+    }
+}
+
+impl<F> Clone for CustomDistance<F> {
+    fn clone(&self) -> Self {
+        CustomDistance(Arc::clone(&self.0))
+    }
+}
+
+impl<F> fmt::Debug for CustomDistance<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CustomDistance")
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -74,6 +331,80 @@ mod tests {
         assert_eq!(euclidean_distance(&x, &y), f64::from(3).sqrt());
     }
 
+    #[test]
+    fn cosine_distance_test() {
+        assert_eq!(cosine_distance(&[1.0, 0.0], &[0.0, 3.0]), 1.0);
+        assert!(cosine_distance(&[1.0, 1.0], &[2.0, 2.0]) < 1e-12);
+        assert_eq!(cosine_distance(&[1.0, 0.0], &[-2.0, 0.0]), 2.0);
+        assert_eq!(cosine_distance(&[0.0, 0.0], &[1.0, 2.0]), 1.0);
+        assert!(
+            (cosine_distance(&[1.0f32, 0.0], &[1.0, 1.0]) - (1.0 - 0.5f32.sqrt())).abs() < 1e-6
+        );
+    }
+
+    #[test]
+    fn cosine_similarity_test() {
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 3.0]), 0.0);
+        assert!((cosine_similarity(&[1.0, 1.0], &[2.0, 2.0]) - 1.0).abs() < 1e-12);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[-2.0, 0.0]), -1.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[0.0, 0.0]), 0.0);
+        let (p, q) = ([3.0, 1.0, 0.0], [1.0, 2.0, 2.0]);
+        assert!((cosine_similarity(&p, &q) + cosine_distance(&p, &q) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn minkowski_distance_test() {
+        let x: Vec<f64> = vec![5.0, 6.0];
+        let y: Vec<f64> = vec![-7.0, 11.0];
+
+        assert!((minkowski_distance(&x, &y, 1.0) - manhattan_distance(&x, &y)).abs() < 1e-12);
+        assert!((minkowski_distance(&x, &y, 2.0) - euclidean_distance(&x, &y)).abs() < 1e-12);
+        assert!((minkowski_distance(&x, &y, 3.0) - 1853f64.cbrt()).abs() < 1e-12);
+        assert!((minkowski_distance(&x, &y, 100.0) - 12.0).abs() < 1e-3);
+        assert_eq!(minkowski_distance(&[1.0f32, 2.0], &[1.0, 2.0], 3.0), 0.0);
+
+        assert!(validate(Distance::Minkowski(1.0)).is_ok());
+        assert!(validate(Distance::Minkowski(0.5)).is_err());
+        assert!(validate(Distance::Minkowski(f64::NAN)).is_err());
+        assert!(validate(Distance::Minkowski(f64::INFINITY)).is_err());
+    }
+
+    #[test]
+    fn hamming_distance_test() {
+        assert_eq!(hamming_distance(&[1.0, 0.0, 2.0], &[1.0, 1.0, 0.0]), 2.0);
+        assert_eq!(hamming_distance(&[3.0f32, 4.0], &[3.0, 4.0]), 0.0);
+
+        // Across a word boundary.
+        let p: Vec<f64> = (0..70).map(|i| (i % 3 == 0) as u8 as f64).collect();
+        let q: Vec<f64> = (0..70).map(|i| (i % 2 == 0) as u8 as f64).collect();
+        let (p_bits, q_bits) = (pack_bits(&p), pack_bits(&q));
+        assert_eq!(p_bits.len(), 2);
+        assert_eq!(
+            packed_hamming_distance(&p_bits, &q_bits) as f64,
+            hamming_distance(&p, &q)
+        );
+        assert_eq!(pack_bits(&[0.0, 2.0, 0.0, -1.0]), vec![0b1010]);
+    }
+
+    #[test]
+    fn jaccard_distance_test() {
+        assert_eq!(
+            jaccard_distance(&[1.0, 1.0, 0.0, 1.0], &[1.0, 0.0, 1.0, 1.0]),
+            0.5
+        );
+        assert_eq!(jaccard_distance(&[2.0f32, 0.0], &[1.0, 0.0]), 0.0);
+        assert_eq!(jaccard_distance(&[1.0, 0.0], &[0.0, 1.0]), 1.0);
+        assert_eq!(jaccard_distance(&[0.0, 0.0], &[0.0, 0.0]), 0.0);
+
+        let set =
+            |words: &[&str]| -> HashSet<String> { words.iter().map(|w| w.to_string()).collect() };
+        let a = set(&["a", "b", "c", "d"]);
+        assert_eq!(jaccard_set_distance(&a, &set(&["b", "c", "d", "e"])), 0.4);
+        assert_eq!(jaccard_set_distance(&a, &a), 0.0);
+        assert_eq!(jaccard_set_distance(&a, &set(&[])), 1.0);
+        assert_eq!(jaccard_set_distance(&set(&[]), &set(&[])), 0.0);
+    }
+
     #[test]
     fn manhattan_distance_test() {
         let x: Vec<f64> = vec![0.0, 0.0];
@@ -90,5 +421,10 @@ mod tests {
         let y: Vec<f64> = vec![1.0, 1.0, 1.0];
 
         assert_eq!(manhattan_distance(&x, &y), 3.0);
+
+        let x: Vec<f32> = vec![0.0, 0.0];
+        let y: Vec<f32> = vec![-1.0, 1.0];
+
+        assert_eq!(manhattan_distance(&x, &y), 2.0f32);
     }
 }
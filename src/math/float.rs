@@ -0,0 +1,128 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+//! A trait for the floating point types models can compute with.
+
+/*!
+Models that are generic over `Float` can store and compute in either `f32` or `f64`. `f32`
+halves the memory of large datasets, such as embeddings, at the cost of precision.
+
+# Example
+```rust
+use rml::knn::KNN;
+
+let mut knn: KNN<f32> = KNN::unfitted(1, None, None);
+rml::traits::Fit::fit(&mut knn, &[vec![0.0], vec![10.0]], &[0, 1]).unwrap();
+assert_eq!(knn.predict(&[9.0]).unwrap(), 1);
+```
+!*/
+
+use std::fmt::{Debug, Display};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, Neg, Sub};
+use std::str::FromStr;
+
+/// A floating point type: `f32` or `f64`.
+pub trait Float:
+    Copy
+    + Default
+    + PartialEq
+    + PartialOrd
+    + Debug
+    + Display
+    + FromStr
+    + Send
+    + Sync
+    + Sum
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + DivAssign
+    + 'static
+{
+    /// Zero.
+    fn zero() -> Self;
+    /// Convert from an `f64`, rounding if needed.
+    fn from_f64(x: f64) -> Self;
+    /// Convert to an `f64`.
+    fn to_f64(self) -> f64;
+    /// The absolute value.
+    fn abs(self) -> Self;
+    /// The square root.
+    fn sqrt(self) -> Self;
+    /// Raise to an integer power.
+    fn powi(self, n: i32) -> Self;
+    /// A total ordering, in which NaN sorts after every number.
+    fn total_cmp(&self, other: &Self) -> std::cmp::Ordering;
+}
+
+macro_rules! impl_float {
+    ($($t:ty),*) => {
+        $(
+            impl Float for $t {
+                fn zero() -> Self {
+                    0.0
+                }
+
+                fn from_f64(x: f64) -> Self {
+                    x as $t
+                }
+
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+
+                fn abs(self) -> Self {
+                    <$t>::abs(self)
+                }
+
+                fn sqrt(self) -> Self {
+                    <$t>::sqrt(self)
+                }
+
+                fn powi(self, n: i32) -> Self {
+                    <$t>::powi(self, n)
+                }
+
+                fn total_cmp(&self, other: &Self) -> std::cmp::Ordering {
+                    <$t>::total_cmp(self, other)
+                }
+            }
+        )*
+    };
+}
+
+impl_float!(f32, f64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hypotenuse<F: Float>(a: F, b: F) -> F {
+        (a.powi(2) + b.powi(2)).sqrt()
+    }
+
+    #[test]
+    fn float_test() {
+        assert_eq!(hypotenuse(3.0f32, 4.0), 5.0);
+        assert_eq!(hypotenuse(3.0f64, 4.0), 5.0);
+        assert_eq!(f32::from_f64(0.5).to_f64(), 0.5);
+        assert_eq!(f64::NAN.total_cmp(&1.0), std::cmp::Ordering::Greater);
+    }
+}
@@ -0,0 +1,117 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Dense linear algebra shared by the estimators.
+
+/// The smallest pivot accepted for `a`, relative to its largest absolute entry.
+fn tolerance(a: &[Vec<f64>]) -> f64 {
+    let largest = a.iter().flatten().fold(0.0_f64, |m, v| m.max(v.abs()));
+    largest * a.len() as f64 * f64::EPSILON
+}
+
+/// Solve `a x = b` by Gaussian elimination with partial pivoting. `None` if `a` is singular.
+pub(crate) fn solve(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    let tol = tolerance(&a);
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() <= tol {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        let pivot_row = a[col].clone();
+        for row in col + 1..n {
+            let factor = a[row][col] / pivot_row[col];
+            for (v, p) in a[row][col..].iter_mut().zip(&pivot_row[col..]) {
+                *v -= factor * p;
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let rest: f64 = (row + 1..n).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - rest) / a[row][row];
+    }
+    Some(x)
+}
+
+/// The inverse of `a` by Gauss-Jordan elimination with partial pivoting. `None` if `a` is
+/// singular.
+pub(crate) fn inverse(mut a: Vec<Vec<f64>>) -> Option<Vec<Vec<f64>>> {
+    let n = a.len();
+    let mut inv: Vec<Vec<f64>> = (0..n)
+        .map(|i| (0..n).map(|j| (i == j) as u8 as f64).collect())
+        .collect();
+    let tol = tolerance(&a);
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() <= tol {
+            return None;
+        }
+        a.swap(col, pivot);
+        inv.swap(col, pivot);
+        let scale = a[col][col];
+        a[col].iter_mut().for_each(|v| *v /= scale);
+        inv[col].iter_mut().for_each(|v| *v /= scale);
+        let (pivot_row, pivot_inv) = (a[col].clone(), inv[col].clone());
+        for row in (0..n).filter(|&row| row != col) {
+            let factor = a[row][col];
+            for (v, p) in a[row].iter_mut().zip(&pivot_row) {
+                *v -= factor * p;
+            }
+            for (v, p) in inv[row].iter_mut().zip(&pivot_inv) {
+                *v -= factor * p;
+            }
+        }
+    }
+    Some(inv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solve_test() {
+        let x = solve(vec![vec![0.0, 2.0], vec![1.0, 1.0]], vec![4.0, 3.0]).unwrap();
+
+        assert_eq!(x, vec![1.0, 2.0]);
+        assert!(solve(vec![vec![1.0, 1.0], vec![1.0, 1.0]], vec![1.0, 1.0]).is_none());
+    }
+
+    #[test]
+    fn solve_scale_test() {
+        let x = solve(vec![vec![1e-14, 0.0], vec![0.0, 1e-14]], vec![1e-14, 2e-14]).unwrap();
+        assert_eq!(x, vec![1.0, 2.0]);
+
+        let big = 1e8_f64;
+        let next = f64::from_bits(big.to_bits() + 1);
+        assert!(solve(vec![vec![big, big], vec![big, next]], vec![1.0, 1.0]).is_none());
+        assert!(inverse(vec![vec![big, big], vec![big, next]]).is_none());
+        assert!(inverse(vec![vec![1e-14, 0.0], vec![0.0, 1e-14]]).is_some());
+    }
+
+    #[test]
+    fn inverse_test() {
+        let inv = inverse(vec![vec![0.0, 2.0], vec![1.0, 1.0]]).unwrap();
+
+        assert_eq!(inv, vec![vec![-0.5, 1.0], vec![0.5, 0.0]]);
+        assert!(inverse(vec![vec![1.0, 2.0], vec![2.0, 4.0]]).is_none());
+    }
+}
@@ -17,6 +17,18 @@
 
 //! The math module for computing necessary values.
 
+pub mod dense;
+
 pub mod distance;
 
+pub mod float;
+
+pub(crate) mod linalg;
+
 pub mod norm;
+
+pub mod random;
+
+pub mod sparse;
+
+pub use float::Float;
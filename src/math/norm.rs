@@ -17,6 +17,8 @@
 
 //! Contains functions for normalizing vectors.
 
+use super::Float;
+
 /// Describes the types of normalizations that are possible.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Norm {
@@ -27,11 +29,13 @@ pub enum Norm {
 /// Produces an L2 norm from the given data.
 /// # Example
 /// ```rust
+/// use rml::math::norm::l2_norm;
+///
 /// let p: Vec<f64> = vec![2.0, 2.0, 2.0];
 /// println!("{}", l2_norm(&p));
 /// ```
-pub fn l2_norm(p: &[f64]) -> f64 {
-    let norm: f64 = p.iter().map(|x| x.powi(2)).sum();
+pub fn l2_norm<F: Float>(p: &[F]) -> F {
+    let norm: F = p.iter().map(|x| x.powi(2)).sum();
 
     norm.sqrt()
 }
@@ -39,11 +43,13 @@ pub fn l2_norm(p: &[f64]) -> f64 {
 /// Produces an L1 norm from the given data.
 /// # Example
 /// ```rust
+/// use rml::math::norm::l1_norm;
+///
 /// let p: Vec<f64> = vec![2.0, 2.0, 2.0];
 /// println!("{}", l1_norm(&p));
 /// ```
-pub fn l1_norm(p: &[f64]) -> f64 {
-    let norm: f64 = p.iter().map(|x| x.abs()).sum();
+pub fn l1_norm<F: Float>(p: &[F]) -> F {
+    let norm: F = p.iter().map(|x| x.abs()).sum();
 
     norm
 }
@@ -51,15 +57,18 @@ pub fn l1_norm(p: &[f64]) -> f64 {
 /// Produces a normalizard from the given data.
 /// # Example
 /// ```rust
+/// use rml::math::norm::{normalize_vector, Norm};
+///
 /// let mut p: Vec<f64> = vec![2.0, 2.0, 2.0];
-/// println!("{:?}", normalize_vector(&mut p, &Norm::L2));
+/// normalize_vector(&mut p, &Norm::L2);
+/// println!("{:?}", p);
 /// ```
-pub fn normalize_vector(p: &mut [f64], norm_type: &Norm) {
+pub fn normalize_vector<F: Float>(p: &mut [F], norm_type: &Norm) {
     let norm = match norm_type {
         Norm::L1 => l1_norm(p),
         Norm::L2 => l2_norm(p),
     };
-    if norm != 0.0 {
+    if norm != F::zero() {
         p.iter_mut().for_each(|xi| *xi /= norm);
     }
 }
@@ -87,5 +96,9 @@ mod tests {
         let mut p: Vec<f64> = vec![2.0, 2.0, 2.0];
         normalize_vector(&mut p, &Norm::L2);
         assert_eq!(p, vec![2.0 / f64::from(12).sqrt(); 3]);
+
+        let mut p: Vec<f32> = vec![1.0, 3.0];
+        normalize_vector(&mut p, &Norm::L1);
+        assert_eq!(p, vec![0.25, 0.75]);
     }
 }
@@ -0,0 +1,157 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A small, seedable pseudo random number generator.
+
+//! Not suitable for cryptography. Used wherever the library needs reproducible randomness.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static GLOBAL_SEED: AtomicU64 = AtomicU64::new(0);
+
+/// Set the seed used by randomized components that weren't given an explicit seed.
+/// The global seed defaults to 0.
+pub fn set_global_seed(seed: u64) {
+    GLOBAL_SEED.store(seed, Ordering::Relaxed);
+}
+
+/// The seed used by randomized components that weren't given an explicit seed.
+pub fn global_seed() -> u64 {
+    GLOBAL_SEED.load(Ordering::Relaxed)
+}
+
+/// A SplitMix64 pseudo random number generator.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Create a new generator from a seed. The same seed always produces the same sequence.
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    /// Create a new generator from `seed`, or from the global seed if it is `None`.
+    pub fn from_seed(seed: Option<u64>) -> Rng {
+        Rng::new(seed.unwrap_or_else(global_seed))
+    }
+
+    /// Generate a random `u64`.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Generate a random `f64` in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Generate a random `f64` from the standard normal distribution (Box-Muller).
+    pub fn next_normal(&mut self) -> f64 {
+        // 1 - u is in (0, 1], so the logarithm is finite.
+        let u = 1.0 - self.next_f64();
+        let v = self.next_f64();
+        (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+    }
+
+    /// Generate a random `usize` in `[0, n)`. `n` must be greater than 0.
+    pub fn below(&mut self, n: usize) -> usize {
+        // Reject values from the incomplete last range to avoid modulo bias.
+        let n = n as u64;
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next_u64();
+            if x < zone {
+                return (x % n) as usize;
+            }
+        }
+    }
+
+    /// Shuffle a slice in place (Fisher-Yates).
+    pub fn shuffle<T>(&mut self, values: &mut [T]) {
+        for i in (1..values.len()).rev() {
+            values.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn global_seed_test() {
+        // Other tests rely on the global seed, so this one only reads it.
+        assert_eq!(
+            Rng::from_seed(None).next_u64(),
+            Rng::new(global_seed()).next_u64()
+        );
+        assert_eq!(Rng::from_seed(Some(3)).next_u64(), Rng::new(3).next_u64());
+    }
+
+    #[test]
+    fn seed_test() {
+        let a: Vec<u64> = (0..5)
+            .scan(Rng::new(42), |r, _| Some(r.next_u64()))
+            .collect();
+        let b: Vec<u64> = (0..5)
+            .scan(Rng::new(42), |r, _| Some(r.next_u64()))
+            .collect();
+        let c: Vec<u64> = (0..5)
+            .scan(Rng::new(43), |r, _| Some(r.next_u64()))
+            .collect();
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn range_test() {
+        let mut rng = Rng::new(7);
+        for _ in 0..1000 {
+            assert!(rng.below(3) < 3);
+            let x = rng.next_f64();
+            assert!((0.0..1.0).contains(&x));
+        }
+    }
+
+    #[test]
+    fn normal_test() {
+        let mut rng = Rng::new(11);
+        let samples: Vec<f64> = (0..10_000).map(|_| rng.next_normal()).collect();
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        let var = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / samples.len() as f64;
+
+        assert!(mean.abs() < 0.05);
+        assert!((var - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn shuffle_test() {
+        let mut values: Vec<usize> = (0..20).collect();
+        Rng::new(1).shuffle(&mut values);
+
+        assert_ne!(values, (0..20).collect::<Vec<_>>());
+        values.sort_unstable();
+        assert_eq!(values, (0..20).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,190 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A compressed sparse row (CSR) matrix.
+
+/*!
+Only the non-zero values of each row are stored, with their column indices. This keeps wide
+matrices that are mostly zero, such as one-hot encodings or word counts, small in memory.
+
+# Example
+```rust
+use rml::math::sparse::CsrMatrix;
+
+let mut m = CsrMatrix::new(4);
+m.push_row(vec![(1, 2.0), (3, 5.0)]).unwrap();
+m.push_row(vec![]).unwrap();
+
+assert_eq!(m.n_rows(), 2);
+assert_eq!(m.nnz(), 2);
+assert_eq!(m.to_dense(), vec![vec![0.0, 2.0, 0.0, 5.0], vec![0.0; 4]]);
+```
+!*/
+
+use crate::error::{Error, Result};
+
+/// A matrix stored in compressed sparse row format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsrMatrix<T> {
+    indptr: Vec<usize>,
+    indices: Vec<usize>,
+    data: Vec<T>,
+    n_cols: usize,
+}
+
+impl<T> CsrMatrix<T> {
+    /// Create an empty matrix with no rows and `n_cols` columns.
+    pub fn new(n_cols: usize) -> CsrMatrix<T> {
+        CsrMatrix {
+            indptr: vec![0],
+            indices: Vec::new(),
+            data: Vec::new(),
+            n_cols,
+        }
+    }
+
+    /// The heap memory of the stored values and indices, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        (self.indptr.capacity() + self.indices.capacity()) * std::mem::size_of::<usize>()
+            + self.data.capacity() * std::mem::size_of::<T>()
+    }
+
+    /// Append a row given as `(column, value)` pairs in increasing column order.
+    /// Returns an error, leaving the matrix unchanged, if a column is out of range or out of order.
+    pub fn push_row(&mut self, entries: Vec<(usize, T)>) -> Result<()> {
+        for (i, (col, _)) in entries.iter().enumerate() {
+            if *col >= self.n_cols {
+                return Err(Error::InvalidInput(format!(
+                    "Column {} is out of range.",
+                    col
+                )));
+            }
+            if i > 0 && entries[i - 1].0 >= *col {
+                return Err(Error::InvalidInput(
+                    "Columns must be in increasing order.".into(),
+                ));
+            }
+        }
+        for (col, value) in entries {
+            self.indices.push(col);
+            self.data.push(value);
+        }
+        self.indptr.push(self.data.len());
+        Ok(())
+    }
+
+    /// The number of rows.
+    pub fn n_rows(&self) -> usize {
+        self.indptr.len() - 1
+    }
+
+    /// The number of columns.
+    pub fn n_cols(&self) -> usize {
+        self.n_cols
+    }
+
+    /// The number of stored (non-zero) values.
+    pub fn nnz(&self) -> usize {
+        self.data.len()
+    }
+
+    /// The column indices and values of row `i`.
+    ///
+    /// # Panics
+    /// If `i` is out of range.
+    pub fn row(&self, i: usize) -> (&[usize], &[T]) {
+        let (start, end) = (self.indptr[i], self.indptr[i + 1]);
+        (&self.indices[start..end], &self.data[start..end])
+    }
+
+    /// The value at row `i` and column `j`, or `None` if it is not stored.
+    pub fn get(&self, i: usize, j: usize) -> Option<&T> {
+        if i >= self.n_rows() {
+            return None;
+        }
+        let (indices, data) = self.row(i);
+        indices.binary_search(&j).ok().map(|k| &data[k])
+    }
+}
+
+impl<T: Clone + Default + PartialEq> CsrMatrix<T> {
+    /// Create a matrix from dense rows, storing only the values that are not zero
+    /// (`T::default()`). Every row must have `n_cols` values.
+    pub fn from_dense(rows: &[Vec<T>], n_cols: usize) -> Result<CsrMatrix<T>> {
+        let zero = T::default();
+        let mut m = CsrMatrix::new(n_cols);
+        for row in rows {
+            if row.len() != n_cols {
+                return Err(Error::ShapeMismatch(
+                    "Every row must have the same number of columns.".into(),
+                ));
+            }
+            m.push_row(
+                row.iter()
+                    .cloned()
+                    .enumerate()
+                    .filter(|(_, v)| *v != zero)
+                    .collect(),
+            )?;
+        }
+        Ok(m)
+    }
+
+    /// Convert the matrix to dense rows, filling unstored values with zero (`T::default()`).
+    pub fn to_dense(&self) -> Vec<Vec<T>> {
+        (0..self.n_rows())
+            .map(|i| {
+                let mut row = vec![T::default(); self.n_cols];
+                let (indices, data) = self.row(i);
+                for (&j, v) in indices.iter().zip(data) {
+                    row[j] = v.clone();
+                }
+                row
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csr_matrix_test() {
+        let dense = vec![vec![0, 3, 0], vec![0, 0, 0], vec![1, 0, 2]];
+        let m = CsrMatrix::from_dense(&dense, 3).unwrap();
+
+        assert_eq!(m.n_rows(), 3);
+        assert_eq!(m.nnz(), 3);
+        assert_eq!(m.row(2), (&[0, 2][..], &[1, 2][..]));
+        assert_eq!(m.get(0, 1), Some(&3));
+        assert_eq!(m.get(0, 0), None);
+        assert_eq!(m.to_dense(), dense);
+        assert!(m.memory_usage() >= (4 + 3) * std::mem::size_of::<usize>() + 3 * 4);
+    }
+
+    #[test]
+    fn push_row_test() {
+        let mut m = CsrMatrix::new(2);
+
+        assert!(m.push_row(vec![(2, 1.0)]).is_err());
+        assert!(m.push_row(vec![(1, 1.0), (0, 1.0)]).is_err());
+        assert_eq!(m.n_rows(), 0);
+        assert!(m.push_row(vec![(0, 1.0), (1, 1.0)]).is_ok());
+        assert_eq!(m.n_rows(), 1);
+    }
+}
@@ -0,0 +1,52 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+//! Metrics for evaluating hard class predictions.
+
+use crate::error::{check_lengths, Error, Result};
+
+/// The fraction of samples predicted correctly.
+/// Returns an error if there are no samples or the lengths differ.
+///
+/// # Example
+/// ```rust
+/// use rml::metrics::accuracy;
+///
+/// assert_eq!(accuracy(&[0, 1, 1, 0], &[0, 1, 0, 0]).unwrap(), 0.75);
+/// ```
+pub fn accuracy<L: PartialEq>(y_true: &[L], y_pred: &[L]) -> Result<f64> {
+    if y_true.is_empty() {
+        return Err(Error::InvalidInput("No samples to score.".into()));
+    }
+    check_lengths("True and predicted labels", y_true.len(), y_pred.len())?;
+    let correct = y_true.iter().zip(y_pred).filter(|(t, p)| t == p).count();
+    Ok(correct as f64 / y_true.len() as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accuracy_test() {
+        assert_eq!(accuracy(&["a", "b"], &["a", "a"]).unwrap(), 0.5);
+        assert!(accuracy::<i32>(&[], &[]).is_err());
+        assert!(matches!(
+            accuracy(&[1], &[1, 2]),
+            Err(Error::ShapeMismatch(_))
+        ));
+    }
+}
@@ -0,0 +1,233 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+//! The confusion matrix of a classifier.
+
+use crate::error::{check_lengths, Error, Result};
+use std::fmt;
+
+/// Which totals a confusion matrix is normalized by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalize {
+    /// Each row sums to 1, giving the fraction of each true class predicted as each class.
+    True,
+    /// Each column sums to 1, giving the fraction of each prediction that had each true class.
+    Pred,
+    /// The whole matrix sums to 1.
+    All,
+}
+
+/// Counts of true labels against predicted labels.
+///
+/// Row `i` holds the samples whose true label is `labels()[i]`, and column `j` those predicted
+/// as `labels()[j]`.
+///
+/// # Example
+/// ```rust
+/// use rml::metrics::ConfusionMatrix;
+///
+/// let y_true = vec![0, 0, 1, 1, 2];
+/// let y_pred = vec![0, 1, 1, 1, 2];
+/// let cm = ConfusionMatrix::new(&y_true, &y_pred).unwrap();
+///
+/// assert_eq!(cm.counts()[0], vec![1, 1, 0]);
+/// assert_eq!(cm.accuracy(), 0.8);
+/// println!("{}", cm);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfusionMatrix<L> {
+    labels: Vec<L>,
+    counts: Vec<Vec<usize>>,
+}
+
+impl<L: Ord + Clone> ConfusionMatrix<L> {
+    /// Build the matrix from true and predicted labels. The classes are every label that occurs
+    /// in either, in sorted order.
+    pub fn new(y_true: &[L], y_pred: &[L]) -> Result<ConfusionMatrix<L>> {
+        let mut labels: Vec<L> = y_true.iter().chain(y_pred).cloned().collect();
+        labels.sort();
+        labels.dedup();
+        ConfusionMatrix::with_labels(y_true, y_pred, labels)
+    }
+
+    /// Build the matrix with the given classes, in the given order. This keeps classes that
+    /// never occur. Returns an error if a label is not one of `labels`.
+    pub fn with_labels(y_true: &[L], y_pred: &[L], labels: Vec<L>) -> Result<ConfusionMatrix<L>> {
+        check_lengths("True and predicted labels", y_true.len(), y_pred.len())?;
+        let index = |l: &L| {
+            labels
+                .iter()
+                .position(|c| c == l)
+                .ok_or_else(|| Error::InvalidInput("Unknown label.".into()))
+        };
+
+        let mut counts = vec![vec![0; labels.len()]; labels.len()];
+        for (t, p) in y_true.iter().zip(y_pred) {
+            counts[index(t)?][index(p)?] += 1;
+        }
+        Ok(ConfusionMatrix { labels, counts })
+    }
+}
+
+impl<L> ConfusionMatrix<L> {
+    /// The classes, in the order of the rows and columns.
+    pub fn labels(&self) -> &[L] {
+        &self.labels
+    }
+
+    /// The counts, with true labels as rows and predicted labels as columns.
+    pub fn counts(&self) -> &Vec<Vec<usize>> {
+        &self.counts
+    }
+
+    /// The number of samples.
+    pub fn n_samples(&self) -> usize {
+        self.counts.iter().flatten().sum()
+    }
+
+    /// The fraction of samples predicted correctly, or 0 if there are none.
+    pub fn accuracy(&self) -> f64 {
+        let correct: usize = (0..self.labels.len()).map(|i| self.counts[i][i]).sum();
+        ratio(correct, self.n_samples())
+    }
+
+    /// The counts divided by the row, column or overall totals. Rows or columns with no
+    /// samples are left as zeros.
+    pub fn normalized(&self, by: Normalize) -> Vec<Vec<f64>> {
+        let n = self.labels.len();
+        let total = self.n_samples();
+        (0..n)
+            .map(|i| {
+                (0..n)
+                    .map(|j| {
+                        let denominator = match by {
+                            Normalize::True => self.support(i),
+                            Normalize::Pred => self.predicted(j),
+                            Normalize::All => total,
+                        };
+                        ratio(self.counts[i][j], denominator)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The number of samples whose true label is class `i`.
+    pub fn support(&self, i: usize) -> usize {
+        self.counts[i].iter().sum()
+    }
+
+    /// The number of samples predicted as class `i`.
+    pub fn predicted(&self, i: usize) -> usize {
+        self.counts.iter().map(|row| row[i]).sum()
+    }
+
+    /// The precision of each class: the fraction of its predictions that were correct.
+    /// A class that was never predicted has a precision of 0.
+    pub fn precision(&self) -> Vec<f64> {
+        (0..self.labels.len())
+            .map(|i| ratio(self.counts[i][i], self.predicted(i)))
+            .collect()
+    }
+
+    /// The recall of each class: the fraction of its samples that were predicted correctly.
+    /// A class with no samples has a recall of 0.
+    pub fn recall(&self) -> Vec<f64> {
+        (0..self.labels.len())
+            .map(|i| ratio(self.counts[i][i], self.support(i)))
+            .collect()
+    }
+}
+
+/// `a / b`, or 0 if `b` is 0.
+fn ratio(a: usize, b: usize) -> f64 {
+    if b == 0 {
+        0.0
+    } else {
+        a as f64 / b as f64
+    }
+}
+
+impl<L: fmt::Display> fmt::Display for ConfusionMatrix<L> {
+    /// Formats the matrix as an aligned table with true labels as rows.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<String> = self.labels.iter().map(|l| l.to_string()).collect();
+        let width = names
+            .iter()
+            .map(String::len)
+            .chain(self.counts.iter().flatten().map(|c| c.to_string().len()))
+            .chain(Some("true\\pred".len()))
+            .max()
+            .unwrap_or(0);
+
+        write!(f, "{:>width$}", "true\\pred", width = width)?;
+        for name in &names {
+            write!(f, " {:>width$}", name, width = width)?;
+        }
+        for (name, row) in names.iter().zip(&self.counts) {
+            write!(f, "\n{:>width$}", name, width = width)?;
+            for count in row {
+                write!(f, " {:>width$}", count, width = width)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confusion_matrix_test() {
+        let y_true = vec!["cat", "cat", "dog", "dog", "dog"];
+        let y_pred = vec!["cat", "dog", "dog", "dog", "cat"];
+        let cm = ConfusionMatrix::new(&y_true, &y_pred).unwrap();
+
+        assert_eq!(cm.labels(), &["cat", "dog"]);
+        assert_eq!(cm.counts(), &vec![vec![1, 1], vec![1, 2]]);
+        assert_eq!(cm.n_samples(), 5);
+        assert_eq!(cm.precision(), vec![0.5, 2.0 / 3.0]);
+        assert_eq!(cm.recall(), vec![0.5, 2.0 / 3.0]);
+        assert_eq!(
+            cm.normalized(Normalize::True),
+            vec![vec![0.5, 0.5], vec![1.0 / 3.0, 2.0 / 3.0]]
+        );
+        assert_eq!(cm.normalized(Normalize::All)[1][1], 0.4);
+        assert!(ConfusionMatrix::new(&y_true, &y_pred[..2]).is_err());
+    }
+
+    #[test]
+    fn with_labels_test() {
+        let cm = ConfusionMatrix::with_labels(&[1, 1], &[1, 0], vec![0, 1, 2]).unwrap();
+
+        assert_eq!(cm.counts()[1], vec![1, 1, 0]);
+        assert_eq!(cm.recall()[2], 0.0);
+        assert_eq!(cm.normalized(Normalize::Pred)[1], vec![1.0, 1.0, 0.0]);
+        assert!(ConfusionMatrix::with_labels(&[3], &[3], vec![0]).is_err());
+        let table = cm.to_string();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[0].split_whitespace().collect::<Vec<_>>(),
+            ["true\\pred", "0", "1", "2"]
+        );
+        assert_eq!(
+            lines[2].split_whitespace().collect::<Vec<_>>(),
+            ["1", "1", "1", "0"]
+        );
+    }
+}
@@ -0,0 +1,30 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+//! Metrics for evaluating predictions.
+
+mod classification;
+mod confusion_matrix;
+mod probability;
+mod report;
+
+pub mod ranking;
+pub mod regression;
+
+pub use classification::accuracy;
+pub use confusion_matrix::{ConfusionMatrix, Normalize};
+pub use probability::{brier_score, log_loss, EPSILON};
+pub use report::{classification_report, ClassMetrics, ClassificationReport};
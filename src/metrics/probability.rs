@@ -0,0 +1,123 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+//! Metrics for predicted class probabilities.
+
+/*!
+These metrics score the probability a classifier gives to each class rather than its hard
+predictions, so they reward well calibrated models. Labels are class indices, and each row of
+`probabilities` holds one probability per class.
+
+# Example
+```rust
+use rml::metrics::{brier_score, log_loss};
+
+let y_true = vec![0, 1];
+let probabilities = vec![vec![0.9, 0.1], vec![0.2, 0.8]];
+
+assert!((log_loss(&y_true, &probabilities).unwrap() - 0.164).abs() < 1e-3);
+assert!((brier_score(&y_true, &probabilities).unwrap() - 0.05).abs() < 1e-12);
+```
+!*/
+
+use crate::error::{check_lengths, Error, Result};
+
+/// Probabilities are clipped to `[EPSILON, 1 - EPSILON]` before taking logarithms.
+pub const EPSILON: f64 = 1e-15;
+
+/// The mean negative log-likelihood of the true classes (cross-entropy).
+///
+/// Probabilities are clipped to `[EPSILON, 1 - EPSILON]`, so a confident wrong prediction gives a
+/// large but finite loss. Lower is better.
+pub fn log_loss(y_true: &[usize], probabilities: &[Vec<f64>]) -> Result<f64> {
+    check(y_true, probabilities)?;
+    let total: f64 = y_true
+        .iter()
+        .zip(probabilities)
+        .map(|(&y, p)| -p[y].clamp(EPSILON, 1.0 - EPSILON).ln())
+        .sum();
+    Ok(total / y_true.len() as f64)
+}
+
+/// The mean squared difference between the probabilities and the one-hot encoded true classes,
+/// summed over the classes.
+///
+/// Probabilities are clipped to `[0, 1]`. The score ranges from 0 (perfect) to 2. For two classes
+/// it is twice the usual binary Brier score.
+pub fn brier_score(y_true: &[usize], probabilities: &[Vec<f64>]) -> Result<f64> {
+    check(y_true, probabilities)?;
+    let total: f64 = y_true
+        .iter()
+        .zip(probabilities)
+        .map(|(&y, p)| {
+            p.iter()
+                .enumerate()
+                .map(|(k, &pk)| {
+                    let target = if k == y { 1.0 } else { 0.0 };
+                    (pk.clamp(0.0, 1.0) - target).powi(2)
+                })
+                .sum::<f64>()
+        })
+        .sum();
+    Ok(total / y_true.len() as f64)
+}
+
+/// Check that there is one probability row per label and every label is a valid class index.
+fn check(y_true: &[usize], probabilities: &[Vec<f64>]) -> Result<()> {
+    if y_true.is_empty() {
+        return Err(Error::InvalidInput("No samples to score.".into()));
+    }
+    check_lengths(
+        "Labels and probabilities",
+        y_true.len(),
+        probabilities.len(),
+    )?;
+    match y_true
+        .iter()
+        .zip(probabilities)
+        .find(|(&y, p)| y >= p.len())
+    {
+        Some((y, _)) => Err(Error::ShapeMismatch(format!(
+            "Class {} has no probability.",
+            y
+        ))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_loss_test() {
+        let p = vec![vec![0.5, 0.5], vec![0.0, 1.0]];
+        assert!((log_loss(&[0, 1], &p).unwrap() - 0.5f64.ln().abs() / 2.0).abs() < 1e-12);
+
+        let wrong = log_loss(&[0], &[vec![0.0, 1.0]]).unwrap();
+        assert!(wrong.is_finite() && (wrong + EPSILON.ln()).abs() < 1e-9);
+        assert!(log_loss(&[2], &[vec![0.5, 0.5]]).is_err());
+        assert!(log_loss(&[], &[]).is_err());
+    }
+
+    #[test]
+    fn brier_score_test() {
+        assert_eq!(brier_score(&[1], &[vec![0.0, 1.0]]).unwrap(), 0.0);
+        assert_eq!(brier_score(&[0], &[vec![0.0, 1.0]]).unwrap(), 2.0);
+        assert_eq!(brier_score(&[0], &[vec![-0.5, 1.5]]).unwrap(), 2.0);
+        assert!(brier_score(&[0, 1], &[vec![1.0]]).is_err());
+    }
+}
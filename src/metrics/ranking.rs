@@ -0,0 +1,140 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+//! Metrics for evaluating ranked result lists.
+
+/*!
+Each query is described by the relevance of its results, in the order they were ranked. NDCG
+takes graded relevance (higher is more relevant), while mean average precision and mean
+reciprocal rank take whether each result is relevant. Every metric is averaged over the queries
+and returns an error if there are none.
+
+# Example
+```rust
+use rml::metrics::ranking::{mean_reciprocal_rank, ndcg_at_k};
+
+// Two queries: the first found the best result second, the second found it first.
+let graded = vec![vec![1.0, 3.0, 0.0], vec![2.0, 1.0, 0.0]];
+let relevant = vec![vec![false, true, false], vec![true, false, false]];
+
+assert_eq!(mean_reciprocal_rank(&relevant).unwrap(), 0.75);
+assert!(ndcg_at_k(&graded, 3).unwrap() < 1.0);
+```
+!*/
+
+use crate::error::{Error, Result};
+
+/// The normalized discounted cumulative gain of the top `k` results.
+///
+/// The gain of a result is `2^relevance - 1`, discounted by `log2(rank + 1)`, and normalized by
+/// the gain of the ideal ordering of the same results. A query with no relevant results scores 0.
+pub fn ndcg_at_k(relevance: &[Vec<f64>], k: usize) -> Result<f64> {
+    mean(relevance, |r| {
+        let mut ideal = r.clone();
+        ideal.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+        let ideal_dcg = dcg(&ideal, k);
+        if ideal_dcg > 0.0 {
+            dcg(r, k) / ideal_dcg
+        } else {
+            0.0
+        }
+    })
+}
+
+/// The mean over queries of the average precision: the precision at the rank of each relevant
+/// result, averaged over the relevant results. A query with no relevant results scores 0.
+pub fn mean_average_precision(relevant: &[Vec<bool>]) -> Result<f64> {
+    mean(relevant, |r| {
+        let mut hits = 0;
+        let mut total = 0.0;
+        for (i, _) in r.iter().enumerate().filter(|(_, &rel)| rel) {
+            hits += 1;
+            total += hits as f64 / (i + 1) as f64;
+        }
+        if hits > 0 {
+            total / hits as f64
+        } else {
+            0.0
+        }
+    })
+}
+
+/// The mean over queries of `1 / rank` of the first relevant result. A query with no relevant
+/// results scores 0.
+pub fn mean_reciprocal_rank(relevant: &[Vec<bool>]) -> Result<f64> {
+    mean(relevant, |r| {
+        r.iter()
+            .position(|&rel| rel)
+            .map_or(0.0, |i| 1.0 / (i + 1) as f64)
+    })
+}
+
+/// The discounted cumulative gain of the top `k` results.
+fn dcg(relevance: &[f64], k: usize) -> f64 {
+    relevance
+        .iter()
+        .take(k)
+        .enumerate()
+        .map(|(i, r)| (2f64.powf(*r) - 1.0) / (i as f64 + 2.0).log2())
+        .sum()
+}
+
+/// The mean of `score` over the queries.
+fn mean<Q, F>(queries: &[Q], score: F) -> Result<f64>
+where
+    F: Fn(&Q) -> f64,
+{
+    if queries.is_empty() {
+        return Err(Error::InvalidInput("No queries to score.".into()));
+    }
+    Ok(queries.iter().map(score).sum::<f64>() / queries.len() as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ndcg_test() {
+        assert_eq!(ndcg_at_k(&[vec![3.0, 2.0, 0.0]], 3).unwrap(), 1.0);
+        assert_eq!(ndcg_at_k(&[vec![0.0, 0.0]], 2).unwrap(), 0.0);
+
+        // DCG = 1 / log2(3), ideal DCG = 1.
+        let score = ndcg_at_k(&[vec![0.0, 1.0]], 2).unwrap();
+        assert!((score - 1.0 / 3f64.log2()).abs() < 1e-12);
+        assert_eq!(ndcg_at_k(&[vec![0.0, 1.0]], 1).unwrap(), 0.0);
+        assert!(ndcg_at_k(&[], 1).is_err());
+    }
+
+    #[test]
+    fn mean_average_precision_test() {
+        // Precisions at the relevant ranks are 1 and 2/3.
+        let relevant = vec![vec![true, false, true], vec![false, false]];
+        assert_eq!(
+            mean_average_precision(&relevant).unwrap(),
+            (1.0 + 2.0 / 3.0) / 2.0 / 2.0
+        );
+    }
+
+    #[test]
+    fn mean_reciprocal_rank_test() {
+        let relevant = vec![vec![false, false, true], vec![false], vec![true]];
+        assert_eq!(
+            mean_reciprocal_rank(&relevant).unwrap(),
+            (1.0 / 3.0 + 1.0) / 3.0
+        );
+    }
+}
@@ -0,0 +1,147 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+//! Metrics for evaluating regression predictions.
+
+/*!
+Every metric takes the true and predicted targets and returns an error if they are empty or have
+different lengths.
+
+# Example
+```rust
+use rml::metrics::regression::{mean_absolute_error, r2_score};
+
+let y_true = vec![3.0, -0.5, 2.0, 7.0];
+let y_pred = vec![2.5, 0.0, 2.0, 8.0];
+
+assert_eq!(mean_absolute_error(&y_true, &y_pred).unwrap(), 0.5);
+assert!((r2_score(&y_true, &y_pred).unwrap() - 0.9486).abs() < 1e-4);
+```
+!*/
+
+use crate::error::{check_lengths, Error, Result};
+
+/// The mean of the squared errors.
+pub fn mean_squared_error(y_true: &[f64], y_pred: &[f64]) -> Result<f64> {
+    Ok(mean(&errors(y_true, y_pred, |e| e * e)?))
+}
+
+/// The square root of the mean squared error, in the units of the target.
+pub fn root_mean_squared_error(y_true: &[f64], y_pred: &[f64]) -> Result<f64> {
+    Ok(mean_squared_error(y_true, y_pred)?.sqrt())
+}
+
+/// The mean of the absolute errors.
+pub fn mean_absolute_error(y_true: &[f64], y_pred: &[f64]) -> Result<f64> {
+    Ok(mean(&errors(y_true, y_pred, f64::abs)?))
+}
+
+/// The mean of the absolute errors relative to the true values, as a fraction (not a percent).
+/// Returns an error if a true value is 0.
+pub fn mean_absolute_percentage_error(y_true: &[f64], y_pred: &[f64]) -> Result<f64> {
+    if y_true.contains(&0.0) {
+        return Err(Error::InvalidInput(
+            "Percentage error is undefined when a true value is 0.".into(),
+        ));
+    }
+    let relative: Vec<f64> = errors(y_true, y_pred, f64::abs)?
+        .iter()
+        .zip(y_true)
+        .map(|(e, t)| e / t.abs())
+        .collect();
+    Ok(mean(&relative))
+}
+
+/// The median of the absolute errors, which is robust to outliers.
+pub fn median_absolute_error(y_true: &[f64], y_pred: &[f64]) -> Result<f64> {
+    let mut e = errors(y_true, y_pred, f64::abs)?;
+    e.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let mid = e.len() / 2;
+    Ok(if e.len() % 2 == 0 {
+        (e[mid - 1] + e[mid]) / 2.0
+    } else {
+        e[mid]
+    })
+}
+
+/// The coefficient of determination: 1 minus the residual sum of squares over the total sum of
+/// squares. 1 is a perfect fit, 0 is no better than predicting the mean, and it can be negative.
+///
+/// If the true values are constant, returns 1 for a perfect fit and 0 otherwise.
+pub fn r2_score(y_true: &[f64], y_pred: &[f64]) -> Result<f64> {
+    let residual: f64 = errors(y_true, y_pred, |e| e * e)?.iter().sum();
+    let m = mean(y_true);
+    let total: f64 = y_true.iter().map(|t| (t - m).powi(2)).sum();
+    if total == 0.0 {
+        return Ok(if residual == 0.0 { 1.0 } else { 0.0 });
+    }
+    Ok(1.0 - residual / total)
+}
+
+/// Apply `f` to each error `y_pred - y_true`.
+fn errors<F>(y_true: &[f64], y_pred: &[f64], f: F) -> Result<Vec<f64>>
+where
+    F: Fn(f64) -> f64,
+{
+    if y_true.is_empty() {
+        return Err(Error::InvalidInput("No samples to score.".into()));
+    }
+    check_lengths("True and predicted values", y_true.len(), y_pred.len())?;
+    Ok(y_true.iter().zip(y_pred).map(|(t, p)| f(p - t)).collect())
+}
+
+/// The mean of a non-empty slice.
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_metrics_test() {
+        let y_true = vec![1.0, 2.0, 4.0, 10.0];
+        let y_pred = vec![2.0, 2.0, 2.0, 10.0];
+
+        assert_eq!(mean_squared_error(&y_true, &y_pred).unwrap(), 1.25);
+        assert_eq!(
+            root_mean_squared_error(&y_true, &y_pred).unwrap(),
+            1.25f64.sqrt()
+        );
+        assert_eq!(mean_absolute_error(&y_true, &y_pred).unwrap(), 0.75);
+        assert_eq!(
+            mean_absolute_percentage_error(&y_true, &y_pred).unwrap(),
+            0.375
+        );
+        assert_eq!(median_absolute_error(&y_true, &y_pred).unwrap(), 0.5);
+        assert_eq!(median_absolute_error(&[0.0], &[3.0]).unwrap(), 3.0);
+    }
+
+    #[test]
+    fn r2_score_test() {
+        assert_eq!(r2_score(&[1.0, 2.0, 3.0], &[1.0, 2.0, 3.0]).unwrap(), 1.0);
+        assert_eq!(r2_score(&[1.0, 2.0, 3.0], &[2.0, 2.0, 2.0]).unwrap(), 0.0);
+        assert_eq!(r2_score(&[5.0, 5.0], &[5.0, 4.0]).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn invalid_input_test() {
+        assert!(mean_squared_error(&[], &[]).is_err());
+        assert!(r2_score(&[1.0], &[1.0, 2.0]).is_err());
+        assert!(mean_absolute_percentage_error(&[0.0], &[1.0]).is_err());
+    }
+}
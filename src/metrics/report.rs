@@ -0,0 +1,235 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+//! A per-class summary of classification metrics.
+
+use super::ConfusionMatrix;
+use crate::error::{Error, Result};
+use std::fmt;
+
+/// Precision, recall, F1 score and support of one class, or an average over classes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassMetrics {
+    /// The class name, or the name of the average.
+    pub name: String,
+    /// The fraction of predictions of the class that were correct.
+    pub precision: f64,
+    /// The fraction of samples of the class that were predicted correctly.
+    pub recall: f64,
+    /// The harmonic mean of precision and recall.
+    pub f1: f64,
+    /// The number of samples whose true label is the class.
+    pub support: usize,
+}
+
+/// The metrics of every class, with overall accuracy and averages, created by
+/// [`classification_report`]. `Display` formats it as a table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassificationReport {
+    /// The metrics of each class, in sorted label order.
+    pub classes: Vec<ClassMetrics>,
+    /// The fraction of samples predicted correctly.
+    pub accuracy: f64,
+    /// The unweighted mean of the class metrics.
+    pub macro_avg: ClassMetrics,
+    /// The mean of the class metrics weighted by support.
+    pub weighted_avg: ClassMetrics,
+}
+
+/// Compute the precision, recall, F1 score and support of each class.
+///
+/// The classes are every label in `y_true` or `y_pred`, in sorted order. If `label_names` is
+/// given it names these classes in the same order, otherwise the labels themselves are used.
+/// Metrics that divide by zero are reported as 0.
+///
+/// # Example
+/// ```rust
+/// use rml::metrics::classification_report;
+///
+/// let names = vec![String::from("cat"), String::from("dog")];
+/// let report = classification_report(&[0, 0, 1, 1], &[0, 1, 1, 1], Some(&names)).unwrap();
+///
+/// assert_eq!(report.classes[1].name, "dog");
+/// assert_eq!(report.classes[1].recall, 1.0);
+/// assert_eq!(report.accuracy, 0.75);
+/// println!("{}", report);
+/// ```
+pub fn classification_report<L>(
+    y_true: &[L],
+    y_pred: &[L],
+    label_names: Option<&[String]>,
+) -> Result<ClassificationReport>
+where
+    L: Ord + Clone + fmt::Display,
+{
+    let cm = ConfusionMatrix::new(y_true, y_pred)?;
+    let names: Vec<String> = match label_names {
+        Some(names) if names.len() != cm.labels().len() => {
+            return Err(Error::ShapeMismatch(format!(
+                "There are {} classes but {} label names.",
+                cm.labels().len(),
+                names.len()
+            )))
+        }
+        Some(names) => names.to_vec(),
+        None => cm.labels().iter().map(|l| l.to_string()).collect(),
+    };
+
+    let classes: Vec<ClassMetrics> = names
+        .into_iter()
+        .zip(cm.precision())
+        .zip(cm.recall())
+        .enumerate()
+        .map(|(i, ((name, precision), recall))| ClassMetrics {
+            name,
+            precision,
+            recall,
+            f1: f1(precision, recall),
+            support: cm.support(i),
+        })
+        .collect();
+
+    let total = cm.n_samples();
+    let macro_avg = average(&classes, "macro avg", |_| 1.0 / classes.len() as f64);
+    let weighted_avg = average(&classes, "weighted avg", |c| {
+        if total == 0 {
+            0.0
+        } else {
+            c.support as f64 / total as f64
+        }
+    });
+
+    Ok(ClassificationReport {
+        classes,
+        accuracy: cm.accuracy(),
+        macro_avg,
+        weighted_avg,
+    })
+}
+
+/// The harmonic mean of precision and recall, or 0 if both are 0.
+fn f1(precision: f64, recall: f64) -> f64 {
+    if precision + recall == 0.0 {
+        0.0
+    } else {
+        2.0 * precision * recall / (precision + recall)
+    }
+}
+
+/// Average the class metrics with the weight given by `weight`.
+fn average<F>(classes: &[ClassMetrics], name: &str, weight: F) -> ClassMetrics
+where
+    F: Fn(&ClassMetrics) -> f64,
+{
+    let sum = |metric: fn(&ClassMetrics) -> f64| -> f64 {
+        classes.iter().map(|c| weight(c) * metric(c)).sum()
+    };
+    ClassMetrics {
+        name: String::from(name),
+        precision: sum(|c| c.precision),
+        recall: sum(|c| c.recall),
+        f1: sum(|c| c.f1),
+        support: classes.iter().map(|c| c.support).sum(),
+    }
+}
+
+impl fmt::Display for ClassificationReport {
+    /// Formats the report as a table with two decimal places.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self
+            .classes
+            .iter()
+            .map(|c| c.name.len())
+            .chain(Some("weighted avg".len()))
+            .max()
+            .unwrap_or(0);
+        let row = |f: &mut fmt::Formatter, c: &ClassMetrics| {
+            writeln!(
+                f,
+                "{:>width$} {:>9.2} {:>9.2} {:>9.2} {:>9}",
+                c.name,
+                c.precision,
+                c.recall,
+                c.f1,
+                c.support,
+                width = width
+            )
+        };
+
+        writeln!(
+            f,
+            "{:>width$} {:>9} {:>9} {:>9} {:>9}",
+            "",
+            "precision",
+            "recall",
+            "f1-score",
+            "support",
+            width = width
+        )?;
+        writeln!(f)?;
+        for c in &self.classes {
+            row(f, c)?;
+        }
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:>width$} {:>9} {:>9} {:>9.2} {:>9}",
+            "accuracy",
+            "",
+            "",
+            self.accuracy,
+            self.macro_avg.support,
+            width = width
+        )?;
+        row(f, &self.macro_avg)?;
+        row(f, &self.weighted_avg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classification_report_test() {
+        let report = classification_report(&[0, 0, 0, 1], &[0, 0, 1, 1], None).unwrap();
+
+        assert_eq!(report.classes[0].name, "0");
+        assert_eq!(report.classes[0].precision, 1.0);
+        assert_eq!(report.classes[0].recall, 2.0 / 3.0);
+        assert_eq!(report.classes[0].f1, 0.8);
+        assert_eq!(report.classes[1].support, 1);
+        assert_eq!(report.macro_avg.recall, (2.0 / 3.0 + 1.0) / 2.0);
+        assert_eq!(report.weighted_avg.recall, 0.75);
+        assert_eq!(report.weighted_avg.support, 4);
+
+        let table = report.to_string();
+        assert!(table.contains("precision"));
+        assert!(table
+            .lines()
+            .any(|l| l.trim_start().starts_with("macro avg")));
+    }
+
+    #[test]
+    fn label_names_test() {
+        let names = vec![String::from("a")];
+        assert!(classification_report(&[0, 1], &[0, 1], Some(&names)).is_err());
+
+        let report = classification_report(&[0], &[1], None).unwrap();
+        assert_eq!(report.classes[1].f1, 0.0);
+        assert_eq!(report.accuracy, 0.0);
+    }
+}
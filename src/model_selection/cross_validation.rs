@@ -0,0 +1,134 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+//! Scoring estimators with cross-validation.
+
+use super::KFold;
+use crate::error::{check_lengths, Result};
+use crate::parallel;
+use crate::traits::{Fit, Predict};
+use rayon::prelude::*;
+
+/// Score an estimator on each fold of `cv`.
+///
+/// For each fold a clone of `estimator` is fit to the training samples and `scorer` is called
+/// with the true and predicted targets of the validation samples. Folds are evaluated in
+/// parallel, and the scores are returned in fold order. Any metric in `rml::metrics` with the
+/// signature `(y_true, y_pred) -> Result<f64, _>` can be used as the scorer.
+///
+/// # Example
+/// ```rust
+/// use rml::knn::KNN;
+/// use rml::metrics::accuracy;
+/// use rml::model_selection::{cross_val_score, KFold};
+///
+/// let x: Vec<Vec<f64>> = (0..20).map(|i| vec![(i % 2) as f64]).collect();
+/// let y: Vec<i32> = (0..20).map(|i| i % 2).collect();
+/// let knn = KNN::unfitted(3, None, None);
+///
+/// let scores = cross_val_score(&knn, &x, &y, &KFold::new(4).unwrap(), accuracy).unwrap();
+/// assert_eq!(scores, vec![1.0; 4]);
+/// ```
+pub fn cross_val_score<E, T, U, S>(
+    estimator: &E,
+    x: &[Vec<T>],
+    y: &[U],
+    cv: &KFold,
+    scorer: S,
+) -> Result<Vec<f64>>
+where
+    E: Fit<T, U> + Predict<T, U> + Clone + Sync,
+    T: Clone + Sync,
+    U: Clone + Sync,
+    S: Fn(&[U], &[U]) -> Result<f64> + Sync,
+{
+    check_lengths("Features and targets", x.len(), y.len())?;
+
+    let folds = cv.split(x.len())?;
+    parallel::install(None, || {
+        folds
+            .par_iter()
+            .map(|(train, validation)| {
+                let take_x =
+                    |rows: &[usize]| rows.iter().map(|&i| x[i].clone()).collect::<Vec<_>>();
+                let take_y =
+                    |rows: &[usize]| rows.iter().map(|&i| y[i].clone()).collect::<Vec<_>>();
+
+                let mut model = estimator.clone();
+                model.fit(&take_x(train), &take_y(train))?;
+                let pred = model.predict(&take_x(validation))?;
+                scorer(&take_y(validation), &pred)
+            })
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::regression::mean_absolute_error;
+
+    /// Predicts the mean of the training targets.
+    #[derive(Clone)]
+    struct Mean(f64);
+
+    impl Fit<f64, f64> for Mean {
+        fn fit(&mut self, _: &[Vec<f64>], y: &[f64]) -> Result<()> {
+            self.0 = y.iter().sum::<f64>() / y.len() as f64;
+            Ok(())
+        }
+    }
+
+    impl Predict<f64, f64> for Mean {
+        fn predict(&self, x: &[Vec<f64>]) -> Result<Vec<f64>> {
+            Ok(vec![self.0; x.len()])
+        }
+    }
+
+    #[test]
+    fn cross_val_score_test() {
+        let x = vec![vec![0.0]; 4];
+        let y = vec![0.0, 2.0, 4.0, 6.0];
+        let scores = cross_val_score(
+            &Mean(0.0),
+            &x,
+            &y,
+            &KFold::new(2).unwrap(),
+            mean_absolute_error,
+        );
+
+        assert_eq!(scores.unwrap(), vec![4.0, 4.0]);
+        assert!(cross_val_score(
+            &Mean(0.0),
+            &x,
+            &y[..3],
+            &KFold::new(2).unwrap(),
+            mean_absolute_error
+        )
+        .is_err());
+    }
+
+    #[cfg(feature = "datasets")]
+    #[test]
+    fn knn_iris_test() {
+        let iris = crate::datasets::load_iris();
+        let knn = crate::knn::KNN::unfitted(5, None, None);
+        let cv = KFold::new(5).unwrap().shuffle(7);
+        let scores = cross_val_score(&knn, &iris.x, &iris.y, &cv, crate::metrics::accuracy);
+
+        assert!(scores.unwrap().iter().all(|&s| s > 0.85));
+    }
+}
@@ -0,0 +1,194 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+//! Learning curves: scores as a function of the training set size.
+
+use super::KFold;
+use crate::error::{check_lengths, Error, Result};
+use crate::parallel;
+use crate::traits::{Fit, Predict};
+use rayon::prelude::*;
+
+/// The scores of an estimator trained on one training set size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LearningCurvePoint {
+    /// The number of training samples.
+    pub train_size: usize,
+    /// The score on the training samples, for each fold.
+    pub train_scores: Vec<f64>,
+    /// The score on the validation samples, for each fold.
+    pub validation_scores: Vec<f64>,
+}
+
+impl LearningCurvePoint {
+    /// The mean training score.
+    pub fn mean_train_score(&self) -> f64 {
+        self.train_scores.iter().sum::<f64>() / self.train_scores.len() as f64
+    }
+
+    /// The mean validation score.
+    pub fn mean_validation_score(&self) -> f64 {
+        self.validation_scores.iter().sum::<f64>() / self.validation_scores.len() as f64
+    }
+}
+
+/// Train an estimator on increasing fractions of the training set and score it on the training
+/// and validation samples of each fold of `cv`.
+///
+/// `train_sizes` are fractions in `(0, 1]` of each fold's training samples; the first samples of
+/// the fold are used, so shuffle `cv` if the data is ordered. A validation score that keeps
+/// rising with the size suggests more data would help, while low training and validation
+/// scores suggest the model lacks capacity. Fits are run in parallel.
+///
+/// # Example
+/// ```rust
+/// use rml::knn::KNN;
+/// use rml::metrics::accuracy;
+/// use rml::model_selection::{learning_curve, KFold};
+///
+/// let x: Vec<Vec<f64>> = (0..40).map(|i| vec![(i % 2) as f64]).collect();
+/// let y: Vec<i32> = (0..40).map(|i| i % 2).collect();
+/// let knn = KNN::unfitted(3, None, None);
+///
+/// let curve = learning_curve(&knn, &x, &y, &[0.25, 0.5, 1.0], &KFold::new(4).unwrap(), accuracy).unwrap();
+/// for point in &curve {
+///     println!("{} {} {}", point.train_size, point.mean_train_score(), point.mean_validation_score());
+/// }
+/// ```
+pub fn learning_curve<E, T, U, S>(
+    estimator: &E,
+    x: &[Vec<T>],
+    y: &[U],
+    train_sizes: &[f64],
+    cv: &KFold,
+    scorer: S,
+) -> Result<Vec<LearningCurvePoint>>
+where
+    E: Fit<T, U> + Predict<T, U> + Clone + Sync,
+    T: Clone + Sync,
+    U: Clone + Sync,
+    S: Fn(&[U], &[U]) -> Result<f64> + Sync,
+{
+    check_lengths("Features and targets", x.len(), y.len())?;
+    if let Some(f) = train_sizes.iter().find(|&&f| !(f > 0.0 && f <= 1.0)) {
+        return Err(Error::InvalidInput(format!(
+            "Training size {} is not in (0, 1].",
+            f
+        )));
+    }
+    let folds = cv.split(x.len())?;
+    // Every fold has a training set of the same size, give or take one sample.
+    let n_train = folds
+        .iter()
+        .map(|(train, _)| train.len())
+        .min()
+        .unwrap_or(0);
+    let sizes: Vec<usize> = train_sizes
+        .iter()
+        .map(|f| ((f * n_train as f64).round() as usize).max(1))
+        .collect();
+
+    let jobs: Vec<(usize, usize)> = (0..sizes.len())
+        .flat_map(|s| (0..folds.len()).map(move |f| (s, f)))
+        .collect();
+    let scores: Vec<(f64, f64)> = parallel::install(None, || {
+        jobs.par_iter()
+            .map(|&(s, f)| {
+                let take_x =
+                    |rows: &[usize]| rows.iter().map(|&i| x[i].clone()).collect::<Vec<_>>();
+                let take_y =
+                    |rows: &[usize]| rows.iter().map(|&i| y[i].clone()).collect::<Vec<_>>();
+                let (train, validation) = &folds[f];
+                let train = &train[..sizes[s]];
+                let score = |model: &E, rows: &[usize]| -> Result<f64> {
+                    scorer(&take_y(rows), &model.predict(&take_x(rows))?)
+                };
+
+                let mut model = estimator.clone();
+                model.fit(&take_x(train), &take_y(train))?;
+                Ok((score(&model, train)?, score(&model, validation)?))
+            })
+            .collect::<Result<_>>()
+    })?;
+
+    Ok(sizes
+        .iter()
+        .enumerate()
+        .map(|(s, &train_size)| {
+            let fold_scores = &scores[s * folds.len()..(s + 1) * folds.len()];
+            LearningCurvePoint {
+                train_size,
+                train_scores: fold_scores.iter().map(|p| p.0).collect(),
+                validation_scores: fold_scores.iter().map(|p| p.1).collect(),
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::regression::mean_absolute_error;
+
+    /// Predicts the mean of the training targets.
+    #[derive(Clone)]
+    struct Mean(f64);
+
+    impl Fit<f64, f64> for Mean {
+        fn fit(&mut self, _: &[Vec<f64>], y: &[f64]) -> Result<()> {
+            self.0 = y.iter().sum::<f64>() / y.len() as f64;
+            Ok(())
+        }
+    }
+
+    impl Predict<f64, f64> for Mean {
+        fn predict(&self, x: &[Vec<f64>]) -> Result<Vec<f64>> {
+            Ok(vec![self.0; x.len()])
+        }
+    }
+
+    #[test]
+    fn learning_curve_test() {
+        let x = vec![vec![0.0]; 4];
+        let y = vec![0.0, 2.0, 4.0, 6.0];
+        let curve = learning_curve(
+            &Mean(0.0),
+            &x,
+            &y,
+            &[0.5, 1.0],
+            &KFold::new(2).unwrap(),
+            mean_absolute_error,
+        )
+        .unwrap();
+
+        assert_eq!(curve[0].train_size, 1);
+        assert_eq!(curve[1].train_size, 2);
+        // Fold 0 trains on [4, 6] and validates on [0, 2]; fold 1 the reverse.
+        assert_eq!(curve[0].train_scores, vec![0.0, 0.0]);
+        assert_eq!(curve[0].validation_scores, vec![3.0, 5.0]);
+        assert_eq!(curve[1].train_scores, vec![1.0, 1.0]);
+        assert_eq!(curve[1].mean_validation_score(), 4.0);
+        assert!(learning_curve(
+            &Mean(0.0),
+            &x,
+            &y,
+            &[0.0],
+            &KFold::new(2).unwrap(),
+            mean_absolute_error
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,29 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+//! Tools for splitting data and evaluating estimators on held-out samples.
+
+mod cross_validation;
+mod learning_curve;
+mod randomized;
+mod search;
+mod split;
+
+pub use cross_validation::cross_val_score;
+pub use learning_curve::{learning_curve, LearningCurvePoint};
+pub use randomized::{Distribution, RandomizedSearchCV};
+pub use search::{GridSearchCV, Param, ParameterGrid, Params, SearchResult};
+pub use split::{Fold, KFold};
@@ -0,0 +1,277 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+//! Randomized hyperparameter search.
+
+use super::search::{evaluate, format_results, refit_best};
+use super::{KFold, Param, Params, SearchResult};
+use crate::error::{Error, Result};
+use crate::math::random::Rng;
+use crate::observer::FitObserver;
+use crate::traits::{Fit, Predict, PredictProba};
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// Where the values of a hyperparameter are sampled from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Distribution {
+    /// An integer in `[low, high]`, uniformly.
+    IntRange(i64, i64),
+    /// A real number in `[low, high)`, uniformly.
+    Uniform(f64, f64),
+    /// A real number in `[low, high)` whose logarithm is uniform, for scales such as
+    /// regularization strengths. Both bounds must be positive.
+    LogUniform(f64, f64),
+    /// One of the given values, uniformly.
+    Choice(Vec<Param>),
+}
+
+impl Distribution {
+    /// Draw a value.
+    fn sample(&self, rng: &mut Rng) -> Result<Param> {
+        match self {
+            Distribution::IntRange(low, high) if low <= high => {
+                let span = (high - low) as u64 + 1;
+                Ok(Param::Int(low + rng.below(span as usize) as i64))
+            }
+            Distribution::Uniform(low, high) if low <= high => {
+                Ok(Param::Float(low + rng.next_f64() * (high - low)))
+            }
+            Distribution::LogUniform(low, high) if 0.0 < *low && low <= high => {
+                let (low, high) = (low.ln(), high.ln());
+                Ok(Param::Float((low + rng.next_f64() * (high - low)).exp()))
+            }
+            Distribution::Choice(values) if !values.is_empty() => {
+                Ok(values[rng.below(values.len())].clone())
+            }
+            d => Err(Error::InvalidInput(format!("Cannot sample from {:?}.", d))),
+        }
+    }
+}
+
+/// A search over `n_iter` combinations of hyperparameters sampled at random, for search spaces
+/// too large for a `ParameterGrid`.
+///
+/// Combinations are sampled with a fixed seed, so a search is reproducible. Otherwise it
+/// works like `GridSearchCV`.
+///
+/// # Example
+/// ```rust
+/// use rml::knn::KNN;
+/// use rml::metrics::accuracy;
+/// use rml::model_selection::{Distribution, KFold, Params, RandomizedSearchCV};
+///
+/// let x: Vec<Vec<f64>> = (0..30).map(|i| vec![(i % 3) as f64]).collect();
+/// let y: Vec<i32> = (0..30).map(|i| i % 3).collect();
+///
+/// let build = |p: &Params| Ok(KNN::unfitted(p.int("k")? as i32, None, None));
+/// let mut search = RandomizedSearchCV::new(build, KFold::new(3).unwrap(), accuracy, 4)
+///     .add("k", Distribution::IntRange(1, 9))
+///     .seed(42);
+/// search.fit(&x, &y).unwrap();
+///
+/// assert_eq!(search.results().len(), 4);
+/// ```
+pub struct RandomizedSearchCV<E, F, S> {
+    distributions: Vec<(String, Distribution)>,
+    build: F,
+    cv: KFold,
+    scorer: S,
+    n_iter: usize,
+    seed: Option<u64>,
+    observer: Option<Arc<dyn FitObserver>>,
+    results: Vec<SearchResult>,
+    best: Option<(usize, E)>,
+    estimator: PhantomData<E>,
+}
+
+impl<E, F, S> RandomizedSearchCV<E, F, S> {
+    /// Create a search that samples `n_iter` combinations, builds an estimator for each with
+    /// `build`, and scores it on the folds of `cv` with `scorer`. The seed defaults to the global
+    /// seed.
+    pub fn new(build: F, cv: KFold, scorer: S, n_iter: usize) -> RandomizedSearchCV<E, F, S> {
+        RandomizedSearchCV {
+            distributions: Vec::new(),
+            build,
+            cv,
+            scorer,
+            n_iter,
+            seed: None,
+            observer: None,
+            results: Vec::new(),
+            best: None,
+            estimator: PhantomData,
+        }
+    }
+
+    /// Report each scored combination to `observer`.
+    pub fn observer(mut self, observer: impl FitObserver + 'static) -> Self {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Add a hyperparameter and the distribution its values are sampled from.
+    pub fn add(mut self, name: &str, distribution: Distribution) -> Self {
+        self.distributions.push((String::from(name), distribution));
+        self
+    }
+
+    /// Set the seed used to sample combinations.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Sample the combinations to evaluate.
+    pub fn combinations(&self) -> Result<Vec<Params>> {
+        let mut rng = Rng::from_seed(self.seed);
+        (0..self.n_iter)
+            .map(|_| {
+                let mut params = Params::new();
+                for (name, distribution) in &self.distributions {
+                    params.set(name, distribution.sample(&mut rng)?);
+                }
+                Ok(params)
+            })
+            .collect()
+    }
+
+    /// Score the sampled combinations, then refit the best estimator on all of `x` and `y`.
+    pub fn fit<T, U>(&mut self, x: &[Vec<T>], y: &[U]) -> Result<()>
+    where
+        E: Fit<T, U> + Predict<T, U> + Clone + Sync,
+        F: Fn(&Params) -> Result<E> + Sync,
+        S: Fn(&[U], &[U]) -> Result<f64> + Sync,
+        T: Clone + Sync,
+        U: Clone + Sync,
+    {
+        self.results = evaluate(
+            self.combinations()?,
+            &self.build,
+            x,
+            y,
+            &self.cv,
+            &self.scorer,
+            self.observer.as_deref(),
+        )?;
+        self.best = Some(refit_best(&self.results, &self.build, x, y)?);
+        Ok(())
+    }
+
+    /// The scores of every sampled combination, in sampling order.
+    pub fn results(&self) -> &[SearchResult] {
+        &self.results
+    }
+
+    /// The hyperparameters with the highest mean score, once fit.
+    pub fn best_params(&self) -> Option<&Params> {
+        self.best.as_ref().map(|(i, _)| &self.results[*i].params)
+    }
+
+    /// The highest mean score, once fit.
+    pub fn best_score(&self) -> Option<f64> {
+        self.best.as_ref().map(|(i, _)| self.results[*i].mean_score)
+    }
+
+    /// The best estimator, refit on all of the data, once fit.
+    pub fn best_estimator(&self) -> Option<&E> {
+        self.best.as_ref().map(|(_, e)| e)
+    }
+}
+
+impl<E, F, S> fmt::Display for RandomizedSearchCV<E, F, S> {
+    /// Formats the results as a table ranked by mean score.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        format_results(f, &self.results)
+    }
+}
+
+impl<E, F, S, T, U> Predict<T, U> for RandomizedSearchCV<E, F, S>
+where
+    E: Predict<T, U>,
+{
+    /// Predict with the best estimator. Returns an error if the search has not been fit.
+    fn predict(&self, x: &[Vec<T>]) -> Result<Vec<U>> {
+        match self.best_estimator() {
+            Some(estimator) => estimator.predict(x),
+            None => Err(Error::NotFitted),
+        }
+    }
+}
+
+impl<E, F, S, T> PredictProba<T> for RandomizedSearchCV<E, F, S>
+where
+    E: PredictProba<T>,
+{
+    /// Predict probabilities with the best estimator. Returns an error if the search has not
+    /// been fit.
+    fn predict_proba(&self, x: &[Vec<T>]) -> Result<Vec<Vec<f64>>> {
+        match self.best_estimator() {
+            Some(estimator) => estimator.predict_proba(x),
+            None => Err(Error::NotFitted),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distribution_test() {
+        let mut rng = Rng::new(5);
+        for _ in 0..100 {
+            match Distribution::IntRange(-1, 1).sample(&mut rng).unwrap() {
+                Param::Int(v) => assert!((-1..=1).contains(&v)),
+                p => panic!("Expected an integer, got {:?}", p),
+            }
+            match Distribution::LogUniform(1e-3, 1e3)
+                .sample(&mut rng)
+                .unwrap()
+            {
+                Param::Float(v) => assert!((1e-3..1e3).contains(&v)),
+                p => panic!("Expected a real number, got {:?}", p),
+            }
+        }
+        assert!(Distribution::Uniform(1.0, 0.0).sample(&mut rng).is_err());
+        assert!(Distribution::LogUniform(0.0, 1.0).sample(&mut rng).is_err());
+        assert!(Distribution::Choice(vec![]).sample(&mut rng).is_err());
+    }
+
+    #[test]
+    fn combinations_test() {
+        let build =
+            |_: &Params| -> Result<crate::knn::KNN> { Err(Error::InvalidInput("unused".into())) };
+        let search = |seed| {
+            RandomizedSearchCV::<crate::knn::KNN, _, _>::new(
+                build,
+                KFold::new(2).unwrap(),
+                crate::metrics::accuracy::<i32>,
+                5,
+            )
+            .add("alpha", Distribution::Uniform(0.0, 1.0))
+            .add("kind", Distribution::Choice(vec!["a".into(), "b".into()]))
+            .seed(seed)
+        };
+        let combinations = search(1).combinations().unwrap();
+
+        assert_eq!(combinations.len(), 5);
+        assert!(combinations.iter().all(|p| p.float("alpha").is_ok()));
+        assert_eq!(combinations, search(1).combinations().unwrap());
+        assert_ne!(combinations, search(2).combinations().unwrap());
+    }
+}
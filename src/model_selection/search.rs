@@ -0,0 +1,545 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+//! Hyperparameter search by cross-validation.
+
+/*!
+A search evaluates an estimator for many combinations of hyperparameters with
+`cross_val_score`, then refits the best one on all of the data.
+
+Hyperparameters are passed to a `build` function as named `Params`, which creates the estimator.
+
+# Example
+```rust
+use rml::knn::KNN;
+use rml::math::distance::Distance;
+use rml::metrics::accuracy;
+use rml::model_selection::{GridSearchCV, KFold, ParameterGrid, Params};
+
+let x: Vec<Vec<f64>> = (0..30).map(|i| vec![(i % 3) as f64, (i % 2) as f64]).collect();
+let y: Vec<i32> = (0..30).map(|i| i % 3).collect();
+
+let grid = ParameterGrid::new()
+    .add("k", vec![1.into(), 3.into(), 5.into()])
+    .add("distance", vec!["euclidean".into(), "manhattan".into()]);
+let build = |p: &Params| {
+    let distance = match p.text("distance")? {
+        "manhattan" => Distance::Manhattan,
+        _ => Distance::Euclidean,
+    };
+    Ok(KNN::unfitted(p.int("k")? as i32, Some(distance), None))
+};
+
+let mut search = GridSearchCV::new(grid, build, KFold::new(3).unwrap().shuffle(0), accuracy);
+search.fit(&x, &y).unwrap();
+
+assert_eq!(search.results().len(), 6);
+println!("Best: {} ({})", search.best_params().unwrap(), search.best_score().unwrap());
+println!("{}", search);
+```
+!*/
+
+use super::{cross_val_score, KFold};
+use crate::error::{Error, Result};
+use crate::observer::{FitObserver, Progress};
+use crate::parallel;
+use crate::traits::{Fit, Predict, PredictProba};
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The value of a hyperparameter.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Param {
+    /// An integer, such as the number of neighbors.
+    Int(i64),
+    /// A real number, such as a regularization strength.
+    Float(f64),
+    /// A named option, such as a distance metric.
+    Text(String),
+}
+
+impl From<i64> for Param {
+    fn from(value: i64) -> Self {
+        Param::Int(value)
+    }
+}
+
+impl From<i32> for Param {
+    fn from(value: i32) -> Self {
+        Param::Int(value.into())
+    }
+}
+
+impl From<f64> for Param {
+    fn from(value: f64) -> Self {
+        Param::Float(value)
+    }
+}
+
+impl From<&str> for Param {
+    fn from(value: &str) -> Self {
+        Param::Text(String::from(value))
+    }
+}
+
+impl From<String> for Param {
+    fn from(value: String) -> Self {
+        Param::Text(value)
+    }
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Param::Int(v) => write!(f, "{}", v),
+            Param::Float(v) => write!(f, "{}", v),
+            Param::Text(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+/// A set of named hyperparameter values.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Params(BTreeMap<String, Param>);
+
+impl Params {
+    /// Create an empty set.
+    pub fn new() -> Params {
+        Params::default()
+    }
+
+    /// Set the value of a hyperparameter.
+    pub fn set(&mut self, name: &str, value: Param) {
+        self.0.insert(String::from(name), value);
+    }
+
+    /// The value of a hyperparameter, if it is set.
+    pub fn get(&self, name: &str) -> Option<&Param> {
+        self.0.get(name)
+    }
+
+    /// The value of an integer hyperparameter.
+    pub fn int(&self, name: &str) -> Result<i64> {
+        match self.get(name) {
+            Some(Param::Int(v)) => Ok(*v),
+            _ => Err(Error::InvalidInput(format!(
+                "No integer parameter named '{}'.",
+                name
+            ))),
+        }
+    }
+
+    /// The value of a real hyperparameter. Integers are converted.
+    pub fn float(&self, name: &str) -> Result<f64> {
+        match self.get(name) {
+            Some(Param::Float(v)) => Ok(*v),
+            Some(Param::Int(v)) => Ok(*v as f64),
+            _ => Err(Error::InvalidInput(format!(
+                "No real parameter named '{}'.",
+                name
+            ))),
+        }
+    }
+
+    /// The value of a named option.
+    pub fn text(&self, name: &str) -> Result<&str> {
+        match self.get(name) {
+            Some(Param::Text(v)) => Ok(v),
+            _ => Err(Error::InvalidInput(format!(
+                "No text parameter named '{}'.",
+                name
+            ))),
+        }
+    }
+
+    /// Iterate over the names and values, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Param)> {
+        self.0.iter()
+    }
+}
+
+impl fmt::Display for Params {
+    /// Formats the parameters as `name=value` pairs, e.g. `distance=manhattan, k=3`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let pairs: Vec<String> = self.iter().map(|(n, v)| format!("{}={}", n, v)).collect();
+        write!(f, "{}", pairs.join(", "))
+    }
+}
+
+/// Every combination of a list of values for each hyperparameter.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ParameterGrid {
+    params: Vec<(String, Vec<Param>)>,
+}
+
+impl ParameterGrid {
+    /// Create an empty grid, which has a single empty combination.
+    pub fn new() -> ParameterGrid {
+        ParameterGrid::default()
+    }
+
+    /// Add a hyperparameter and the values to try.
+    pub fn add(mut self, name: &str, values: Vec<Param>) -> Self {
+        self.params.push((String::from(name), values));
+        self
+    }
+
+    /// The number of combinations.
+    pub fn len(&self) -> usize {
+        self.params.iter().map(|(_, v)| v.len()).product()
+    }
+
+    /// Whether there are no combinations, because a hyperparameter has no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every combination of the values.
+    pub fn combinations(&self) -> Vec<Params> {
+        self.params
+            .iter()
+            .fold(vec![Params::new()], |combinations, (name, values)| {
+                combinations
+                    .iter()
+                    .flat_map(|params| {
+                        values.iter().map(move |value| {
+                            let mut params = params.clone();
+                            params.set(name, value.clone());
+                            params
+                        })
+                    })
+                    .collect()
+            })
+    }
+}
+
+/// The cross-validation scores of one combination of hyperparameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    /// The hyperparameters.
+    pub params: Params,
+    /// The score on each fold.
+    pub scores: Vec<f64>,
+    /// The mean of the fold scores.
+    pub mean_score: f64,
+    /// The standard deviation of the fold scores.
+    pub std_score: f64,
+}
+
+/// An exhaustive search over a `ParameterGrid`.
+///
+/// Every combination is scored with cross-validation, in parallel. Higher scores are better, so
+/// pass a negated metric when minimizing an error.
+pub struct GridSearchCV<E, F, S> {
+    grid: ParameterGrid,
+    build: F,
+    cv: KFold,
+    scorer: S,
+    observer: Option<Arc<dyn FitObserver>>,
+    results: Vec<SearchResult>,
+    best: Option<(usize, E)>,
+    estimator: PhantomData<E>,
+}
+
+impl<E, F, S> GridSearchCV<E, F, S> {
+    /// Create a search that builds an estimator with `build` for each combination in `grid`,
+    /// and scores it on the folds of `cv` with `scorer`.
+    pub fn new(grid: ParameterGrid, build: F, cv: KFold, scorer: S) -> GridSearchCV<E, F, S> {
+        GridSearchCV {
+            grid,
+            build,
+            cv,
+            scorer,
+            observer: None,
+            results: Vec::new(),
+            best: None,
+            estimator: PhantomData,
+        }
+    }
+
+    /// Report each scored combination to `observer`.
+    pub fn observer(mut self, observer: impl FitObserver + 'static) -> Self {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Score every combination, then refit the best estimator on all of `x` and `y`.
+    pub fn fit<T, U>(&mut self, x: &[Vec<T>], y: &[U]) -> Result<()>
+    where
+        E: Fit<T, U> + Predict<T, U> + Clone + Sync,
+        F: Fn(&Params) -> Result<E> + Sync,
+        S: Fn(&[U], &[U]) -> Result<f64> + Sync,
+        T: Clone + Sync,
+        U: Clone + Sync,
+    {
+        self.results = evaluate(
+            self.grid.combinations(),
+            &self.build,
+            x,
+            y,
+            &self.cv,
+            &self.scorer,
+            self.observer.as_deref(),
+        )?;
+        self.best = Some(refit_best(&self.results, &self.build, x, y)?);
+        Ok(())
+    }
+
+    /// The scores of every combination, in grid order.
+    pub fn results(&self) -> &[SearchResult] {
+        &self.results
+    }
+
+    /// The hyperparameters with the highest mean score, once fit.
+    pub fn best_params(&self) -> Option<&Params> {
+        self.best.as_ref().map(|(i, _)| &self.results[*i].params)
+    }
+
+    /// The highest mean score, once fit.
+    pub fn best_score(&self) -> Option<f64> {
+        self.best.as_ref().map(|(i, _)| self.results[*i].mean_score)
+    }
+
+    /// The best estimator, refit on all of the data, once fit.
+    pub fn best_estimator(&self) -> Option<&E> {
+        self.best.as_ref().map(|(_, e)| e)
+    }
+}
+
+impl<E, F, S> fmt::Display for GridSearchCV<E, F, S> {
+    /// Formats the results as a table ranked by mean score.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        format_results(f, &self.results)
+    }
+}
+
+/// Score each combination of hyperparameters with cross-validation, in parallel.
+pub(super) fn evaluate<E, F, S, T, U>(
+    combinations: Vec<Params>,
+    build: &F,
+    x: &[Vec<T>],
+    y: &[U],
+    cv: &KFold,
+    scorer: &S,
+    observer: Option<&dyn FitObserver>,
+) -> Result<Vec<SearchResult>>
+where
+    E: Fit<T, U> + Predict<T, U> + Clone + Sync,
+    F: Fn(&Params) -> Result<E> + Sync,
+    S: Fn(&[U], &[U]) -> Result<f64> + Sync,
+    T: Clone + Sync,
+    U: Clone + Sync,
+{
+    if combinations.is_empty() {
+        return Err(Error::InvalidInput(
+            "There are no hyperparameter combinations to search.".into(),
+        ));
+    }
+    // Instant::now panics on wasm32-unknown-unknown, so only time searches that are observed.
+    let start = observer.map(|_| Instant::now());
+    let total = combinations.len();
+    let completed = AtomicUsize::new(0);
+    parallel::install(None, || {
+        combinations
+            .into_par_iter()
+            .map(|params| {
+                let estimator = build(&params).map_err(|e| e.context(&params))?;
+                let scores = cross_val_score(&estimator, x, y, cv, scorer)
+                    .map_err(|e| e.context(&params))?;
+                let mean_score = scores.iter().sum::<f64>() / scores.len() as f64;
+                let variance = scores.iter().map(|s| (s - mean_score).powi(2)).sum::<f64>()
+                    / scores.len() as f64;
+                if let Some(observer) = observer {
+                    let iteration = completed.fetch_add(1, Ordering::Relaxed) + 1;
+                    observer.on_iteration(&Progress {
+                        iteration,
+                        total: Some(total),
+                        loss: None,
+                        score: Some(mean_score),
+                        samples: iteration * x.len(),
+                        elapsed: start.map_or(Duration::ZERO, |s| s.elapsed()),
+                    });
+                }
+                Ok(SearchResult {
+                    params,
+                    scores,
+                    mean_score,
+                    std_score: variance.sqrt(),
+                })
+            })
+            .collect()
+    })
+}
+
+/// Find the result with the highest mean score (the first on ties), and refit its estimator on
+/// all of the data.
+pub(super) fn refit_best<E, F, T, U>(
+    results: &[SearchResult],
+    build: &F,
+    x: &[Vec<T>],
+    y: &[U],
+) -> Result<(usize, E)>
+where
+    E: Fit<T, U>,
+    F: Fn(&Params) -> Result<E>,
+{
+    let best = (0..results.len()).fold(0, |best, i| {
+        if results[i].mean_score > results[best].mean_score {
+            i
+        } else {
+            best
+        }
+    });
+    let mut estimator = build(&results[best].params)?;
+    estimator.fit(x, y)?;
+    Ok((best, estimator))
+}
+
+/// Write search results as a table ranked by mean score.
+pub(super) fn format_results(f: &mut fmt::Formatter, results: &[SearchResult]) -> fmt::Result {
+    let mut order: Vec<usize> = (0..results.len()).collect();
+    order.sort_by(|&a, &b| {
+        results[b]
+            .mean_score
+            .partial_cmp(&results[a].mean_score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    write!(f, "{:>4} {:>10} {:>10}  params", "rank", "mean", "std")?;
+    for (rank, &i) in order.iter().enumerate() {
+        let r = &results[i];
+        write!(
+            f,
+            "\n{:>4} {:>10.4} {:>10.4}  {}",
+            rank + 1,
+            r.mean_score,
+            r.std_score,
+            r.params
+        )?;
+    }
+    Ok(())
+}
+
+impl<E, F, S, T, U> Predict<T, U> for GridSearchCV<E, F, S>
+where
+    E: Predict<T, U>,
+{
+    /// Predict with the best estimator. Returns an error if the search has not been fit.
+    fn predict(&self, x: &[Vec<T>]) -> Result<Vec<U>> {
+        match self.best_estimator() {
+            Some(estimator) => estimator.predict(x),
+            None => Err(Error::NotFitted),
+        }
+    }
+}
+
+impl<E, F, S, T> PredictProba<T> for GridSearchCV<E, F, S>
+where
+    E: PredictProba<T>,
+{
+    /// Predict probabilities with the best estimator. Returns an error if the search has not
+    /// been fit.
+    fn predict_proba(&self, x: &[Vec<T>]) -> Result<Vec<Vec<f64>>> {
+        match self.best_estimator() {
+            Some(estimator) => estimator.predict_proba(x),
+            None => Err(Error::NotFitted),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parameter_grid_test() {
+        let grid = ParameterGrid::new()
+            .add("a", vec![1.into(), 2.into()])
+            .add("b", vec!["x".into(), "y".into(), "z".into()]);
+        let combinations = grid.combinations();
+
+        assert_eq!(grid.len(), 6);
+        assert_eq!(combinations.len(), 6);
+        assert_eq!(combinations[4].to_string(), "a=2, b=y");
+        assert_eq!(combinations[4].int("a").unwrap(), 2);
+        assert_eq!(combinations[4].float("a").unwrap(), 2.0);
+        assert!(combinations[4].text("a").is_err());
+        assert_eq!(ParameterGrid::new().combinations(), vec![Params::new()]);
+        assert!(ParameterGrid::new().add("a", vec![]).is_empty());
+    }
+
+    #[cfg(feature = "datasets")]
+    #[test]
+    fn knn_grid_search_test() {
+        use crate::knn::KNN;
+        use crate::math::distance::Distance;
+
+        let iris = crate::datasets::load_iris();
+        let grid = ParameterGrid::new()
+            .add("k", vec![1.into(), 7.into(), 101.into()])
+            .add("distance", vec!["euclidean".into(), "manhattan".into()]);
+        let build = |p: &Params| {
+            let distance = match p.text("distance")? {
+                "manhattan" => Distance::Manhattan,
+                _ => Distance::Euclidean,
+            };
+            Ok(KNN::unfitted(p.int("k")? as i32, Some(distance), None))
+        };
+        let cv = KFold::new(5).unwrap().shuffle(3);
+        let mut search = GridSearchCV::new(grid, build, cv, crate::metrics::accuracy);
+        search.fit(&iris.x, &iris.y).unwrap();
+
+        assert_eq!(search.results().len(), 6);
+        assert!(search.best_score().unwrap() > 0.9);
+        assert_ne!(search.best_params().unwrap().int("k").unwrap(), 101);
+        assert_eq!(search.best_estimator().unwrap().x.n_rows(), 150);
+        assert_eq!(search.to_string().lines().count(), 7);
+        assert_eq!(search.predict(&iris.x[..2]).unwrap(), vec![0, 0]);
+    }
+    #[test]
+    fn observer_test() {
+        use crate::knn::KNN;
+        use std::sync::Mutex;
+
+        let x: Vec<Vec<f64>> = (0..20).map(|i| vec![(i % 2) as f64]).collect();
+        let y: Vec<i32> = (0..20).map(|i| i % 2).collect();
+        let grid = ParameterGrid::new().add("k", vec![1.into(), 3.into(), 5.into()]);
+        let build = |p: &Params| Ok(KNN::unfitted(p.int("k")? as i32, None, None));
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let events = Arc::clone(&seen);
+        let mut search = GridSearchCV::new(
+            grid,
+            build,
+            KFold::new(2).unwrap(),
+            crate::metrics::accuracy,
+        )
+        .observer(move |p: &Progress| events.lock().unwrap().push(p.clone()));
+        search.fit(&x, &y).unwrap();
+
+        let mut seen = seen.lock().unwrap().clone();
+        seen.sort_by_key(|p| p.iteration);
+        assert_eq!(seen.len(), 3);
+        assert_eq!(seen[2].iteration, 3);
+        assert_eq!(seen[2].total, Some(3));
+        assert_eq!(seen[2].samples, 60);
+        assert_eq!(seen[0].score, Some(1.0));
+    }
+}
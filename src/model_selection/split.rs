@@ -0,0 +1,129 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+//! Splitting sample indices into training and validation folds.
+
+use crate::error::{Error, Result};
+use crate::math::random::Rng;
+
+/// The training and validation sample indices of one fold.
+pub type Fold = (Vec<usize>, Vec<usize>);
+
+/// Splits samples into `n_splits` consecutive folds. Each fold is used once for validation
+/// while the others are used for training.
+///
+/// # Example
+/// ```rust
+/// use rml::model_selection::KFold;
+///
+/// let folds = KFold::new(3).unwrap().shuffle(42).split(10).unwrap();
+///
+/// assert_eq!(folds.len(), 3);
+/// assert_eq!(folds[0].0.len() + folds[0].1.len(), 10);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KFold {
+    n_splits: usize,
+    seed: Option<u64>,
+}
+
+impl KFold {
+    /// Create a splitter with `n_splits` folds that keeps the samples in order.
+    /// Returns an error if `n_splits` is less than 2.
+    pub fn new(n_splits: usize) -> Result<KFold> {
+        if n_splits < 2 {
+            return Err(Error::InvalidInput(format!(
+                "There must be at least 2 folds, not {}.",
+                n_splits
+            )));
+        }
+        Ok(KFold {
+            n_splits,
+            seed: None,
+        })
+    }
+
+    /// Shuffle the samples with a generator seeded by `seed` before splitting.
+    pub fn shuffle(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// The number of folds.
+    pub fn n_splits(&self) -> usize {
+        self.n_splits
+    }
+
+    /// Split `n_samples` samples into folds. The first `n_samples % n_splits` folds get one
+    /// extra validation sample. Returns an error if there are fewer samples than folds.
+    pub fn split(&self, n_samples: usize) -> Result<Vec<Fold>> {
+        if n_samples < self.n_splits {
+            return Err(Error::InvalidInput(format!(
+                "Cannot split {} samples into {} folds.",
+                n_samples, self.n_splits
+            )));
+        }
+        let mut order: Vec<usize> = (0..n_samples).collect();
+        if let Some(seed) = self.seed {
+            Rng::new(seed).shuffle(&mut order);
+        }
+
+        let mut start = 0;
+        Ok((0..self.n_splits)
+            .map(|i| {
+                let size = n_samples / self.n_splits + usize::from(i < n_samples % self.n_splits);
+                let end = start + size;
+                let fold = (
+                    order[..start]
+                        .iter()
+                        .chain(&order[end..])
+                        .cloned()
+                        .collect(),
+                    order[start..end].to_vec(),
+                );
+                start = end;
+                fold
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kfold_test() {
+        let folds = KFold::new(3).unwrap().split(7).unwrap();
+        let sizes: Vec<usize> = folds.iter().map(|(_, v)| v.len()).collect();
+
+        assert_eq!(sizes, vec![3, 2, 2]);
+        assert_eq!(folds[1], (vec![0, 1, 2, 5, 6], vec![3, 4]));
+        assert!(KFold::new(3).unwrap().split(2).is_err());
+        assert!(matches!(KFold::new(1), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn shuffle_test() {
+        let folds = KFold::new(2).unwrap().shuffle(1).split(10).unwrap();
+        let mut validation: Vec<usize> = folds.iter().flat_map(|(_, v)| v.clone()).collect();
+
+        assert_ne!(validation, (0..10).collect::<Vec<_>>());
+        validation.sort_unstable();
+        assert_eq!(validation, (0..10).collect::<Vec<_>>());
+        assert_eq!(folds, KFold::new(2).unwrap().shuffle(1).split(10).unwrap());
+    }
+}
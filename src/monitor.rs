@@ -0,0 +1,369 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Detecting drift between training data and new data.
+
+/*!
+A deployed model silently degrades when the data it sees drifts away from the data it was
+trained on. A `Profile` summarizes the training features, and optionally the model's predictions
+on them, compactly enough to store with the model. `Profile::check` compares an incoming batch
+against it and reports, per feature, the population stability index (PSI) and the
+Kolmogorov-Smirnov (KS) statistic, and the PSI of the predicted classes.
+
+As a rule of thumb, a PSI below 0.1 means no significant change, 0.1 to 0.2 a moderate change,
+and above 0.2 a significant change that warrants retraining.
+
+# Example
+```rust
+use rml::monitor::Profile;
+
+let train: Vec<Vec<f64>> = (0..100).map(|i| vec![i as f64, (i % 10) as f64]).collect();
+let profile = Profile::new(&train).unwrap();
+
+// The first feature has shifted, the second has not.
+let batch: Vec<Vec<f64>> = (0..100).map(|i| vec![i as f64 + 50.0, (i % 10) as f64]).collect();
+let report = profile.check(&batch, None).unwrap();
+
+assert_eq!(report.drifted(0.2), vec![0]);
+```
+!*/
+
+use crate::error::{Error, Result};
+
+/// The number of values kept per feature to estimate KS statistics.
+const SKETCH_SIZE: usize = 1000;
+/// The fraction used in place of empty bins, so the PSI stays finite.
+const MIN_FRACTION: f64 = 1e-4;
+
+/// The population stability index of `actual` against `expected`, with bins at the quantiles of
+/// `expected`. Returns an error if either sample is empty or `n_bins` is 0.
+pub fn population_stability_index(expected: &[f64], actual: &[f64], n_bins: usize) -> Result<f64> {
+    if expected.is_empty() || actual.is_empty() {
+        return Err(Error::InvalidInput("Cannot compare empty samples.".into()));
+    }
+    if n_bins == 0 {
+        return Err(Error::InvalidInput("There must be at least 1 bin.".into()));
+    }
+    let profile = FeatureProfile::new(expected.to_vec(), n_bins);
+    Ok(psi(&profile.expected, &profile.fractions(actual)))
+}
+
+/// The two-sample Kolmogorov-Smirnov statistic: the largest difference between the empirical
+/// distribution functions of `a` and `b`. Returns an error if either sample is empty.
+pub fn ks_statistic(a: &[f64], b: &[f64]) -> Result<f64> {
+    if a.is_empty() || b.is_empty() {
+        return Err(Error::InvalidInput("Cannot compare empty samples.".into()));
+    }
+    Ok(ks_sorted(&sorted(a.to_vec()), &sorted(b.to_vec())))
+}
+
+/// A summary of training data to compare new data against.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    features: Vec<FeatureProfile>,
+    predictions: Option<Vec<f64>>,
+}
+
+/// The drift of one feature.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeatureDrift {
+    /// The population stability index.
+    pub psi: f64,
+    /// The Kolmogorov-Smirnov statistic.
+    pub ks: f64,
+}
+
+/// The drift of a batch from a `Profile`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DriftReport {
+    /// The drift of each feature.
+    pub features: Vec<FeatureDrift>,
+    /// The population stability index of the predicted classes, if they were compared.
+    pub prediction_psi: Option<f64>,
+}
+
+impl DriftReport {
+    /// The indices of the features whose PSI is above `threshold`.
+    pub fn drifted(&self, threshold: f64) -> Vec<usize> {
+        self.features
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| f.psi > threshold)
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+impl Profile {
+    /// Profile each feature of `x`, with PSI bins at its deciles.
+    /// Returns an error if `x` is empty or its samples have different dimensions.
+    pub fn new(x: &[Vec<f64>]) -> Result<Profile> {
+        Profile::with_bins(x, 10)
+    }
+
+    /// Profile each feature of `x`, with `n_bins` PSI bins at its quantiles.
+    /// Returns an error if `x` is empty, its samples have different dimensions, or `n_bins` is 0.
+    pub fn with_bins(x: &[Vec<f64>], n_bins: usize) -> Result<Profile> {
+        if n_bins == 0 {
+            return Err(Error::InvalidInput("There must be at least 1 bin.".into()));
+        }
+        let n_features = check_samples(x, None)?;
+        Ok(Profile {
+            features: (0..n_features)
+                .map(|j| FeatureProfile::new(x.iter().map(|row| row[j]).collect(), n_bins))
+                .collect(),
+            predictions: None,
+        })
+    }
+
+    /// Also profile the classes a model predicts for the training data, so that batches can be
+    /// checked for prediction drift. Classes must be in `0..n_classes`.
+    pub fn predictions(mut self, predictions: &[i32], n_classes: usize) -> Result<Self> {
+        self.predictions = Some(class_fractions(predictions, n_classes)?);
+        Ok(self)
+    }
+
+    /// The number of features profiled.
+    pub fn n_features(&self) -> usize {
+        self.features.len()
+    }
+
+    /// Compare a batch, and optionally the classes predicted for it, against the profile.
+    /// Returns an error if the batch is empty or has a different number of features, or if
+    /// predictions are given but were not profiled.
+    pub fn check(&self, x: &[Vec<f64>], predictions: Option<&[i32]>) -> Result<DriftReport> {
+        check_samples(x, Some(self.features.len()))?;
+        let features = self
+            .features
+            .iter()
+            .enumerate()
+            .map(|(j, profile)| {
+                let column = sorted(x.iter().map(|row| row[j]).collect());
+                FeatureDrift {
+                    psi: psi(&profile.expected, &profile.fractions(&column)),
+                    ks: ks_sorted(&profile.sketch, &column),
+                }
+            })
+            .collect();
+        let prediction_psi = match (predictions, &self.predictions) {
+            (None, _) => None,
+            (Some(predictions), Some(expected)) => Some(psi(
+                expected,
+                &class_fractions(predictions, expected.len())?,
+            )),
+            (Some(_), None) => {
+                return Err(Error::InvalidInput(
+                    "The profile has no predictions to compare against.".into(),
+                ))
+            }
+        };
+        Ok(DriftReport {
+            features,
+            prediction_psi,
+        })
+    }
+}
+
+/// The profile of one feature.
+#[derive(Debug, Clone, PartialEq)]
+struct FeatureProfile {
+    /// Evenly spaced order statistics of the training values.
+    sketch: Vec<f64>,
+    /// The inner edges of the PSI bins between the smallest and largest training values.
+    edges: Vec<f64>,
+    /// The smallest and largest training values. Values outside them have their own bins.
+    range: (f64, f64),
+    /// The fraction of training values in each bin.
+    expected: Vec<f64>,
+}
+
+impl FeatureProfile {
+    fn new(values: Vec<f64>, n_bins: usize) -> FeatureProfile {
+        let values = sorted(values);
+        let quantile = |q: f64| values[((values.len() - 1) as f64 * q).round() as usize];
+        let mut edges: Vec<f64> = (1..n_bins)
+            .map(|i| quantile(i as f64 / n_bins as f64))
+            .collect();
+        edges.dedup();
+        let sketch = if values.len() <= SKETCH_SIZE {
+            values.clone()
+        } else {
+            (0..SKETCH_SIZE)
+                .map(|i| quantile(i as f64 / (SKETCH_SIZE - 1) as f64))
+                .collect()
+        };
+        let mut profile = FeatureProfile {
+            sketch,
+            edges,
+            range: (values[0], values[values.len() - 1]),
+            expected: Vec::new(),
+        };
+        profile.expected = profile.fractions(&values);
+        profile
+    }
+
+    /// The fraction of `values` in each bin: below the training range, in each quantile bin,
+    /// and above the training range.
+    fn fractions(&self, values: &[f64]) -> Vec<f64> {
+        let mut counts = vec![0usize; self.edges.len() + 3];
+        let last = counts.len() - 1;
+        for v in values {
+            let bin = if *v < self.range.0 {
+                0
+            } else if *v > self.range.1 {
+                last
+            } else {
+                1 + self.edges.partition_point(|e| e <= v)
+            };
+            counts[bin] += 1;
+        }
+        counts
+            .iter()
+            .map(|&c| c as f64 / values.len() as f64)
+            .collect()
+    }
+}
+
+/// The population stability index of the bin fractions `actual` against `expected`.
+fn psi(expected: &[f64], actual: &[f64]) -> f64 {
+    expected
+        .iter()
+        .zip(actual)
+        .map(|(&e, &a)| {
+            let (e, a) = (e.max(MIN_FRACTION), a.max(MIN_FRACTION));
+            (a - e) * (a / e).ln()
+        })
+        .sum()
+}
+
+/// The KS statistic of two sorted samples.
+fn ks_sorted(a: &[f64], b: &[f64]) -> f64 {
+    let (mut i, mut j, mut max) = (0, 0, 0.0f64);
+    while i < a.len() && j < b.len() {
+        let v = if a[i].total_cmp(&b[j]).is_le() {
+            a[i]
+        } else {
+            b[j]
+        };
+        while i < a.len() && a[i].total_cmp(&v).is_le() {
+            i += 1;
+        }
+        while j < b.len() && b[j].total_cmp(&v).is_le() {
+            j += 1;
+        }
+        max = max.max((i as f64 / a.len() as f64 - j as f64 / b.len() as f64).abs());
+    }
+    max
+}
+
+/// The fraction of predictions of each class.
+fn class_fractions(predictions: &[i32], n_classes: usize) -> Result<Vec<f64>> {
+    if predictions.is_empty() {
+        return Err(Error::InvalidInput("There are no predictions.".into()));
+    }
+    let mut counts = vec![0usize; n_classes];
+    for &p in predictions {
+        match counts.get_mut(p as usize).filter(|_| p >= 0) {
+            Some(count) => *count += 1,
+            None => {
+                return Err(Error::InvalidInput(format!(
+                    "Class {} is not in 0..{}.",
+                    p, n_classes
+                )))
+            }
+        }
+    }
+    Ok(counts
+        .iter()
+        .map(|&c| c as f64 / predictions.len() as f64)
+        .collect())
+}
+
+/// Check that `x` is not empty and every sample has `n_features` features, or as many as the
+/// first sample. Returns the number of features.
+fn check_samples(x: &[Vec<f64>], n_features: Option<usize>) -> Result<usize> {
+    let n_features = match (n_features, x.first()) {
+        (_, None) => return Err(Error::InvalidInput("There are no samples.".into())),
+        (Some(n), _) => n,
+        (None, Some(first)) => first.len(),
+    };
+    match x.iter().find(|row| row.len() != n_features) {
+        Some(row) => Err(Error::ShapeMismatch(format!(
+            "Expected {} features but a sample has {}.",
+            n_features,
+            row.len()
+        ))),
+        None => Ok(n_features),
+    }
+}
+
+fn sorted(mut values: Vec<f64>) -> Vec<f64> {
+    values.sort_by(f64::total_cmp);
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ks_statistic_test() {
+        assert_eq!(
+            ks_statistic(&[1.0, 2.0, 3.0], &[1.0, 2.0, 3.0]).unwrap(),
+            0.0
+        );
+        assert_eq!(ks_statistic(&[1.0, 2.0], &[3.0, 4.0]).unwrap(), 1.0);
+        assert_eq!(
+            ks_statistic(&[1.0, 2.0, 3.0, 4.0], &[3.0, 4.0, 5.0, 6.0]).unwrap(),
+            0.5
+        );
+        assert!(ks_statistic(&[], &[1.0]).is_err());
+    }
+
+    #[test]
+    fn psi_test() {
+        let expected: Vec<f64> = (0..1000).map(|i| i as f64).collect();
+        let shifted: Vec<f64> = expected.iter().map(|v| v + 300.0).collect();
+
+        assert!(population_stability_index(&expected, &expected, 10).unwrap() < 1e-9);
+        assert!(population_stability_index(&expected, &shifted, 10).unwrap() > 0.2);
+        assert!(population_stability_index(&expected, &[], 10).is_err());
+    }
+
+    #[test]
+    fn profile_test() {
+        let train: Vec<Vec<f64>> = (0..2000).map(|i| vec![(i % 100) as f64, 1.0]).collect();
+        let predictions: Vec<i32> = (0..2000).map(|i| i % 2).collect();
+        let profile = Profile::new(&train)
+            .unwrap()
+            .predictions(&predictions, 2)
+            .unwrap();
+
+        let report = profile.check(&train, Some(&predictions)).unwrap();
+        assert!(report.drifted(0.01).is_empty());
+        assert!(report.features[0].ks < 0.01);
+        assert!(report.prediction_psi.unwrap() < 1e-9);
+
+        let batch = vec![vec![10.0, 2.0]; 50];
+        let report = profile.check(&batch, Some(&[1; 50])).unwrap();
+        assert_eq!(report.drifted(0.2), vec![0, 1]);
+        assert_eq!(report.features[1].ks, 1.0);
+        assert!(report.prediction_psi.unwrap() > 0.2);
+        assert!(profile.check(&[vec![1.0]], None).is_err());
+        assert!(profile.check(&batch, Some(&[2; 50])).is_err());
+    }
+}
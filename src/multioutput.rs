@@ -0,0 +1,162 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Multi-target regression and multi-label classification.
+
+/*!
+A `MultiOutputWrapper` fits one clone of a single-output estimator per target, so any estimator
+can predict several targets at once. Targets are rows of `Vec<U>` with one entry per target, such
+as the rows of the indicator matrix of a `preprocessing::label::MultiLabelBinarizer`.
+
+# Example
+```rust
+use rml::knn::KNN;
+use rml::multioutput::MultiOutputWrapper;
+use rml::traits::{Fit, Predict};
+
+let x = vec![vec![0.0], vec![1.0], vec![10.0], vec![11.0]];
+// Two labels per sample.
+let y = vec![vec![1, 0], vec![1, 1], vec![0, 1], vec![0, 0]];
+
+let mut model = MultiOutputWrapper::new(KNN::unfitted(1, None, None));
+model.fit(&x, &y).unwrap();
+
+assert_eq!(model.predict(&[vec![0.9], vec![10.2]]).unwrap(), vec![vec![1, 1], vec![0, 1]]);
+```
+!*/
+
+use crate::describe::{Describe, Summary};
+use crate::error::{check_lengths, Error, Result};
+use crate::traits::{Fit, Predict};
+
+/// Fits one clone of an estimator per target.
+#[derive(Debug, Clone)]
+pub struct MultiOutputWrapper<E> {
+    /// The unfitted estimator cloned for each target.
+    pub estimator: E,
+    estimators: Vec<E>,
+}
+
+impl<E> MultiOutputWrapper<E> {
+    /// Wrap an estimator.
+    pub fn new(estimator: E) -> MultiOutputWrapper<E> {
+        MultiOutputWrapper {
+            estimator,
+            estimators: Vec::new(),
+        }
+    }
+
+    /// The fitted estimator of each target.
+    pub fn estimators(&self) -> &[E] {
+        &self.estimators
+    }
+}
+
+impl<E, T, U> Fit<T, Vec<U>> for MultiOutputWrapper<E>
+where
+    E: Fit<T, U> + Clone,
+    U: Clone,
+{
+    /// Fit a clone of the estimator to each target.
+    /// Returns an error if the samples have different numbers of targets, or a fit fails.
+    fn fit(&mut self, x: &[Vec<T>], y: &[Vec<U>]) -> Result<()> {
+        check_lengths("Features and targets", x.len(), y.len())?;
+        let n_targets = y.first().map_or(0, Vec::len);
+        if let Some(row) = y.iter().find(|row| row.len() != n_targets) {
+            return Err(Error::ShapeMismatch(format!(
+                "Expected {} targets but a sample has {}.",
+                n_targets,
+                row.len()
+            )));
+        }
+        self.estimators = (0..n_targets)
+            .map(|j| {
+                let column: Vec<U> = y.iter().map(|row| row[j].clone()).collect();
+                let mut estimator = self.estimator.clone();
+                estimator
+                    .fit(x, &column)
+                    .map_err(|e| e.context(format!("Target {}", j)))?;
+                Ok(estimator)
+            })
+            .collect::<Result<_>>()?;
+        Ok(())
+    }
+}
+
+impl<E, T, U> Predict<T, Vec<U>> for MultiOutputWrapper<E>
+where
+    E: Predict<T, U>,
+{
+    /// Predict every target of each sample in `x`.
+    fn predict(&self, x: &[Vec<T>]) -> Result<Vec<Vec<U>>> {
+        if self.estimators.is_empty() {
+            return Err(Error::NotFitted);
+        }
+        let mut rows: Vec<Vec<U>> = (0..x.len())
+            .map(|_| Vec::with_capacity(self.estimators.len()))
+            .collect();
+        for estimator in &self.estimators {
+            for (row, prediction) in rows.iter_mut().zip(estimator.predict(x)?) {
+                row.push(prediction);
+            }
+        }
+        Ok(rows)
+    }
+}
+
+impl<E: Describe> Describe for MultiOutputWrapper<E> {
+    /// Summarize the wrapper, with the fitted estimator of each target as its parts, or the
+    /// unfitted estimator if it is not fitted.
+    fn describe(&self) -> Summary {
+        let summary = Summary::new("MultiOutputWrapper").param("targets", self.estimators.len());
+        if self.estimators.is_empty() {
+            return summary.part(self.estimator.describe());
+        }
+        self.estimators
+            .iter()
+            .fold(summary, |summary, e| summary.part(e.describe()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knn::KNN;
+
+    #[test]
+    fn multi_output_test() {
+        let x = vec![vec![0.0], vec![1.0], vec![5.0]];
+        let y = vec![vec![0, 1, 0], vec![0, 1, 1], vec![1, 0, 1]];
+        let mut model = MultiOutputWrapper::new(KNN::unfitted(1, None, None));
+        assert!(matches!(model.predict(&x), Err(Error::NotFitted)));
+
+        model.fit(&x, &y).unwrap();
+        assert_eq!(model.estimators().len(), 3);
+        assert_eq!(model.predict(&x).unwrap(), y);
+        assert_eq!(model.describe().parts.len(), 3);
+    }
+
+    #[test]
+    fn ragged_targets_test() {
+        let mut model = MultiOutputWrapper::new(KNN::unfitted(1, None, None));
+
+        assert!(matches!(
+            model.fit(&[vec![0.0], vec![1.0]], &[vec![0, 1], vec![1]]),
+            Err(Error::ShapeMismatch(_))
+        ));
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+//! Progress reporting for long-running fits.
+
+/*!
+A `FitObserver` is notified as a fit makes progress. Any `Fn(&Progress)` closure is an observer
+that is called on every event; `PrintObserver` prints each event to standard error.
+
+# Example
+```rust
+use rml::knn::KNN;
+use rml::metrics::accuracy;
+use rml::model_selection::{GridSearchCV, KFold, ParameterGrid, Params};
+use rml::observer::Progress;
+
+let x: Vec<Vec<f64>> = (0..20).map(|i| vec![(i % 2) as f64]).collect();
+let y: Vec<i32> = (0..20).map(|i| i % 2).collect();
+
+let grid = ParameterGrid::new().add("k", vec![1.into(), 3.into(), 5.into()]);
+let build = |p: &Params| Ok(KNN::unfitted(p.int("k")? as i32, None, None));
+let mut search = GridSearchCV::new(grid, build, KFold::new(2).unwrap(), accuracy)
+    .observer(|p: &Progress| println!("{}/{} combinations scored", p.iteration, p.total.unwrap()));
+search.fit(&x, &y).unwrap();
+```
+!*/
+
+use std::fmt;
+use std::time::Duration;
+
+/// A snapshot of the progress of a fit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Progress {
+    /// The number of completed iterations or epochs, starting at 1.
+    pub iteration: usize,
+    /// The total number of iterations or epochs, if known.
+    pub total: Option<usize>,
+    /// The current training loss, if the fit minimizes one.
+    pub loss: Option<f64>,
+    /// The latest validation score, if the fit computes one.
+    pub score: Option<f64>,
+    /// The number of samples processed so far.
+    pub samples: usize,
+    /// The time since the fit started.
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.iteration)?;
+        if let Some(total) = self.total {
+            write!(f, "/{}", total)?;
+        }
+        if let Some(loss) = self.loss {
+            write!(f, " loss={:.6}", loss)?;
+        }
+        if let Some(score) = self.score {
+            write!(f, " score={:.6}", score)?;
+        }
+        write!(
+            f,
+            " samples={} elapsed={:.3}s",
+            self.samples,
+            self.elapsed.as_secs_f64()
+        )
+    }
+}
+
+/// Receives progress events from a fit.
+///
+/// Observers may be called from several threads at once, so events can arrive out of order.
+pub trait FitObserver: Send + Sync {
+    /// Called after each iteration, such as one scored combination of a hyperparameter search.
+    fn on_iteration(&self, _progress: &Progress) {}
+
+    /// Called after each pass over the training data.
+    fn on_epoch(&self, _progress: &Progress) {}
+}
+
+impl<F: Fn(&Progress) + Send + Sync> FitObserver for F {
+    fn on_iteration(&self, progress: &Progress) {
+        self(progress)
+    }
+
+    fn on_epoch(&self, progress: &Progress) {
+        self(progress)
+    }
+}
+
+/// An observer that prints every event to standard error.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrintObserver;
+
+impl FitObserver for PrintObserver {
+    fn on_iteration(&self, progress: &Progress) {
+        eprintln!("iteration {}", progress);
+    }
+
+    fn on_epoch(&self, progress: &Progress) {
+        eprintln!("epoch {}", progress);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn closure_observer_test() {
+        let seen = Mutex::new(Vec::new());
+        let observer = |p: &Progress| seen.lock().unwrap().push(p.iteration);
+        let progress = Progress {
+            iteration: 2,
+            total: Some(4),
+            loss: Some(0.5),
+            score: None,
+            samples: 10,
+            elapsed: Duration::from_millis(1500),
+        };
+        observer.on_iteration(&progress);
+        observer.on_epoch(&progress);
+
+        assert_eq!(*seen.lock().unwrap(), vec![2, 2]);
+        assert_eq!(
+            progress.to_string(),
+            "2/4 loss=0.500000 samples=10 elapsed=1.500s"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+//! Control over the number of threads used for parallel computation.
+
+/*!
+Parallel code in rml runs on rayon thread pools. By default it uses rayon's global pool, which
+has one thread per CPU. The global setting changes this for the whole crate, and estimators that
+support it, such as `KNN`, can override it.
+
+`Parallelism::Sequential` runs all work on a single thread, which is useful when the crate is
+embedded in an application that already parallelizes at a higher level.
+
+Parallel results are the same for any number of threads: neighbor selection breaks ties between
+points at the same distance by index.
+
+# Example
+```rust
+use rml::knn::KNN;
+use rml::parallel::{self, Parallelism};
+
+// Use at most 2 threads everywhere...
+parallel::set_parallelism(Parallelism::Threads(2));
+
+// ...except for this model, which runs on a single thread.
+let knn: KNN = KNN::unfitted(3, None, None).parallelism(Parallelism::Sequential);
+```
+!*/
+
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+/// The number of threads to use for parallel computation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Parallelism {
+    /// Use rayon's global thread pool, or the pool the caller is already running in.
+    #[default]
+    Auto,
+    /// Run on a single thread.
+    Sequential,
+    /// Run on a pool with the given number of threads. 0 is the same as `Auto`.
+    Threads(usize),
+}
+
+impl Parallelism {
+    /// The number of threads of the dedicated pool, or `None` for `Auto`.
+    fn threads(self) -> Option<usize> {
+        match self {
+            Parallelism::Auto | Parallelism::Threads(0) => None,
+            Parallelism::Sequential => Some(1),
+            Parallelism::Threads(n) => Some(n),
+        }
+    }
+}
+
+static GLOBAL: RwLock<Parallelism> = RwLock::new(Parallelism::Auto);
+
+/// Set the parallelism used by all computations that don't override it.
+pub fn set_parallelism(parallelism: Parallelism) {
+    *GLOBAL.write().unwrap_or_else(|e| e.into_inner()) = parallelism;
+}
+
+/// The global parallelism setting.
+pub fn parallelism() -> Parallelism {
+    *GLOBAL.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Run `op` with the given parallelism, or the global setting if `None`.
+pub(crate) fn install<R, F>(parallelism: Option<Parallelism>, op: F) -> R
+where
+    R: Send,
+    F: FnOnce() -> R + Send,
+{
+    match parallelism.unwrap_or_else(self::parallelism).threads() {
+        Some(n) => match pool(n) {
+            Some(pool) => pool.install(op),
+            None => op(),
+        },
+        None => op(),
+    }
+}
+
+/// A shared pool with `n` threads, created on first use. `None` if the threads can't be spawned.
+fn pool(n: usize) -> Option<Arc<ThreadPool>> {
+    static POOLS: Mutex<BTreeMap<usize, Arc<ThreadPool>>> = Mutex::new(BTreeMap::new());
+
+    let mut pools = POOLS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(pool) = pools.get(&n) {
+        return Some(pool.clone());
+    }
+    let pool = Arc::new(ThreadPoolBuilder::new().num_threads(n).build().ok()?);
+    pools.insert(n, pool.clone());
+    Some(pool)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn install_test() {
+        assert_eq!(Parallelism::default(), Parallelism::Auto);
+        assert_eq!(
+            install(Some(Parallelism::Sequential), rayon::current_num_threads),
+            1
+        );
+        assert_eq!(
+            install(Some(Parallelism::Threads(3)), rayon::current_num_threads),
+            3
+        );
+        assert_eq!(
+            install(Some(Parallelism::Auto), rayon::current_num_threads),
+            rayon::current_num_threads()
+        );
+    }
+}
@@ -0,0 +1,247 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+//! Chaining preprocessing steps and a final estimator.
+
+/*!
+A `Pipeline` fits each preprocessing step in turn, feeding the output of one into the next,
+then fits the estimator on the result. Predicting sends new samples through the same fitted
+steps, so test data is always preprocessed exactly like the training data, and nothing is
+learned from it.
+
+Because a pipeline is itself an estimator, it can be passed to `model_selection` functions, which
+then refit the preprocessing on each training fold rather than on all of the data.
+
+# Example
+```rust
+use rml::knn::KNN;
+use rml::math::norm::Norm;
+use rml::pipeline::Pipeline;
+use rml::preprocessing::scale::{Normalizer, StandardScaler};
+use rml::traits::{Fit, Predict};
+
+let x = vec![vec![1.0, 200.0], vec![2.0, 100.0], vec![9.0, 210.0], vec![8.0, 90.0]];
+let y = vec![0, 0, 1, 1];
+
+let mut model = Pipeline::new(KNN::unfitted(1, None, None))
+    .step(StandardScaler::new())
+    .step(Normalizer::new(Norm::L2));
+model.fit(&x, &y).unwrap();
+
+assert_eq!(model.predict(&[vec![8.5, 150.0]]).unwrap(), vec![1]);
+```
+!*/
+
+use crate::describe::{Describe, Summary};
+use crate::error::Result;
+use crate::traits::{Fit, FitWeighted, Predict, PredictProba, Transform};
+
+/// A preprocessing step of a pipeline. Implemented for every cloneable, thread-safe type that
+/// implements `Fit` and `Transform`.
+pub trait Transformer<T, U>: Fit<T, U> + Transform<T> + Send + Sync {
+    /// Clone the step into a new box.
+    fn clone_box(&self) -> Box<dyn Transformer<T, U>>;
+}
+
+impl<T, U, X> Transformer<T, U> for X
+where
+    X: Fit<T, U> + Transform<T> + Clone + Send + Sync + 'static,
+{
+    fn clone_box(&self) -> Box<dyn Transformer<T, U>> {
+        Box::new(self.clone())
+    }
+}
+
+/// Preprocessing steps followed by an estimator, fit and used as one.
+pub struct Pipeline<T, U, E> {
+    steps: Vec<Box<dyn Transformer<T, U>>>,
+    /// The final estimator.
+    pub estimator: E,
+}
+
+impl<T, U, E> Pipeline<T, U, E> {
+    /// Create a pipeline with no preprocessing steps.
+    pub fn new(estimator: E) -> Pipeline<T, U, E> {
+        Pipeline {
+            steps: Vec::new(),
+            estimator,
+        }
+    }
+
+    /// Add a preprocessing step after the existing ones.
+    pub fn step<X: Transformer<T, U> + 'static>(mut self, step: X) -> Self {
+        self.steps.push(Box::new(step));
+        self
+    }
+
+    /// Add a boxed preprocessing step after the existing ones.
+    pub fn boxed_step(mut self, step: Box<dyn Transformer<T, U>>) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// The number of preprocessing steps.
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Whether the pipeline has no preprocessing steps.
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Fit each step to the output of the previous one, returning the output of the last.
+    fn fit_steps(&mut self, x: &[Vec<T>], y: &[U]) -> Result<Vec<Vec<T>>>
+    where
+        T: Clone,
+    {
+        let mut x = x.to_vec();
+        for step in &mut self.steps {
+            step.fit(&x, y)?;
+            x = step.transform(&x)?;
+        }
+        Ok(x)
+    }
+
+    /// Send samples through the fitted preprocessing steps.
+    pub fn preprocess(&self, x: &[Vec<T>]) -> Result<Vec<Vec<T>>>
+    where
+        T: Clone,
+    {
+        self.steps
+            .iter()
+            .try_fold(x.to_vec(), |x, step| step.transform(&x))
+    }
+}
+
+impl<T, U, E: Describe> Describe for Pipeline<T, U, E> {
+    /// Summarize the pipeline, with the estimator as its part. The steps are only counted.
+    fn describe(&self) -> Summary {
+        Summary::new("Pipeline")
+            .param("steps", self.steps.len())
+            .part(self.estimator.describe())
+    }
+}
+
+impl<T, U, E: Clone> Clone for Pipeline<T, U, E> {
+    fn clone(&self) -> Self {
+        Pipeline {
+            steps: self.steps.iter().map(|s| s.clone_box()).collect(),
+            estimator: self.estimator.clone(),
+        }
+    }
+}
+
+impl<T, U, E> Fit<T, U> for Pipeline<T, U, E>
+where
+    T: Clone,
+    E: Fit<T, U>,
+{
+    /// Fit each step to the output of the previous one, then fit the estimator.
+    fn fit(&mut self, x: &[Vec<T>], y: &[U]) -> Result<()> {
+        let x = self.fit_steps(x, y)?;
+        self.estimator.fit(&x, y)
+    }
+}
+
+impl<T, U, E> FitWeighted<T, U> for Pipeline<T, U, E>
+where
+    T: Clone,
+    E: FitWeighted<T, U>,
+{
+    /// Fit each step to the output of the previous one, ignoring the weights, then fit the
+    /// estimator with the weights.
+    fn fit_weighted(&mut self, x: &[Vec<T>], y: &[U], sample_weight: Option<&[f64]>) -> Result<()> {
+        let x = self.fit_steps(x, y)?;
+        self.estimator.fit_weighted(&x, y, sample_weight)
+    }
+}
+
+impl<T, U, E> Predict<T, U> for Pipeline<T, U, E>
+where
+    T: Clone,
+    E: Predict<T, U>,
+{
+    /// Preprocess `x`, then predict with the estimator.
+    fn predict(&self, x: &[Vec<T>]) -> Result<Vec<U>> {
+        self.estimator.predict(&self.preprocess(x)?)
+    }
+}
+
+impl<T, U, E> PredictProba<T> for Pipeline<T, U, E>
+where
+    T: Clone,
+    E: PredictProba<T>,
+{
+    /// Preprocess `x`, then predict probabilities with the estimator.
+    fn predict_proba(&self, x: &[Vec<T>]) -> Result<Vec<Vec<f64>>> {
+        self.estimator.predict_proba(&self.preprocess(x)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preprocessing::scale::StandardScaler;
+
+    /// Remembers the samples it was fit to and predicts the first feature of each sample.
+    #[derive(Clone, Default)]
+    struct Echo(Vec<Vec<f64>>);
+
+    impl Fit<f64, i32> for Echo {
+        fn fit(&mut self, x: &[Vec<f64>], _: &[i32]) -> Result<()> {
+            self.0 = x.to_vec();
+            Ok(())
+        }
+    }
+
+    impl Predict<f64, i32> for Echo {
+        fn predict(&self, x: &[Vec<f64>]) -> Result<Vec<i32>> {
+            Ok(x.iter().map(|row| row[0] as i32).collect())
+        }
+    }
+
+    #[test]
+    fn pipeline_test() {
+        let mut model = Pipeline::new(Echo::default()).step(StandardScaler::new());
+        model.fit(&[vec![0.0], vec![2.0]], &[0, 1]).unwrap();
+
+        assert_eq!(model.len(), 1);
+        assert_eq!(model.estimator.0, vec![vec![-1.0], vec![1.0]]);
+        // New samples use the mean and scale learned from the training data.
+        assert_eq!(model.clone().predict(&[vec![4.0]]).unwrap(), vec![3]);
+    }
+
+    #[cfg(feature = "datasets")]
+    #[test]
+    fn knn_pipeline_cross_val_test() {
+        use crate::knn::KNN;
+        use crate::model_selection::{cross_val_score, KFold};
+
+        let iris = crate::datasets::load_iris();
+        let model = Pipeline::new(KNN::unfitted(5, None, None)).step(StandardScaler::new());
+        let scores = cross_val_score(
+            &model,
+            &iris.x,
+            &iris.y,
+            &KFold::new(5).unwrap().shuffle(1),
+            crate::metrics::accuracy,
+        )
+        .unwrap();
+
+        assert!(scores.iter().all(|&s| s > 0.85));
+    }
+}
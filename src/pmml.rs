@@ -0,0 +1,310 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Export of fitted models to PMML, behind the `pmml` feature.
+
+/*!
+[PMML](https://dmg.org/pmml/v4-4-1/GeneralStructure.html) is an XML format for predictive models
+that existing inference runtimes can score, so a model trained with rml can be served without
+rml. Models that can be exported implement `ToPmml`.
+
+`KNN` is exported as a `NearestNeighborModel` holding its training data. Its normalization is
+exported as derived fields, so the runtime normalizes the inputs the same way. The input fields
+are named `x0`, `x1`, ... and the target field `class`.
+
+# Example
+```rust
+use rml::knn::KNN;
+use rml::pmml::ToPmml;
+
+let knn: KNN = KNN::new(1, vec![vec![0.0, 1.0], vec![1.0, 0.0]], vec![0, 1], None, None).unwrap();
+let xml = knn.to_pmml().unwrap();
+
+assert!(xml.contains("<NearestNeighborModel"));
+```
+!*/
+
+use crate::error::{Error, Result};
+use crate::knn::{Weighting, KNN};
+use crate::math::distance::Distance;
+use crate::math::norm::Norm;
+use crate::math::Float;
+#[cfg(feature = "fs")]
+use std::fs::File;
+#[cfg(feature = "fs")]
+use std::io::BufWriter;
+use std::io::Write;
+
+/// A fitted model that can be written as a PMML document.
+pub trait ToPmml {
+    /// Write the model as a PMML document to `w`.
+    /// Returns an error if the model is not fitted or writing fails.
+    fn write_pmml<W: Write>(&self, w: &mut W) -> Result<()>;
+
+    /// The model as a PMML document.
+    fn to_pmml(&self) -> Result<String> {
+        let mut buf = Vec::new();
+        self.write_pmml(&mut buf)?;
+        Ok(String::from_utf8(buf)?)
+    }
+
+    /// Write the model as a PMML document to the file at `path`.
+    #[cfg(feature = "fs")]
+    fn save_pmml(&self, path: &str) -> Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        self.write_pmml(&mut w)?;
+        w.flush()?;
+        Ok(())
+    }
+}
+
+impl<F: Float> ToPmml for KNN<F> {
+    fn write_pmml<W: Write>(&self, w: &mut W) -> Result<()> {
+        if self.x.is_empty() {
+            return Err(Error::NotFitted);
+        }
+        if self.weights.is_some() {
+            return Err(Error::InvalidInput(
+                "PMML cannot express sample weights of a KNN.".into(),
+            ));
+        }
+        if self.custom_distance.is_some() {
+            return Err(Error::InvalidInput(
+                "PMML cannot express a custom distance.".into(),
+            ));
+        }
+        if self.distance == Some(Distance::Cosine) {
+            return Err(Error::InvalidInput(
+                "PMML cannot express cosine distance.".into(),
+            ));
+        }
+        if self.distance == Some(Distance::Hamming) {
+            return Err(Error::InvalidInput(
+                "PMML cannot express Hamming distance.".into(),
+            ));
+        }
+        if self.distance == Some(Distance::Precomputed) {
+            return Err(Error::InvalidInput(
+                "PMML cannot express precomputed distances.".into(),
+            ));
+        }
+        if self.standardize.is_some() {
+            return Err(Error::InvalidInput(
+                "PMML cannot express feature standardization of a KNN.".into(),
+            ));
+        }
+        if self.weighting != Weighting::Uniform {
+            return Err(Error::InvalidInput(
+                "PMML cannot express distance weighting of a KNN.".into(),
+            ));
+        }
+        let n_features = self.x.n_cols();
+        let inputs: Vec<String> = (0..n_features).map(|j| format!("x{}", j)).collect();
+        // The fields the training instances and distances refer to.
+        let fields: Vec<String> = match self.normalize {
+            Some(_) => (0..n_features).map(|j| format!("norm_x{}", j)).collect(),
+            None => inputs.clone(),
+        };
+
+        writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            w,
+            r#"<PMML xmlns="http://www.dmg.org/PMML-4_4" version="4.4">"#
+        )?;
+        writeln!(
+            w,
+            r#"  <Header><Application name="rml" version="{}"/></Header>"#,
+            env!("CARGO_PKG_VERSION")
+        )?;
+
+        writeln!(
+            w,
+            r#"  <DataDictionary numberOfFields="{}">"#,
+            n_features + 1
+        )?;
+        for name in &inputs {
+            writeln!(
+                w,
+                r#"    <DataField name="{}" optype="continuous" dataType="double"/>"#,
+                name
+            )?;
+        }
+        writeln!(
+            w,
+            r#"    <DataField name="class" optype="categorical" dataType="integer">"#
+        )?;
+        for class in &self.classes {
+            writeln!(w, r#"      <Value value="{}"/>"#, class)?;
+        }
+        writeln!(w, "    </DataField>")?;
+        writeln!(w, "  </DataDictionary>")?;
+
+        writeln!(
+            w,
+            r#"  <NearestNeighborModel modelName="KNN" functionName="classification" numberOfNeighbors="{}" categoricalScoringMethod="majorityVote">"#,
+            self.k
+        )?;
+        writeln!(w, "    <MiningSchema>")?;
+        for name in &inputs {
+            writeln!(w, r#"      <MiningField name="{}"/>"#, name)?;
+        }
+        writeln!(w, r#"      <MiningField name="class" usageType="target"/>"#)?;
+        writeln!(w, "    </MiningSchema>")?;
+        if let Some(norm) = &self.normalize {
+            write_normalization(w, &inputs, norm)?;
+        }
+
+        writeln!(
+            w,
+            r#"    <TrainingInstances recordCount="{}" fieldCount="{}" isTransformed="true">"#,
+            self.x.n_rows(),
+            n_features + 1
+        )?;
+        writeln!(w, "      <InstanceFields>")?;
+        for (field, column) in fields.iter().zip(&inputs) {
+            writeln!(
+                w,
+                r#"        <InstanceField field="{}" column="{}"/>"#,
+                field, column
+            )?;
+        }
+        writeln!(
+            w,
+            r#"        <InstanceField field="class" column="class"/>"#
+        )?;
+        writeln!(w, "      </InstanceFields>")?;
+        writeln!(w, "      <InlineTable>")?;
+        for (xi, yi) in self.x.rows().zip(&self.y) {
+            write!(w, "        <row>")?;
+            for (column, v) in inputs.iter().zip(xi) {
+                write!(w, "<{0}>{1}</{0}>", column, v.to_f64())?;
+            }
+            writeln!(w, "<class>{}</class></row>", yi)?;
+        }
+        writeln!(w, "      </InlineTable>")?;
+        writeln!(w, "    </TrainingInstances>")?;
+
+        let measure = match self.distance {
+            Some(Distance::Manhattan) => "cityBlock".to_string(),
+            Some(Distance::Minkowski(power)) => format!(r#"minkowski p-parameter="{}""#, power),
+            _ => "euclidean".to_string(),
+        };
+        writeln!(w, r#"    <ComparisonMeasure kind="distance">"#)?;
+        writeln!(w, "      <{}/>", measure)?;
+        writeln!(w, "    </ComparisonMeasure>")?;
+        writeln!(w, "    <KNNInputs>")?;
+        for field in &fields {
+            writeln!(w, r#"      <KNNInput field="{}"/>"#, field)?;
+        }
+        writeln!(w, "    </KNNInputs>")?;
+        writeln!(w, "  </NearestNeighborModel>")?;
+        writeln!(w, "</PMML>")?;
+        Ok(())
+    }
+}
+
+/// Write derived fields `norm_x0`, `norm_x1`, ... dividing the inputs by their norm, leaving
+/// them unchanged if the norm is 0, as `math::norm::normalize_vector` does.
+fn write_normalization<W: Write>(w: &mut W, inputs: &[String], norm: &Norm) -> Result<()> {
+    writeln!(w, "    <LocalTransformations>")?;
+    writeln!(
+        w,
+        r#"      <DerivedField name="norm" optype="continuous" dataType="double">"#
+    )?;
+    if *norm == Norm::L2 {
+        write!(w, r#"        <Apply function="sqrt">"#)?;
+    }
+    write!(w, r#"<Apply function="sum">"#)?;
+    for name in inputs {
+        match norm {
+            Norm::L1 => write!(
+                w,
+                r#"<Apply function="abs"><FieldRef field="{}"/></Apply>"#,
+                name
+            )?,
+            Norm::L2 => write!(
+                w,
+                r#"<Apply function="pow"><FieldRef field="{}"/><Constant>2</Constant></Apply>"#,
+                name
+            )?,
+        }
+    }
+    write!(w, "</Apply>")?;
+    if *norm == Norm::L2 {
+        write!(w, "</Apply>")?;
+    }
+    writeln!(w)?;
+    writeln!(w, "      </DerivedField>")?;
+    for name in inputs {
+        writeln!(
+            w,
+            r#"      <DerivedField name="norm_{0}" optype="continuous" dataType="double">"#,
+            name
+        )?;
+        writeln!(
+            w,
+            r#"        <Apply function="if"><Apply function="greaterThan"><FieldRef field="norm"/><Constant>0</Constant></Apply><Apply function="/"><FieldRef field="{0}"/><FieldRef field="norm"/></Apply><FieldRef field="{0}"/></Apply>"#,
+            name
+        )?;
+        writeln!(w, "      </DerivedField>")?;
+    }
+    writeln!(w, "    </LocalTransformations>")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn knn_pmml_test() {
+        let knn: KNN = KNN::new(
+            1,
+            vec![vec![0.0, 1.5], vec![2.0, 0.0]],
+            vec![0, 1],
+            Some(Distance::Manhattan),
+            None,
+        )
+        .unwrap();
+        let xml = knn.to_pmml().unwrap();
+
+        assert!(xml.contains(r#"numberOfNeighbors="1""#));
+        assert!(xml.contains("<row><x0>0</x0><x1>1.5</x1><class>0</class></row>"));
+        assert!(xml.contains("<cityBlock/>"));
+        assert!(xml.contains(r#"<KNNInput field="x1"/>"#));
+        assert!(!xml.contains("LocalTransformations"));
+        assert!(xml.trim_end().ends_with("</PMML>"));
+    }
+
+    #[test]
+    fn normalized_knn_pmml_test() {
+        let knn: KNN = KNN::new(1, vec![vec![3.0, 4.0]], vec![0], None, Some(Norm::L2)).unwrap();
+        let xml = knn.to_pmml().unwrap();
+
+        assert!(xml.contains(r#"<Apply function="sqrt">"#));
+        assert!(xml.contains(r#"<DerivedField name="norm_x0""#));
+        assert!(xml.contains("<row><x0>0.6</x0><x1>0.8</x1><class>0</class></row>"));
+        assert!(xml.contains(r#"<KNNInput field="norm_x1"/>"#));
+    }
+
+    #[test]
+    fn unfitted_pmml_test() {
+        let knn: KNN = KNN::unfitted(1, None, None);
+
+        assert!(matches!(knn.to_pmml(), Err(Error::NotFitted)));
+    }
+}
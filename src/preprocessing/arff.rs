@@ -0,0 +1,440 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Loading data from ARFF (Attribute-Relation File Format) files.
+
+/*!
+Supports numeric, nominal, string and date attributes, missing values (`?`), and both dense
+and sparse (`{index value, ...}`) data rows.
+
+# Example
+```rust
+use rml::preprocessing::arff::parse_arff;
+
+let contents = "
+@relation weather
+@attribute temperature numeric
+@attribute play {yes, no}
+@data
+85, no
+70, yes
+";
+
+let arff = parse_arff(contents).unwrap();
+let data = arff.to_dataset("play").unwrap();
+assert_eq!(data.x, vec![vec![85.0], vec![70.0]]);
+assert_eq!(data.y, vec![1, 0]);
+```
+!*/
+
+use crate::datasets::Dataset;
+use crate::error::{Error, Result};
+#[cfg(feature = "fs")]
+use std::fs;
+
+/// The type of an attribute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttributeType {
+    /// `numeric`, `real` or `integer` attributes.
+    Numeric,
+    /// An attribute taking one of the listed values.
+    Nominal(Vec<String>),
+    /// A free-form string attribute.
+    String,
+    /// A date attribute, with its optional format string. Values are kept as strings.
+    Date(Option<String>),
+}
+
+/// A declared attribute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attribute {
+    /// The name of the attribute.
+    pub name: String,
+    /// The type of the attribute.
+    pub kind: AttributeType,
+}
+
+/// A single value of a data row.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// A numeric value.
+    Numeric(f64),
+    /// The index of a nominal value in the attribute's declaration.
+    Nominal(usize),
+    /// A string or date value.
+    String(String),
+    /// A missing value (`?`).
+    Missing,
+}
+
+/// A parsed ARFF file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arff {
+    /// The name of the relation.
+    pub relation: String,
+    /// The declared attributes, in order.
+    pub attributes: Vec<Attribute>,
+    /// The data rows, with one value per attribute.
+    pub data: Vec<Vec<Value>>,
+}
+
+impl Arff {
+    /// Find the index of an attribute by name, ignoring case.
+    pub fn attribute_index(&self, name: &str) -> Option<usize> {
+        self.attributes
+            .iter()
+            .position(|a| a.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Convert the data to a dataset of numeric features, labeled by the nominal attribute `class`.
+    ///
+    /// Nominal features are encoded as the index of their value and missing features as NaN.
+    /// The attribute names become the feature names, and the values of `class` the label names.
+    /// String and date attributes cannot be converted and produce an error.
+    pub fn to_dataset(&self, class: &str) -> Result<Dataset<f64, usize>> {
+        let class_idx = self
+            .attribute_index(class)
+            .ok_or_else(|| Error::Parse(format!("No attribute named '{}'.", class)))?;
+        let label_names = match &self.attributes[class_idx].kind {
+            AttributeType::Nominal(values) => values.clone(),
+            _ => {
+                return Err(Error::Parse(format!(
+                    "Class attribute '{}' must be nominal.",
+                    class
+                )))
+            }
+        };
+
+        let mut x = Vec::with_capacity(self.data.len());
+        let mut y = Vec::with_capacity(self.data.len());
+        for row in &self.data {
+            match row[class_idx] {
+                Value::Nominal(label) => y.push(label),
+                _ => {
+                    return Err(Error::Parse(
+                        "Rows must not be missing the class label.".into(),
+                    ))
+                }
+            }
+            let mut features = Vec::with_capacity(row.len() - 1);
+            for (i, value) in row.iter().enumerate().filter(|(i, _)| *i != class_idx) {
+                features.push(match value {
+                    Value::Numeric(v) => *v,
+                    Value::Nominal(v) => *v as f64,
+                    Value::Missing => f64::NAN,
+                    Value::String(_) => {
+                        return Err(Error::Parse(format!(
+                            "Attribute '{}' is not numeric or nominal.",
+                            self.attributes[i].name
+                        )))
+                    }
+                });
+            }
+            x.push(features);
+        }
+
+        let feature_names = self
+            .attributes
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != class_idx)
+            .map(|(_, a)| a.name.clone())
+            .collect();
+        Ok(Dataset::new(x, y)?
+            .with_feature_names(feature_names)
+            .with_label_names(label_names))
+    }
+}
+
+/// Read and parse an ARFF file.
+#[cfg(feature = "fs")]
+pub fn read_arff(path: &str) -> Result<Arff> {
+    parse_arff(&fs::read_to_string(path)?)
+}
+
+/// Parse the contents of an ARFF file.
+pub fn parse_arff(contents: &str) -> Result<Arff> {
+    let mut arff = Arff {
+        relation: String::new(),
+        attributes: Vec::new(),
+        data: Vec::new(),
+    };
+    let mut in_data = false;
+
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('%') {
+            continue;
+        }
+        let context = |e: Error| e.context(format!("Line {}", number + 1));
+
+        if in_data {
+            let row = if line.starts_with('{') {
+                parse_sparse_row(line, &arff.attributes)
+            } else {
+                parse_dense_row(line, &arff.attributes)
+            };
+            arff.data.push(row.map_err(context)?);
+            continue;
+        }
+
+        let (keyword, rest) = split_keyword(line);
+        match keyword.to_ascii_lowercase().as_str() {
+            "@relation" => arff.relation = unquote(rest).to_string(),
+            "@attribute" => arff
+                .attributes
+                .push(parse_attribute(rest).map_err(context)?),
+            "@data" => in_data = true,
+            _ => {
+                return Err(context(Error::Parse(format!(
+                    "Unknown declaration '{}'.",
+                    keyword
+                ))))
+            }
+        }
+    }
+    Ok(arff)
+}
+
+/// Split a line into its first word and the rest.
+fn split_keyword(line: &str) -> (&str, &str) {
+    match line.find(char::is_whitespace) {
+        Some(i) => (&line[..i], line[i..].trim()),
+        None => (line, ""),
+    }
+}
+
+/// Remove surrounding quotes from a token.
+fn unquote(token: &str) -> &str {
+    let token = token.trim();
+    let quoted = token.len() >= 2
+        && ((token.starts_with('\'') && token.ends_with('\''))
+            || (token.starts_with('"') && token.ends_with('"')));
+    if quoted {
+        &token[1..token.len() - 1]
+    } else {
+        token
+    }
+}
+
+/// Split a comma (or `separator`) separated list, respecting quotes.
+fn split_values(line: &str, separator: char) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    for c in line.chars() {
+        match quote {
+            Some(q) if c == q => {
+                quote = None;
+                current.push(c);
+            }
+            Some(_) => current.push(c),
+            None if c == '\'' || c == '"' => {
+                quote = Some(c);
+                current.push(c);
+            }
+            None if c == separator => {
+                values.push(unquote(&current).to_string());
+                current.clear();
+            }
+            None => current.push(c),
+        }
+    }
+    values.push(unquote(&current).to_string());
+    values
+}
+
+/// Parse the part of an `@attribute` line after the keyword.
+fn parse_attribute(rest: &str) -> Result<Attribute> {
+    let rest = rest.trim();
+    let (name, kind) = match rest.chars().next() {
+        Some(q @ '\'') | Some(q @ '"') => {
+            let end = rest[1..]
+                .find(q)
+                .ok_or_else(|| Error::Parse("Unterminated attribute name.".into()))?
+                + 1;
+            (&rest[1..end], rest[end + 1..].trim())
+        }
+        _ => split_keyword(rest),
+    };
+    if name.is_empty() || kind.is_empty() {
+        return Err(Error::Parse("Attributes need a name and a type.".into()));
+    }
+
+    let kind = if kind.starts_with('{') {
+        let inner = kind
+            .strip_prefix('{')
+            .and_then(|k| k.strip_suffix('}'))
+            .ok_or_else(|| Error::Parse("Unterminated nominal attribute.".into()))?;
+        AttributeType::Nominal(split_values(inner, ','))
+    } else {
+        let (type_name, format) = split_keyword(kind);
+        match type_name.to_ascii_lowercase().as_str() {
+            "numeric" | "real" | "integer" => AttributeType::Numeric,
+            "string" => AttributeType::String,
+            "date" if format.is_empty() => AttributeType::Date(None),
+            "date" => AttributeType::Date(Some(unquote(format).to_string())),
+            _ => return Err(Error::Parse(format!("Unknown attribute type '{}'.", kind))),
+        }
+    };
+
+    Ok(Attribute {
+        name: name.to_string(),
+        kind,
+    })
+}
+
+/// Parse a single value for the given attribute.
+fn parse_value(token: &str, attribute: &Attribute) -> Result<Value> {
+    if token == "?" {
+        return Ok(Value::Missing);
+    }
+    Ok(match &attribute.kind {
+        AttributeType::Numeric => Value::Numeric(token.parse()?),
+        AttributeType::Nominal(values) => {
+            Value::Nominal(values.iter().position(|v| v == token).ok_or_else(|| {
+                Error::Parse(format!(
+                    "'{}' is not a value of '{}'.",
+                    token, attribute.name
+                ))
+            })?)
+        }
+        AttributeType::String | AttributeType::Date(_) => Value::String(token.to_string()),
+    })
+}
+
+/// Parse a dense data row.
+fn parse_dense_row(line: &str, attributes: &[Attribute]) -> Result<Vec<Value>> {
+    let tokens = split_values(line, ',');
+    if tokens.len() != attributes.len() {
+        return Err(Error::Parse(format!(
+            "Expected {} values, found {}.",
+            attributes.len(),
+            tokens.len()
+        )));
+    }
+    tokens
+        .iter()
+        .zip(attributes)
+        .map(|(token, attribute)| parse_value(token, attribute))
+        .collect()
+}
+
+/// Parse a sparse data row. Values that are not listed are zero (or the first nominal value).
+fn parse_sparse_row(line: &str, attributes: &[Attribute]) -> Result<Vec<Value>> {
+    let inner = line
+        .strip_prefix('{')
+        .and_then(|l| l.strip_suffix('}'))
+        .ok_or_else(|| Error::Parse("Unterminated sparse row.".into()))?;
+
+    let mut row: Vec<Value> = attributes
+        .iter()
+        .map(|a| match a.kind {
+            AttributeType::Nominal(_) => Value::Nominal(0),
+            AttributeType::String | AttributeType::Date(_) => Value::String(String::new()),
+            AttributeType::Numeric => Value::Numeric(0.0),
+        })
+        .collect();
+
+    for entry in split_values(inner, ',') {
+        if entry.is_empty() {
+            continue;
+        }
+        let (index, token) = split_keyword(&entry);
+        let index: usize = index.parse()?;
+        let attribute = attributes
+            .get(index)
+            .ok_or_else(|| Error::Parse(format!("Attribute index {} is out of range.", index)))?;
+        row[index] = parse_value(unquote(token), attribute)?;
+    }
+    Ok(row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WEATHER: &str = "
+% A small test file.
+@RELATION weather
+@ATTRIBUTE outlook {sunny, overcast, 'light rain'}
+@ATTRIBUTE temperature REAL
+@ATTRIBUTE 'wind speed' numeric
+@ATTRIBUTE note string
+@ATTRIBUTE play {yes, no}
+
+@DATA
+sunny, 85, 3, 'hot day', no
+'light rain', ?, 10, none, yes
+{1 70, 4 yes}
+";
+
+    #[test]
+    fn parse_arff_test() {
+        let arff = parse_arff(WEATHER).unwrap();
+
+        assert_eq!(arff.relation, "weather");
+        assert_eq!(arff.attributes.len(), 5);
+        assert_eq!(arff.attributes[2].name, "wind speed");
+        assert_eq!(
+            arff.attributes[0].kind,
+            AttributeType::Nominal(vec![
+                String::from("sunny"),
+                String::from("overcast"),
+                String::from("light rain")
+            ])
+        );
+        assert_eq!(
+            arff.data[1],
+            vec![
+                Value::Nominal(2),
+                Value::Missing,
+                Value::Numeric(10.0),
+                Value::String(String::from("none")),
+                Value::Nominal(0)
+            ]
+        );
+        assert_eq!(arff.data[2][1], Value::Numeric(70.0));
+        assert_eq!(arff.data[2][2], Value::Numeric(0.0));
+    }
+
+    #[test]
+    fn to_dataset_test() {
+        let mut arff = parse_arff(WEATHER).unwrap();
+        assert!(arff.to_dataset("play").is_err());
+
+        arff.attributes.remove(3);
+        arff.data.iter_mut().for_each(|row| {
+            row.remove(3);
+        });
+        let data = arff.to_dataset("play").unwrap();
+        assert_eq!(data.x[0], vec![0.0, 85.0, 3.0]);
+        assert!(data.x[1][1].is_nan());
+        assert_eq!(data.y, vec![1, 0, 0]);
+        assert_eq!(
+            data.feature_names.unwrap(),
+            vec!["outlook", "temperature", "wind speed"]
+        );
+        assert_eq!(data.label_names.unwrap(), vec!["yes", "no"]);
+    }
+
+    #[test]
+    fn parse_arff_bad_value_test() {
+        let contents = "@attribute a {x, y}\n@data\nz\n";
+        assert!(parse_arff(contents).is_err());
+    }
+}
@@ -0,0 +1,1554 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Reading and writing labeled data as CSV files.
+
+/*!
+Reads a CSV file into feature vectors and class labels, optionally keeping the
+header row as the feature names.
+
+# Example
+```rust,no_run
+use rml::preprocessing::csv::{
+    parse_csv_with_labels, ColumnSelection, CsvOptions, LabelColumn, MissingPolicy,
+};
+
+let data = parse_csv_with_labels::<f64, i32>(
+    "./data/optdigits.tra",
+    &CsvOptions::new(),   // Comma separated, no header row
+    &LabelColumn::Last,   // The class is the last value of each row
+    &ColumnSelection::All, // Every other column is a feature
+    &MissingPolicy::Error, // Fail on missing values
+)
+.unwrap();
+
+println!("{} samples", data.x.len());
+```
+!*/
+
+use super::label::LabelEncoder;
+use crate::datasets::Dataset;
+use crate::error::{Error, Result};
+use crate::math::sparse::CsrMatrix;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+#[cfg(feature = "fs")]
+use std::fs::File;
+use std::io::{Read, Write};
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+/// A dataset with encoded labels, and the map from each label to its index.
+pub type EncodedOutput<T> = (Dataset<T, i32>, BTreeMap<String, i32>);
+
+/// A sparse feature matrix and its labels.
+pub type SparseOutput<T, U> = (CsrMatrix<T>, Vec<U>);
+
+/// Describes which column of a row holds the class label.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LabelColumn {
+    /// The first column.
+    First,
+    /// The last column.
+    Last,
+    /// The column at the given (zero based) index.
+    Index(usize),
+    /// The column with the given header name. Requires a header row.
+    Name(String),
+}
+
+/// Describes which columns are used as features.
+/// The label column is never included, even if it is selected here.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColumnSelection {
+    /// Every column except the label.
+    All,
+    /// Only the columns at the given (zero based) indices, in the given order.
+    Indices(Vec<usize>),
+    /// Only the columns with the given header names, in the given order. Requires a header row.
+    Names(Vec<String>),
+}
+
+/// Describes what to do with missing feature values.
+/// A value is missing if the cell is empty, `?` or `NA`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MissingPolicy<T> {
+    /// Return an error.
+    Error,
+    /// Drop the whole row.
+    SkipRow,
+    /// Replace the value with the given one.
+    FillWith(T),
+    /// Replace the value with NaN, so it can be imputed later. Only valid for float features.
+    KeepAsNaN,
+}
+
+/// How whitespace around values is trimmed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trim {
+    /// Keep all whitespace.
+    None,
+    /// Trim the header row only.
+    Headers,
+    /// Trim every field except the headers.
+    Fields,
+    /// Trim the headers and every field.
+    All,
+}
+
+/// Options describing the format of a CSV file.
+///
+/// The defaults are a comma separated file with `"` quotes, no header row, no comments,
+/// uniform row lengths, trimmed fields and `.` as the decimal separator.
+///
+/// # Example
+/// ```rust
+/// use rml::preprocessing::csv::CsvOptions;
+///
+/// // A semicolon separated file with a header and decimal commas (1,5 instead of 1.5).
+/// let options = CsvOptions::new()
+///     .has_headers(true)
+///     .delimiter(b';')
+///     .decimal_comma(true);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvOptions {
+    has_headers: bool,
+    delimiter: u8,
+    quote: u8,
+    comment: Option<u8>,
+    flexible: bool,
+    trim: Trim,
+    decimal_comma: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            has_headers: false,
+            delimiter: b',',
+            quote: b'"',
+            comment: None,
+            flexible: false,
+            trim: Trim::Fields,
+            decimal_comma: false,
+        }
+    }
+}
+
+impl CsvOptions {
+    /// Create the default options.
+    pub fn new() -> CsvOptions {
+        CsvOptions::default()
+    }
+
+    /// Whether the first row is a header row. Headers are kept as the feature names instead of
+    /// being parsed, and allow selecting columns by name.
+    pub fn has_headers(mut self, has_headers: bool) -> Self {
+        self.has_headers = has_headers;
+        self
+    }
+
+    /// The field delimiter.
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// The quote character.
+    pub fn quote(mut self, quote: u8) -> Self {
+        self.quote = quote;
+        self
+    }
+
+    /// Lines starting with this character are skipped.
+    pub fn comment(mut self, comment: Option<u8>) -> Self {
+        self.comment = comment;
+        self
+    }
+
+    /// Whether rows may have different lengths. Fields missing from short rows are treated as
+    /// missing values.
+    pub fn flexible(mut self, flexible: bool) -> Self {
+        self.flexible = flexible;
+        self
+    }
+
+    /// How whitespace around values is trimmed.
+    pub fn trim(mut self, trim: Trim) -> Self {
+        self.trim = trim;
+        self
+    }
+
+    /// Whether numbers use a comma as the decimal separator, e.g. `1,5`. This requires a
+    /// delimiter other than a comma.
+    pub fn decimal_comma(mut self, decimal_comma: bool) -> Self {
+        self.decimal_comma = decimal_comma;
+        self
+    }
+
+    /// Create a CSV reader over `rdr` configured with these options.
+    fn reader<R: Read>(&self, rdr: R) -> Result<::csv::Reader<R>> {
+        if self.decimal_comma && self.delimiter == b',' {
+            return Err(Error::InvalidInput(
+                "Decimal commas require a delimiter other than a comma.".into(),
+            ));
+        }
+        let trim = match self.trim {
+            Trim::None => ::csv::Trim::None,
+            Trim::Headers => ::csv::Trim::Headers,
+            Trim::Fields => ::csv::Trim::Fields,
+            Trim::All => ::csv::Trim::All,
+        };
+        Ok(::csv::ReaderBuilder::new()
+            .has_headers(self.has_headers)
+            .delimiter(self.delimiter)
+            .quote(self.quote)
+            .comment(self.comment)
+            .flexible(self.flexible)
+            .trim(trim)
+            .from_reader(rdr))
+    }
+
+    /// Get a field from a record. Fields past the end of a short row are empty if the
+    /// options are flexible, and an error otherwise.
+    fn field<'a>(&self, record: &'a ::csv::StringRecord, i: usize) -> Result<Cow<'a, str>> {
+        let field = match record.get(i) {
+            Some(field) => field,
+            None if self.flexible => "",
+            None => return Err(Error::Parse(format!("Row has no column {}.", i))),
+        };
+        if self.decimal_comma && field.contains(',') {
+            Ok(Cow::Owned(field.replace(',', ".")))
+        } else {
+            Ok(Cow::Borrowed(field))
+        }
+    }
+}
+
+/// Parse a CSV file into features of type `T` and labels of type `U`.
+///
+/// The format of the file is described by `options`. If it has a header row, the headers are
+/// kept as the dataset's feature names instead of being parsed.
+/// Selecting columns or the label by name requires a header row.
+/// Missing features are handled according to `missing`, while a missing label is an error
+/// unless `missing` is `MissingPolicy::SkipRow`.
+#[cfg(feature = "fs")]
+pub fn parse_csv_with_labels<T, U>(
+    path: &str,
+    options: &CsvOptions,
+    label: &LabelColumn,
+    features: &ColumnSelection,
+    missing: &MissingPolicy<T>,
+) -> Result<Dataset<T, U>>
+where
+    T: FromStr + Clone,
+    U: FromStr,
+    <T as FromStr>::Err: Display,
+    <U as FromStr>::Err: Display,
+{
+    parse_csv_with_labels_from_reader(File::open(path)?, options, label, features, missing)
+}
+
+/// Parse CSV data from any reader, such as an in-memory buffer or a network stream.
+///
+/// Takes the same options as [`parse_csv_with_labels`].
+///
+/// # Example
+/// ```rust
+/// use rml::preprocessing::csv::{
+///     parse_csv_with_labels_from_reader, ColumnSelection, CsvOptions, LabelColumn, MissingPolicy,
+/// };
+///
+/// let bytes = b"1,2,0\n3,4,1\n";
+/// let data = parse_csv_with_labels_from_reader::<_, f64, i32>(
+///     &bytes[..],
+///     &CsvOptions::new(),
+///     &LabelColumn::Last,
+///     &ColumnSelection::All,
+///     &MissingPolicy::Error,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(data.y, vec![0, 1]);
+/// ```
+pub fn parse_csv_with_labels_from_reader<R, T, U>(
+    rdr: R,
+    options: &CsvOptions,
+    label: &LabelColumn,
+    features: &ColumnSelection,
+    missing: &MissingPolicy<T>,
+) -> Result<Dataset<T, U>>
+where
+    R: Read,
+    T: FromStr + Clone,
+    U: FromStr,
+    <T as FromStr>::Err: Display,
+    <U as FromStr>::Err: Display,
+{
+    read_chunks_from_reader(rdr, usize::MAX, options, label, features, missing)?.collect_all()
+}
+
+/// Parse a CSV file whose class labels are strings, encoding them as contiguous integers.
+///
+/// Takes the same options as [`parse_csv_with_labels`]. Classes are numbered in sorted order and
+/// their names are kept as the dataset's label names. Also returns the label-to-index map.
+#[cfg(feature = "fs")]
+pub fn parse_csv_with_encoded_labels<T>(
+    path: &str,
+    options: &CsvOptions,
+    label: &LabelColumn,
+    features: &ColumnSelection,
+    missing: &MissingPolicy<T>,
+) -> Result<EncodedOutput<T>>
+where
+    T: FromStr + Clone,
+    <T as FromStr>::Err: Display,
+{
+    parse_csv_with_encoded_labels_from_reader(File::open(path)?, options, label, features, missing)
+}
+
+/// Parse CSV data with string class labels from any reader, encoding them as integers.
+///
+/// Takes the same options as [`parse_csv_with_encoded_labels`].
+///
+/// # Example
+/// ```rust
+/// use rml::preprocessing::csv::{
+///     parse_csv_with_encoded_labels_from_reader, ColumnSelection, CsvOptions, LabelColumn,
+///     MissingPolicy,
+/// };
+///
+/// let bytes = b"5.1,setosa\n6.3,virginica\n5.0,setosa\n";
+/// let (data, mapping) = parse_csv_with_encoded_labels_from_reader::<_, f64>(
+///     &bytes[..],
+///     &CsvOptions::new(),
+///     &LabelColumn::Last,
+///     &ColumnSelection::All,
+///     &MissingPolicy::Error,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(data.y, vec![0, 1, 0]);
+/// assert_eq!(mapping["virginica"], 1);
+/// ```
+pub fn parse_csv_with_encoded_labels_from_reader<R, T>(
+    rdr: R,
+    options: &CsvOptions,
+    label: &LabelColumn,
+    features: &ColumnSelection,
+    missing: &MissingPolicy<T>,
+) -> Result<EncodedOutput<T>>
+where
+    R: Read,
+    T: FromStr + Clone,
+    <T as FromStr>::Err: Display,
+{
+    let data: Dataset<T, String> =
+        parse_csv_with_labels_from_reader(rdr, options, label, features, missing)?;
+    let mut encoder = LabelEncoder::new();
+    let y = encoder.fit_transform(&data.y)?;
+    let mapping = encoder.mapping();
+    Ok((
+        Dataset {
+            x: data.x,
+            y,
+            feature_names: data.feature_names,
+            label_names: Some(encoder.classes),
+        },
+        mapping,
+    ))
+}
+
+/// Parse a CSV file for multi-label classification, where the label column holds every label of
+/// the row separated by `separator`, e.g. `comedy|drama`.
+///
+/// Takes the same options as [`parse_csv_with_labels`]. An empty label column is an empty label
+/// set, not a missing value. The label sets can be converted to an indicator matrix with
+/// `preprocessing::label::MultiLabelBinarizer`.
+#[cfg(feature = "fs")]
+pub fn parse_csv_multi_label<T>(
+    path: &str,
+    options: &CsvOptions,
+    label: &LabelColumn,
+    features: &ColumnSelection,
+    missing: &MissingPolicy<T>,
+    separator: char,
+) -> Result<Dataset<T, Vec<String>>>
+where
+    T: FromStr + Clone,
+    <T as FromStr>::Err: Display,
+{
+    parse_csv_multi_label_from_reader(
+        File::open(path)?,
+        options,
+        label,
+        features,
+        missing,
+        separator,
+    )
+}
+
+/// Parse multi-label CSV data from any reader.
+///
+/// Takes the same options as [`parse_csv_multi_label`].
+///
+/// # Example
+/// ```rust
+/// use rml::preprocessing::csv::{
+///     parse_csv_multi_label_from_reader, ColumnSelection, CsvOptions, LabelColumn, MissingPolicy,
+/// };
+///
+/// let bytes = b"0.5,comedy|drama\n0.1,horror\n";
+/// let data = parse_csv_multi_label_from_reader::<_, f64>(
+///     &bytes[..],
+///     &CsvOptions::new(),
+///     &LabelColumn::Last,
+///     &ColumnSelection::All,
+///     &MissingPolicy::Error,
+///     '|',
+/// )
+/// .unwrap();
+///
+/// assert_eq!(data.y[0], vec!["comedy", "drama"]);
+/// ```
+pub fn parse_csv_multi_label_from_reader<R, T>(
+    rdr: R,
+    options: &CsvOptions,
+    label: &LabelColumn,
+    features: &ColumnSelection,
+    missing: &MissingPolicy<T>,
+    separator: char,
+) -> Result<Dataset<T, Vec<String>>>
+where
+    R: Read,
+    T: FromStr + Clone,
+    <T as FromStr>::Err: Display,
+{
+    let mut chunks = read_chunks_from_reader(rdr, usize::MAX, options, label, features, missing)?;
+    // An empty label column is an empty label set, not a missing label.
+    chunks.empty_labels = true;
+    let data: Dataset<T, String> = chunks.collect_all()?;
+    Ok(Dataset {
+        x: data.x,
+        y: data
+            .y
+            .into_iter()
+            .map(|labels| {
+                labels
+                    .split(separator)
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .collect(),
+        feature_names: data.feature_names,
+        label_names: None,
+    })
+}
+
+/// Parse a CSV file of numeric features into a sparse matrix.
+///
+/// Takes the same options as [`parse_csv_with_labels`], but zero values are dropped as each row
+/// is parsed, so wide files that are mostly zero, such as one-hot encodings or word counts, never
+/// have to fit in memory as dense rows.
+#[cfg(feature = "fs")]
+pub fn parse_csv_sparse<T, U>(
+    path: &str,
+    options: &CsvOptions,
+    label: &LabelColumn,
+    features: &ColumnSelection,
+    missing: &MissingPolicy<T>,
+) -> Result<SparseOutput<T, U>>
+where
+    T: FromStr + Clone + Default + PartialEq,
+    U: FromStr,
+    <T as FromStr>::Err: Display,
+    <U as FromStr>::Err: Display,
+{
+    parse_csv_sparse_from_reader(File::open(path)?, options, label, features, missing)
+}
+
+/// Parse CSV data from any reader into a sparse matrix.
+///
+/// Takes the same options as [`parse_csv_sparse`].
+///
+/// # Example
+/// ```rust
+/// use rml::preprocessing::csv::{
+///     parse_csv_sparse_from_reader, ColumnSelection, CsvOptions, LabelColumn, MissingPolicy,
+/// };
+///
+/// let bytes = b"0,0,3,1\n2,0,0,0\n";
+/// let (x, y) = parse_csv_sparse_from_reader::<_, f64, i32>(
+///     &bytes[..],
+///     &CsvOptions::new(),
+///     &LabelColumn::Last,
+///     &ColumnSelection::All,
+///     &MissingPolicy::Error,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(x.nnz(), 2);
+/// assert_eq!(x.get(0, 2), Some(&3.0));
+/// assert_eq!(y, vec![1, 0]);
+/// ```
+pub fn parse_csv_sparse_from_reader<R, T, U>(
+    rdr: R,
+    options: &CsvOptions,
+    label: &LabelColumn,
+    features: &ColumnSelection,
+    missing: &MissingPolicy<T>,
+) -> Result<SparseOutput<T, U>>
+where
+    R: Read,
+    T: FromStr + Clone + Default + PartialEq,
+    U: FromStr,
+    <T as FromStr>::Err: Display,
+    <U as FromStr>::Err: Display,
+{
+    read_chunks_from_reader(rdr, usize::MAX, options, label, features, missing)?.collect_sparse()
+}
+
+/// Read a CSV file in chunks of at most `chunk_size` rows.
+///
+/// Takes the same options as [`parse_csv_with_labels`], but only keeps one chunk in memory at a
+/// time so files larger than memory can be processed incrementally.
+///
+/// # Example
+/// ```rust,no_run
+/// use rml::preprocessing::csv::{
+///     read_chunks, ColumnSelection, CsvOptions, LabelColumn, MissingPolicy,
+/// };
+///
+/// let chunks = read_chunks::<f64, i32>(
+///     "./data/optdigits.tra",
+///     1000,
+///     &CsvOptions::new(),
+///     &LabelColumn::Last,
+///     &ColumnSelection::All,
+///     &MissingPolicy::Error,
+/// )
+/// .unwrap();
+///
+/// for chunk in chunks {
+///     println!("{} samples", chunk.unwrap().x.len());
+/// }
+/// ```
+#[cfg(feature = "fs")]
+pub fn read_chunks<T, U>(
+    path: &str,
+    chunk_size: usize,
+    options: &CsvOptions,
+    label: &LabelColumn,
+    features: &ColumnSelection,
+    missing: &MissingPolicy<T>,
+) -> Result<CsvChunks<T, U>>
+where
+    T: Clone,
+{
+    read_chunks_from_reader(
+        File::open(path)?,
+        chunk_size,
+        options,
+        label,
+        features,
+        missing,
+    )
+}
+
+/// Read CSV data from any reader in chunks of at most `chunk_size` rows.
+///
+/// Takes the same options as [`read_chunks`].
+pub fn read_chunks_from_reader<R, T, U>(
+    rdr: R,
+    chunk_size: usize,
+    options: &CsvOptions,
+    label: &LabelColumn,
+    features: &ColumnSelection,
+    missing: &MissingPolicy<T>,
+) -> Result<CsvChunks<T, U, R>>
+where
+    R: Read,
+    T: Clone,
+{
+    if chunk_size == 0 {
+        return Err(Error::InvalidInput(
+            "Chunk size must be greater than 0.".into(),
+        ));
+    }
+    let mut reader = options.reader(rdr)?;
+
+    let headers: Option<Vec<String>> = if options.has_headers {
+        Some(reader.headers()?.iter().map(String::from).collect())
+    } else {
+        None
+    };
+
+    Ok(CsvChunks {
+        records: reader.into_records(),
+        pending: None,
+        options: options.clone(),
+        empty_labels: false,
+        headers,
+        label: label.clone(),
+        features: features.clone(),
+        missing: missing.clone(),
+        columns: None,
+        chunk_size,
+        done: false,
+        labels: PhantomData,
+    })
+}
+
+/// An iterator over chunks of CSV data, created by [`read_chunks`] or
+/// [`read_chunks_from_reader`].
+pub struct CsvChunks<T, U, R = std::fs::File> {
+    records: ::csv::StringRecordsIntoIter<R>,
+    pending: Option<::csv::StringRecord>,
+    options: CsvOptions,
+    empty_labels: bool,
+    headers: Option<Vec<String>>,
+    label: LabelColumn,
+    features: ColumnSelection,
+    missing: MissingPolicy<T>,
+    columns: Option<(usize, Vec<usize>)>,
+    chunk_size: usize,
+    done: bool,
+    labels: PhantomData<U>,
+}
+
+impl<T, U, R: Read> CsvChunks<T, U, R> {
+    /// The header names of the selected feature columns, if the file has a header row.
+    /// Resolving them requires reading ahead to the first row.
+    pub fn feature_names(&mut self) -> Result<Option<Vec<String>>> {
+        if self.columns.is_none() {
+            match self.records.next() {
+                Some(record) => {
+                    let record = record?;
+                    self.resolve(record.len())?;
+                    self.pending = Some(record);
+                }
+                None => return Ok(None),
+            }
+        }
+        Ok(self.selected_names())
+    }
+
+    /// Resolve the label and feature columns for rows of length `width`.
+    fn resolve(&mut self, width: usize) -> Result<()> {
+        self.columns = Some(resolve_columns(
+            width,
+            self.headers.as_deref(),
+            &self.label,
+            &self.features,
+        )?);
+        Ok(())
+    }
+
+    /// The header names of the resolved feature columns.
+    fn selected_names(&self) -> Option<Vec<String>> {
+        match (&self.headers, &self.columns) {
+            (Some(h), Some((_, feature_idx))) => {
+                Some(feature_idx.iter().map(|&i| h[i].clone()).collect())
+            }
+            _ => None,
+        }
+    }
+
+    /// Read every remaining row into a single dataset.
+    fn collect_all(mut self) -> Result<Dataset<T, U>>
+    where
+        T: FromStr + Clone,
+        U: FromStr,
+        <T as FromStr>::Err: Display,
+        <U as FromStr>::Err: Display,
+    {
+        self.chunk_size = usize::MAX;
+        let feature_names = self.feature_names()?;
+        self.next().unwrap_or_else(|| {
+            Ok(Dataset {
+                feature_names,
+                ..Dataset::default()
+            })
+        })
+    }
+
+    /// Read every remaining row into a sparse matrix, keeping only the values that are not
+    /// zero (`T::default()`).
+    fn collect_sparse(mut self) -> Result<SparseOutput<T, U>>
+    where
+        T: FromStr + Clone + Default + PartialEq,
+        U: FromStr,
+        <T as FromStr>::Err: Display,
+        <U as FromStr>::Err: Display,
+    {
+        self.feature_names()?;
+        let n_cols = self.columns.as_ref().map_or(0, |(_, f)| f.len());
+        let mut x = CsrMatrix::new(n_cols);
+        let mut y = Vec::new();
+        let zero = T::default();
+
+        while let Some(record) = self.pending.take().map(Ok).or_else(|| self.records.next()) {
+            let record = record?;
+            let (label_idx, feature_idx) = self.columns.as_ref().unwrap();
+            let label = match parse_label(
+                &record,
+                &self.options,
+                *label_idx,
+                &self.missing,
+                self.empty_labels,
+            )? {
+                Some(label) => label,
+                None => continue,
+            };
+            let mut entries = Vec::new();
+            let push = |position, value| {
+                if value != zero {
+                    entries.push((position, value));
+                }
+            };
+            if parse_features(&record, &self.options, feature_idx, &self.missing, push)? {
+                x.push_row(entries)?;
+                y.push(label);
+            }
+        }
+        Ok((x, y))
+    }
+
+    /// Read the next chunk, or `None` if the file is exhausted.
+    fn read_chunk(&mut self) -> Result<Option<Dataset<T, U>>>
+    where
+        T: FromStr + Clone,
+        U: FromStr,
+        <T as FromStr>::Err: Display,
+        <U as FromStr>::Err: Display,
+    {
+        let mut out = Dataset::default();
+        let mut read_any = false;
+
+        while out.y.len() < self.chunk_size {
+            let record = match self.pending.take() {
+                Some(record) => record,
+                None => match self.records.next() {
+                    Some(record) => record?,
+                    None => break,
+                },
+            };
+            read_any = true;
+            if self.columns.is_none() {
+                self.resolve(record.len())?;
+            }
+            let (label_idx, feature_idx) = self.columns.as_ref().unwrap();
+            if let Some((row, label)) = parse_record(
+                &record,
+                &self.options,
+                *label_idx,
+                feature_idx,
+                &self.missing,
+                self.empty_labels,
+            )? {
+                out.x.push(row);
+                out.y.push(label);
+            }
+        }
+
+        if !read_any {
+            return Ok(None);
+        }
+        out.feature_names = self.selected_names();
+        Ok(Some(out))
+    }
+}
+
+impl<T, U, R> Iterator for CsvChunks<T, U, R>
+where
+    R: Read,
+    T: FromStr + Clone,
+    U: FromStr,
+    <T as FromStr>::Err: Display,
+    <U as FromStr>::Err: Display,
+{
+    type Item = Result<Dataset<T, U>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.read_chunk() {
+            Ok(Some(chunk)) => Some(Ok(chunk)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// A parsed feature row and its label.
+type Row<T, U> = (Vec<T>, U);
+
+/// Parse a single record into a feature row and its label.
+/// Returns `None` if the row is skipped because of a missing value.
+/// If `empty_labels` is true, an empty label is kept instead of being treated as missing.
+fn parse_record<T, U>(
+    record: &::csv::StringRecord,
+    options: &CsvOptions,
+    label_idx: usize,
+    feature_idx: &[usize],
+    missing: &MissingPolicy<T>,
+    empty_labels: bool,
+) -> Result<Option<Row<T, U>>>
+where
+    T: FromStr + Clone,
+    U: FromStr,
+    <T as FromStr>::Err: Display,
+    <U as FromStr>::Err: Display,
+{
+    let label = match parse_label(record, options, label_idx, missing, empty_labels)? {
+        Some(label) => label,
+        None => return Ok(None),
+    };
+    let mut row: Vec<T> = Vec::with_capacity(feature_idx.len());
+    if !parse_features(record, options, feature_idx, missing, |_, v| row.push(v))? {
+        return Ok(None);
+    }
+    Ok(Some((row, label)))
+}
+
+/// Parse the label of a record.
+/// Returns `None` if the row is skipped because the label is missing.
+fn parse_label<T, U>(
+    record: &::csv::StringRecord,
+    options: &CsvOptions,
+    label_idx: usize,
+    missing: &MissingPolicy<T>,
+    empty_labels: bool,
+) -> Result<Option<U>>
+where
+    U: FromStr,
+    <U as FromStr>::Err: Display,
+{
+    let label_field = options.field(record, label_idx)?;
+    if is_missing(&label_field) && !(empty_labels && label_field.is_empty()) {
+        return match missing {
+            MissingPolicy::SkipRow => Ok(None),
+            _ => Err(Error::Parse(format!(
+                "Missing label on line {}.",
+                record.position().map_or(0, |p| p.line())
+            ))),
+        };
+    }
+    Ok(Some(parse_field(record, &label_field)?))
+}
+
+/// Parse the features of a record, passing the position of each selected column and its value
+/// to `push`. Returns `false` if the row is skipped because of a missing value.
+fn parse_features<T, F>(
+    record: &::csv::StringRecord,
+    options: &CsvOptions,
+    feature_idx: &[usize],
+    missing: &MissingPolicy<T>,
+    mut push: F,
+) -> Result<bool>
+where
+    T: FromStr + Clone,
+    <T as FromStr>::Err: Display,
+    F: FnMut(usize, T),
+{
+    for (position, &i) in feature_idx.iter().enumerate() {
+        let field = options.field(record, i)?;
+        if !is_missing(&field) {
+            push(position, parse_field(record, &field)?);
+            continue;
+        }
+        match missing {
+            MissingPolicy::Error => {
+                return Err(Error::Parse(format!(
+                    "Missing value in column {} on line {}.",
+                    i,
+                    record.position().map_or(0, |p| p.line())
+                )))
+            }
+            MissingPolicy::SkipRow => return Ok(false),
+            MissingPolicy::FillWith(value) => push(position, value.clone()),
+            MissingPolicy::KeepAsNaN => push(position, parse_field(record, "NaN")?),
+        }
+    }
+    Ok(true)
+}
+
+/// Parse a single field, reporting the line of the record on failure.
+fn parse_field<T>(record: &::csv::StringRecord, field: &str) -> Result<T>
+where
+    T: FromStr,
+    <T as FromStr>::Err: Display,
+{
+    field.parse().map_err(|e| {
+        Error::Parse(format!(
+            "Could not parse '{}' on line {}: {}",
+            field,
+            record.position().map_or(0, |p| p.line()),
+            e
+        ))
+    })
+}
+
+/// Whether a field represents a missing value.
+fn is_missing(field: &str) -> bool {
+    field.is_empty() || field == "?" || field == "NA"
+}
+
+/// Find the index of a header name.
+fn find_header(headers: Option<&[String]>, name: &str) -> Result<usize> {
+    let headers = headers.ok_or_else(|| {
+        Error::InvalidInput("Selecting columns by name requires a header row.".into())
+    })?;
+    headers
+        .iter()
+        .position(|h| h == name)
+        .ok_or_else(|| Error::InvalidInput(format!("No column named '{}'.", name)))
+}
+
+/// Resolve the label index and the feature indices for rows of length `width`.
+fn resolve_columns(
+    width: usize,
+    headers: Option<&[String]>,
+    label: &LabelColumn,
+    features: &ColumnSelection,
+) -> Result<(usize, Vec<usize>)> {
+    if width == 0 {
+        return Err(Error::InvalidInput(
+            "Rows must have at least one column.".into(),
+        ));
+    }
+    let label_idx = match label {
+        LabelColumn::First => 0,
+        LabelColumn::Last => width - 1,
+        LabelColumn::Index(i) => *i,
+        LabelColumn::Name(name) => find_header(headers, name)?,
+    };
+    if label_idx >= width {
+        return Err(Error::InvalidInput(format!(
+            "Label column {} is out of range.",
+            label_idx
+        )));
+    }
+
+    let feature_idx: Vec<usize> = match features {
+        ColumnSelection::All => (0..width).collect(),
+        ColumnSelection::Indices(indices) => indices.clone(),
+        ColumnSelection::Names(names) => names
+            .iter()
+            .map(|name| find_header(headers, name))
+            .collect::<Result<_>>()?,
+    };
+    if let Some(i) = feature_idx.iter().find(|&&i| i >= width) {
+        return Err(Error::InvalidInput(format!(
+            "Feature column {} is out of range.",
+            i
+        )));
+    }
+
+    Ok((
+        label_idx,
+        feature_idx
+            .into_iter()
+            .filter(|&i| i != label_idx)
+            .collect(),
+    ))
+}
+
+/// A single column of a CSV file with an inferred type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    /// Every value parsed as a number. Missing values are stored as NaN.
+    Numeric(Vec<f64>),
+    /// Every value was `true` or `false` (case insensitive). Missing values are stored as None.
+    Boolean(Vec<Option<bool>>),
+    /// Any other column, kept as strings.
+    Categorical(Vec<String>),
+}
+
+impl Column {
+    /// The number of values in the column.
+    pub fn len(&self) -> usize {
+        match self {
+            Column::Numeric(v) => v.len(),
+            Column::Boolean(v) => v.len(),
+            Column::Categorical(v) => v.len(),
+        }
+    }
+
+    /// Whether the column has no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The output of reading a CSV file with per-column type inference.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedCsv {
+    /// The header names, if the file has a header row.
+    pub names: Option<Vec<String>>,
+    /// The columns of the file, in file order.
+    pub columns: Vec<Column>,
+}
+
+/// Read a CSV file, inferring the type of each column independently.
+///
+/// A column is `Boolean` if every non-missing value is `true` or `false` and at least one value
+/// is not missing, `Numeric` if every non-missing value parses as a number, and `Categorical`
+/// otherwise. A value is missing if the cell is empty, `?` or `NA`.
+#[cfg(feature = "fs")]
+pub fn read_csv_infer(path: &str, options: &CsvOptions) -> Result<TypedCsv> {
+    read_csv_infer_from_reader(File::open(path)?, options)
+}
+
+/// Read CSV data from any reader, inferring the type of each column independently.
+///
+/// Uses the same inference rules as [`read_csv_infer`]. Every row must have as many fields as
+/// the first row, or no more if the options are flexible; otherwise a shape mismatch naming the
+/// line is returned.
+pub fn read_csv_infer_from_reader<R: Read>(rdr: R, options: &CsvOptions) -> Result<TypedCsv> {
+    // Row lengths are checked here so a bad row is reported as a shape error with its line.
+    let mut reader = options.clone().flexible(true).reader(rdr)?;
+
+    let names: Option<Vec<String>> = if options.has_headers {
+        Some(reader.headers()?.iter().map(String::from).collect())
+    } else {
+        None
+    };
+
+    let mut width = names.as_ref().map(Vec::len);
+    let mut raw: Vec<Vec<String>> = Vec::new();
+    for line in reader.records() {
+        let record = line?;
+        let expected = *width.get_or_insert(record.len());
+        if record.len() > expected || (record.len() < expected && !options.flexible) {
+            return Err(Error::ShapeMismatch(format!(
+                "Line {} has {} fields, but the first row has {}.",
+                record.position().map_or(0, |p| p.line()),
+                record.len(),
+                expected
+            )));
+        }
+        if raw.is_empty() {
+            raw = vec![Vec::new(); expected];
+        }
+        for (i, column) in raw.iter_mut().enumerate() {
+            column.push(options.field(&record, i)?.into_owned());
+        }
+    }
+
+    Ok(TypedCsv {
+        names,
+        columns: raw.into_iter().map(infer_column).collect(),
+    })
+}
+
+/// Convert a column of raw strings to the narrowest type that fits every value.
+fn infer_column(values: Vec<String>) -> Column {
+    let present = || values.iter().filter(|v| !is_missing(v));
+    if present().next().is_some() && present().all(|v| parse_bool(v).is_some()) {
+        return Column::Boolean(values.iter().map(|v| parse_bool(v)).collect());
+    }
+    if present().all(|v| v.parse::<f64>().is_ok()) {
+        return Column::Numeric(
+            values
+                .iter()
+                .map(|v| v.parse::<f64>().unwrap_or(f64::NAN))
+                .collect(),
+        );
+    }
+    Column::Categorical(values)
+}
+
+/// Parse a boolean, ignoring case.
+fn parse_bool(value: &str) -> Option<bool> {
+    if value.eq_ignore_ascii_case("true") {
+        Some(true)
+    } else if value.eq_ignore_ascii_case("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Write a dataset to a CSV file, with the label as the last column.
+///
+/// A header row is written if the dataset has feature names, with the label column named `label`.
+#[cfg(feature = "fs")]
+pub fn write_dataset<T, U>(path: &str, data: &Dataset<T, U>) -> Result<()>
+where
+    T: Display,
+    U: Display,
+{
+    write_dataset_to_writer(File::create(path)?, data)
+}
+
+/// Write a dataset as CSV to a writer. See `write_dataset`.
+pub fn write_dataset_to_writer<W, T, U>(wtr: W, data: &Dataset<T, U>) -> Result<()>
+where
+    W: Write,
+    T: Display,
+    U: Display,
+{
+    let mut writer = ::csv::Writer::from_writer(wtr);
+    if let Some(names) = &data.feature_names {
+        writer.write_record(names.iter().map(String::as_str).chain(Some("label")))?;
+    }
+    for (x, y) in data.x.iter().zip(&data.y) {
+        writer.write_record(x.iter().map(T::to_string).chain(Some(y.to_string())))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write predictions to a CSV file with the columns `id` and `prediction`, where `id` is the
+/// index of the sample.
+///
+/// If `probabilities` is given, one `prob_<class>` column is added per class, named by
+/// `class_names` or by the class index.
+#[cfg(feature = "fs")]
+pub fn write_predictions<U: Display>(
+    path: &str,
+    predictions: &[U],
+    probabilities: Option<&[Vec<f64>]>,
+    class_names: Option<&[String]>,
+) -> Result<()> {
+    write_predictions_to_writer(File::create(path)?, predictions, probabilities, class_names)
+}
+
+/// Write predictions as CSV to a writer. See `write_predictions`.
+pub fn write_predictions_to_writer<W: Write, U: Display>(
+    wtr: W,
+    predictions: &[U],
+    probabilities: Option<&[Vec<f64>]>,
+    class_names: Option<&[String]>,
+) -> Result<()> {
+    if let Some(p) = probabilities {
+        if p.len() != predictions.len() {
+            return Err(Error::ShapeMismatch(
+                "There must be one probability vector per prediction.".into(),
+            ));
+        }
+    }
+    let num_classes = probabilities.and_then(|p| p.first()).map_or(0, |p| p.len());
+
+    let mut writer = ::csv::Writer::from_writer(wtr);
+    let mut header = vec![String::from("id"), String::from("prediction")];
+    header.extend(
+        (0..num_classes).map(|i| match class_names.and_then(|n| n.get(i)) {
+            Some(name) => format!("prob_{}", name),
+            None => format!("prob_{}", i),
+        }),
+    );
+    writer.write_record(&header)?;
+
+    for (i, prediction) in predictions.iter().enumerate() {
+        let mut record = vec![i.to_string(), prediction.to_string()];
+        if let Some(p) = probabilities {
+            record.extend(p[i].iter().map(f64::to_string));
+        }
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "fs")]
+    use std::fs;
+    #[cfg(feature = "fs")]
+    use std::path::PathBuf;
+
+    #[cfg(feature = "fs")]
+    fn write_temp(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rml_csv_{}.csv", name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn parse_csv_with_labels_test() {
+        let path = write_temp("plain", "1,2,0\n3,4,1\n");
+        let data = parse_csv_with_labels::<f64, i32>(
+            path.to_str().unwrap(),
+            &CsvOptions::new(),
+            &LabelColumn::Last,
+            &ColumnSelection::All,
+            &MissingPolicy::Error,
+        )
+        .unwrap();
+
+        assert_eq!(data.x, vec![vec![1.0, 2.0], vec![3.0, 4.0]]);
+        assert_eq!(data.y, vec![0, 1]);
+        assert_eq!(data.feature_names, None);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn parse_csv_with_headers_and_names_test() {
+        let path = write_temp("named", "a,class,b,c\n1,5,2,3\n4,6,5,6\n");
+        let data = parse_csv_with_labels::<f64, i32>(
+            path.to_str().unwrap(),
+            &CsvOptions::new().has_headers(true),
+            &LabelColumn::Name(String::from("class")),
+            &ColumnSelection::Names(vec![String::from("c"), String::from("a")]),
+            &MissingPolicy::Error,
+        )
+        .unwrap();
+
+        assert_eq!(data.x, vec![vec![3.0, 1.0], vec![6.0, 4.0]]);
+        assert_eq!(data.y, vec![5, 6]);
+        assert_eq!(
+            data.feature_names,
+            Some(vec![String::from("c"), String::from("a")])
+        );
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn parse_csv_name_without_header_test() {
+        let path = write_temp("noheader", "1,2,0\n");
+        let data = parse_csv_with_labels::<f64, i32>(
+            path.to_str().unwrap(),
+            &CsvOptions::new(),
+            &LabelColumn::Name(String::from("class")),
+            &ColumnSelection::All,
+            &MissingPolicy::Error,
+        );
+
+        assert!(data.is_err());
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn read_csv_infer_test() {
+        let path = write_temp("infer", "n,flag,color\n1.5,true,red\nNA,FALSE,2\n?,,?\n");
+        let data =
+            read_csv_infer(path.to_str().unwrap(), &CsvOptions::new().has_headers(true)).unwrap();
+
+        assert_eq!(
+            data.names,
+            Some(vec![
+                String::from("n"),
+                String::from("flag"),
+                String::from("color")
+            ])
+        );
+        match &data.columns[0] {
+            Column::Numeric(v) => assert!(v[0] == 1.5 && v[1].is_nan() && v[2].is_nan()),
+            c => panic!("Expected a numeric column, got {:?}", c),
+        }
+        assert_eq!(
+            data.columns[1],
+            Column::Boolean(vec![Some(true), Some(false), None])
+        );
+        assert_eq!(
+            data.columns[2],
+            Column::Categorical(vec![
+                String::from("red"),
+                String::from("2"),
+                String::from("?")
+            ])
+        );
+    }
+
+    #[test]
+    fn read_csv_infer_width_test() {
+        let options = CsvOptions::new().has_headers(true);
+        for (text, line) in [
+            ("a,b\n1,2\n3,4,5\n", 3),
+            ("a,b\n1,2\n3\n", 3),
+            ("a,b\n1\n", 2),
+        ] {
+            match read_csv_infer_from_reader(text.as_bytes(), &options) {
+                Err(Error::ShapeMismatch(msg)) => {
+                    assert!(msg.starts_with(&format!("Line {} ", line)), "{}", msg)
+                }
+                r => panic!("Expected a shape mismatch, got {:?}", r),
+            }
+        }
+
+        let flexible = options.flexible(true);
+        let typed = read_csv_infer_from_reader("a,b\n1,2\n3\n".as_bytes(), &flexible).unwrap();
+        match &typed.columns[1] {
+            Column::Numeric(v) => assert!(v[0] == 2.0 && v[1].is_nan()),
+            c => panic!("Expected a numeric column, got {:?}", c),
+        }
+        assert!(read_csv_infer_from_reader("a,b\n1,2,3\n".as_bytes(), &flexible).is_err());
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn missing_policy_test() {
+        let path = write_temp("missing", "1,,0\n3,4,1\n?,6,1\n");
+        let path = path.to_str().unwrap();
+        let parse = |policy: &MissingPolicy<f64>| {
+            parse_csv_with_labels::<f64, i32>(
+                path,
+                &CsvOptions::new(),
+                &LabelColumn::Last,
+                &ColumnSelection::All,
+                policy,
+            )
+        };
+
+        assert!(parse(&MissingPolicy::Error).is_err());
+        assert_eq!(
+            parse(&MissingPolicy::SkipRow).unwrap().x,
+            vec![vec![3.0, 4.0]]
+        );
+        assert_eq!(
+            parse(&MissingPolicy::FillWith(-1.0)).unwrap().x,
+            vec![vec![1.0, -1.0], vec![3.0, 4.0], vec![-1.0, 6.0]]
+        );
+        let nan = parse(&MissingPolicy::KeepAsNaN).unwrap();
+        assert!(nan.x[0][1].is_nan() && nan.x[2][0].is_nan());
+        assert_eq!(nan.y, vec![0, 1, 1]);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn read_chunks_test() {
+        let path = write_temp("chunks", "a,b,y\n1,2,0\n3,4,1\n5,6,0\n");
+        let chunks: Vec<Dataset<f64, i32>> = read_chunks(
+            path.to_str().unwrap(),
+            2,
+            &CsvOptions::new().has_headers(true),
+            &LabelColumn::Last,
+            &ColumnSelection::All,
+            &MissingPolicy::Error,
+        )
+        .unwrap()
+        .collect::<Result<_>>()
+        .unwrap();
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].x, vec![vec![1.0, 2.0], vec![3.0, 4.0]]);
+        assert_eq!(chunks[1].y, vec![0]);
+        assert_eq!(
+            chunks[1].feature_names,
+            Some(vec![String::from("a"), String::from("b")])
+        );
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn write_dataset_test() {
+        let path = std::env::temp_dir().join("rml_csv_write_dataset.csv");
+        let path = path.to_str().unwrap();
+        let data = Dataset::new(vec![vec![1.5, 2.0], vec![3.0, 4.0]], vec![0, 1])
+            .unwrap()
+            .with_feature_names(vec![String::from("a"), String::from("b")]);
+
+        write_dataset(path, &data).unwrap();
+        let read = parse_csv_with_labels::<f64, i32>(
+            path,
+            &CsvOptions::new().has_headers(true),
+            &LabelColumn::Name(String::from("label")),
+            &ColumnSelection::All,
+            &MissingPolicy::Error,
+        )
+        .unwrap();
+        assert_eq!(read, data);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn write_predictions_test() {
+        let path = std::env::temp_dir().join("rml_csv_write_predictions.csv");
+        let names = vec![String::from("cat"), String::from("dog")];
+
+        write_predictions(
+            path.to_str().unwrap(),
+            &[1, 0],
+            Some(&[vec![0.25, 0.75], vec![1.0, 0.0]]),
+            Some(&names),
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "id,prediction,prob_cat,prob_dog\n0,1,0.25,0.75\n1,0,1,0\n"
+        );
+    }
+
+    #[test]
+    fn to_writer_test() {
+        let data = Dataset::new(vec![vec![1.5, 2.0]], vec![1])
+            .unwrap()
+            .with_feature_names(vec![String::from("a"), String::from("b")]);
+        let mut out = Vec::new();
+        write_dataset_to_writer(&mut out, &data).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "a,b,label\n1.5,2,1\n");
+
+        let mut out = Vec::new();
+        write_predictions_to_writer(&mut out, &[0, 1], None, None).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "id,prediction\n0,0\n1,1\n");
+        assert!(write_predictions_to_writer(Vec::new(), &[0], Some(&[]), None).is_err());
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn csv_options_test() {
+        let path = write_temp(
+            "options",
+            "# exported data\nx;y;class\n1,5;'a;b';0\n 2 ;3\n",
+        );
+        let options = CsvOptions::new()
+            .has_headers(true)
+            .delimiter(b';')
+            .quote(b'\'')
+            .comment(Some(b'#'))
+            .flexible(true)
+            .decimal_comma(true);
+        let data = parse_csv_with_labels::<f64, i32>(
+            path.to_str().unwrap(),
+            &options,
+            &LabelColumn::Name(String::from("class")),
+            &ColumnSelection::Names(vec![String::from("x")]),
+            &MissingPolicy::SkipRow,
+        )
+        .unwrap();
+        // The second row is short, so its label is missing and the row is skipped.
+        assert_eq!(data.x, vec![vec![1.5]]);
+        assert_eq!(data.y, vec![0]);
+
+        let typed = read_csv_infer(path.to_str().unwrap(), &options).unwrap();
+        assert_eq!(typed.columns[0], Column::Numeric(vec![1.5, 2.0]));
+        assert_eq!(
+            typed.columns[1],
+            Column::Categorical(vec![String::from("a;b"), String::from("3")])
+        );
+        match &typed.columns[2] {
+            Column::Numeric(v) => assert!(v[0] == 0.0 && v[1].is_nan()),
+            c => panic!("Expected a numeric column, got {:?}", c),
+        }
+    }
+
+    #[test]
+    fn from_reader_test() {
+        let bytes = "a,b,y\n1,2,0\n3,4,1\n5,6,0\n".as_bytes();
+        let data = parse_csv_with_labels_from_reader::<_, f64, i32>(
+            bytes,
+            &CsvOptions::new().has_headers(true),
+            &LabelColumn::Last,
+            &ColumnSelection::All,
+            &MissingPolicy::Error,
+        )
+        .unwrap();
+        assert_eq!(data.n_samples(), 3);
+        assert_eq!(
+            data.feature_names,
+            Some(vec![String::from("a"), String::from("b")])
+        );
+
+        let chunks = read_chunks_from_reader::<_, f64, i32>(
+            bytes,
+            2,
+            &CsvOptions::new().has_headers(true),
+            &LabelColumn::First,
+            &ColumnSelection::All,
+            &MissingPolicy::Error,
+        )
+        .unwrap();
+        assert_eq!(chunks.count(), 2);
+
+        let typed = read_csv_infer_from_reader(bytes, &CsvOptions::new()).unwrap();
+        assert_eq!(typed.columns[2].len(), 4);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn parse_csv_multi_label_test() {
+        let path = write_temp("multi_label", "x,tags\n1,a|b\n2,\n3, c \n");
+        let data = parse_csv_multi_label::<f64>(
+            path.to_str().unwrap(),
+            &CsvOptions::new().has_headers(true),
+            &LabelColumn::Name(String::from("tags")),
+            &ColumnSelection::All,
+            &MissingPolicy::Error,
+            '|',
+        )
+        .unwrap();
+
+        assert_eq!(data.x, vec![vec![1.0], vec![2.0], vec![3.0]]);
+        assert_eq!(
+            data.y,
+            vec![
+                vec![String::from("a"), String::from("b")],
+                vec![],
+                vec![String::from("c")]
+            ]
+        );
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn parse_csv_with_encoded_labels_test() {
+        let path = write_temp("encoded_labels", "a,class\n1,dog\n2,cat\n3,dog\n");
+        let (data, mapping) = parse_csv_with_encoded_labels::<f64>(
+            path.to_str().unwrap(),
+            &CsvOptions::new().has_headers(true),
+            &LabelColumn::Name(String::from("class")),
+            &ColumnSelection::All,
+            &MissingPolicy::Error,
+        )
+        .unwrap();
+
+        assert_eq!(data.y, vec![1, 0, 1]);
+        assert_eq!(
+            data.label_names,
+            Some(vec![String::from("cat"), String::from("dog")])
+        );
+        assert_eq!(mapping["cat"], 0);
+        assert_eq!(mapping.len(), 2);
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn parse_csv_sparse_test() {
+        let path = write_temp("sparse", "a,b,c,y\n0,1.5,0,0\n0,0,0,1\n,2,0,1\n4,0,0,0\n");
+        let (x, y) = parse_csv_sparse::<f64, i32>(
+            path.to_str().unwrap(),
+            &CsvOptions::new().has_headers(true),
+            &LabelColumn::Last,
+            &ColumnSelection::All,
+            &MissingPolicy::SkipRow,
+        )
+        .unwrap();
+
+        assert_eq!(x.n_cols(), 3);
+        assert_eq!(x.nnz(), 2);
+        assert_eq!(
+            x.to_dense(),
+            vec![vec![0.0, 1.5, 0.0], vec![0.0; 3], vec![4.0, 0.0, 0.0]]
+        );
+        assert_eq!(y, vec![0, 1, 0]);
+    }
+}
@@ -0,0 +1,277 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Transformers for class labels.
+
+use crate::describe::{Describe, Summary};
+use crate::error::{Error, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+
+/// Encodes class labels of any ordered type as contiguous integers `0..n_classes`.
+///
+/// # Example
+/// ```rust
+/// use rml::preprocessing::label::LabelEncoder;
+///
+/// let y = vec!["spam", "ham", "spam"];
+/// let mut encoder = LabelEncoder::new();
+/// let encoded = encoder.fit_transform(&y).unwrap();
+///
+/// assert_eq!(encoder.classes, vec!["ham", "spam"]);
+/// assert_eq!(encoded, vec![1, 0, 1]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelEncoder<L> {
+    /// The known classes, in sorted order. Class `classes[i]` is encoded as `i`.
+    pub classes: Vec<L>,
+}
+
+impl<L: Ord + Clone> Default for LabelEncoder<L> {
+    fn default() -> Self {
+        LabelEncoder {
+            classes: Vec::new(),
+        }
+    }
+}
+
+impl<L: Ord + Clone> LabelEncoder<L> {
+    /// Create a new, unfitted encoder.
+    pub fn new() -> LabelEncoder<L> {
+        LabelEncoder::default()
+    }
+
+    /// The heap memory of the classes, in bytes. Memory owned by the classes themselves, such
+    /// as the text of string labels, is not counted.
+    pub fn memory_usage(&self) -> usize {
+        self.classes.capacity() * std::mem::size_of::<L>()
+    }
+
+    /// Learn the classes from the labels in `y`.
+    pub fn fit(&mut self, y: &[L]) {
+        let classes: BTreeSet<&L> = y.iter().collect();
+        self.classes = classes.into_iter().cloned().collect();
+    }
+
+    /// Encode each label as the index of its class.
+    /// Returns an error if a label was not seen during fitting.
+    pub fn transform(&self, y: &[L]) -> Result<Vec<i32>> {
+        y.iter()
+            .map(|l| {
+                self.classes
+                    .binary_search(l)
+                    .map(|i| i as i32)
+                    .map_err(|_| Error::InvalidInput("Unknown label.".into()))
+            })
+            .collect()
+    }
+
+    /// Fit to `y`, then transform it.
+    pub fn fit_transform(&mut self, y: &[L]) -> Result<Vec<i32>> {
+        self.fit(y);
+        self.transform(y)
+    }
+
+    /// Convert encoded labels back to their classes.
+    /// Returns an error if a value is not a valid class index.
+    pub fn inverse_transform(&self, y: &[i32]) -> Result<Vec<L>> {
+        y.iter()
+            .map(|&i| {
+                usize::try_from(i)
+                    .ok()
+                    .and_then(|i| self.classes.get(i))
+                    .cloned()
+                    .ok_or_else(|| Error::InvalidInput("Unknown class index.".into()))
+            })
+            .collect()
+    }
+
+    /// The label-to-index map.
+    pub fn mapping(&self) -> BTreeMap<L, i32> {
+        self.classes
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (c.clone(), i as i32))
+            .collect()
+    }
+}
+
+impl<L: Ord + Clone> Describe for LabelEncoder<L> {
+    fn describe(&self) -> Summary {
+        let mut summary = Summary::new("LabelEncoder");
+        summary.n_classes = Some(self.classes.len());
+        summary.memory = self.memory_usage();
+        summary
+    }
+}
+
+/// Converts sets of labels to and from a binary indicator matrix, with one column per class.
+///
+/// # Example
+/// ```rust
+/// use rml::preprocessing::label::MultiLabelBinarizer;
+///
+/// let y = vec![vec!["comedy", "drama"], vec!["horror"]];
+/// let mut binarizer = MultiLabelBinarizer::new();
+/// let indicators = binarizer.fit_transform(&y).unwrap();
+///
+/// assert_eq!(binarizer.classes, vec!["comedy", "drama", "horror"]);
+/// assert_eq!(indicators, vec![vec![1, 1, 0], vec![0, 0, 1]]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiLabelBinarizer<L> {
+    /// The known classes, in sorted order. Column `i` of the indicator matrix is `classes[i]`.
+    pub classes: Vec<L>,
+}
+
+impl<L: Ord + Clone> Default for MultiLabelBinarizer<L> {
+    fn default() -> Self {
+        MultiLabelBinarizer {
+            classes: Vec::new(),
+        }
+    }
+}
+
+impl<L: Ord + Clone> MultiLabelBinarizer<L> {
+    /// Create a new, unfitted binarizer.
+    pub fn new() -> MultiLabelBinarizer<L> {
+        MultiLabelBinarizer::default()
+    }
+
+    /// The heap memory of the classes, in bytes. Memory owned by the classes themselves, such
+    /// as the text of string labels, is not counted.
+    pub fn memory_usage(&self) -> usize {
+        self.classes.capacity() * std::mem::size_of::<L>()
+    }
+
+    /// Learn the classes from the label sets in `y`.
+    pub fn fit(&mut self, y: &[Vec<L>]) {
+        let classes: BTreeSet<&L> = y.iter().flatten().collect();
+        self.classes = classes.into_iter().cloned().collect();
+    }
+
+    /// Convert each label set to the indices of its classes, in sorted order.
+    /// Returns an error if a label was not seen during fitting.
+    pub fn to_indices(&self, y: &[Vec<L>]) -> Result<Vec<Vec<usize>>> {
+        y.iter()
+            .map(|labels| {
+                let mut indices = labels
+                    .iter()
+                    .map(|l| {
+                        self.classes
+                            .binary_search(l)
+                            .map_err(|_| Error::InvalidInput("Unknown label.".into()))
+                    })
+                    .collect::<Result<Vec<usize>>>()?;
+                indices.sort_unstable();
+                indices.dedup();
+                Ok(indices)
+            })
+            .collect()
+    }
+
+    /// Convert each label set to a row of the indicator matrix.
+    /// Returns an error if a label was not seen during fitting.
+    pub fn transform(&self, y: &[Vec<L>]) -> Result<Vec<Vec<u8>>> {
+        Ok(self
+            .to_indices(y)?
+            .into_iter()
+            .map(|indices| {
+                let mut row = vec![0; self.classes.len()];
+                indices.into_iter().for_each(|i| row[i] = 1);
+                row
+            })
+            .collect())
+    }
+
+    /// Fit to `y`, then transform it.
+    pub fn fit_transform(&mut self, y: &[Vec<L>]) -> Result<Vec<Vec<u8>>> {
+        self.fit(y);
+        self.transform(y)
+    }
+
+    /// Convert rows of the indicator matrix back to label sets.
+    pub fn inverse_transform(&self, indicators: &[Vec<u8>]) -> Vec<Vec<L>> {
+        indicators
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(&self.classes)
+                    .filter(|(&v, _)| v != 0)
+                    .map(|(_, c)| c.clone())
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl<L: Ord + Clone> Describe for MultiLabelBinarizer<L> {
+    fn describe(&self) -> Summary {
+        let mut summary = Summary::new("MultiLabelBinarizer");
+        summary.n_classes = Some(self.classes.len());
+        summary.memory = self.memory_usage();
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_encoder_test() {
+        let y = vec!["b", "a", "c", "a"];
+        let mut encoder = LabelEncoder::new();
+        let encoded = encoder.fit_transform(&y).unwrap();
+
+        assert_eq!(encoded, vec![1, 0, 2, 0]);
+        assert_eq!(encoder.mapping()["c"], 2);
+        assert_eq!(encoder.inverse_transform(&encoded).unwrap(), y);
+        assert!(encoder.transform(&["d"]).is_err());
+        assert!(encoder.inverse_transform(&[3]).is_err());
+        assert_eq!(encoder.describe().n_classes, Some(3));
+    }
+
+    #[test]
+    fn multi_label_binarizer_test() {
+        let y = vec![vec![3, 1], vec![2], vec![], vec![1, 1]];
+        let mut binarizer = MultiLabelBinarizer::new();
+        let indicators = binarizer.fit_transform(&y).unwrap();
+
+        assert_eq!(binarizer.classes, vec![1, 2, 3]);
+        assert_eq!(
+            indicators,
+            vec![vec![1, 0, 1], vec![0, 1, 0], vec![0, 0, 0], vec![1, 0, 0]]
+        );
+        assert_eq!(
+            binarizer.to_indices(&y).unwrap(),
+            vec![vec![0, 2], vec![1], vec![], vec![0]]
+        );
+        assert_eq!(
+            binarizer.inverse_transform(&indicators),
+            vec![vec![1, 3], vec![2], vec![], vec![1]]
+        );
+    }
+
+    #[test]
+    fn unknown_label_test() {
+        let mut binarizer = MultiLabelBinarizer::new();
+        binarizer.fit(&[vec!["a"]]);
+
+        assert!(binarizer.transform(&[vec!["b"]]).is_err());
+    }
+}
@@ -0,0 +1,537 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Reading and writing NumPy `.npy` and `.npz` files.
+
+/*!
+Supports 1D and 2D arrays of `f64` (`<f8`) and `f32` (`<f4`), in either C or Fortran order
+and either byte order. `.npz` bundles are supported when their entries are stored uncompressed,
+which is what `numpy.savez` produces (`numpy.savez_compressed` is not supported).
+
+# Example
+```rust,no_run
+use rml::preprocessing::npy::{read_npy, write_npy, NpyArray};
+
+let x = NpyArray::from_rows(&[vec![1.0, 2.0], vec![3.0, 4.0]]).unwrap();
+write_npy("features.npy", &x).unwrap();
+
+let loaded: NpyArray<f64> = read_npy("features.npy").unwrap();
+assert_eq!(loaded.to_rows().unwrap(), vec![vec![1.0, 2.0], vec![3.0, 4.0]]);
+```
+!*/
+
+use crate::error::{Error, Result};
+use std::convert::{TryFrom, TryInto};
+#[cfg(feature = "fs")]
+use std::fs;
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+/// An element type that can be stored in a `.npy` file.
+pub trait NpyElement: Copy {
+    /// The NumPy type code, without the byte order character.
+    const TYPE_CODE: &'static str;
+    /// The size of one element in bytes.
+    const SIZE: usize;
+
+    /// Read an element from little endian bytes.
+    fn from_le(bytes: &[u8]) -> Self;
+    /// Append the little endian bytes of the element to `out`.
+    fn write_le(self, out: &mut Vec<u8>);
+}
+
+impl NpyElement for f64 {
+    const TYPE_CODE: &'static str = "f8";
+    const SIZE: usize = 8;
+
+    fn from_le(bytes: &[u8]) -> Self {
+        f64::from_le_bytes(bytes.try_into().unwrap())
+    }
+
+    fn write_le(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl NpyElement for f32 {
+    const TYPE_CODE: &'static str = "f4";
+    const SIZE: usize = 4;
+
+    fn from_le(bytes: &[u8]) -> Self {
+        f32::from_le_bytes(bytes.try_into().unwrap())
+    }
+
+    fn write_le(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+/// A 1D or 2D array, stored in row-major (C) order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NpyArray<T> {
+    /// The shape of the array, with one or two dimensions.
+    pub shape: Vec<usize>,
+    /// The elements in row-major order.
+    pub data: Vec<T>,
+}
+
+impl<T: NpyElement> NpyArray<T> {
+    /// Create a 1D array.
+    pub fn from_vec(data: Vec<T>) -> NpyArray<T> {
+        NpyArray {
+            shape: vec![data.len()],
+            data,
+        }
+    }
+
+    /// Create a 2D array from rows, which must all have the same length.
+    pub fn from_rows(rows: &[Vec<T>]) -> Result<NpyArray<T>> {
+        let cols = rows.first().map_or(0, |r| r.len());
+        if rows.iter().any(|r| r.len() != cols) {
+            return Err(Error::ShapeMismatch(
+                "All rows must have the same length.".into(),
+            ));
+        }
+        Ok(NpyArray {
+            shape: vec![rows.len(), cols],
+            data: rows.iter().flatten().cloned().collect(),
+        })
+    }
+
+    /// Split a 2D array into rows.
+    pub fn to_rows(&self) -> Result<Vec<Vec<T>>> {
+        match self.shape[..] {
+            [_, 0] => Ok(vec![Vec::new(); self.shape[0]]),
+            [_, cols] => Ok(self.data.chunks(cols).map(|r| r.to_vec()).collect()),
+            _ => Err(Error::Parse(
+                "Only 2D arrays can be split into rows.".into(),
+            )),
+        }
+    }
+}
+
+/// Read a `.npy` file.
+#[cfg(feature = "fs")]
+pub fn read_npy<T: NpyElement>(path: &str) -> Result<NpyArray<T>> {
+    parse_npy(&fs::read(path)?)
+}
+
+/// Write a `.npy` file.
+#[cfg(feature = "fs")]
+pub fn write_npy<T: NpyElement>(path: &str, array: &NpyArray<T>) -> Result<()> {
+    fs::write(path, npy_bytes(array)?)?;
+    Ok(())
+}
+
+/// Parse the contents of a `.npy` file.
+pub fn parse_npy<T: NpyElement>(bytes: &[u8]) -> Result<NpyArray<T>> {
+    if bytes.len() < 10 || &bytes[..6] != MAGIC {
+        return Err(Error::Parse("Not a .npy file.".into()));
+    }
+    let (header_len, start) = match bytes[6] {
+        1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+        2 | 3 if bytes.len() >= 12 => (read_u32(bytes, 8) as usize, 12),
+        v => return Err(Error::Parse(format!("Unsupported .npy version {}.", v))),
+    };
+    let header = std::str::from_utf8(
+        bytes
+            .get(start..start + header_len)
+            .ok_or_else(|| Error::Parse("Truncated .npy header.".into()))?,
+    )?;
+
+    let descr = header_value(header, "descr")?;
+    let descr = descr.trim_matches(|c| c == '\'' || c == '"');
+    let big_endian = match descr.as_bytes().first() {
+        Some(b'<') | Some(b'|') | Some(b'=') => false,
+        Some(b'>') => true,
+        _ => return Err(Error::Parse(format!("Unsupported dtype '{}'.", descr))),
+    };
+    if &descr[1..] != T::TYPE_CODE {
+        return Err(Error::Parse(format!(
+            "Expected dtype {}, found '{}'.",
+            T::TYPE_CODE,
+            descr
+        )));
+    }
+
+    let fortran_order = match header_value(header, "fortran_order")? {
+        "True" => true,
+        "False" => false,
+        v => return Err(Error::Parse(format!("Invalid fortran_order '{}'.", v))),
+    };
+
+    let shape: Vec<usize> = header_value(header, "shape")?
+        .trim_matches(|c| c == '(' || c == ')')
+        .split(',')
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .map(|d| d.parse().map_err(Error::from))
+        .collect::<Result<_>>()?;
+    if shape.is_empty() || shape.len() > 2 {
+        return Err(Error::Parse("Only 1D and 2D arrays are supported.".into()));
+    }
+
+    let size = shape
+        .iter()
+        .try_fold(T::SIZE, |size, &d| size.checked_mul(d))
+        .ok_or_else(|| Error::Parse("The .npy shape is too large.".into()))?;
+    let body = &bytes[start + header_len..];
+    if body.len() < size {
+        return Err(Error::Parse("Truncated .npy data.".into()));
+    }
+    let mut data: Vec<T> = body[..size]
+        .chunks(T::SIZE)
+        .map(|chunk| {
+            if big_endian {
+                let swapped: Vec<u8> = chunk.iter().rev().cloned().collect();
+                T::from_le(&swapped)
+            } else {
+                T::from_le(chunk)
+            }
+        })
+        .collect();
+
+    if fortran_order && shape.len() == 2 {
+        let (rows, cols) = (shape[0], shape[1]);
+        data = (0..rows * cols)
+            .map(|i| data[(i % cols) * rows + i / cols])
+            .collect();
+    }
+    Ok(NpyArray { shape, data })
+}
+
+/// Encode an array as the contents of a `.npy` file (format version 1.0, little endian, C order).
+pub fn npy_bytes<T: NpyElement>(array: &NpyArray<T>) -> Result<Vec<u8>> {
+    if array.shape.is_empty() || array.shape.len() > 2 {
+        return Err(Error::Parse("Only 1D and 2D arrays are supported.".into()));
+    }
+    if array.shape.iter().product::<usize>() != array.data.len() {
+        return Err(Error::ShapeMismatch(
+            "The shape does not match the number of elements.".into(),
+        ));
+    }
+
+    let shape = match array.shape[..] {
+        [n] => format!("({},)", n),
+        [r, c] => format!("({}, {})", r, c),
+        _ => unreachable!(),
+    };
+    let mut header = format!(
+        "{{'descr': '<{}', 'fortran_order': False, 'shape': {}, }}",
+        T::TYPE_CODE,
+        shape
+    );
+    // The magic, version, header length, header and newline are padded to a multiple of 64.
+    while (10 + header.len() + 1) % 64 != 0 {
+        header.push(' ');
+    }
+    header.push('\n');
+
+    let mut out = Vec::with_capacity(10 + header.len() + array.data.len() * T::SIZE);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&[1, 0]);
+    out.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    out.extend_from_slice(header.as_bytes());
+    array.data.iter().for_each(|&x| x.write_le(&mut out));
+    Ok(out)
+}
+
+/// Arrays paired with their names in a `.npz` file.
+pub type NamedArrays<T> = Vec<(String, NpyArray<T>)>;
+
+/// Read every array in a `.npz` file, returning the arrays with their names.
+#[cfg(feature = "fs")]
+pub fn read_npz<T: NpyElement>(path: &str) -> Result<NamedArrays<T>> {
+    parse_npz(&fs::read(path)?)
+}
+
+/// Parse every array in the contents of a `.npz` file, returning the arrays with their names.
+pub fn parse_npz<T: NpyElement>(bytes: &[u8]) -> Result<NamedArrays<T>> {
+    read_zip_entries(bytes)?
+        .into_iter()
+        .map(|(name, data)| {
+            let name = name.strip_suffix(".npy").unwrap_or(&name).to_string();
+            Ok((name, parse_npy(data)?))
+        })
+        .collect()
+}
+
+/// Write named arrays to a `.npz` file, readable by `numpy.load`.
+#[cfg(feature = "fs")]
+pub fn write_npz<T: NpyElement>(path: &str, arrays: &[(&str, &NpyArray<T>)]) -> Result<()> {
+    fs::write(path, npz_bytes(arrays)?)?;
+    Ok(())
+}
+
+/// Encode named arrays as the contents of a `.npz` file.
+pub fn npz_bytes<T: NpyElement>(arrays: &[(&str, &NpyArray<T>)]) -> Result<Vec<u8>> {
+    let mut out: Vec<u8> = Vec::new();
+    let mut central: Vec<u8> = Vec::new();
+
+    for (name, array) in arrays {
+        let name = format!("{}.npy", name);
+        let data = npy_bytes(array)?;
+        let crc = crc32(&data);
+        let offset = out.len() as u32;
+
+        out.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        write_entry_fields(&mut out, crc, data.len() as u32, name.len() as u16);
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(&data);
+
+        central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes());
+        write_entry_fields(&mut central, crc, data.len() as u32, name.len() as u16);
+        central.extend_from_slice(&[0; 6]); // Comment length, disk, internal attributes.
+        central.extend_from_slice(&[0; 4]); // External attributes.
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+
+    let central_offset = out.len() as u32;
+    out.extend_from_slice(&central);
+    out.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    out.extend_from_slice(&[0; 4]); // Disk numbers.
+    out.extend_from_slice(&(arrays.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(arrays.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    out.extend_from_slice(&central_offset.to_le_bytes());
+    out.extend_from_slice(&[0; 2]); // Comment length.
+    Ok(out)
+}
+
+/// Write the fields shared by local and central zip headers for a stored entry.
+fn write_entry_fields(out: &mut Vec<u8>, crc: u32, size: u32, name_len: u16) {
+    out.extend_from_slice(&20u16.to_le_bytes()); // Version needed.
+    out.extend_from_slice(&[0; 4]); // Flags and method (stored).
+    out.extend_from_slice(&[0, 0, 0x21, 0]); // Time and date, 1980-01-01.
+    out.extend_from_slice(&crc.to_le_bytes());
+    out.extend_from_slice(&size.to_le_bytes());
+    out.extend_from_slice(&size.to_le_bytes());
+    out.extend_from_slice(&name_len.to_le_bytes());
+    out.extend_from_slice(&[0; 2]); // Extra field length.
+}
+
+/// The name and contents of a zip entry.
+type ZipEntry<'a> = (String, &'a [u8]);
+
+/// Read the names and contents of the stored entries of a zip archive.
+fn read_zip_entries(bytes: &[u8]) -> Result<Vec<ZipEntry<'_>>> {
+    let eocd = (0..bytes.len().saturating_sub(21))
+        .rev()
+        .find(|&i| read_u32(bytes, i) == 0x0605_4b50)
+        .ok_or_else(|| Error::Parse("Not a .npz file.".into()))?;
+    let count = read_u16(bytes, eocd + 10) as usize;
+    let mut pos = read_u32(bytes, eocd + 16) as usize;
+
+    let mut entries = Vec::with_capacity(count);
+    for _ in 0..count {
+        if pos + 46 > bytes.len() || read_u32(bytes, pos) != 0x0201_4b50 {
+            return Err(Error::Parse("Corrupt .npz central directory.".into()));
+        }
+        let method = read_u16(bytes, pos + 10);
+        let crc = read_u32(bytes, pos + 16);
+        let mut size = read_u32(bytes, pos + 20) as u64;
+        let name_len = read_u16(bytes, pos + 28) as usize;
+        let extra_len = read_u16(bytes, pos + 30) as usize;
+        let comment_len = read_u16(bytes, pos + 32) as usize;
+        if pos + 46 + name_len + extra_len > bytes.len() {
+            return Err(Error::Parse("Corrupt .npz central directory.".into()));
+        }
+        let mut offset = read_u32(bytes, pos + 42) as u64;
+        let name = String::from_utf8(bytes[pos + 46..pos + 46 + name_len].to_vec())?;
+
+        // Zip64 entries (numpy always writes them) keep the real sizes in an extra field.
+        let extra = &bytes[pos + 46 + name_len..pos + 46 + name_len + extra_len];
+        if let Some(zip64) = find_zip64_extra(extra) {
+            let mut values = zip64.chunks_exact(8).map(|c| read_u64(c, 0));
+            if read_u32(bytes, pos + 24) == u32::MAX {
+                values.next();
+            }
+            if size == u32::MAX as u64 {
+                size = values
+                    .next()
+                    .ok_or_else(|| Error::Parse("Corrupt zip64 field.".into()))?;
+            }
+            if offset == u32::MAX as u64 {
+                offset = values
+                    .next()
+                    .ok_or_else(|| Error::Parse("Corrupt zip64 field.".into()))?;
+            }
+        }
+        if method != 0 {
+            return Err(Error::Parse(format!(
+                "Entry '{}' is compressed, which is not supported.",
+                name
+            )));
+        }
+
+        let local = usize::try_from(offset)
+            .ok()
+            .filter(|&local| local.checked_add(30).is_some_and(|end| end <= bytes.len()))
+            .filter(|&local| read_u32(bytes, local) == 0x0403_4b50)
+            .ok_or_else(|| Error::Parse("Corrupt .npz local header.".into()))?;
+        let start = local
+            + 30
+            + read_u16(bytes, local + 26) as usize
+            + read_u16(bytes, local + 28) as usize;
+        let data = usize::try_from(size)
+            .ok()
+            .and_then(|size| start.checked_add(size))
+            .and_then(|end| bytes.get(start..end))
+            .ok_or_else(|| Error::Parse("Truncated .npz entry.".into()))?;
+        if crc32(data) != crc {
+            return Err(Error::Parse(format!(
+                "Checksum mismatch in entry '{}'.",
+                name
+            )));
+        }
+        entries.push((name, data));
+        pos += 46 + name_len + extra_len + comment_len;
+    }
+    Ok(entries)
+}
+
+/// Find the data of the zip64 extended information extra field.
+fn find_zip64_extra(mut extra: &[u8]) -> Option<&[u8]> {
+    while extra.len() >= 4 {
+        let id = read_u16(extra, 0);
+        let len = read_u16(extra, 2) as usize;
+        let data = extra.get(4..4 + len)?;
+        if id == 0x0001 {
+            return Some(data);
+        }
+        extra = &extra[4 + len..];
+    }
+    None
+}
+
+/// Find the value of `key` in a `.npy` header dictionary.
+fn header_value<'a>(header: &'a str, key: &str) -> Result<&'a str> {
+    let start = header
+        .find(&format!("'{}':", key))
+        .ok_or_else(|| Error::Parse(format!("Missing '{}' in .npy header.", key)))?
+        + key.len()
+        + 3;
+    let rest = header[start..].trim_start();
+    let end = if rest.starts_with('(') {
+        rest.find(')').map(|i| i + 1)
+    } else {
+        rest.find([',', '}'])
+    }
+    .ok_or_else(|| Error::Parse("Malformed .npy header.".into()))?;
+    Ok(rest[..end].trim())
+}
+
+/// Compute the CRC-32 checksum used by zip files.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in bytes {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (!(crc & 1)).wrapping_add(1));
+        }
+    }
+    !crc
+}
+
+fn read_u16(bytes: &[u8], i: usize) -> u16 {
+    u16::from_le_bytes([bytes[i], bytes[i + 1]])
+}
+
+fn read_u32(bytes: &[u8], i: usize) -> u32 {
+    u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], i: usize) -> u64 {
+    u64::from_le_bytes(bytes[i..i + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn npy_round_trip_test() {
+        let x = NpyArray::from_rows(&[vec![1.0f64, 2.0, 3.0], vec![4.0, 5.0, 6.0]]).unwrap();
+        let bytes = npy_bytes(&x).unwrap();
+
+        assert_eq!(bytes.len() % 64, 48);
+        assert_eq!(parse_npy::<f64>(&bytes).unwrap(), x);
+        assert!(parse_npy::<f32>(&bytes).is_err());
+
+        let y = NpyArray::from_vec(vec![1.5f32, -2.0]);
+        assert_eq!(parse_npy::<f32>(&npy_bytes(&y).unwrap()).unwrap(), y);
+    }
+
+    #[test]
+    fn parse_fortran_big_endian_test() {
+        let header = "{'descr': '>f8', 'fortran_order': True, 'shape': (2, 2), }\n";
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&[1, 0]);
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        for v in &[1.0f64, 3.0, 2.0, 4.0] {
+            bytes.extend_from_slice(&v.to_be_bytes());
+        }
+
+        let x = parse_npy::<f64>(&bytes).unwrap();
+        assert_eq!(x.to_rows().unwrap(), vec![vec![1.0, 2.0], vec![3.0, 4.0]]);
+    }
+
+    #[test]
+    fn parse_huge_shape_test() {
+        let header =
+            "{'descr': '<f8', 'fortran_order': False, 'shape': (4294967296, 4294967296), }\n";
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&[1, 0]);
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+
+        assert!(matches!(parse_npy::<f64>(&bytes), Err(Error::Parse(_))));
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn npz_round_trip_test() {
+        let path = std::env::temp_dir().join("rml_npz_round_trip.npz");
+        let path = path.to_str().unwrap();
+        let x = NpyArray::from_rows(&[vec![1.0f64, 2.0], vec![3.0, 4.0]]).unwrap();
+        let y = NpyArray::from_vec(vec![0.0f64, 1.0]);
+
+        write_npz(path, &[("x", &x), ("y", &y)]).unwrap();
+        let arrays = read_npz::<f64>(path).unwrap();
+        assert_eq!(arrays, vec![(String::from("x"), x), (String::from("y"), y)]);
+    }
+
+    #[test]
+    fn npz_bytes_test() {
+        let x = NpyArray::from_vec(vec![1.5f32, -2.0]);
+        let bytes = npz_bytes(&[("x", &x)]).unwrap();
+
+        assert_eq!(
+            parse_npz::<f32>(&bytes).unwrap(),
+            vec![(String::from("x"), x)]
+        );
+        assert!(parse_npz::<f32>(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn crc32_test() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+}
@@ -0,0 +1,278 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+//! Scaling and normalizing features.
+
+/*!
+`StandardScaler` learns the mean and standard deviation of each feature and rescales features to
+zero mean and unit variance. `Normalizer` scales each sample to unit norm and learns nothing.
+
+Both implement `traits::Fit` for any target type and `traits::Transform`.
+
+# Example
+```rust
+use rml::preprocessing::scale::StandardScaler;
+use rml::traits::{Fit, Transform};
+
+let x = vec![vec![1.0, 10.0], vec![3.0, 10.0]];
+let mut scaler = StandardScaler::new();
+let scaled = scaler.fit_transform(&x, &[0, 1]).unwrap();
+
+assert_eq!(scaled, vec![vec![-1.0, 0.0], vec![1.0, 0.0]]);
+```
+!*/
+
+use crate::describe::{Describe, Summary};
+use crate::error::{Error, Result};
+use crate::math::norm::{normalize_vector, Norm};
+use crate::traits::{Fit, IncrementalFit, Transform};
+
+/// Rescales each feature to zero mean and unit variance.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StandardScaler {
+    /// The mean of each feature, once fit.
+    pub mean: Vec<f64>,
+    /// The variance of each feature, once fit.
+    pub var: Vec<f64>,
+    /// The standard deviation of each feature, once fit. Constant features have a scale of 1,
+    /// so they are only centered.
+    pub scale: Vec<f64>,
+    /// The number of samples fit so far.
+    pub n_samples: usize,
+}
+
+impl StandardScaler {
+    /// Create a new, unfitted scaler.
+    pub fn new() -> StandardScaler {
+        StandardScaler::default()
+    }
+
+    /// The heap memory of the learned statistics, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        (self.mean.capacity() + self.var.capacity() + self.scale.capacity())
+            * std::mem::size_of::<f64>()
+    }
+}
+
+impl<U> Fit<f64, U> for StandardScaler {
+    /// Learn the mean and standard deviation of each feature of `x`. The targets are ignored.
+    fn fit(&mut self, x: &[Vec<f64>], y: &[U]) -> Result<()> {
+        *self = StandardScaler::new();
+        self.partial_fit(x, y)
+    }
+}
+
+impl<U> IncrementalFit<f64, U> for StandardScaler {
+    /// Update the mean and standard deviation of each feature with the samples in `x`, as if
+    /// they had been fit together with the samples seen before. The targets are ignored.
+    fn partial_fit(&mut self, x: &[Vec<f64>], _: &[U]) -> Result<()> {
+        let n_features = check_features(x, (self.n_samples > 0).then_some(self.mean.len()))?;
+        if x.is_empty() {
+            return Ok(());
+        }
+        let n = x.len() as f64;
+        let mean: Vec<f64> = (0..n_features)
+            .map(|j| x.iter().map(|row| row[j]).sum::<f64>() / n)
+            .collect();
+        let var: Vec<f64> = (0..n_features)
+            .map(|j| x.iter().map(|row| (row[j] - mean[j]).powi(2)).sum::<f64>() / n)
+            .collect();
+
+        if self.n_samples == 0 {
+            self.mean = mean;
+            self.var = var;
+        } else {
+            // Combine the statistics of the seen and new samples (Chan et al.).
+            let seen = self.n_samples as f64;
+            let total = seen + n;
+            for j in 0..n_features {
+                let delta = mean[j] - self.mean[j];
+                self.var[j] =
+                    (self.var[j] * seen + var[j] * n + delta.powi(2) * seen * n / total) / total;
+                self.mean[j] += delta * n / total;
+            }
+        }
+        self.n_samples += x.len();
+        self.scale = self
+            .var
+            .iter()
+            .map(|&v| if v > 0.0 { v.sqrt() } else { 1.0 })
+            .collect();
+        Ok(())
+    }
+}
+
+impl Transform<f64> for StandardScaler {
+    /// Subtract the mean and divide by the standard deviation of each feature.
+    /// Returns an error if the scaler is not fit or the number of features differs.
+    fn transform(&self, x: &[Vec<f64>]) -> Result<Vec<Vec<f64>>> {
+        if self.mean.is_empty() {
+            return Err(Error::NotFitted);
+        }
+        check_features(x, Some(self.mean.len()))?;
+        Ok(x.iter()
+            .map(|row| {
+                row.iter()
+                    .zip(self.mean.iter().zip(&self.scale))
+                    .map(|(v, (m, s))| (v - m) / s)
+                    .collect()
+            })
+            .collect())
+    }
+}
+
+impl Describe for StandardScaler {
+    fn describe(&self) -> Summary {
+        let mut summary = Summary::new("StandardScaler");
+        if !self.mean.is_empty() {
+            summary.n_samples = Some(self.n_samples);
+            summary.n_features = Some(self.mean.len());
+        }
+        summary.memory = self.memory_usage();
+        summary
+    }
+}
+
+/// Scales each sample to unit norm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Normalizer {
+    /// The norm each sample is scaled to.
+    pub norm: Norm,
+}
+
+impl Normalizer {
+    /// Create a normalizer with the given norm.
+    pub fn new(norm: Norm) -> Normalizer {
+        Normalizer { norm }
+    }
+}
+
+impl<U> Fit<f64, U> for Normalizer {
+    /// Does nothing, as each sample is normalized independently.
+    fn fit(&mut self, _: &[Vec<f64>], _: &[U]) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Transform<f64> for Normalizer {
+    /// Divide each sample by its norm. Samples with a norm of 0 are unchanged.
+    fn transform(&self, x: &[Vec<f64>]) -> Result<Vec<Vec<f64>>> {
+        Ok(x.iter()
+            .map(|row| {
+                let mut row = row.clone();
+                normalize_vector(&mut row, &self.norm);
+                row
+            })
+            .collect())
+    }
+}
+
+impl Describe for Normalizer {
+    fn describe(&self) -> Summary {
+        Summary::new("Normalizer").param("norm", self.norm)
+    }
+}
+
+/// Check that `x` is not empty and every sample has `n_features` features, or as many as the
+/// first sample. Returns the number of features.
+fn check_features(x: &[Vec<f64>], n_features: Option<usize>) -> Result<usize> {
+    let n_features = match (n_features, x.first()) {
+        (Some(n), _) => n,
+        (None, Some(first)) => first.len(),
+        (None, None) => return Err(Error::InvalidInput("No samples to fit.".into())),
+    };
+    match x.iter().find(|row| row.len() != n_features) {
+        Some(row) => Err(Error::ShapeMismatch(format!(
+            "Expected {} features but a sample has {}.",
+            n_features,
+            row.len()
+        ))),
+        None => Ok(n_features),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standard_scaler_test() {
+        let x = vec![vec![0.0, 5.0], vec![4.0, 5.0], vec![8.0, 5.0]];
+        let mut scaler = StandardScaler::new();
+        Fit::<f64, i32>::fit(&mut scaler, &x, &[]).unwrap();
+
+        assert_eq!(scaler.mean, vec![4.0, 5.0]);
+        assert_eq!(scaler.scale[1], 1.0);
+        let scaled = scaler
+            .transform(&[vec![4.0 + scaler.scale[0], 6.0]])
+            .unwrap();
+        assert_eq!(scaled, vec![vec![1.0, 1.0]]);
+        assert!(matches!(
+            scaler.transform(&[vec![1.0]]),
+            Err(Error::ShapeMismatch(_))
+        ));
+        assert!(matches!(
+            StandardScaler::new().transform(&x),
+            Err(Error::NotFitted)
+        ));
+    }
+
+    #[test]
+    fn partial_fit_test() {
+        let x = vec![
+            vec![1.0, 5.0],
+            vec![2.0, 5.0],
+            vec![4.0, 5.0],
+            vec![9.0, 5.0],
+        ];
+        let mut full = StandardScaler::new();
+        Fit::<f64, i32>::fit(&mut full, &x, &[]).unwrap();
+        let mut chunked = StandardScaler::new();
+        for chunk in x.chunks(3) {
+            IncrementalFit::<f64, i32>::partial_fit(&mut chunked, chunk, &[]).unwrap();
+        }
+
+        assert_eq!(chunked.n_samples, 4);
+        assert_eq!(chunked.mean, full.mean);
+        assert!((chunked.var[0] - full.var[0]).abs() < 1e-12);
+        assert_eq!(chunked.scale[1], 1.0);
+        assert!(IncrementalFit::<f64, i32>::partial_fit(&mut chunked, &[vec![1.0]], &[]).is_err());
+    }
+
+    #[test]
+    fn normalizer_test() {
+        let x = vec![vec![3.0, 4.0], vec![0.0, 0.0]];
+        let normalized = Normalizer::new(Norm::L2)
+            .fit_transform(&x, &[0, 0])
+            .unwrap();
+
+        assert_eq!(normalized, vec![vec![0.6, 0.8], vec![0.0, 0.0]]);
+    }
+
+    #[test]
+    fn describe_test() {
+        let mut scaler = StandardScaler::new();
+        assert_eq!(scaler.describe().n_features, None);
+
+        scaler.fit(&[vec![1.0, 2.0, 3.0]], &[0]).unwrap();
+        assert_eq!(scaler.describe().n_features, Some(3));
+        assert_eq!(scaler.describe().memory, 9 * 8);
+        assert_eq!(
+            Normalizer::new(Norm::L1).describe().params,
+            vec![("norm".to_string(), "L1".to_string())]
+        );
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Sliding-window features for time series.
+
+/*!
+Turns a time series into a supervised dataset for forecasting. Each sample's features are
+`window` consecutive time steps, flattened in time order, and its target is the time step
+`horizon` steps after the end of the window.
+
+# Example
+```rust
+use rml::preprocessing::window::sliding_windows_univariate;
+
+let series = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+let data = sliding_windows_univariate(&series, 3, 1, 1).unwrap();
+
+assert_eq!(data.x[0], vec![1.0, 2.0, 3.0]);
+assert_eq!(data.y, vec![4.0, 5.0, 6.0]);
+```
+!*/
+
+use crate::datasets::Dataset;
+use crate::error::{Error, Result};
+
+/// Convert a multivariate time series into windows and targets.
+///
+/// Each element of `series` is one time step holding every variable. A window of `window` steps
+/// starts every `stride` steps, and its target is the whole time step `horizon` steps after the
+/// window's last step, so `horizon = 1` forecasts the next step. Windows without a target are
+/// dropped. Returns an error if `window`, `stride` or `horizon` is 0, or if the time steps have
+/// different numbers of variables.
+pub fn sliding_windows<T: Clone>(
+    series: &[Vec<T>],
+    window: usize,
+    stride: usize,
+    horizon: usize,
+) -> Result<Dataset<T, Vec<T>>> {
+    if window == 0 || stride == 0 || horizon == 0 {
+        return Err(Error::InvalidInput(
+            "Window, stride and horizon must be greater than 0.".into(),
+        ));
+    }
+    if let Some(first) = series.first() {
+        if series.iter().any(|step| step.len() != first.len()) {
+            return Err(Error::ShapeMismatch(
+                "Every time step must have the same number of variables.".into(),
+            ));
+        }
+    }
+
+    let mut data = Dataset::default();
+    let mut start = 0;
+    while start + window + horizon <= series.len() {
+        let end = start + window;
+        data.x.push(series[start..end].concat());
+        data.y.push(series[end + horizon - 1].clone());
+        start += stride;
+    }
+    Ok(data)
+}
+
+/// Convert a univariate time series into windows and targets.
+///
+/// Takes the same arguments as [`sliding_windows`], with one value per time step.
+pub fn sliding_windows_univariate<T: Clone>(
+    series: &[T],
+    window: usize,
+    stride: usize,
+    horizon: usize,
+) -> Result<Dataset<T, T>> {
+    let steps: Vec<Vec<T>> = series.iter().map(|v| vec![v.clone()]).collect();
+    let data = sliding_windows(&steps, window, stride, horizon)?;
+    Ok(Dataset {
+        x: data.x,
+        y: data.y.into_iter().flatten().collect(),
+        ..Dataset::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sliding_windows_test() {
+        let series = vec![
+            vec![0, 10],
+            vec![1, 11],
+            vec![2, 12],
+            vec![3, 13],
+            vec![4, 14],
+        ];
+        let data = sliding_windows(&series, 2, 2, 1).unwrap();
+
+        assert_eq!(data.x, vec![vec![0, 10, 1, 11], vec![2, 12, 3, 13]]);
+        assert_eq!(data.y, vec![vec![2, 12], vec![4, 14]]);
+        assert!(sliding_windows(&series, 0, 1, 1).is_err());
+        assert!(sliding_windows(&[vec![1], vec![]], 1, 1, 1).is_err());
+    }
+
+    #[test]
+    fn horizon_test() {
+        let series: Vec<i32> = (0..6).collect();
+        let data = sliding_windows_univariate(&series, 2, 1, 3).unwrap();
+
+        assert_eq!(data.x, vec![vec![0, 1], vec![1, 2]]);
+        assert_eq!(data.y, vec![4, 5]);
+        assert!(sliding_windows_univariate(&series, 6, 1, 1)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,438 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Building pipelines from component names and parameters.
+
+/*!
+A `Registry` maps names such as `"standard_scaler"` or `"knn"` to constructors that take a map
+of string parameters, so a pipeline can be assembled from configuration at runtime instead of
+from compile-time types. Components are written as `name` or `name(key=value, ...)`, and a
+pipeline as its preprocessing steps followed by the estimator.
+
+The built-in components are:
+
+- `standard_scaler`
+- `normalizer(norm=l1|l2)`, L2 by default
+- `knn(k=5, distance=euclidean|manhattan|minkowski|hamming|cosine|precomputed, p=2,
+  normalize=l1|l2, standardize=zscore|minmax, weighting=uniform|distance,
+  index=brute_force|ball_tree|hnsw)`, where `p` is the power of the Minkowski distance
+
+Other components can be added with `register_transformer` and `register_estimator`.
+
+# Example
+```rust
+use rml::registry::Registry;
+use rml::traits::{Fit, Predict};
+
+let x = vec![vec![1.0, 200.0], vec![2.0, 100.0], vec![9.0, 210.0], vec![8.0, 90.0]];
+let y = vec![0, 0, 1, 1];
+
+let config = ["standard_scaler", "knn(k=1, distance=manhattan)"];
+let mut model = Registry::new().pipeline(&config).unwrap();
+model.fit(&x, &y).unwrap();
+
+assert_eq!(model.predict(&[vec![8.5, 150.0]]).unwrap(), vec![1]);
+```
+!*/
+
+use crate::error::{Error, Result};
+use crate::knn::{IndexKind, Standardization, Weighting, KNN};
+use crate::math::distance::Distance;
+use crate::math::norm::Norm;
+use crate::pipeline::{Pipeline, Transformer};
+use crate::preprocessing::scale::{Normalizer, StandardScaler};
+use crate::traits::{Fit, Predict};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// The parameters of a component, by name.
+pub type Params = BTreeMap<String, String>;
+
+/// A type-erased estimator built by a registry.
+pub type Estimator = Box<dyn Classifier>;
+
+/// A pipeline assembled by a registry.
+pub type DynPipeline = Pipeline<f64, i32, Estimator>;
+
+type TransformerFactory =
+    Box<dyn Fn(&Params) -> Result<Box<dyn Transformer<f64, i32>>> + Send + Sync>;
+type EstimatorFactory = Box<dyn Fn(&Params) -> Result<Estimator> + Send + Sync>;
+
+/// An estimator that can be built by a registry. Implemented for every cloneable, thread-safe
+/// type that implements `Fit` and `Predict`.
+pub trait Classifier: Fit<f64, i32> + Predict<f64, i32> + Send + Sync {
+    /// Clone the estimator into a new box.
+    fn clone_box(&self) -> Estimator;
+}
+
+impl<X> Classifier for X
+where
+    X: Fit<f64, i32> + Predict<f64, i32> + Clone + Send + Sync + 'static,
+{
+    fn clone_box(&self) -> Estimator {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Estimator {
+    fn clone(&self) -> Self {
+        self.as_ref().clone_box()
+    }
+}
+
+impl Fit<f64, i32> for Estimator {
+    fn fit(&mut self, x: &[Vec<f64>], y: &[i32]) -> Result<()> {
+        self.as_mut().fit(x, y)
+    }
+}
+
+impl Predict<f64, i32> for Estimator {
+    fn predict(&self, x: &[Vec<f64>]) -> Result<Vec<i32>> {
+        self.as_ref().predict(x)
+    }
+}
+
+/// A component name with its parameters, parsed from `name` or `name(key=value, ...)`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Component {
+    /// The name the component is registered under.
+    pub name: String,
+    /// The parameters passed to its constructor.
+    pub params: Params,
+}
+
+impl Component {
+    /// Create a component with no parameters.
+    pub fn new(name: &str) -> Component {
+        Component {
+            name: name.to_string(),
+            params: Params::new(),
+        }
+    }
+
+    /// Set a parameter.
+    pub fn param(mut self, key: &str, value: impl Display) -> Self {
+        self.params.insert(key.to_string(), value.to_string());
+        self
+    }
+}
+
+impl FromStr for Component {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Component> {
+        let s = s.trim();
+        let (name, args) = match s.find('(') {
+            Some(open) if s.ends_with(')') => (&s[..open], &s[open + 1..s.len() - 1]),
+            Some(_) => return Err(Error::Parse(format!("Missing ')' in \"{}\".", s))),
+            None => (s, ""),
+        };
+        let mut component = Component::new(name.trim());
+        if component.name.is_empty() {
+            return Err(Error::Parse(format!(
+                "Missing component name in \"{}\".",
+                s
+            )));
+        }
+        for arg in args.split(',').map(str::trim).filter(|a| !a.is_empty()) {
+            let (key, value) = arg
+                .split_once('=')
+                .ok_or_else(|| Error::Parse(format!("Expected key=value but got \"{}\".", arg)))?;
+            component = component.param(key.trim(), value.trim());
+        }
+        Ok(component)
+    }
+}
+
+/// Maps component names to constructors.
+pub struct Registry {
+    transformers: BTreeMap<String, TransformerFactory>,
+    estimators: BTreeMap<String, EstimatorFactory>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Registry::new()
+    }
+}
+
+impl Registry {
+    /// Create a registry with the built-in components.
+    pub fn new() -> Registry {
+        let mut registry = Registry::empty();
+        registry.register_transformer("standard_scaler", |params| {
+            check_params(params, &[])?;
+            Ok(Box::new(StandardScaler::new()))
+        });
+        registry.register_transformer("normalizer", |params| {
+            check_params(params, &["norm"])?;
+            let norm = parse_norm(params.get("norm"))?.unwrap_or(Norm::L2);
+            Ok(Box::new(Normalizer::new(norm)))
+        });
+        registry.register_estimator("knn", |params| {
+            check_params(
+                params,
+                &[
+                    "k",
+                    "distance",
+                    "p",
+                    "normalize",
+                    "standardize",
+                    "weighting",
+                    "index",
+                ],
+            )?;
+            let k = get(params, "k", 5)?;
+            let distance = match params.get("distance").map(String::as_str) {
+                None | Some("euclidean") => Distance::Euclidean,
+                Some("manhattan") => Distance::Manhattan,
+                Some("minkowski") => Distance::Minkowski(get(params, "p", 2.0)?),
+                Some("hamming") => Distance::Hamming,
+                Some("cosine") => Distance::Cosine,
+                Some("precomputed") => Distance::Precomputed,
+                Some(other) => {
+                    return Err(Error::InvalidInput(format!(
+                        "Unknown distance \"{}\".",
+                        other
+                    )))
+                }
+            };
+            let normalize = parse_norm(params.get("normalize"))?;
+            let standardize = match params.get("standardize").map(String::as_str) {
+                None => None,
+                Some("zscore") => Some(Standardization::ZScore),
+                Some("minmax") => Some(Standardization::MinMax),
+                Some(other) => {
+                    return Err(Error::InvalidInput(format!(
+                        "Unknown standardization \"{}\".",
+                        other
+                    )))
+                }
+            };
+            let weighting = match params.get("weighting").map(String::as_str) {
+                None | Some("uniform") => Weighting::Uniform,
+                Some("distance") => Weighting::Distance,
+                Some(other) => {
+                    return Err(Error::InvalidInput(format!(
+                        "Unknown weighting \"{}\".",
+                        other
+                    )))
+                }
+            };
+            let index = match params.get("index").map(String::as_str) {
+                None | Some("brute_force") => IndexKind::BruteForce,
+                Some("ball_tree") => IndexKind::BallTree,
+                Some("hnsw") => IndexKind::hnsw(),
+                Some(other) => {
+                    return Err(Error::InvalidInput(format!("Unknown index \"{}\".", other)))
+                }
+            };
+            let mut knn = KNN::<f64>::unfitted(k, Some(distance), normalize)
+                .weighting(weighting)
+                .with_index(index);
+            knn.standardize = standardize;
+            Ok(Box::new(knn))
+        });
+        registry
+    }
+
+    /// Create a registry without any components.
+    pub fn empty() -> Registry {
+        Registry {
+            transformers: BTreeMap::new(),
+            estimators: BTreeMap::new(),
+        }
+    }
+
+    /// Register a preprocessing step under `name`, replacing any component with that name.
+    pub fn register_transformer<C>(&mut self, name: &str, constructor: C)
+    where
+        C: Fn(&Params) -> Result<Box<dyn Transformer<f64, i32>>> + Send + Sync + 'static,
+    {
+        self.estimators.remove(name);
+        self.transformers
+            .insert(name.to_string(), Box::new(constructor));
+    }
+
+    /// Register an estimator under `name`, replacing any component with that name.
+    pub fn register_estimator<C>(&mut self, name: &str, constructor: C)
+    where
+        C: Fn(&Params) -> Result<Estimator> + Send + Sync + 'static,
+    {
+        self.transformers.remove(name);
+        self.estimators
+            .insert(name.to_string(), Box::new(constructor));
+    }
+
+    /// The names of the registered components, in sorted order.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .transformers
+            .keys()
+            .chain(self.estimators.keys())
+            .map(String::as_str)
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Build the preprocessing step `component`.
+    /// Returns an error if it is not a registered preprocessing step or its parameters are
+    /// invalid.
+    pub fn transformer(&self, component: &Component) -> Result<Box<dyn Transformer<f64, i32>>> {
+        let constructor = self
+            .transformers
+            .get(&component.name)
+            .ok_or_else(|| self.unknown(&component.name, "preprocessing step"))?;
+        constructor(&component.params).map_err(|e| e.context(&component.name))
+    }
+
+    /// Build the estimator `component`.
+    /// Returns an error if it is not a registered estimator or its parameters are invalid.
+    pub fn estimator(&self, component: &Component) -> Result<Estimator> {
+        let constructor = self
+            .estimators
+            .get(&component.name)
+            .ok_or_else(|| self.unknown(&component.name, "estimator"))?;
+        constructor(&component.params).map_err(|e| e.context(&component.name))
+    }
+
+    /// Build a pipeline from its components: the preprocessing steps in order, then the
+    /// estimator. Returns an error if there are no components, or one fails to parse or build.
+    pub fn pipeline<S: AsRef<str>>(&self, components: &[S]) -> Result<DynPipeline> {
+        let components = components
+            .iter()
+            .map(|c| c.as_ref().parse())
+            .collect::<Result<Vec<Component>>>()?;
+        self.pipeline_from_components(&components)
+    }
+
+    /// Build a pipeline from parsed components, like [`Registry::pipeline`].
+    pub fn pipeline_from_components(&self, components: &[Component]) -> Result<DynPipeline> {
+        let (estimator, steps) = components
+            .split_last()
+            .ok_or_else(|| Error::InvalidInput("A pipeline needs an estimator.".into()))?;
+        steps.iter().try_fold(
+            Pipeline::new(self.estimator(estimator)?),
+            |pipeline, step| Ok(pipeline.boxed_step(self.transformer(step)?)),
+        )
+    }
+
+    fn unknown(&self, name: &str, kind: &str) -> Error {
+        Error::InvalidInput(format!(
+            "Unknown {} \"{}\". Registered components are: {}.",
+            kind,
+            name,
+            self.names().join(", ")
+        ))
+    }
+}
+
+/// Check that `params` only has the keys in `allowed`.
+fn check_params(params: &Params, allowed: &[&str]) -> Result<()> {
+    match params.keys().find(|k| !allowed.contains(&k.as_str())) {
+        Some(key) => Err(Error::InvalidInput(format!(
+            "Unknown parameter \"{}\".",
+            key
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Parse the parameter `key`, or return `default` if it is not set.
+fn get<P: FromStr>(params: &Params, key: &str, default: P) -> Result<P> {
+    match params.get(key) {
+        Some(value) => value
+            .parse()
+            .map_err(|_| Error::Parse(format!("Invalid value \"{}\" for {}.", value, key))),
+        None => Ok(default),
+    }
+}
+
+fn parse_norm(value: Option<&String>) -> Result<Option<Norm>> {
+    match value.map(String::as_str) {
+        None => Ok(None),
+        Some("l1") => Ok(Some(Norm::L1)),
+        Some("l2") => Ok(Some(Norm::L2)),
+        Some(other) => Err(Error::InvalidInput(format!("Unknown norm \"{}\".", other))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn component_parse_test() {
+        let c: Component = " knn( k = 3, distance=manhattan ) ".parse().unwrap();
+
+        assert_eq!(
+            c,
+            Component::new("knn")
+                .param("k", 3)
+                .param("distance", "manhattan")
+        );
+        assert_eq!(
+            "standard_scaler".parse::<Component>().unwrap().params.len(),
+            0
+        );
+        assert!("knn(k=3".parse::<Component>().is_err());
+        assert!("knn(k)".parse::<Component>().is_err());
+        assert!("(k=3)".parse::<Component>().is_err());
+    }
+
+    #[test]
+    fn pipeline_test() {
+        let registry = Registry::new();
+        let mut model = registry
+            .pipeline(&["normalizer(norm=l1)", "knn(k=1, index=ball_tree)"])
+            .unwrap();
+        model
+            .fit(&[vec![1.0, 0.0], vec![0.0, 1.0]], &[0, 1])
+            .unwrap();
+
+        assert_eq!(model.len(), 1);
+        assert_eq!(model.clone().predict(&[vec![5.0, 1.0]]).unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn registry_errors_test() {
+        let mut registry = Registry::new();
+
+        assert!(registry.pipeline::<&str>(&[]).is_err());
+        assert!(registry.pipeline(&["knn", "standard_scaler"]).is_err());
+        assert!(registry.pipeline(&["knn(k=three)"]).is_err());
+        assert!(registry.pipeline(&["knn(leaf_size=3)"]).is_err());
+        assert!(registry.pipeline(&["knn(standardize=robust)"]).is_err());
+        assert!(registry.pipeline(&["knn(standardize=zscore)"]).is_ok());
+        assert!(registry.pipeline(&["knn(distance=minkowski, p=3)"]).is_ok());
+        assert!(registry
+            .pipeline(&["knn(distance=minkowski, p=high)"])
+            .is_err());
+        assert!(registry.pipeline(&["forest"]).is_err());
+
+        registry.register_estimator("forest", |_| {
+            Ok(Box::new(KNN::<f64>::unfitted(1, None, None)))
+        });
+        assert!(registry.pipeline(&["forest"]).is_ok());
+        assert_eq!(
+            registry.names(),
+            vec!["forest", "knn", "normalizer", "standard_scaler"]
+        );
+    }
+}
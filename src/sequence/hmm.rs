@@ -0,0 +1,413 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Hidden Markov models.
+
+use crate::describe::{Describe, Summary};
+use crate::error::{Error, Result};
+use crate::math::random::Rng;
+use std::mem;
+
+/// A hidden Markov model with discrete observations `0..n_symbols`.
+///
+/// Training by Baum-Welch starts from random probabilities and raises the likelihood of the
+/// training sequences on every iteration until it improves by less than `tol`. It finds a
+/// local optimum, so different seeds can give different models. The forward and backward
+/// passes are scaled at every step, so long sequences don't underflow.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HMM {
+    /// The number of hidden states.
+    pub n_states: usize,
+    /// The maximum number of Baum-Welch iterations.
+    pub max_iter: usize,
+    /// The fit stops once the log likelihood improves by less than this.
+    pub tol: f64,
+    /// The seed used to choose the initial probabilities, or None for the global seed.
+    pub seed: Option<u64>,
+    /// The probability of starting in each state.
+    pub start: Vec<f64>,
+    /// `transition[i][j]` is the probability of moving from state `i` to state `j`.
+    pub transition: Vec<Vec<f64>>,
+    /// `emission[i][k]` is the probability of observing symbol `k` in state `i`.
+    pub emission: Vec<Vec<f64>>,
+    /// The log likelihood of the training sequences, once fit.
+    pub log_likelihood: f64,
+    /// The number of iterations the last fit ran.
+    pub n_iter: usize,
+}
+
+/// The scaled forward and backward probabilities of one sequence.
+struct Passes {
+    alpha: Vec<Vec<f64>>,
+    beta: Vec<Vec<f64>>,
+    scale: Vec<f64>,
+}
+
+impl HMM {
+    /// Create an unfitted HMM with `n_states` hidden states, at most 100 iterations and a
+    /// tolerance of `1e-6`.
+    pub fn new(n_states: usize) -> HMM {
+        HMM {
+            n_states,
+            max_iter: 100,
+            tol: 1e-6,
+            seed: None,
+            start: Vec::new(),
+            transition: Vec::new(),
+            emission: Vec::new(),
+            log_likelihood: 0.0,
+            n_iter: 0,
+        }
+    }
+
+    /// Set the maximum number of Baum-Welch iterations.
+    pub fn max_iter(mut self, max_iter: usize) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    /// Set the tolerance on the improvement of the log likelihood.
+    pub fn tol(mut self, tol: f64) -> Self {
+        self.tol = tol;
+        self
+    }
+
+    /// The seed used to choose the initial probabilities.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// The number of distinct observations.
+    pub fn n_symbols(&self) -> usize {
+        self.emission.first().map_or(0, Vec::len)
+    }
+
+    /// Train on the observation `sequences` by Baum-Welch, replacing anything learned before.
+    /// The symbols are `0..m`, where `m - 1` is the largest observation.
+    /// Returns an error if there are no hidden states or no observations.
+    pub fn fit(&mut self, sequences: &[Vec<usize>]) -> Result<()> {
+        if self.n_states == 0 {
+            return Err(Error::InvalidInput(
+                "There must be at least 1 state.".into(),
+            ));
+        }
+        let n_symbols = match sequences.iter().flatten().max() {
+            Some(&max) => max + 1,
+            None => return Err(Error::InvalidInput("There are no observations.".into())),
+        };
+        let mut rng = Rng::from_seed(self.seed);
+        let mut random_row = |len: usize| {
+            let row: Vec<f64> = (0..len).map(|_| 0.5 + rng.next_f64()).collect();
+            let total: f64 = row.iter().sum();
+            row.into_iter().map(|p| p / total).collect::<Vec<f64>>()
+        };
+        self.start = random_row(self.n_states);
+        self.transition = (0..self.n_states)
+            .map(|_| random_row(self.n_states))
+            .collect();
+        self.emission = (0..self.n_states).map(|_| random_row(n_symbols)).collect();
+        let sequences: Vec<&Vec<usize>> = sequences.iter().filter(|s| !s.is_empty()).collect();
+
+        let mut previous = f64::NEG_INFINITY;
+        self.n_iter = 0;
+        while self.n_iter < self.max_iter {
+            self.n_iter += 1;
+            self.log_likelihood = self.baum_welch_step(&sequences);
+            if self.log_likelihood - previous < self.tol {
+                break;
+            }
+            previous = self.log_likelihood;
+        }
+        Ok(())
+    }
+
+    /// The log probability of the observation `sequence` under the model, summed over every
+    /// path of hidden states. An empty sequence has a log probability of 0, and one the model
+    /// can't emit, such as one with a symbol never seen in training, has negative infinity.
+    /// Returns an error if the HMM is not fit or a symbol is past the largest one it was fit on.
+    pub fn score(&self, sequence: &[usize]) -> Result<f64> {
+        self.check(sequence)?;
+        Ok(self.forward(sequence).1.iter().map(|c| c.ln()).sum())
+    }
+
+    /// The most likely path of hidden states for the observation `sequence`, by the Viterbi
+    /// algorithm, and its joint log probability with the sequence.
+    /// Returns an error under the same conditions as [`HMM::score`].
+    pub fn decode(&self, sequence: &[usize]) -> Result<(Vec<usize>, f64)> {
+        self.check(sequence)?;
+        if sequence.is_empty() {
+            return Ok((Vec::new(), 0.0));
+        }
+        let n = self.n_states;
+        let mut delta: Vec<f64> = (0..n)
+            .map(|i| self.start[i].ln() + self.emission[i][sequence[0]].ln())
+            .collect();
+        let mut back: Vec<Vec<usize>> = Vec::with_capacity(sequence.len());
+        for &o in &sequence[1..] {
+            let (next, from): (Vec<f64>, Vec<usize>) = (0..n)
+                .map(|j| {
+                    let (best, p) = (0..n)
+                        .map(|i| (i, delta[i] + self.transition[i][j].ln()))
+                        .max_by(|a, b| a.1.total_cmp(&b.1))
+                        .unwrap_or((0, f64::NEG_INFINITY));
+                    (p + self.emission[j][o].ln(), best)
+                })
+                .unzip();
+            delta = next;
+            back.push(from);
+        }
+        let (last, log_prob) = delta
+            .iter()
+            .copied()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap_or((0, f64::NEG_INFINITY));
+        let mut path = vec![last];
+        for from in back.iter().rev() {
+            path.push(from[path[path.len() - 1]]);
+        }
+        path.reverse();
+        Ok((path, log_prob))
+    }
+
+    /// Check that the HMM is fit and knows every symbol of `sequence`.
+    fn check(&self, sequence: &[usize]) -> Result<()> {
+        if self.emission.is_empty() {
+            return Err(Error::NotFitted);
+        }
+        match sequence.iter().find(|&&o| o >= self.n_symbols()) {
+            Some(o) => Err(Error::InvalidInput(format!(
+                "Symbol {} is not in 0..{}.",
+                o,
+                self.n_symbols()
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// The forward probabilities of each step, each normalized to sum to 1, and the
+    /// normalizing constants, whose logs sum to the log likelihood. Once a step is impossible
+    /// its constant is 0, and so are the probabilities and constants of every later step.
+    fn forward(&self, sequence: &[usize]) -> (Vec<Vec<f64>>, Vec<f64>) {
+        let n = self.n_states;
+        let mut alpha: Vec<Vec<f64>> = Vec::with_capacity(sequence.len());
+        let mut scale = Vec::with_capacity(sequence.len());
+        for (t, &o) in sequence.iter().enumerate() {
+            let mut a: Vec<f64> = (0..n)
+                .map(|j| {
+                    let prior = match t {
+                        0 => self.start[j],
+                        _ => (0..n)
+                            .map(|i| alpha[t - 1][i] * self.transition[i][j])
+                            .sum(),
+                    };
+                    prior * self.emission[j][o]
+                })
+                .collect();
+            let c: f64 = a.iter().sum();
+            if c > 0.0 {
+                a.iter_mut().for_each(|p| *p /= c);
+            }
+            alpha.push(a);
+            scale.push(c);
+        }
+        (alpha, scale)
+    }
+
+    /// The forward and backward passes over `sequence`, with the backward probabilities
+    /// scaled by the same constants as the forward ones.
+    fn passes(&self, sequence: &[usize]) -> Passes {
+        let n = self.n_states;
+        let (alpha, scale) = self.forward(sequence);
+        let mut beta = vec![vec![1.0; n]; sequence.len()];
+        for t in (0..sequence.len() - 1).rev() {
+            let o = sequence[t + 1];
+            beta[t] = (0..n)
+                .map(|i| {
+                    (0..n)
+                        .map(|j| self.transition[i][j] * self.emission[j][o] * beta[t + 1][j])
+                        .sum::<f64>()
+                        / scale[t + 1]
+                })
+                .collect();
+        }
+        Passes { alpha, beta, scale }
+    }
+
+    /// Re-estimate every probability from the expected counts under the current model and
+    /// return the log likelihood of `sequences` under the current model.
+    fn baum_welch_step(&mut self, sequences: &[&Vec<usize>]) -> f64 {
+        let (n, m) = (self.n_states, self.n_symbols());
+        let mut start = vec![0.0; n];
+        let mut transition = vec![vec![0.0; n]; n];
+        let mut emission = vec![vec![0.0; m]; n];
+        let mut log_likelihood = 0.0;
+        for sequence in sequences {
+            let Passes { alpha, beta, scale } = self.passes(sequence);
+            log_likelihood += scale.iter().map(|c| c.ln()).sum::<f64>();
+            for (t, &o) in sequence.iter().enumerate() {
+                for i in 0..n {
+                    let gamma = alpha[t][i] * beta[t][i];
+                    if t == 0 {
+                        start[i] += gamma;
+                    }
+                    emission[i][o] += gamma;
+                    if t + 1 < sequence.len() {
+                        let next = sequence[t + 1];
+                        for (j, count) in transition[i].iter_mut().enumerate() {
+                            *count += alpha[t][i]
+                                * self.transition[i][j]
+                                * self.emission[j][next]
+                                * beta[t + 1][j]
+                                / scale[t + 1];
+                        }
+                    }
+                }
+            }
+        }
+
+        // Rows without any expected count keep their probabilities.
+        let normalize = |counts: Vec<f64>, old: &mut Vec<f64>| {
+            let total: f64 = counts.iter().sum();
+            if total > 0.0 {
+                *old = counts.into_iter().map(|c| c / total).collect();
+            }
+        };
+        normalize(start, &mut self.start);
+        for (counts, old) in transition.into_iter().zip(self.transition.iter_mut()) {
+            normalize(counts, old);
+        }
+        for (counts, old) in emission.into_iter().zip(self.emission.iter_mut()) {
+            normalize(counts, old);
+        }
+        log_likelihood
+    }
+}
+
+impl Describe for HMM {
+    fn describe(&self) -> Summary {
+        let mut summary = Summary::new("HMM")
+            .param("n_states", self.n_states)
+            .param("max_iter", self.max_iter)
+            .param("tol", self.tol)
+            .param("seed", self.seed);
+        if !self.emission.is_empty() {
+            summary.n_features = Some(self.n_symbols());
+        }
+        let values = self.start.capacity()
+            + self.transition.iter().map(Vec::capacity).sum::<usize>()
+            + self.emission.iter().map(Vec::capacity).sum::<usize>();
+        summary.memory = values * mem::size_of::<f64>();
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A two-state model that mostly stays put, emitting mostly 0 in state 0 and mostly 2 in
+    /// state 1.
+    fn model() -> HMM {
+        let mut hmm = HMM::new(2);
+        hmm.start = vec![0.6, 0.4];
+        hmm.transition = vec![vec![0.9, 0.1], vec![0.2, 0.8]];
+        hmm.emission = vec![vec![0.8, 0.15, 0.05], vec![0.05, 0.15, 0.8]];
+        hmm
+    }
+
+    #[test]
+    fn score_test() {
+        let hmm = model();
+        // Summing over the four paths of two states by hand.
+        let (s, a, e) = (&hmm.start, &hmm.transition, &hmm.emission);
+        let mut expected = 0.0;
+        for i in 0..2 {
+            for j in 0..2 {
+                expected += s[i] * e[i][0] * a[i][j] * e[j][2];
+            }
+        }
+        assert!((hmm.score(&[0, 2]).unwrap() - expected.ln()).abs() < 1e-12);
+        assert_eq!(hmm.score(&[]).unwrap(), 0.0);
+        assert!(hmm.score(&[3]).is_err());
+        assert!(matches!(HMM::new(2).score(&[0]), Err(Error::NotFitted)));
+
+        // Symbol 1 is in range but neither state emits it.
+        let mut hmm = model();
+        hmm.emission = vec![vec![0.9, 0.0, 0.1], vec![0.1, 0.0, 0.9]];
+        assert_eq!(hmm.score(&[1, 0]).unwrap(), f64::NEG_INFINITY);
+        assert_eq!(hmm.score(&[0, 1, 2]).unwrap(), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn decode_test() {
+        let hmm = model();
+        let (path, log_prob) = hmm.decode(&[0, 0, 2, 2, 2, 0]).unwrap();
+
+        assert_eq!(path, vec![0, 0, 1, 1, 1, 0]);
+        assert!(log_prob < hmm.score(&[0, 0, 2, 2, 2, 0]).unwrap());
+    }
+
+    #[test]
+    fn fit_test() {
+        // Sample sequences from the model, then learn it back.
+        let truth = model();
+        let mut rng = Rng::new(17);
+        let mut draw = |p: &[f64]| {
+            let mut target = rng.next_f64();
+            p.iter()
+                .position(|&q| {
+                    target -= q;
+                    target < 0.0
+                })
+                .unwrap_or(p.len() - 1)
+        };
+        let mut sequences = Vec::new();
+        let mut paths = Vec::new();
+        for _ in 0..20 {
+            let mut state = draw(&truth.start);
+            let (mut sequence, mut path) = (Vec::new(), Vec::new());
+            for _ in 0..50 {
+                path.push(state);
+                sequence.push(draw(&truth.emission[state]));
+                state = draw(&truth.transition[state]);
+            }
+            sequences.push(sequence);
+            paths.push(path);
+        }
+
+        let mut hmm = HMM::new(2).seed(5);
+        hmm.fit(&sequences).unwrap();
+        assert!(hmm.n_iter > 1);
+        let mut first = HMM::new(2).seed(5).max_iter(1);
+        first.fit(&sequences).unwrap();
+        assert!(hmm.log_likelihood > first.log_likelihood);
+
+        // The learned states may be swapped, so compare up to relabeling.
+        let (mut same, mut total) = (0, 0);
+        for (sequence, path) in sequences.iter().zip(&paths) {
+            let (decoded, _) = hmm.decode(sequence).unwrap();
+            same += decoded.iter().zip(path).filter(|(a, b)| a == b).count();
+            total += path.len();
+        }
+        let agreement = same as f64 / total as f64;
+        assert!(agreement.max(1.0 - agreement) > 0.85);
+        assert!(HMM::new(0).fit(&sequences).is_err());
+        assert!(HMM::new(2).fit(&[vec![]]).is_err());
+    }
+}
@@ -0,0 +1,41 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Models of sequences.
+
+/*!
+`HMM` is a hidden Markov model over discrete observations, such as token ids. It is trained
+without labels by Baum-Welch, scores sequences with the forward algorithm and recovers the most
+likely hidden states with the Viterbi algorithm.
+
+# Example
+```rust
+use rml::sequence::HMM;
+
+let sequences = vec![vec![0, 0, 1, 1, 0, 0, 1, 1], vec![0, 1, 1, 0, 0, 1]];
+let mut hmm = HMM::new(2).seed(1);
+hmm.fit(&sequences).unwrap();
+
+let (states, log_prob) = hmm.decode(&[0, 0, 1, 1]).unwrap();
+assert_eq!(states.len(), 4);
+assert!(log_prob <= hmm.score(&[0, 0, 1, 1]).unwrap());
+```
+!*/
+
+mod hmm;
+
+pub use hmm::HMM;
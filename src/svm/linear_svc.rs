@@ -0,0 +1,321 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Linear support vector classification.
+
+use crate::describe::{Describe, Summary};
+use crate::error::{check_lengths, Error, Result};
+use crate::math::dense::DenseMatrix;
+use crate::math::random::Rng;
+use crate::math::Float;
+use crate::parallel::{self, Parallelism};
+use crate::traits::{Fit, Predict};
+use rayon::prelude::*;
+use std::mem;
+
+/// A linear support vector classifier, trained by stochastic sub-gradient descent on the
+/// L2-regularized hinge loss (Pegasos).
+///
+/// Step `t` moves the weights by `1 / (alpha * t)`, and the weights are kept within the ball of
+/// radius `1 / sqrt(alpha)` that holds the optimum. The intercept is learned as the weight of a
+/// constant feature, so it is regularized too. With more than two classes one classifier is
+/// trained per class against the rest, in parallel, and the class with the highest score wins.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinearSVC {
+    /// The strength of the L2 penalty.
+    pub alpha: f64,
+    /// The number of passes over the training data.
+    pub max_iter: usize,
+    /// Whether to fit an intercept.
+    pub fit_intercept: bool,
+    /// The seed used to shuffle the samples on each pass, or None for the global seed.
+    pub seed: Option<u64>,
+    /// The parallelism of training the per-class classifiers, or None for the global setting.
+    pub parallelism: Option<Parallelism>,
+    /// The sorted distinct labels, once fit.
+    pub classes: Vec<i32>,
+    /// The weights of each classifier, once fit. A binary problem has a single classifier for
+    /// the larger label; otherwise there is one per class.
+    pub coef: Vec<Vec<f64>>,
+    /// The intercept of each classifier, once fit.
+    pub intercept: Vec<f64>,
+}
+
+impl Default for LinearSVC {
+    fn default() -> Self {
+        LinearSVC::new()
+    }
+}
+
+impl LinearSVC {
+    /// Create an unfitted LinearSVC with a penalty of `1e-4`, 20 passes and an intercept.
+    pub fn new() -> LinearSVC {
+        LinearSVC {
+            alpha: 1e-4,
+            max_iter: 20,
+            fit_intercept: true,
+            seed: None,
+            parallelism: None,
+            classes: Vec::new(),
+            coef: Vec::new(),
+            intercept: Vec::new(),
+        }
+    }
+
+    /// Set the strength of the L2 penalty.
+    pub fn alpha(mut self, alpha: f64) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Set the number of passes over the training data.
+    pub fn max_iter(mut self, max_iter: usize) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    /// Set whether to fit an intercept.
+    pub fn fit_intercept(mut self, fit_intercept: bool) -> Self {
+        self.fit_intercept = fit_intercept;
+        self
+    }
+
+    /// The seed used to shuffle the samples.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Set the parallelism of training, overriding the global setting.
+    pub fn parallelism(mut self, parallelism: Parallelism) -> Self {
+        self.parallelism = Some(parallelism);
+        self
+    }
+
+    /// The signed distance of each sample of `x` to the hyperplane of each classifier, in the
+    /// order of `coef`.
+    /// Returns an error if the LinearSVC is not fit or a sample has the wrong number of features.
+    pub fn decision_function<F: Float>(&self, x: &[Vec<F>]) -> Result<Vec<Vec<f64>>> {
+        if self.coef.is_empty() {
+            return Err(Error::NotFitted);
+        }
+        x.iter()
+            .map(|row| {
+                check_lengths("Features and coefficients", row.len(), self.coef[0].len())?;
+                Ok(self
+                    .coef
+                    .iter()
+                    .zip(&self.intercept)
+                    .map(|(w, b)| b + row.iter().zip(w).map(|(v, w)| v.to_f64() * w).sum::<f64>())
+                    .collect())
+            })
+            .collect()
+    }
+
+    /// Train one classifier of the samples with `positive` labels against the rest, visiting
+    /// the samples in each of `orders` in turn.
+    fn train<F: Float>(
+        &self,
+        x: &DenseMatrix<F>,
+        positive: &[bool],
+        orders: &[Vec<usize>],
+    ) -> (Vec<f64>, f64) {
+        let mut w = vec![0.0; x.n_cols()];
+        let mut b = 0.0;
+        let radius = 1.0 / self.alpha.sqrt();
+        let mut t = 0.0;
+        for &i in orders.iter().flatten() {
+            t += 1.0;
+            let eta = 1.0 / (self.alpha * t);
+            let row = x.row(i);
+            let y = if positive[i] { 1.0 } else { -1.0 };
+            let margin = y * (b + row.iter().zip(&w).map(|(v, w)| v.to_f64() * w).sum::<f64>());
+
+            let shrink = 1.0 - 1.0 / t;
+            w.iter_mut().for_each(|w| *w *= shrink);
+            b *= shrink;
+            if margin < 1.0 {
+                for (w, v) in w.iter_mut().zip(row) {
+                    *w += eta * y * v.to_f64();
+                }
+                if self.fit_intercept {
+                    b += eta * y;
+                }
+            }
+
+            let norm = (w.iter().map(|w| w * w).sum::<f64>() + b * b).sqrt();
+            if norm > radius {
+                w.iter_mut().for_each(|w| *w *= radius / norm);
+                b *= radius / norm;
+            }
+        }
+        (w, b)
+    }
+}
+
+impl<F: Float> Fit<F, i32> for LinearSVC {
+    /// Train on the samples `x` and their labels `y`.
+    /// Returns an error if the samples have different dimensions, `x` and `y` have different
+    /// lengths, there are fewer than two classes, or `alpha` is not positive.
+    fn fit(&mut self, x: &[Vec<F>], y: &[i32]) -> Result<()> {
+        check_lengths("Features and labels", x.len(), y.len())?;
+        if !(self.alpha > 0.0 && self.alpha.is_finite()) {
+            return Err(Error::InvalidInput(format!(
+                "The penalty {} is not a finite, positive number.",
+                self.alpha
+            )));
+        }
+        let mut classes = y.to_vec();
+        classes.sort_unstable();
+        classes.dedup();
+        if classes.len() < 2 {
+            return Err(Error::InvalidInput(
+                "There must be at least two classes.".into(),
+            ));
+        }
+        let x = DenseMatrix::from_rows(x)?;
+
+        let mut rng = Rng::from_seed(self.seed);
+        let orders: Vec<Vec<usize>> = (0..self.max_iter)
+            .map(|_| {
+                let mut order: Vec<usize> = (0..x.n_rows()).collect();
+                rng.shuffle(&mut order);
+                order
+            })
+            .collect();
+        let positives = match classes.len() {
+            2 => &classes[1..],
+            _ => &classes[..],
+        };
+        let models: Vec<(Vec<f64>, f64)> = parallel::install(self.parallelism, || {
+            positives
+                .par_iter()
+                .map(|&class| {
+                    let positive: Vec<bool> = y.iter().map(|&l| l == class).collect();
+                    self.train(&x, &positive, &orders)
+                })
+                .collect()
+        });
+        let (coef, intercept) = models.into_iter().unzip();
+        self.coef = coef;
+        self.intercept = intercept;
+        self.classes = classes;
+        Ok(())
+    }
+}
+
+impl<F: Float> Predict<F, i32> for LinearSVC {
+    /// Predict the class of each sample in `x`.
+    /// Returns an error under the same conditions as [`LinearSVC::decision_function`].
+    fn predict(&self, x: &[Vec<F>]) -> Result<Vec<i32>> {
+        Ok(self
+            .decision_function(x)?
+            .into_iter()
+            .map(|scores| match scores.len() {
+                1 => self.classes[(scores[0] > 0.0) as usize],
+                _ => {
+                    let best = (0..scores.len())
+                        .max_by(|&a, &b| scores[a].total_cmp(&scores[b]).then(b.cmp(&a)))
+                        .unwrap_or(0);
+                    self.classes[best]
+                }
+            })
+            .collect())
+    }
+}
+
+impl Describe for LinearSVC {
+    fn describe(&self) -> Summary {
+        let mut summary = Summary::new("LinearSVC")
+            .param("alpha", self.alpha)
+            .param("max_iter", self.max_iter)
+            .param("fit_intercept", self.fit_intercept)
+            .param("seed", self.seed)
+            .param("parallelism", self.parallelism);
+        if let Some(w) = self.coef.first() {
+            summary.n_features = Some(w.len());
+            summary.n_classes = Some(self.classes.len());
+        }
+        let weights =
+            self.coef.iter().map(Vec::capacity).sum::<usize>() + self.intercept.capacity();
+        summary.memory =
+            weights * mem::size_of::<f64>() + self.classes.capacity() * mem::size_of::<i32>();
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::accuracy;
+
+    /// Gaussian blobs of 50 points around each center, labeled by center.
+    fn blobs(centers: &[(f64, f64)]) -> (Vec<Vec<f64>>, Vec<i32>) {
+        let mut rng = Rng::new(21);
+        let mut x = Vec::new();
+        let mut y = Vec::new();
+        for (label, &(cx, cy)) in centers.iter().enumerate() {
+            for _ in 0..50 {
+                x.push(vec![cx + rng.next_normal(), cy + rng.next_normal()]);
+                y.push(label as i32 * 3 - 1);
+            }
+        }
+        (x, y)
+    }
+
+    #[test]
+    fn binary_test() {
+        let (x, y) = blobs(&[(-3.0, 0.0), (3.0, 1.0)]);
+        let mut svc = LinearSVC::new().alpha(0.01).seed(4);
+        svc.fit(&x, &y).unwrap();
+
+        assert_eq!(svc.classes, vec![-1, 2]);
+        assert_eq!(svc.coef.len(), 1);
+        assert!(accuracy(&y, &svc.predict(&x).unwrap()).unwrap() > 0.97);
+        let mut again = LinearSVC::new().alpha(0.01).seed(4);
+        again.fit(&x, &y).unwrap();
+        assert_eq!(again, svc);
+    }
+
+    #[test]
+    fn multiclass_test() {
+        let (x, y) = blobs(&[(0.0, 6.0), (-6.0, -3.0), (6.0, -3.0)]);
+        let mut svc = LinearSVC::new().alpha(0.01).seed(5);
+        svc.fit(&x, &y).unwrap();
+
+        assert_eq!(svc.coef.len(), 3);
+        assert_eq!(svc.decision_function(&x).unwrap()[0].len(), 3);
+        assert!(accuracy(&y, &svc.predict(&x).unwrap()).unwrap() > 0.95);
+        assert_eq!(svc.describe().n_classes, Some(3));
+    }
+
+    #[test]
+    fn validation_test() {
+        let x = vec![vec![0.0], vec![1.0]];
+        assert!(LinearSVC::new().fit(&x, &[1, 1]).is_err());
+        assert!(LinearSVC::new().fit(&x, &[0]).is_err());
+        assert!(LinearSVC::new().alpha(0.0).fit(&x, &[0, 1]).is_err());
+        assert!(matches!(
+            LinearSVC::new().decision_function(&x),
+            Err(Error::NotFitted)
+        ));
+
+        let mut svc = LinearSVC::new();
+        svc.fit(&x, &[0, 1]).unwrap();
+        assert!(svc.predict(&[vec![0.0, 1.0]]).is_err());
+    }
+}
@@ -0,0 +1,43 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Support vector machines.
+
+/*!
+`LinearSVC` is a linear classifier trained on the hinge loss. Prediction costs one dot product per
+class, independent of the number of training samples. `OneClassSVM` learns the region of normal
+data from normal samples alone and flags samples outside it as novelties.
+
+# Example
+```rust
+use rml::svm::LinearSVC;
+use rml::traits::{Fit, Predict};
+
+let x = vec![vec![0.0, 0.0], vec![0.0, 1.0], vec![4.0, 4.0], vec![4.0, 5.0]];
+let y = vec![0, 0, 1, 1];
+let mut svc = LinearSVC::new().seed(1);
+svc.fit(&x, &y).unwrap();
+
+assert_eq!(svc.predict(&[vec![0.5, 0.5], vec![5.0, 4.0]]).unwrap(), vec![0, 1]);
+```
+!*/
+
+mod linear_svc;
+mod one_class;
+
+pub use linear_svc::LinearSVC;
+pub use one_class::{Kernel, OneClassSVM};
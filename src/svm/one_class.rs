@@ -0,0 +1,350 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! One-class support vector machines for novelty detection.
+
+use crate::describe::{Describe, Summary};
+use crate::error::{check_lengths, Error, Result};
+use crate::math::dense::DenseMatrix;
+use crate::math::Float;
+use crate::parallel::{self, Parallelism};
+use rayon::prelude::*;
+
+/// The kernel of a support vector machine.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kernel {
+    /// The dot product `x · y`.
+    Linear,
+    /// The radial basis function `exp(-gamma * |x - y|²)` with the given gamma.
+    Rbf(f64),
+}
+
+impl Kernel {
+    fn apply<F: Float>(&self, x: &[F], y: &[F]) -> f64 {
+        match *self {
+            Kernel::Linear => x.iter().zip(y).map(|(a, b)| a.to_f64() * b.to_f64()).sum(),
+            Kernel::Rbf(gamma) => {
+                let d: f64 = x
+                    .iter()
+                    .zip(y)
+                    .map(|(a, b)| (*a - *b).to_f64().powi(2))
+                    .sum();
+                (-gamma * d).exp()
+            }
+        }
+    }
+}
+
+/// A one-class support vector machine, which learns the region holding the training data so
+/// that new samples outside it can be flagged as novelties. Train it on normal samples only.
+///
+/// `nu` is an upper bound on the fraction of training samples left outside the region and a
+/// lower bound on the fraction of support vectors. The dual problem is solved by sequential
+/// minimal optimization over the full kernel matrix, which takes `O(n²)` memory for `n`
+/// training samples.
+///
+/// # Example
+/// ```rust
+/// use rml::svm::{Kernel, OneClassSVM};
+///
+/// let x: Vec<Vec<f64>> = (0..50)
+///     .map(|i| vec![(i % 7) as f64 * 0.1, (i % 5) as f64 * 0.1])
+///     .collect();
+/// let mut svm = OneClassSVM::new(Kernel::Rbf(1.0)).nu(0.1);
+/// svm.fit(&x).unwrap();
+///
+/// assert_eq!(svm.predict(&[vec![0.3, 0.2], vec![5.0, 5.0]]).unwrap(), vec![1, -1]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct OneClassSVM<F = f64> {
+    /// The kernel.
+    pub kernel: Kernel,
+    /// The bound on the fraction of training outliers, in `(0, 1]`.
+    pub nu: f64,
+    /// The tolerance of the optimality conditions.
+    pub tol: f64,
+    /// The maximum number of optimization steps.
+    pub max_iter: usize,
+    /// The parallelism of kernel computations, or None for the global setting.
+    pub parallelism: Option<Parallelism>,
+    /// The support vectors, once fit.
+    pub support_vectors: DenseMatrix<F>,
+    /// The dual coefficient of each support vector, once fit.
+    pub dual_coef: Vec<f64>,
+    /// The offset of the decision function, once fit.
+    pub rho: f64,
+}
+
+impl<F: Float> OneClassSVM<F> {
+    /// Create an unfitted OneClassSVM with the given kernel, `nu` of 0.5, a tolerance of `1e-3`
+    /// and at most 100,000 optimization steps.
+    pub fn new(kernel: Kernel) -> OneClassSVM<F> {
+        OneClassSVM {
+            kernel,
+            nu: 0.5,
+            tol: 1e-3,
+            max_iter: 100_000,
+            parallelism: None,
+            support_vectors: DenseMatrix::new(0),
+            dual_coef: Vec::new(),
+            rho: 0.0,
+        }
+    }
+
+    /// Set the bound on the fraction of training outliers.
+    pub fn nu(mut self, nu: f64) -> Self {
+        self.nu = nu;
+        self
+    }
+
+    /// Set the tolerance of the optimality conditions.
+    pub fn tol(mut self, tol: f64) -> Self {
+        self.tol = tol;
+        self
+    }
+
+    /// Set the maximum number of optimization steps.
+    pub fn max_iter(mut self, max_iter: usize) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    /// Set the parallelism of kernel computations, overriding the global setting.
+    pub fn parallelism(mut self, parallelism: Parallelism) -> Self {
+        self.parallelism = Some(parallelism);
+        self
+    }
+
+    /// The heap memory of the support vectors and their coefficients, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.support_vectors.memory_usage() + self.dual_coef.capacity() * std::mem::size_of::<f64>()
+    }
+
+    /// Learn the region of the normal samples `x`.
+    /// Returns an error if there are no samples, the samples have different dimensions, `nu` is
+    /// not in `(0, 1]`, or the RBF gamma is not positive.
+    pub fn fit(&mut self, x: &[Vec<F>]) -> Result<()> {
+        let x = DenseMatrix::from_rows(x)?;
+        let n = x.n_rows();
+        if n == 0 {
+            return Err(Error::InvalidInput("There are no samples.".into()));
+        }
+        if !(self.nu > 0.0 && self.nu <= 1.0) {
+            return Err(Error::InvalidInput(format!(
+                "nu is {} but must be in (0, 1].",
+                self.nu
+            )));
+        }
+        if let Kernel::Rbf(gamma) = self.kernel {
+            if !(gamma > 0.0 && gamma.is_finite()) {
+                return Err(Error::InvalidInput(format!(
+                    "The RBF gamma {} is not a finite, positive number.",
+                    gamma
+                )));
+            }
+        }
+
+        let kernel = self.kernel;
+        let k: Vec<Vec<f64>> = parallel::install(self.parallelism, || {
+            (0..n)
+                .into_par_iter()
+                .map(|i| x.rows().map(|q| kernel.apply(x.row(i), q)).collect())
+                .collect()
+        });
+
+        // Minimize a' K a / 2 subject to 0 <= a <= 1 and sum(a) = nu * n.
+        let total = self.nu * n as f64;
+        let mut alpha: Vec<f64> = (0..n).map(|i| (total - i as f64).clamp(0.0, 1.0)).collect();
+        let mut gradient: Vec<f64> = k
+            .iter()
+            .map(|row| row.iter().zip(&alpha).map(|(k, a)| k * a).sum())
+            .collect();
+        for _ in 0..self.max_iter {
+            // The maximal violating pair: i can grow and j can shrink.
+            let i = (0..n)
+                .filter(|&i| alpha[i] < 1.0)
+                .min_by(|&a, &b| gradient[a].total_cmp(&gradient[b]));
+            let j = (0..n)
+                .filter(|&j| alpha[j] > 0.0)
+                .max_by(|&a, &b| gradient[a].total_cmp(&gradient[b]));
+            let (i, j) = match (i, j) {
+                (Some(i), Some(j)) if gradient[j] - gradient[i] > self.tol => (i, j),
+                _ => break,
+            };
+            let curvature = (k[i][i] + k[j][j] - 2.0 * k[i][j]).max(1e-12);
+            let step = ((gradient[j] - gradient[i]) / curvature)
+                .min(1.0 - alpha[i])
+                .min(alpha[j]);
+            alpha[i] += step;
+            alpha[j] -= step;
+            for (g, row) in gradient.iter_mut().zip(&k) {
+                *g += step * (row[i] - row[j]);
+            }
+        }
+
+        // Free coefficients sit exactly on the boundary; otherwise take the middle of the
+        // feasible offsets.
+        let free: Vec<f64> = (0..n)
+            .filter(|&i| alpha[i] > 0.0 && alpha[i] < 1.0)
+            .map(|i| gradient[i])
+            .collect();
+        self.rho = if free.is_empty() {
+            let lower = (0..n)
+                .filter(|&i| alpha[i] >= 1.0)
+                .map(|i| gradient[i])
+                .fold(f64::NEG_INFINITY, f64::max);
+            let upper = (0..n)
+                .filter(|&i| alpha[i] <= 0.0)
+                .map(|i| gradient[i])
+                .fold(f64::INFINITY, f64::min);
+            match (lower.is_finite(), upper.is_finite()) {
+                (true, true) => (lower + upper) / 2.0,
+                (true, false) => lower,
+                _ => upper,
+            }
+        } else {
+            free.iter().sum::<f64>() / free.len() as f64
+        };
+
+        self.support_vectors = DenseMatrix::new(x.n_cols());
+        self.dual_coef.clear();
+        for (i, &a) in alpha.iter().enumerate().filter(|(_, &a)| a > 0.0) {
+            self.support_vectors.push_row(x.row(i))?;
+            self.dual_coef.push(a);
+        }
+        Ok(())
+    }
+
+    /// The signed score of each sample of `x`: positive inside the learned region and negative
+    /// outside it.
+    /// Returns an error if the OneClassSVM is not fit or a sample has the wrong number of
+    /// features.
+    pub fn decision_function(&self, x: &[Vec<F>]) -> Result<Vec<f64>> {
+        if self.dual_coef.is_empty() {
+            return Err(Error::NotFitted);
+        }
+        for row in x {
+            check_lengths(
+                "Features and support vectors",
+                row.len(),
+                self.support_vectors.n_cols(),
+            )?;
+        }
+        Ok(parallel::install(self.parallelism, || {
+            x.par_iter()
+                .map(|row| {
+                    self.support_vectors
+                        .rows()
+                        .zip(&self.dual_coef)
+                        .map(|(sv, a)| a * self.kernel.apply(sv, row))
+                        .sum::<f64>()
+                        - self.rho
+                })
+                .collect()
+        }))
+    }
+
+    /// 1 for each sample of `x` inside the learned region and -1 for each novelty.
+    /// Returns an error under the same conditions as [`OneClassSVM::decision_function`].
+    pub fn predict(&self, x: &[Vec<F>]) -> Result<Vec<i32>> {
+        Ok(self
+            .decision_function(x)?
+            .into_iter()
+            .map(|score| if score >= 0.0 { 1 } else { -1 })
+            .collect())
+    }
+}
+
+impl<F: Float> Describe for OneClassSVM<F> {
+    fn describe(&self) -> Summary {
+        let mut summary = Summary::new("OneClassSVM")
+            .param("kernel", self.kernel)
+            .param("nu", self.nu)
+            .param("tol", self.tol)
+            .param("max_iter", self.max_iter)
+            .param("parallelism", self.parallelism);
+        if !self.dual_coef.is_empty() {
+            summary.n_features = Some(self.support_vectors.n_cols());
+        }
+        summary.memory = self.memory_usage();
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::random::Rng;
+
+    /// 200 samples of a standard normal distribution around `center`.
+    fn normal(center: f64) -> Vec<Vec<f64>> {
+        let mut rng = Rng::new(12);
+        (0..200)
+            .map(|_| vec![center + rng.next_normal(), center + rng.next_normal()])
+            .collect()
+    }
+
+    #[test]
+    fn rbf_test() {
+        let x = normal(0.0);
+        let mut svm = OneClassSVM::new(Kernel::Rbf(0.1)).nu(0.1);
+        svm.fit(&x).unwrap();
+
+        let outliers = svm
+            .predict(&x)
+            .unwrap()
+            .iter()
+            .filter(|&&p| p == -1)
+            .count();
+        assert!(outliers as f64 <= 0.1 * 200.0 + 5.0);
+        assert!(svm.dual_coef.len() as f64 >= 0.1 * 200.0);
+        assert_eq!(
+            svm.predict(&[vec![0.0, 0.0], vec![5.0, -5.0]]).unwrap(),
+            vec![1, -1]
+        );
+        assert!(svm.memory_usage() > 0);
+    }
+
+    #[test]
+    fn linear_test() {
+        let x = normal(5.0);
+        let mut svm = OneClassSVM::new(Kernel::Linear).nu(0.2);
+        svm.fit(&x).unwrap();
+
+        // A linear one-class SVM separates the data from the origin.
+        assert_eq!(
+            svm.predict(&[vec![0.0, 0.0], vec![6.0, 6.0]]).unwrap(),
+            vec![-1, 1]
+        );
+    }
+
+    #[test]
+    fn validation_test() {
+        let x = vec![vec![0.0], vec![1.0]];
+        assert!(OneClassSVM::new(Kernel::Linear).nu(0.0).fit(&x).is_err());
+        assert!(OneClassSVM::new(Kernel::Rbf(-1.0)).fit(&x).is_err());
+        assert!(OneClassSVM::<f64>::new(Kernel::Linear).fit(&[]).is_err());
+        assert!(matches!(
+            OneClassSVM::<f64>::new(Kernel::Linear).predict(&x),
+            Err(Error::NotFitted)
+        ));
+
+        let mut svm = OneClassSVM::new(Kernel::Linear);
+        svm.fit(&x).unwrap();
+        assert!(svm.decision_function(&[vec![0.0, 1.0]]).is_err());
+    }
+}
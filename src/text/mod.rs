@@ -0,0 +1,43 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Models of text.
+
+/*!
+`NGramModel` is a smoothed n-gram language model over tokenized sentences. It scores sentences,
+measures perplexity and samples new text.
+
+# Example
+```rust
+use rml::text::NGramModel;
+
+let corpus = vec![
+    vec!["the", "cat", "sat"],
+    vec!["the", "dog", "sat"],
+    vec!["the", "cat", "ran"],
+];
+let mut model = NGramModel::new(2).unwrap();
+model.fit(&corpus).unwrap();
+
+let likely = model.log_prob(&["the", "cat", "sat"]).unwrap();
+assert!(likely > model.log_prob(&["sat", "the", "cat"]).unwrap());
+```
+!*/
+
+mod ngram;
+
+pub use ngram::NGramModel;
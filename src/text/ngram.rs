@@ -0,0 +1,328 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! N-gram language models.
+
+use crate::describe::{Describe, Summary};
+use crate::error::{Error, Result};
+use crate::math::random::Rng;
+use std::collections::{BTreeSet, HashMap};
+
+/// The token padding the start of each sentence.
+const START: &str = "<s>";
+/// The token ending each sentence.
+const END: &str = "</s>";
+/// The token standing for every word not seen in training.
+const UNKNOWN: &str = "<unk>";
+
+/// An n-gram language model with add-k smoothing.
+///
+/// Each sentence is padded with `n - 1` start tokens and ends with an end token, so the model
+/// learns how sentences begin and end. The probability of a word after a context of `n - 1`
+/// words is `(count(context, word) + k) / (count(context) + k * V)`, where `V` is the size of the
+/// vocabulary including the end token and the unknown token, which stands for every word not
+/// seen in training.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NGramModel {
+    /// The order of the model: the number of words in each n-gram.
+    pub n: usize,
+    /// The count added to every n-gram.
+    pub k: f64,
+    /// The words seen in training, the end token and the unknown token, once fit.
+    pub vocabulary: BTreeSet<String>,
+    /// The count of each word after each context.
+    counts: HashMap<Vec<String>, HashMap<String, usize>>,
+    /// The number of words after each context.
+    totals: HashMap<Vec<String>, usize>,
+}
+
+impl NGramModel {
+    /// Create an unfitted model of order `n` with Laplace (add-one) smoothing.
+    /// Returns an error if `n` is 0.
+    pub fn new(n: usize) -> Result<NGramModel> {
+        if n == 0 {
+            return Err(Error::InvalidInput("The order must be at least 1.".into()));
+        }
+        Ok(NGramModel {
+            n,
+            k: 1.0,
+            vocabulary: BTreeSet::new(),
+            counts: HashMap::new(),
+            totals: HashMap::new(),
+        })
+    }
+
+    /// Set the count added to every n-gram.
+    pub fn k(mut self, k: f64) -> Self {
+        self.k = k;
+        self
+    }
+
+    /// Count the n-grams of the tokenized `sentences`, replacing anything learned before.
+    /// Returns an error if there are no sentences, `n` is 0 or `k` is not positive.
+    pub fn fit<S: AsRef<str>>(&mut self, sentences: &[Vec<S>]) -> Result<()> {
+        if sentences.is_empty() {
+            return Err(Error::InvalidInput("There are no sentences.".into()));
+        }
+        if self.n == 0 {
+            return Err(Error::InvalidInput("The order must be at least 1.".into()));
+        }
+        if !(self.k > 0.0 && self.k.is_finite()) {
+            return Err(Error::InvalidInput(format!(
+                "The smoothing count {} is not a finite, positive number.",
+                self.k
+            )));
+        }
+        self.vocabulary = sentences
+            .iter()
+            .flatten()
+            .map(|w| w.as_ref().to_string())
+            .chain([END, UNKNOWN].iter().map(|w| w.to_string()))
+            .collect();
+        self.counts.clear();
+        self.totals.clear();
+        for sentence in sentences {
+            let words = self.pad(sentence);
+            for gram in words.windows(self.n) {
+                let (context, word) = gram.split_at(self.n - 1);
+                *self
+                    .counts
+                    .entry(context.to_vec())
+                    .or_default()
+                    .entry(word[0].clone())
+                    .or_insert(0) += 1;
+                *self.totals.entry(context.to_vec()).or_insert(0) += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// The smoothed probability of `word` after `context`. Only the last `n - 1` words of the
+    /// context are used, and a shorter context is padded with start tokens.
+    /// Returns an error if the model is not fit.
+    pub fn probability<S: AsRef<str>>(&self, context: &[S], word: &str) -> Result<f64> {
+        self.check_fitted()?;
+        let mut padded: Vec<String> =
+            vec![START.to_string(); (self.n - 1).saturating_sub(context.len())];
+        let skip = context.len().saturating_sub(self.n - 1);
+        padded.extend(context[skip..].iter().map(|w| self.known(w.as_ref())));
+        Ok(self.smoothed(&padded, &self.known(word)))
+    }
+
+    /// The natural log of the probability of the tokenized `sentence`, including its end.
+    /// Returns an error if the model is not fit.
+    pub fn log_prob<S: AsRef<str>>(&self, sentence: &[S]) -> Result<f64> {
+        self.check_fitted()?;
+        Ok(self
+            .pad(sentence)
+            .windows(self.n)
+            .map(|gram| {
+                let (context, word) = gram.split_at(self.n - 1);
+                self.smoothed(context, &word[0]).ln()
+            })
+            .sum())
+    }
+
+    /// The perplexity of the model on the tokenized `sentences`: the exponential of the mean
+    /// negative log probability per word, counting the end of each sentence as a word. Lower
+    /// is better.
+    /// Returns an error if the model is not fit or there are no sentences.
+    pub fn perplexity<S: AsRef<str>>(&self, sentences: &[Vec<S>]) -> Result<f64> {
+        if sentences.is_empty() {
+            return Err(Error::InvalidInput("There are no sentences.".into()));
+        }
+        let words: usize = sentences.iter().map(|s| s.len() + 1).sum();
+        let log_prob = sentences
+            .iter()
+            .map(|s| self.log_prob(s))
+            .sum::<Result<f64>>()?;
+        Ok((-log_prob / words as f64).exp())
+    }
+
+    /// Sample a sentence of at most `max_len` words, drawing each word from the smoothed
+    /// distribution after the words before it until the end token is drawn. The unknown token
+    /// is never drawn.
+    /// Returns an error if the model is not fit.
+    pub fn sample(&self, max_len: usize, rng: &mut Rng) -> Result<Vec<String>> {
+        self.check_fitted()?;
+        let mut words: Vec<String> = vec![START.to_string(); self.n - 1];
+        while words.len() - (self.n - 1) < max_len {
+            let context = &words[words.len() - (self.n - 1)..];
+            let candidates: Vec<(&String, f64)> = self
+                .vocabulary
+                .iter()
+                .filter(|w| w.as_str() != UNKNOWN)
+                .map(|w| (w, self.smoothed(context, w)))
+                .collect();
+            let mut target = rng.next_f64() * candidates.iter().map(|(_, p)| p).sum::<f64>();
+            let word = candidates
+                .iter()
+                .find(|(_, p)| {
+                    target -= p;
+                    target < 0.0
+                })
+                .unwrap_or(&candidates[candidates.len() - 1])
+                .0;
+            if word == END {
+                break;
+            }
+            words.push(word.clone());
+        }
+        Ok(words.split_off(self.n - 1))
+    }
+
+    /// Returns [`Error::NotFitted`] if the model is not fit, or an error if its order was set
+    /// to 0 since.
+    fn check_fitted(&self) -> Result<()> {
+        if self.vocabulary.is_empty() {
+            return Err(Error::NotFitted);
+        }
+        if self.n == 0 {
+            return Err(Error::InvalidInput("The order must be at least 1.".into()));
+        }
+        Ok(())
+    }
+
+    /// The word, or the unknown token if it wasn't seen in training.
+    fn known(&self, word: &str) -> String {
+        match self.vocabulary.contains(word) {
+            true => word.to_string(),
+            false => UNKNOWN.to_string(),
+        }
+    }
+
+    /// The sentence with unseen words replaced, padded with start tokens and the end token.
+    fn pad<S: AsRef<str>>(&self, sentence: &[S]) -> Vec<String> {
+        let mut words = vec![START.to_string(); self.n - 1];
+        words.extend(sentence.iter().map(|w| self.known(w.as_ref())));
+        words.push(END.to_string());
+        words
+    }
+
+    /// The smoothed probability of `word` after `context`.
+    fn smoothed(&self, context: &[String], word: &str) -> f64 {
+        let count = self
+            .counts
+            .get(context)
+            .and_then(|next| next.get(word))
+            .copied()
+            .unwrap_or(0);
+        let total = self.totals.get(context).copied().unwrap_or(0);
+        (count as f64 + self.k) / (total as f64 + self.k * self.vocabulary.len() as f64)
+    }
+}
+
+impl Describe for NGramModel {
+    fn describe(&self) -> Summary {
+        let mut summary = Summary::new("NGramModel")
+            .param("n", self.n)
+            .param("k", self.k);
+        summary.n_features = Some(self.vocabulary.len());
+        summary.memory = self
+            .counts
+            .iter()
+            .map(|(context, next)| {
+                context.iter().map(String::capacity).sum::<usize>()
+                    + next.keys().map(String::capacity).sum::<usize>()
+            })
+            .sum::<usize>()
+            + self.vocabulary.iter().map(String::capacity).sum::<usize>();
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corpus() -> Vec<Vec<&'static str>> {
+        vec![
+            vec!["i", "like", "green", "eggs"],
+            vec!["i", "like", "ham"],
+            vec!["you", "like", "ham"],
+        ]
+    }
+
+    #[test]
+    fn probability_test() {
+        let mut model = NGramModel::new(2).unwrap();
+        model.fit(&corpus()).unwrap();
+
+        // i, like, green, eggs, ham, you, </s> and <unk>.
+        assert_eq!(model.vocabulary.len(), 8);
+        // "like" follows "i" twice out of 2, plus one smoothing count over 8 words.
+        assert!((model.probability(&["i"], "like").unwrap() - 3.0 / 10.0).abs() < 1e-12);
+        assert!((model.probability(&["like"], "ham").unwrap() - 3.0 / 11.0).abs() < 1e-12);
+        assert!((model.probability::<&str>(&[], "i").unwrap() - 3.0 / 11.0).abs() < 1e-12);
+        assert_eq!(
+            model.probability(&["zebra"], "like").unwrap(),
+            model.probability(&["unseen"], "like").unwrap()
+        );
+        let total: f64 = model
+            .vocabulary
+            .iter()
+            .map(|w| model.probability(&["like"], w).unwrap())
+            .sum();
+        assert!((total - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn score_test() {
+        let mut model = NGramModel::new(3).unwrap().k(0.1);
+        model.fit(&corpus()).unwrap();
+
+        let likely = model.log_prob(&["i", "like", "ham"]).unwrap();
+        assert!(likely > model.log_prob(&["ham", "like", "i"]).unwrap());
+        assert!(likely < 0.0);
+        let seen = model.perplexity(&corpus()).unwrap();
+        let unseen = model.perplexity(&[vec!["eggs", "green", "you"]]).unwrap();
+        assert!(seen < unseen);
+        assert!(model.perplexity::<&str>(&[]).is_err());
+
+        let unfitted = NGramModel::new(2).unwrap();
+        assert!(matches!(
+            unfitted.probability(&["i"], "like"),
+            Err(Error::NotFitted)
+        ));
+        assert!(matches!(unfitted.log_prob(&["i"]), Err(Error::NotFitted)));
+        assert!(matches!(
+            unfitted.perplexity(&corpus()),
+            Err(Error::NotFitted)
+        ));
+    }
+
+    #[test]
+    fn sample_test() {
+        let mut model = NGramModel::new(2).unwrap().k(1e-6);
+        model.fit(&corpus()).unwrap();
+        let sentence = model.sample(10, &mut Rng::new(3)).unwrap();
+
+        // With almost no smoothing only seen bigrams are drawn.
+        assert!(!sentence.is_empty());
+        assert!(sentence.len() <= 10);
+        assert!(["i", "you"].contains(&sentence[0].as_str()));
+        assert!(sentence.windows(2).all(|w| corpus()
+            .iter()
+            .any(|s| s.windows(2).any(|b| b[0] == w[0] && b[1] == w[1]))));
+        assert!(matches!(
+            NGramModel::new(2).unwrap().sample(5, &mut Rng::new(1)),
+            Err(Error::NotFitted)
+        ));
+        assert!(NGramModel::new(0).is_err());
+        assert!(NGramModel::new(2).unwrap().k(0.0).fit(&corpus()).is_err());
+    }
+}
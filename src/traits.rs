@@ -0,0 +1,110 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+//! Traits shared by the estimators.
+
+/*!
+Generic code such as `model_selection::cross_val_score` works with any estimator that
+implements these traits, instead of special-casing each model.
+
+Models implement `Fit` and `Predict`, and classifiers that estimate class probabilities also
+implement `PredictProba`. Models that can weigh some samples more than others, for
+cost-sensitive learning, implement `FitWeighted`. Naturally online estimators implement
+`IncrementalFit`, so they can learn from chunks of a dataset too large to load at once, such as
+those of `preprocessing::csv::read_chunks`. Preprocessors implement `Fit` for any target type, ignoring the
+targets, and `Transform`, so they can be fit alongside a model on the same data.
+
+`Predict::predict` predicts a batch of samples. Models like `KNN` also have an inherent
+`predict` (and `predict_proba`) for a single sample, which method call syntax picks first, so
+call the trait methods as `Predict::predict(&model, &x)` on those.
+
+# Example
+```rust
+use rml::knn::KNN;
+use rml::traits::{Fit, Predict};
+
+let mut knn = KNN::unfitted(1, None, None);
+knn.fit(&[vec![0.0], vec![10.0]], &[0, 1]).unwrap();
+
+assert_eq!(Predict::predict(&knn, &[vec![1.0], vec![9.0]]).unwrap(), vec![0, 1]);
+```
+!*/
+
+use crate::error::Result;
+
+/// An estimator that learns from features of type `T` and targets of type `U`.
+pub trait Fit<T, U> {
+    /// Fit the estimator to the samples `x` and their targets `y`, replacing anything learned
+    /// before.
+    fn fit(&mut self, x: &[Vec<T>], y: &[U]) -> Result<()>;
+}
+
+/// An estimator that can be fit with a weight for each sample.
+pub trait FitWeighted<T, U>: Fit<T, U> {
+    /// Fit the estimator to the samples `x` and their targets `y`, counting each sample as
+    /// many times as its weight in `sample_weight`. `None` weighs all samples equally, like
+    /// [`Fit::fit`].
+    fn fit_weighted(&mut self, x: &[Vec<T>], y: &[U], sample_weight: Option<&[f64]>) -> Result<()>;
+}
+
+/// An estimator that can keep learning from new samples.
+///
+/// # Example
+/// ```rust
+/// use rml::preprocessing::scale::StandardScaler;
+/// use rml::traits::IncrementalFit;
+///
+/// let chunks = vec![(vec![vec![1.0], vec![2.0]], vec![0, 1]), (vec![vec![3.0]], vec![1])];
+/// let mut scaler = StandardScaler::new();
+/// for (x, y) in &chunks {
+///     scaler.partial_fit(x, y).unwrap();
+/// }
+///
+/// assert_eq!(scaler.mean, vec![2.0]);
+/// ```
+pub trait IncrementalFit<T, U> {
+    /// Update the estimator with the samples `x` and their targets `y`, keeping what it
+    /// learned before.
+    fn partial_fit(&mut self, x: &[Vec<T>], y: &[U]) -> Result<()>;
+}
+
+/// An estimator that predicts targets of type `U` from features of type `T`.
+pub trait Predict<T, U> {
+    /// Predict the target of each sample in `x`.
+    fn predict(&self, x: &[Vec<T>]) -> Result<Vec<U>>;
+}
+
+/// A classifier that predicts the probability of each class from features of type `T`.
+pub trait PredictProba<T> {
+    /// Predict the probabilities of each sample in `x`. Entry `j` of each row is the probability
+    /// of class `j`, and each row sums to 1.
+    fn predict_proba(&self, x: &[Vec<T>]) -> Result<Vec<Vec<f64>>>;
+}
+
+/// A preprocessor that maps samples with features of type `T` to new samples.
+pub trait Transform<T> {
+    /// Transform each sample in `x`.
+    fn transform(&self, x: &[Vec<T>]) -> Result<Vec<Vec<T>>>;
+
+    /// Fit to `x` and `y`, then transform `x`.
+    fn fit_transform<U>(&mut self, x: &[Vec<T>], y: &[U]) -> Result<Vec<Vec<T>>>
+    where
+        Self: Fit<T, U> + Sized,
+    {
+        self.fit(x, y)?;
+        self.transform(x)
+    }
+}
@@ -0,0 +1,35 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Changes the global parallelism, so it runs in its own process rather than among the unit
+//! tests.
+
+use rml::knn::KNN;
+use rml::parallel::{self, Parallelism};
+
+#[test]
+fn set_parallelism_test() {
+    assert_eq!(parallel::parallelism(), Parallelism::Auto);
+    parallel::set_parallelism(Parallelism::Threads(2));
+    assert_eq!(parallel::parallelism(), Parallelism::Threads(2));
+
+    let x: Vec<Vec<f64>> = (0..20).map(|i| vec![i as f64]).collect();
+    let y = (0..20).map(|i| (i >= 10) as i32).collect();
+    let knn = KNN::new(3, x, y, None, None).unwrap();
+    assert_eq!(knn.predict(&[2.0]).unwrap(), 0);
+    assert_eq!(knn.predict(&[17.0]).unwrap(), 1);
+}
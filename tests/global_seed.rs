@@ -0,0 +1,28 @@
+// Copyright 2021 Jonathan Manly.
+
+// This file is part of rml.
+
+// rml is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Lesser General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// rml is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Lesser General Public License for more details.
+
+// You should have received a copy of the GNU Lesser General Public License
+// along with rml.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Changes the global seed, so it runs in its own process rather than among the unit tests.
+
+use rml::math::random::{global_seed, Rng};
+
+#[test]
+fn set_global_seed_test() {
+    rml::set_global_seed(9);
+    assert_eq!(global_seed(), 9);
+    assert_eq!(Rng::from_seed(None).next_u64(), Rng::new(9).next_u64());
+    assert_eq!(Rng::from_seed(Some(3)).next_u64(), Rng::new(3).next_u64());
+}